@@ -0,0 +1,756 @@
+//! Highlights and notes over cloud documents
+//!
+//! Annotations are keyed by `drive_file_id` rather than the local path, so
+//! they survive deleting and re-downloading the local copy. Like reading
+//! progress, they are only removed together with the item itself.
+//!
+//! Rect coordinates are fractions of the page size with the origin at the
+//! top-left corner, so they don't depend on the zoom level the annotation
+//! was made at. `export_annotated_pdf` converts them to PDF user space when
+//! baking them into a copy of the document.
+
+use lopdf::{Document, Object, ObjectId, StringFormat, dictionary};
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+use crate::bookshelf;
+use crate::db::{ToDbError, now_timestamp, open_db};
+use crate::error::{DatabaseError, GoogleDriveError, IoError, PdfError, PedaruError};
+
+/// Version of the JSON produced by `export_annotations`
+const EXPORT_FORMAT_VERSION: u32 = 1;
+
+/// Directory for annotated copies (next to the downloads directory)
+const EXPORTS_DIR_NAME: &str = "exports";
+
+/// Size of a note's icon in PDF units
+const NOTE_ICON_SIZE: f64 = 20.0;
+
+/// Page size used when a page has no MediaBox (US Letter)
+const DEFAULT_PAGE_BOX: [f64; 4] = [0.0, 0.0, 612.0, 792.0];
+
+/// How far up the page tree inherited attributes are looked up
+const MAX_PAGE_TREE_DEPTH: usize = 32;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Kind of annotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationKind {
+    Highlight,
+    Underline,
+    Strikeout,
+    Note,
+}
+
+impl std::fmt::Display for AnnotationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnnotationKind::Highlight => write!(f, "highlight"),
+            AnnotationKind::Underline => write!(f, "underline"),
+            AnnotationKind::Strikeout => write!(f, "strikeout"),
+            AnnotationKind::Note => write!(f, "note"),
+        }
+    }
+}
+
+impl std::str::FromStr for AnnotationKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "highlight" => Ok(AnnotationKind::Highlight),
+            "underline" => Ok(AnnotationKind::Underline),
+            "strikeout" => Ok(AnnotationKind::Strikeout),
+            "note" => Ok(AnnotationKind::Note),
+            _ => Err(format!("Unknown annotation kind: {}", s)),
+        }
+    }
+}
+
+/// Area covered by an annotation, as fractions of the page size
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AnnotationRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// A stored annotation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Annotation {
+    pub id: i64,
+    pub drive_file_id: String,
+    /// 1-based page number
+    pub page: i64,
+    pub kind: AnnotationKind,
+    /// One rect per line for text spanning several lines
+    pub rects: Vec<AnnotationRect>,
+    /// `#RRGGBB` or `#RRGGBBAA`
+    pub color: String,
+    pub note: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// Fields of a new annotation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewAnnotation {
+    pub page: i64,
+    pub kind: AnnotationKind,
+    pub rects: Vec<AnnotationRect>,
+    pub color: String,
+    pub note: Option<String>,
+}
+
+/// Annotations of one document, as exported
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotationExport {
+    pub version: u32,
+    pub drive_file_id: String,
+    pub exported_at: i64,
+    pub annotations: Vec<Annotation>,
+}
+
+// ============================================================================
+// Validation
+// ============================================================================
+
+fn is_valid_color(color: &str) -> bool {
+    color
+        .strip_prefix('#')
+        .is_some_and(|hex| matches!(hex.len(), 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+fn is_valid_rect(rect: &AnnotationRect) -> bool {
+    let in_page = |start: f64, size: f64| {
+        (0.0..=1.0).contains(&start) && size >= 0.0 && start + size <= 1.0 + f64::EPSILON
+    };
+    in_page(rect.x, rect.width) && in_page(rect.y, rect.height)
+}
+
+fn validate(page: i64, rects: &[AnnotationRect], color: &str) -> Result<(), PedaruError> {
+    let problem = if page < 1 {
+        format!("page {} is out of range", page)
+    } else if rects.is_empty() {
+        "no area given".to_string()
+    } else if !rects.iter().all(is_valid_rect) {
+        "area lies outside the page".to_string()
+    } else if !is_valid_color(color) {
+        format!("'{}' is not a #RRGGBB color", color)
+    } else {
+        return Ok(());
+    };
+    Err(PdfError::InvalidAnnotation(problem).into())
+}
+
+/// Blank notes are stored as no note
+fn normalize_note(note: Option<&str>) -> Option<&str> {
+    note.map(str::trim).filter(|note| !note.is_empty())
+}
+
+fn rects_to_json(rects: &[AnnotationRect]) -> Result<String, PedaruError> {
+    serde_json::to_string(rects).map_err(|e| DatabaseError::QueryFailed(e.to_string()).into())
+}
+
+// ============================================================================
+// Public API
+// ============================================================================
+
+/// Store a new annotation on a cloud document
+pub fn add_annotation(
+    app: &AppHandle,
+    drive_file_id: &str,
+    annotation: &NewAnnotation,
+) -> Result<Annotation, PedaruError> {
+    add_annotation_on(&open_db(app)?, drive_file_id, annotation)
+}
+
+fn add_annotation_on(
+    conn: &rusqlite::Connection,
+    drive_file_id: &str,
+    annotation: &NewAnnotation,
+) -> Result<Annotation, PedaruError> {
+    validate(annotation.page, &annotation.rects, &annotation.color)?;
+    conn.execute(
+        "INSERT INTO annotations
+           (drive_file_id, page, kind, rects, color, note, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)",
+        rusqlite::params![
+            drive_file_id,
+            annotation.page,
+            annotation.kind.to_string(),
+            rects_to_json(&annotation.rects)?,
+            annotation.color,
+            normalize_note(annotation.note.as_deref()),
+            now_timestamp()
+        ],
+    )
+    .db_err()?;
+    get_annotation_on(conn, conn.last_insert_rowid())?.ok_or(DatabaseError::NotFound.into())
+}
+
+/// Get all annotations on a cloud document, by page
+pub fn get_annotations(
+    app: &AppHandle,
+    drive_file_id: &str,
+) -> Result<Vec<Annotation>, PedaruError> {
+    get_annotations_on(&open_db(app)?, drive_file_id)
+}
+
+fn get_annotations_on(
+    conn: &rusqlite::Connection,
+    drive_file_id: &str,
+) -> Result<Vec<Annotation>, PedaruError> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "{SELECT_ANNOTATION} WHERE drive_file_id = ?1 ORDER BY page, created_at, id"
+        ))
+        .db_err()?;
+    let annotations = stmt
+        .query_map([drive_file_id], row_to_annotation)
+        .db_err()?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(annotations)
+}
+
+/// Change an annotation's area, color and note
+pub fn update_annotation(
+    app: &AppHandle,
+    id: i64,
+    rects: &[AnnotationRect],
+    color: &str,
+    note: Option<&str>,
+) -> Result<Annotation, PedaruError> {
+    let conn = open_db(app)?;
+    let page: i64 = conn
+        .query_row("SELECT page FROM annotations WHERE id = ?1", [id], |row| {
+            row.get(0)
+        })
+        .optional()
+        .db_err()?
+        .ok_or(DatabaseError::NotFound)?;
+    validate(page, rects, color)?;
+
+    conn.execute(
+        "UPDATE annotations SET rects = ?1, color = ?2, note = ?3, updated_at = ?4 WHERE id = ?5",
+        rusqlite::params![
+            rects_to_json(rects)?,
+            color,
+            normalize_note(note),
+            now_timestamp(),
+            id
+        ],
+    )
+    .db_err()?;
+    get_annotation_on(&conn, id)?.ok_or(DatabaseError::NotFound.into())
+}
+
+/// Delete an annotation, returning whether it existed
+pub fn delete_annotation(app: &AppHandle, id: i64) -> Result<bool, PedaruError> {
+    let conn = open_db(app)?;
+    let deleted = conn
+        .execute("DELETE FROM annotations WHERE id = ?1", [id])
+        .db_err()?;
+    Ok(deleted > 0)
+}
+
+/// Export a cloud document's annotations as JSON
+pub fn export_annotations(app: &AppHandle, drive_file_id: &str) -> Result<String, PedaruError> {
+    export_annotations_on(&open_db(app)?, drive_file_id)
+}
+
+fn export_annotations_on(
+    conn: &rusqlite::Connection,
+    drive_file_id: &str,
+) -> Result<String, PedaruError> {
+    let export = AnnotationExport {
+        version: EXPORT_FORMAT_VERSION,
+        drive_file_id: drive_file_id.to_string(),
+        exported_at: now_timestamp(),
+        annotations: get_annotations_on(conn, drive_file_id)?,
+    };
+    serde_json::to_string_pretty(&export)
+        .map_err(|e| DatabaseError::QueryFailed(e.to_string()).into())
+}
+
+// ============================================================================
+// PDF Export
+// ============================================================================
+
+/// Bake a cloud document's annotations into a copy of its local PDF
+///
+/// Returns the path of the annotated copy. Annotations on pages the
+/// document doesn't have are skipped.
+pub fn export_annotated_pdf(app: &AppHandle, drive_file_id: &str) -> Result<String, PedaruError> {
+    let conn = open_db(app)?;
+    let local_path: Option<String> = conn
+        .query_row(
+            "SELECT local_path FROM bookshelf_cloud
+             WHERE drive_file_id = ?1 AND download_status = 'completed'",
+            [drive_file_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .db_err()?
+        .flatten();
+    let local_path =
+        local_path.ok_or_else(|| GoogleDriveError::FileNotFound(drive_file_id.to_string()))?;
+    let annotations = get_annotations_on(&conn, drive_file_id)?;
+    drop(conn);
+
+    let mut doc = Document::load(&local_path).map_err(|source| PdfError::LoadFailed {
+        path: local_path.clone(),
+        source,
+    })?;
+    let skipped = bake_annotations(&mut doc, &annotations);
+    if skipped > 0 {
+        log::info!(
+            "Skipped {} annotations on missing pages of {}",
+            skipped,
+            drive_file_id
+        );
+    }
+
+    let exports_dir = bookshelf::get_downloads_dir(app)?.with_file_name(EXPORTS_DIR_NAME);
+    std::fs::create_dir_all(&exports_dir).map_err(|source| IoError::CreateDirFailed {
+        path: exports_dir.display().to_string(),
+        source,
+    })?;
+    let stem = PathBuf::from(&local_path).file_stem().map_or_else(
+        || drive_file_id.to_string(),
+        |s| s.to_string_lossy().into_owned(),
+    );
+    let export_path = exports_dir.join(format!("{} (annotated).pdf", stem));
+    doc.save(&export_path)
+        .map_err(|source| PdfError::SaveFailed {
+            path: export_path.display().to_string(),
+            source,
+        })?;
+
+    log::info!(
+        "Exported {} annotations to {}",
+        annotations.len() - skipped,
+        export_path.display()
+    );
+    Ok(export_path.display().to_string())
+}
+
+/// Visible area of a page in user space, and how the viewer rotates it
+#[derive(Debug, Clone, Copy)]
+struct PageBox {
+    llx: f64,
+    lly: f64,
+    urx: f64,
+    ury: f64,
+    /// Clockwise rotation in degrees (0, 90, 180 or 270)
+    rotation: i64,
+}
+
+impl PageBox {
+    /// Convert a point given as fractions of the displayed page (origin at
+    /// the top-left) to PDF user space (origin at the bottom-left)
+    fn to_user_space(self, u: f64, v: f64) -> (f64, f64) {
+        let width = self.urx - self.llx;
+        let height = self.ury - self.lly;
+        match self.rotation {
+            90 => (self.llx + v * width, self.lly + u * height),
+            180 => (self.urx - u * width, self.lly + v * height),
+            270 => (self.urx - v * width, self.ury - u * height),
+            _ => (self.llx + u * width, self.ury - v * height),
+        }
+    }
+
+    /// Corners of a rect in QuadPoints order: upper-left, upper-right,
+    /// lower-left, lower-right (as displayed)
+    fn quad(self, rect: &AnnotationRect) -> [(f64, f64); 4] {
+        let (left, top) = (rect.x, rect.y);
+        let (right, bottom) = (rect.x + rect.width, rect.y + rect.height);
+        [
+            self.to_user_space(left, top),
+            self.to_user_space(right, top),
+            self.to_user_space(left, bottom),
+            self.to_user_space(right, bottom),
+        ]
+    }
+}
+
+/// Look up a page attribute, following the page tree for inherited ones
+fn inherited_attribute<'a>(doc: &'a Document, page_id: ObjectId, key: &[u8]) -> Option<&'a Object> {
+    let mut node_id = page_id;
+    for _ in 0..MAX_PAGE_TREE_DEPTH {
+        let node = doc.get_dictionary(node_id).ok()?;
+        if let Ok(value) = node.get(key) {
+            return doc.dereference(value).ok().map(|(_, value)| value);
+        }
+        node_id = node.get(b"Parent").and_then(Object::as_reference).ok()?;
+    }
+    None
+}
+
+fn page_box(doc: &Document, page_id: ObjectId) -> PageBox {
+    let rect = |key: &[u8]| -> Option<[f64; 4]> {
+        let values = inherited_attribute(doc, page_id, key)?.as_array().ok()?;
+        let values: Vec<f64> = values
+            .iter()
+            .map(|v| v.as_float().map(f64::from))
+            .collect::<Result<_, _>>()
+            .ok()?;
+        let [x0, y0, x1, y1] = values[..] else {
+            return None;
+        };
+        Some([x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)])
+    };
+    let [llx, lly, urx, ury] = rect(b"CropBox")
+        .or_else(|| rect(b"MediaBox"))
+        .unwrap_or(DEFAULT_PAGE_BOX);
+    let rotation = inherited_attribute(doc, page_id, b"Rotate")
+        .and_then(|r| r.as_i64().ok())
+        .map_or(0, |r| r.rem_euclid(360) / 90 * 90);
+    PageBox {
+        llx,
+        lly,
+        urx,
+        ury,
+        rotation,
+    }
+}
+
+/// Parse `#RRGGBB` or `#RRGGBBAA` into RGB components and opacity (0-1)
+fn parse_color(color: &str) -> Option<([f32; 3], f32)> {
+    let hex = color.strip_prefix('#')?;
+    let channel = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|c| u8::from_str_radix(c, 16).ok())
+            .map(|c| f32::from(c) / 255.0)
+    };
+    let rgb = [channel(0)?, channel(2)?, channel(4)?];
+    let alpha = if hex.len() == 8 { channel(6)? } else { 1.0 };
+    Some((rgb, alpha))
+}
+
+/// Encode a PDF text string (PDFDocEncoding for ASCII, UTF-16BE otherwise)
+fn text_string(text: &str) -> Object {
+    if text.is_ascii() {
+        return Object::string_literal(text);
+    }
+    let mut bytes = vec![0xFE, 0xFF];
+    bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+    Object::String(bytes, StringFormat::Hexadecimal)
+}
+
+fn annotation_dictionary(
+    annotation: &Annotation,
+    page_id: ObjectId,
+    page_box: PageBox,
+) -> lopdf::Dictionary {
+    let points: Vec<(f64, f64)> = match annotation.kind {
+        AnnotationKind::Note => {
+            // Icon anchored at the top-left of the first rect
+            let (x, y) = annotation
+                .rects
+                .first()
+                .map_or((0.0, 0.0), |rect| page_box.to_user_space(rect.x, rect.y));
+            vec![(x, y - NOTE_ICON_SIZE), (x + NOTE_ICON_SIZE, y)]
+        }
+        _ => annotation
+            .rects
+            .iter()
+            .flat_map(|rect| page_box.quad(rect))
+            .collect(),
+    };
+    let bound = |pick: fn(&(f64, f64)) -> f64, max: bool| {
+        let values = points.iter().map(pick);
+        if max {
+            values.fold(f64::MIN, f64::max)
+        } else {
+            values.fold(f64::MAX, f64::min)
+        }
+    };
+    let rect: Vec<Object> = [
+        bound(|p| p.0, false),
+        bound(|p| p.1, false),
+        bound(|p| p.0, true),
+        bound(|p| p.1, true),
+    ]
+    .into_iter()
+    .map(Object::from)
+    .collect();
+
+    let subtype = match annotation.kind {
+        AnnotationKind::Highlight => "Highlight",
+        AnnotationKind::Underline => "Underline",
+        AnnotationKind::Strikeout => "StrikeOut",
+        AnnotationKind::Note => "Text",
+    };
+    let (rgb, alpha) = parse_color(&annotation.color).unwrap_or(([1.0, 0.92, 0.23], 1.0));
+
+    let mut dict = dictionary! {
+        "Type" => "Annot",
+        "Subtype" => subtype,
+        "Rect" => rect,
+        "C" => rgb.into_iter().map(Object::from).collect::<Vec<_>>(),
+        "CA" => alpha,
+        // Print flag
+        "F" => 4,
+        "P" => page_id,
+    };
+    if annotation.kind == AnnotationKind::Note {
+        dict.set("Name", "Comment");
+    } else {
+        let quad_points: Vec<Object> = points
+            .iter()
+            .flat_map(|&(x, y)| [Object::from(x), Object::from(y)])
+            .collect();
+        dict.set("QuadPoints", quad_points);
+    }
+    if let Some(note) = &annotation.note {
+        dict.set("Contents", text_string(note));
+    }
+    dict
+}
+
+/// Add annotations to the pages of `doc`, returning how many were skipped
+/// because their page doesn't exist
+fn bake_annotations(doc: &mut Document, annotations: &[Annotation]) -> usize {
+    let pages = doc.get_pages();
+    let mut new_annots: BTreeMap<ObjectId, Vec<Object>> = BTreeMap::new();
+    let mut skipped = 0;
+    for annotation in annotations {
+        let Some(&page_id) = u32::try_from(annotation.page)
+            .ok()
+            .and_then(|page| pages.get(&page))
+        else {
+            skipped += 1;
+            continue;
+        };
+        let dict = annotation_dictionary(annotation, page_id, page_box(doc, page_id));
+        let annot_id = doc.add_object(dict);
+        new_annots.entry(page_id).or_default().push(annot_id.into());
+    }
+
+    for (page_id, annots) in new_annots {
+        let existing = doc
+            .get_dictionary(page_id)
+            .ok()
+            .and_then(|page| page.get(b"Annots").ok())
+            .cloned();
+        match existing {
+            Some(Object::Reference(array_id)) => {
+                if let Ok(Object::Array(array)) = doc.get_object_mut(array_id) {
+                    array.extend(annots);
+                    continue;
+                }
+            }
+            Some(Object::Array(mut array)) => {
+                array.extend(annots);
+                if let Ok(page) = doc.get_dictionary_mut(page_id) {
+                    page.set("Annots", array);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        if let Ok(page) = doc.get_dictionary_mut(page_id) {
+            page.set("Annots", annots);
+        }
+    }
+    skipped
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+const SELECT_ANNOTATION: &str = "SELECT id, drive_file_id, page, kind, rects, color, note,
+                                        created_at, updated_at
+                                 FROM annotations";
+
+fn get_annotation_on(
+    conn: &rusqlite::Connection,
+    id: i64,
+) -> Result<Option<Annotation>, PedaruError> {
+    conn.query_row(
+        &format!("{SELECT_ANNOTATION} WHERE id = ?1"),
+        [id],
+        row_to_annotation,
+    )
+    .optional()
+    .db_err()
+}
+
+fn row_to_annotation(row: &rusqlite::Row) -> rusqlite::Result<Annotation> {
+    let invalid = |index: usize, message: String| {
+        rusqlite::Error::FromSqlConversionFailure(
+            index,
+            rusqlite::types::Type::Text,
+            message.into(),
+        )
+    };
+    let kind: String = row.get(3)?;
+    let rects: String = row.get(4)?;
+    Ok(Annotation {
+        id: row.get(0)?,
+        drive_file_id: row.get(1)?,
+        page: row.get(2)?,
+        kind: kind.parse().map_err(|e| invalid(3, e))?,
+        rects: serde_json::from_str(&rects).map_err(|e| invalid(4, e.to_string()))?,
+        color: row.get(5)?,
+        note: row.get(6)?,
+        created_at: row.get(7)?,
+        updated_at: row.get(8)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migrated_db() -> rusqlite::Connection {
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::migrations::apply_migrations(&mut conn, crate::migrations::MIGRATIONS).unwrap();
+        conn
+    }
+
+    fn highlight(page: i64) -> NewAnnotation {
+        NewAnnotation {
+            page,
+            kind: AnnotationKind::Highlight,
+            rects: vec![AnnotationRect {
+                x: 0.1,
+                y: 0.2,
+                width: 0.5,
+                height: 0.03,
+            }],
+            color: "#ffeb3b".to_string(),
+            note: Some("  ".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_annotations_round_trip_by_page() {
+        let conn = migrated_db();
+        add_annotation_on(&conn, "doc", &highlight(3)).unwrap();
+        let first = add_annotation_on(&conn, "doc", &highlight(1)).unwrap();
+        add_annotation_on(&conn, "other", &highlight(1)).unwrap();
+        assert_eq!(first.note, None);
+
+        let annotations = get_annotations_on(&conn, "doc").unwrap();
+        let pages: Vec<_> = annotations.iter().map(|a| a.page).collect();
+        assert_eq!(pages, [1, 3]);
+        assert_eq!(annotations[0].rects, highlight(1).rects);
+        assert_eq!(annotations[0].kind, AnnotationKind::Highlight);
+
+        let export: AnnotationExport =
+            serde_json::from_str(&export_annotations_on(&conn, "doc").unwrap()).unwrap();
+        assert_eq!(export.annotations.len(), 2);
+    }
+
+    /// Two pages of 200x100 points; the second is rotated 90 degrees
+    fn two_page_document() -> Document {
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let first = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+        });
+        let second = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Rotate" => 90,
+            "Annots" => vec![],
+        });
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![first.into(), second.into()],
+                "Count" => 2,
+                "MediaBox" => vec![0.into(), 0.into(), 200.into(), 100.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+        doc
+    }
+
+    fn stored(page: i64) -> Annotation {
+        let new = highlight(page);
+        Annotation {
+            id: page,
+            drive_file_id: "doc".to_string(),
+            page,
+            kind: new.kind,
+            rects: new.rects,
+            color: "#ff000080".to_string(),
+            note: Some("Résumé".to_string()),
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    fn annotation_rect(doc: &Document, page: u32) -> Vec<f32> {
+        let page_id = doc.get_pages()[&page];
+        let annots = doc.get_page_annotations(page_id).unwrap();
+        assert_eq!(annots.len(), 1);
+        annots[0]
+            .get(b"Rect")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v.as_float().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_bake_annotations_converts_to_user_space() {
+        let mut doc = two_page_document();
+        let skipped = bake_annotations(&mut doc, &[stored(1), stored(2), stored(5)]);
+        assert_eq!(skipped, 1);
+
+        // x 0.1-0.6, y 0.2-0.23 from the top of an unrotated 200x100 page
+        let rect = annotation_rect(&doc, 1);
+        let expected = [20.0, 77.0, 120.0, 80.0];
+        assert!(rect.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-3));
+
+        // Rotated 90 degrees: displayed x runs up the page, y to the right
+        let rect = annotation_rect(&doc, 2);
+        let expected = [40.0, 10.0, 46.0, 60.0];
+        assert!(rect.iter().zip(expected).all(|(a, b)| (a - b).abs() < 1e-3));
+    }
+
+    #[test]
+    fn test_parse_color_with_alpha() {
+        assert_eq!(parse_color("#ff0000"), Some(([1.0, 0.0, 0.0], 1.0)));
+        assert_eq!(parse_color("#00ff0000"), Some(([0.0, 1.0, 0.0], 0.0)));
+        assert_eq!(parse_color("red"), None);
+    }
+
+    #[test]
+    fn test_invalid_annotations_are_rejected() {
+        let conn = migrated_db();
+        let mut outside = highlight(1);
+        outside.rects[0].x = 0.8;
+        let mut bad_color = highlight(1);
+        bad_color.color = "yellow".to_string();
+
+        for annotation in [highlight(0), outside, bad_color] {
+            let err = add_annotation_on(&conn, "doc", &annotation).unwrap_err();
+            assert_eq!(err.code(), "pdf.invalid_annotation");
+        }
+    }
+}
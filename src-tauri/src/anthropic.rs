@@ -0,0 +1,190 @@
+//! Anthropic API client for translation
+//!
+//! This module provides the Anthropic (Messages API) backend for `translation`.
+
+use reqwest::{RequestBuilder, Response};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::{PedaruError, TranslationError};
+use crate::translation::{
+    GenerationParams, TranslationProvider, http_client, send_json_request, send_request,
+};
+
+/// Anthropic Messages endpoint
+const ANTHROPIC_MESSAGES_URL: &str = "https://api.anthropic.com/v1/messages";
+
+/// API version sent in the `anthropic-version` header
+const ANTHROPIC_API_VERSION: &str = "2023-06-01";
+
+/// Default upper bound on generated tokens (required by the Messages API)
+const DEFAULT_MAX_OUTPUT_TOKENS: u32 = 4096;
+
+// ============================================================================
+// Request Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    system: &'a str,
+    messages: Vec<Message<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Message<'a> {
+    role: &'static str,
+    content: &'a str,
+}
+
+// ============================================================================
+// API Functions
+// ============================================================================
+
+/// Anthropic backend for translation and explanation
+pub struct AnthropicProvider {
+    api_key: String,
+    model: String,
+    params: GenerationParams,
+}
+
+impl AnthropicProvider {
+    pub fn new(api_key: &str, model: &str, params: GenerationParams) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            params,
+        }
+    }
+}
+
+impl AnthropicProvider {
+    /// Build a Messages API request, optionally streamed
+    fn build_request(
+        &self,
+        prompt: &str,
+        system_instruction: &str,
+        stream: bool,
+    ) -> Result<RequestBuilder, PedaruError> {
+        let provider = self.name();
+        if self.api_key.is_empty() {
+            return Err(TranslationError::ApiKeyMissing { provider }.into());
+        }
+
+        let request = MessagesRequest {
+            model: &self.model,
+            max_tokens: self
+                .params
+                .max_output_tokens
+                .unwrap_or(DEFAULT_MAX_OUTPUT_TOKENS),
+            system: system_instruction,
+            messages: vec![Message {
+                role: "user",
+                content: prompt,
+            }],
+            temperature: self.params.temperature,
+            stream,
+        };
+
+        Ok(http_client(provider, stream)?
+            .post(ANTHROPIC_MESSAGES_URL)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_API_VERSION)
+            .json(&request))
+    }
+}
+
+impl TranslationProvider for AnthropicProvider {
+    fn name(&self) -> &'static str {
+        "Anthropic"
+    }
+
+    async fn generate(
+        &self,
+        prompt: &str,
+        system_instruction: &str,
+    ) -> Result<String, PedaruError> {
+        let provider = self.name();
+        let request = self.build_request(prompt, system_instruction, false)?;
+        let body = send_json_request(provider, request).await?;
+
+        extract_text(&body).ok_or_else(|| {
+            TranslationError::InvalidResponse {
+                provider,
+                message: "No text in response".to_string(),
+            }
+            .into()
+        })
+    }
+
+    async fn start_stream(
+        &self,
+        prompt: &str,
+        system_instruction: &str,
+    ) -> Result<Response, PedaruError> {
+        let request = self.build_request(prompt, system_instruction, true)?;
+        send_request(self.name(), request).await
+    }
+
+    /// Only `content_block_delta` events carry text
+    fn stream_delta(&self, event: &Value) -> Option<String> {
+        if event.get("type").and_then(Value::as_str) != Some("content_block_delta") {
+            return None;
+        }
+        event
+            .get("delta")?
+            .get("text")?
+            .as_str()
+            .map(str::to_string)
+    }
+}
+
+/// Concatenate the text blocks of a Messages API response
+fn extract_text(body: &Value) -> Option<String> {
+    let text: String = body
+        .get("content")?
+        .as_array()?
+        .iter()
+        .filter(|block| block.get("type").and_then(Value::as_str) == Some("text"))
+        .filter_map(|block| block.get("text").and_then(Value::as_str))
+        .collect();
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_text_joins_text_blocks() {
+        let body = serde_json::json!({
+            "content": [
+                { "type": "text", "text": "{\"translation\": " },
+                { "type": "text", "text": "\"x\"}" }
+            ]
+        });
+        assert_eq!(
+            extract_text(&body).as_deref(),
+            Some("{\"translation\": \"x\"}")
+        );
+        assert_eq!(extract_text(&serde_json::json!({ "content": [] })), None);
+    }
+
+    #[test]
+    fn test_stream_delta_ignores_non_text_events() {
+        let provider = AnthropicProvider::new("key", "m", GenerationParams::default());
+        let delta = serde_json::json!({
+            "type": "content_block_delta",
+            "index": 0,
+            "delta": { "type": "text_delta", "text": "Hi" }
+        });
+        assert_eq!(provider.stream_delta(&delta).as_deref(), Some("Hi"));
+        let start = serde_json::json!({ "type": "message_start", "message": {} });
+        assert_eq!(provider.stream_delta(&start), None);
+    }
+}
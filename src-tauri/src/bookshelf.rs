@@ -7,14 +7,23 @@
 //! - `bookshelf_cloud`: PDFs synced from Google Drive
 //! - `bookshelf_local`: PDFs imported from local filesystem
 
+use base64::{Engine as _, engine::general_purpose};
+use rusqlite::OptionalExtension;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Manager};
 
-use crate::db::{ToDbError, now_timestamp, open_db};
-use crate::error::{DatabaseError, IoError, PedaruError};
+use crate::db::{ToDbError, now_timestamp, open_db, retry_busy};
+use crate::error::{
+    ConfigError, DatabaseError, GoogleDriveError, IoError, NetworkError, PdfError, PedaruError,
+};
+use crate::google_drive::DriveFile;
+use crate::network;
+use crate::settings;
+use crate::thumbnail;
 
 // ============================================================================
 // Types - Cloud Items (Google Drive)
@@ -26,6 +35,7 @@ use crate::error::{DatabaseError, IoError, PedaruError};
 pub enum DownloadStatus {
     #[default]
     Pending,
+    Queued,
     Downloading,
     Completed,
     Error,
@@ -35,6 +45,7 @@ impl std::fmt::Display for DownloadStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             DownloadStatus::Pending => write!(f, "pending"),
+            DownloadStatus::Queued => write!(f, "queued"),
             DownloadStatus::Downloading => write!(f, "downloading"),
             DownloadStatus::Completed => write!(f, "completed"),
             DownloadStatus::Error => write!(f, "error"),
@@ -47,6 +58,7 @@ impl std::str::FromStr for DownloadStatus {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "pending" => Ok(DownloadStatus::Pending),
+            "queued" => Ok(DownloadStatus::Queued),
             "downloading" => Ok(DownloadStatus::Downloading),
             "completed" => Ok(DownloadStatus::Completed),
             "error" => Ok(DownloadStatus::Error),
@@ -55,23 +67,146 @@ impl std::str::FromStr for DownloadStatus {
     }
 }
 
+/// Why the last download of a cloud item stopped
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadFailure {
+    /// Connection lost, timed out or Drive unavailable
+    Network,
+    /// Not enough disk space
+    DiskFull,
+    /// Stopped by the user (the item goes back to pending)
+    Cancelled,
+    /// The file's size or checksum didn't match Drive's
+    Integrity,
+    /// The file is gone from Drive or can't be accessed
+    NotFound,
+    /// Anything else (e.g. a file too large to export)
+    Other,
+}
+
+impl DownloadFailure {
+    /// Classify the error a download failed with
+    pub fn of(error: &PedaruError) -> Self {
+        match error {
+            PedaruError::Network(_) => DownloadFailure::Network,
+            PedaruError::GoogleDrive(e) => match e {
+                GoogleDriveError::DownloadCancelled(_) => DownloadFailure::Cancelled,
+                GoogleDriveError::InsufficientDiskSpace { .. } => DownloadFailure::DiskFull,
+                GoogleDriveError::IntegrityCheckFailed(_) => DownloadFailure::Integrity,
+                GoogleDriveError::FileNotFound(_) => DownloadFailure::NotFound,
+                GoogleDriveError::DownloadFailed(_) | GoogleDriveError::RateLimited { .. } => {
+                    DownloadFailure::Network
+                }
+                _ if e.is_retryable() => DownloadFailure::Network,
+                _ => DownloadFailure::Other,
+            },
+            PedaruError::Io(
+                IoError::WriteFailed { source, .. }
+                | IoError::ReadFailed { source, .. }
+                | IoError::CreateDirFailed { source, .. },
+            ) if source.kind() == std::io::ErrorKind::StorageFull => DownloadFailure::DiskFull,
+            _ => DownloadFailure::Other,
+        }
+    }
+
+    /// Whether downloading again later may succeed
+    ///
+    /// Requested downloads that failed this way are resumed on the next start.
+    pub fn is_retryable(self) -> bool {
+        matches!(self, DownloadFailure::Network | DownloadFailure::Integrity)
+    }
+}
+
+impl std::fmt::Display for DownloadFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DownloadFailure::Network => write!(f, "network"),
+            DownloadFailure::DiskFull => write!(f, "disk_full"),
+            DownloadFailure::Cancelled => write!(f, "cancelled"),
+            DownloadFailure::Integrity => write!(f, "integrity"),
+            DownloadFailure::NotFound => write!(f, "not_found"),
+            DownloadFailure::Other => write!(f, "other"),
+        }
+    }
+}
+
+impl std::str::FromStr for DownloadFailure {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "network" => Ok(DownloadFailure::Network),
+            "disk_full" => Ok(DownloadFailure::DiskFull),
+            "cancelled" => Ok(DownloadFailure::Cancelled),
+            "integrity" => Ok(DownloadFailure::Integrity),
+            "not_found" => Ok(DownloadFailure::NotFound),
+            "other" => Ok(DownloadFailure::Other),
+            _ => Err(format!("Unknown download failure: {}", s)),
+        }
+    }
+}
+
 /// Cloud bookshelf item (from Google Drive)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CloudItem {
     pub id: i64,
     pub drive_file_id: String,
+    /// Google account the item was synced or imported with
+    pub account_id: i64,
     pub drive_folder_id: String,
+    /// Watched folder this item was synced through (differs from
+    /// `drive_folder_id` for files found in subfolders)
+    pub root_folder_id: Option<String>,
+    /// Subfolder path relative to the watched folder
+    pub folder_path: Option<String>,
     pub file_name: String,
     pub file_size: Option<i64>,
+    /// Modification time reported by Drive (RFC 3339)
+    pub drive_modified_time: Option<String>,
+    /// Base64 PNG, only for thumbnails not yet moved out of the database
     pub thumbnail_data: Option<String>,
+    /// Absolute path of the thumbnail PNG, for the frontend to load directly
+    pub thumbnail_path: Option<String>,
     pub local_path: Option<String>,
     pub download_status: DownloadStatus,
     pub download_progress: f64,
+    /// Why the last download failed, when `download_status` is `Error`
+    pub download_error: Option<String>,
+    /// Kind of the last download failure (also set after a cancel)
+    pub download_failure: Option<DownloadFailure>,
+    /// Automatic retries used by the current or last download
+    pub retry_count: i64,
     pub pdf_title: Option<String>,
     pub pdf_author: Option<String>,
+    pub page_count: Option<i64>,
+    /// Name set by the user, overriding `pdf_title` and `file_name`
+    pub custom_name: Option<String>,
+    /// Name to show (see `display_name`)
+    pub display_name: String,
     pub is_favorite: bool,
     pub last_opened: Option<i64>,
+    /// Last page read, if the document has been opened before
+    pub last_read_page: Option<i64>,
+    /// Tag names, sorted alphabetically
+    pub tags: Vec<String>,
+    /// When the item was added to the bookshelf (Unix timestamp)
+    pub created_at: i64,
+    /// When the item was last changed (Unix timestamp)
+    pub updated_at: i64,
+}
+
+/// Name to show for an item: custom name, then PDF title, then file name
+///
+/// Blank values are skipped.
+pub fn display_name(custom_name: Option<&str>, pdf_title: Option<&str>, file_name: &str) -> String {
+    [custom_name, pdf_title]
+        .into_iter()
+        .flatten()
+        .map(str::trim)
+        .find(|name| !name.is_empty())
+        .unwrap_or(file_name)
+        .to_string()
 }
 
 // ============================================================================
@@ -92,6 +227,10 @@ pub struct LocalItem {
     pub pdf_author: Option<String>,
     pub is_favorite: bool,
     pub last_opened: Option<i64>,
+    /// When the file was imported (Unix timestamp)
+    pub created_at: i64,
+    /// When the item was last changed (Unix timestamp)
+    pub updated_at: i64,
 }
 
 // ============================================================================
@@ -103,9 +242,17 @@ pub struct LocalItem {
 #[serde(rename_all = "camelCase")]
 pub struct StoredFolder {
     pub folder_id: String,
+    /// Google account the folder belongs to
+    pub account_id: i64,
     pub folder_name: String,
     pub is_active: bool,
     pub last_synced: Option<i64>,
+    /// Whether subfolders are synced too
+    pub recursive: bool,
+    /// Whether the background scheduler syncs this folder
+    pub auto_sync: bool,
+    /// Shared drive the folder is on (None for My Drive or not yet known)
+    pub drive_id: Option<String>,
 }
 
 /// Download progress event (for cloud items)
@@ -116,15 +263,158 @@ pub struct DownloadProgress {
     pub progress: f64,
     pub downloaded_bytes: u64,
     pub total_bytes: u64,
+    /// Download speed averaged over the last few seconds
+    pub bytes_per_second: f64,
+    /// Estimated time remaining (None when the size or speed is unknown)
+    pub eta_seconds: Option<u64>,
+    /// `Completed` or `Pending` (cancelled) on the last event of a download
+    pub status: DownloadStatus,
+}
+
+/// A file added, updated or removed by a sync
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncedFile {
+    pub drive_file_id: String,
+    pub file_name: String,
 }
 
 /// Sync result (for cloud items)
+///
+/// The counts are the lengths of the lists, kept for older callers.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SyncResult {
     pub new_files: i32,
     pub updated_files: i32,
     pub removed_files: i32,
+    pub added: Vec<SyncedFile>,
+    pub updated: Vec<SyncedFile>,
+    pub removed: Vec<SyncedFile>,
+    /// Whether every folder was synced from Drive's change log rather than
+    /// listed in full
+    pub incremental: bool,
+    /// Files left out because their type isn't synced
+    pub skipped_files: i32,
+}
+
+impl SyncResult {
+    pub fn new(
+        added: Vec<SyncedFile>,
+        updated: Vec<SyncedFile>,
+        removed: Vec<SyncedFile>,
+        incremental: bool,
+        skipped: usize,
+    ) -> Self {
+        Self {
+            new_files: added.len() as i32,
+            updated_files: updated.len() as i32,
+            removed_files: removed.len() as i32,
+            added,
+            updated,
+            removed,
+            incremental,
+            skipped_files: skipped as i32,
+        }
+    }
+}
+
+/// A Drive file to upsert, with the folder it was found in
+#[derive(Debug, Clone, Copy)]
+pub struct DriveFileInput<'a> {
+    pub file: &'a DriveFile,
+    /// Folder directly containing the file
+    pub folder_id: &'a str,
+    /// Subfolder path relative to the watched folder (None at the top level)
+    pub folder_path: Option<&'a str>,
+}
+
+/// What `upsert_cloud_item` changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpsertOutcome {
+    Added,
+    /// The name, size, modified time or location changed on Drive
+    Updated,
+    Unchanged,
+}
+
+/// Saved reading position for a cloud document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingPosition {
+    pub drive_file_id: String,
+    pub page: i64,
+    pub scroll_offset: f64,
+    pub updated_at: i64,
+}
+
+/// Reading statistics for one cloud document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentReadingStats {
+    pub drive_file_id: String,
+    pub display_name: String,
+    /// Furthest page reached
+    pub pages_read: i64,
+    pub page_count: Option<i64>,
+    /// Whether the last page was reached
+    pub completed: bool,
+    pub time_spent_secs: i64,
+    pub session_count: i64,
+    pub last_read_at: Option<i64>,
+}
+
+/// Reading statistics across the bookshelf
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingStats {
+    pub total_pages_read: i64,
+    pub documents_completed: i64,
+    /// Time spent in recorded reading sessions
+    pub total_time_secs: i64,
+    /// Documents with progress or sessions, most recently read first
+    pub documents: Vec<DocumentReadingStats>,
+}
+
+/// Outcome of deleting one item's local copy in a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteResult {
+    pub drive_file_id: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// Result of freeing space in the downloads directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupResult {
+    pub files_removed: i32,
+    pub bytes_freed: u64,
+}
+
+/// Disk space used by the downloads of one watched folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderStorageUsage {
+    pub folder_id: String,
+    pub folder_name: Option<String>,
+    pub total_bytes: u64,
+    pub item_count: i64,
+}
+
+/// Disk space used by downloaded cloud items
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageUsage {
+    pub total_bytes: u64,
+    /// Completed downloads, including ones sharing a file
+    pub item_count: i64,
+    /// Downloads whose file is gone (`verify_cloud_files` resets them)
+    pub missing_count: i64,
+    pub cap_bytes: Option<u64>,
+    /// Largest folders first
+    pub folders: Vec<FolderStorageUsage>,
 }
 
 /// Result of importing local files
@@ -164,7 +454,12 @@ pub fn unregister_download(file_id: &str) {
 }
 
 /// Cancel a download by setting its cancellation flag
+/// (or by dropping it from the queue if it has not started yet)
 pub fn cancel_download(file_id: &str) -> bool {
+    if cancel_queued_download(file_id) {
+        return true;
+    }
+
     let downloads = get_active_downloads();
     let guard = downloads.lock().expect("ACTIVE_DOWNLOADS mutex poisoned");
     if let Some(cancel_flag) = guard.get(file_id) {
@@ -175,6 +470,42 @@ pub fn cancel_download(file_id: &str) -> bool {
     }
 }
 
+/// Cancel every running download and drop every queued one
+///
+/// Returns how many downloads were cancelled.
+pub fn cancel_all_downloads() -> usize {
+    let queued = {
+        let mut queue = get_download_queue()
+            .lock()
+            .expect("DOWNLOAD_QUEUE mutex poisoned");
+        std::mem::take(&mut queue.waiting).len() + std::mem::take(&mut queue.prefetching).len()
+    };
+    if queued > 0 {
+        get_download_queue_changed().notify_waiters();
+    }
+
+    let running = get_active_downloads()
+        .lock()
+        .expect("ACTIVE_DOWNLOADS mutex poisoned")
+        .values()
+        .filter(|cancel_flag| !cancel_flag.swap(true, Ordering::SeqCst))
+        .count();
+
+    queued + running
+}
+
+/// Whether any download is running or waiting for a slot
+pub fn has_active_downloads() -> bool {
+    let running = !get_active_downloads()
+        .lock()
+        .expect("ACTIVE_DOWNLOADS mutex poisoned")
+        .is_empty();
+    let queue = get_download_queue()
+        .lock()
+        .expect("DOWNLOAD_QUEUE mutex poisoned");
+    running || queue.active > 0 || !queue.waiting.is_empty() || !queue.prefetching.is_empty()
+}
+
 /// Get the cancellation flag for a download if it exists
 pub fn get_cancel_flag(file_id: &str) -> Option<Arc<AtomicBool>> {
     let downloads = get_active_downloads();
@@ -182,6 +513,198 @@ pub fn get_cancel_flag(file_id: &str) -> Option<Arc<AtomicBool>> {
     guard.get(file_id).cloned()
 }
 
+// ============================================================================
+// Download Queue
+// ============================================================================
+
+/// FIFO queue that bounds how many downloads run at once
+#[derive(Default)]
+struct DownloadQueue {
+    /// Number of downloads currently holding a slot
+    active: usize,
+    /// Drive file IDs waiting for a slot, in arrival order
+    waiting: VecDeque<String>,
+    /// Prefetches waiting for a slot; they only start when `waiting` is empty
+    prefetching: VecDeque<String>,
+}
+
+impl DownloadQueue {
+    fn contains(&self, file_id: &str) -> bool {
+        self.waiting
+            .iter()
+            .chain(&self.prefetching)
+            .any(|id| id == file_id)
+    }
+
+    /// Take a slot for `file_id` if it is next in line and one is free
+    fn try_start(&mut self, file_id: &str, max: usize) -> bool {
+        if self.active >= max {
+            return false;
+        }
+        let next = match self.waiting.front() {
+            Some(id) => id,
+            None => match self.prefetching.front() {
+                Some(id) => id,
+                None => return false,
+            },
+        };
+        if next != file_id {
+            return false;
+        }
+        if self.waiting.pop_front().is_none() {
+            self.prefetching.pop_front();
+        }
+        self.active += 1;
+        true
+    }
+
+    /// Drop `file_id` from both lines; returns whether it was queued
+    fn remove(&mut self, file_id: &str) -> bool {
+        let before = self.waiting.len() + self.prefetching.len();
+        self.waiting.retain(|id| id != file_id);
+        self.prefetching.retain(|id| id != file_id);
+        self.waiting.len() + self.prefetching.len() != before
+    }
+}
+
+static DOWNLOAD_QUEUE: OnceLock<Mutex<DownloadQueue>> = OnceLock::new();
+static DOWNLOAD_QUEUE_CHANGED: OnceLock<tokio::sync::Notify> = OnceLock::new();
+static MAX_CONCURRENT_DOWNLOADS: AtomicUsize =
+    AtomicUsize::new(settings::DEFAULT_MAX_CONCURRENT_DOWNLOADS);
+
+fn get_download_queue() -> &'static Mutex<DownloadQueue> {
+    DOWNLOAD_QUEUE.get_or_init(|| Mutex::new(DownloadQueue::default()))
+}
+
+fn get_download_queue_changed() -> &'static tokio::sync::Notify {
+    DOWNLOAD_QUEUE_CHANGED.get_or_init(tokio::sync::Notify::new)
+}
+
+/// A held download slot; the next queued download starts when it is dropped
+pub struct DownloadSlot {
+    _private: (),
+}
+
+impl Drop for DownloadSlot {
+    fn drop(&mut self) {
+        {
+            let mut queue = get_download_queue()
+                .lock()
+                .expect("DOWNLOAD_QUEUE mutex poisoned");
+            queue.active = queue.active.saturating_sub(1);
+        }
+        get_download_queue_changed().notify_waiters();
+    }
+}
+
+/// Load the concurrency limit from settings (called on app startup)
+pub fn init_download_queue(app: &AppHandle) {
+    let max = settings::get_setting(app, settings::KEY_MAX_CONCURRENT_DOWNLOADS)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(settings::DEFAULT_MAX_CONCURRENT_DOWNLOADS);
+    MAX_CONCURRENT_DOWNLOADS.store(max, Ordering::SeqCst);
+}
+
+/// Change how many downloads may run at once (persisted in settings)
+pub fn set_max_concurrent_downloads(app: &AppHandle, max: usize) -> Result<(), PedaruError> {
+    let max = max.max(1);
+    settings::set_setting(
+        app,
+        settings::KEY_MAX_CONCURRENT_DOWNLOADS,
+        &max.to_string(),
+    )?;
+    MAX_CONCURRENT_DOWNLOADS.store(max, Ordering::SeqCst);
+    // Raising the limit may let queued downloads start right away
+    get_download_queue_changed().notify_waiters();
+    Ok(())
+}
+
+/// Number of downloads (including prefetches) waiting for a free slot
+pub fn get_download_queue_depth() -> usize {
+    let queue = get_download_queue()
+        .lock()
+        .expect("DOWNLOAD_QUEUE mutex poisoned");
+    queue.waiting.len() + queue.prefetching.len()
+}
+
+/// Wait in the download queue until a slot is free
+///
+/// A queued prefetch of the same item is dropped, since this download takes
+/// its place. Returns `DownloadCancelled` if the item is removed from the
+/// queue by `cancel_download` before it gets a slot.
+pub async fn acquire_download_slot(file_id: &str) -> Result<DownloadSlot, PedaruError> {
+    let dropped_prefetch = {
+        let mut queue = get_download_queue()
+            .lock()
+            .expect("DOWNLOAD_QUEUE mutex poisoned");
+        let before = queue.prefetching.len();
+        queue.prefetching.retain(|id| id != file_id);
+        queue.waiting.push_back(file_id.to_string());
+        queue.prefetching.len() != before
+    };
+    if dropped_prefetch {
+        get_download_queue_changed().notify_waiters();
+    }
+    wait_for_slot(file_id).await
+}
+
+/// Wait behind every other download until a slot is free (for prefetches)
+///
+/// Returns `DownloadCancelled` if the prefetch is dropped from the queue
+/// first, e.g. because the item was requested or prefetching was turned off.
+pub async fn acquire_prefetch_slot(file_id: &str) -> Result<DownloadSlot, PedaruError> {
+    {
+        let mut queue = get_download_queue()
+            .lock()
+            .expect("DOWNLOAD_QUEUE mutex poisoned");
+        if queue.contains(file_id) {
+            return Err(GoogleDriveError::DownloadCancelled(file_id.to_string()).into());
+        }
+        queue.prefetching.push_back(file_id.to_string());
+    }
+    wait_for_slot(file_id).await
+}
+
+async fn wait_for_slot(file_id: &str) -> Result<DownloadSlot, PedaruError> {
+    loop {
+        // Register for wake-ups before checking, so no notification is missed
+        let notified = get_download_queue_changed().notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        {
+            let mut queue = get_download_queue()
+                .lock()
+                .expect("DOWNLOAD_QUEUE mutex poisoned");
+            if !queue.contains(file_id) {
+                return Err(PedaruError::GoogleDrive(
+                    GoogleDriveError::DownloadCancelled(file_id.to_string()),
+                ));
+            }
+            if queue.try_start(file_id, MAX_CONCURRENT_DOWNLOADS.load(Ordering::SeqCst)) {
+                return Ok(DownloadSlot { _private: () });
+            }
+        }
+
+        notified.await;
+    }
+}
+
+/// Remove a download from the queue if it has not started yet
+fn cancel_queued_download(file_id: &str) -> bool {
+    let removed = get_download_queue()
+        .lock()
+        .expect("DOWNLOAD_QUEUE mutex poisoned")
+        .remove(file_id);
+    if removed {
+        get_download_queue_changed().notify_waiters();
+    }
+    removed
+}
+
 /// Get downloads directory path
 pub fn get_downloads_dir(app: &AppHandle) -> Result<std::path::PathBuf, PedaruError> {
     let config_dir = app.path().app_config_dir().map_err(|e| {
@@ -192,435 +715,2930 @@ pub fn get_downloads_dir(app: &AppHandle) -> Result<std::path::PathBuf, PedaruEr
     Ok(config_dir.join("downloads"))
 }
 
-// ============================================================================
-// Folder Operations (Google Drive)
-// ============================================================================
+/// Appended to a download's file name while it is being written
+const PARTIAL_DOWNLOAD_SUFFIX: &str = ".partial";
 
-/// Add a folder to the sync list
-pub fn add_sync_folder(
-    app: &AppHandle,
-    folder_id: &str,
-    folder_name: &str,
-) -> Result<(), PedaruError> {
-    let conn = open_db(app)?;
-    conn.execute(
-        "INSERT INTO drive_folders (folder_id, folder_name, created_at)
-         VALUES (?1, ?2, ?3)
-         ON CONFLICT(folder_id) DO UPDATE SET
-           folder_name = excluded.folder_name,
-           is_active = 1",
-        rusqlite::params![folder_id, folder_name, now_timestamp()],
-    )
-    .db_err()?;
-    Ok(())
+/// Where a download to `dest_path` is written until it is complete and verified
+pub fn partial_download_path(dest_path: &std::path::Path) -> std::path::PathBuf {
+    let mut name = dest_path.file_name().unwrap_or_default().to_os_string();
+    name.push(PARTIAL_DOWNLOAD_SUFFIX);
+    dest_path.with_file_name(name)
 }
 
-/// Remove a folder from the sync list (marks as inactive)
-pub fn remove_sync_folder(app: &AppHandle, folder_id: &str) -> Result<(), PedaruError> {
-    let conn = open_db(app)?;
-    conn.execute(
-        "UPDATE drive_folders SET is_active = 0 WHERE folder_id = ?1",
-        [folder_id],
-    )
-    .db_err()?;
-    Ok(())
+/// Whether `file_name` is a partial download that no item can resume
+fn is_orphaned_partial(file_name: &str, resumable: &HashSet<String>) -> bool {
+    file_name
+        .strip_suffix(PARTIAL_DOWNLOAD_SUFFIX)
+        .is_some_and(|name| !resumable.contains(name))
 }
 
-/// Get all active sync folders
-pub fn get_sync_folders(app: &AppHandle) -> Result<Vec<StoredFolder>, PedaruError> {
-    let conn = open_db(app)?;
+/// Delete partial downloads left behind by downloads that can't be resumed
+///
+/// A partial file is kept while an unfinished item that would download to
+/// the same file name has recorded progress. Returns the number of files deleted.
+pub fn remove_orphaned_partial_downloads(app: &AppHandle) -> Result<usize, PedaruError> {
+    let downloads_dir = get_downloads_dir(app)?;
+    let Ok(entries) = std::fs::read_dir(&downloads_dir) else {
+        return Ok(0);
+    };
+
+    let template = get_download_name_template(app)?;
+    let resumable = resumable_download_names_on(&open_db(app)?, &downloads_dir, &template)?;
+
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        if is_orphaned_partial(&file_name.to_string_lossy(), &resumable)
+            && std::fs::remove_file(entry.path()).is_ok()
+        {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// File names of unfinished downloads that have recorded progress
+fn resumable_download_names_on(
+    conn: &rusqlite::Connection,
+    downloads_dir: &std::path::Path,
+    template: &str,
+) -> Result<HashSet<String>, PedaruError> {
     let mut stmt = conn
         .prepare(
-            "SELECT folder_id, folder_name, is_active, last_synced
-             FROM drive_folders
-             WHERE is_active = 1
-             ORDER BY folder_name",
+            "SELECT drive_file_id, file_name FROM bookshelf_cloud
+             WHERE downloaded_bytes > 0 AND download_status != 'completed'",
         )
         .db_err()?;
+    let items: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .db_err()?
+        .filter_map(|r| r.ok())
+        .collect();
 
-    let folders = stmt
-        .query_map([], |row| {
-            Ok(StoredFolder {
-                folder_id: row.get(0)?,
-                folder_name: row.get(1)?,
-                is_active: row.get::<_, i32>(2)? != 0,
-                last_synced: row.get(3)?,
-            })
+    let mut names = HashSet::new();
+    for (drive_file_id, file_name) in items {
+        let path = download_path_on(conn, downloads_dir, template, &drive_file_id, &file_name)?;
+        if let Some(name) = path.file_name() {
+            names.insert(name.to_string_lossy().into_owned());
+        }
+    }
+    Ok(names)
+}
+
+/// Extra free space required on top of a file's size before downloading it
+const DISK_SPACE_MARGIN_BYTES: u64 = 50 * 1024 * 1024;
+
+/// Get the free space available to the downloads directory
+pub fn free_space_bytes(app: &AppHandle) -> Result<u64, PedaruError> {
+    let downloads_dir = get_downloads_dir(app)?;
+    std::fs::create_dir_all(&downloads_dir).map_err(|source| IoError::CreateDirFailed {
+        path: downloads_dir.display().to_string(),
+        source,
+    })?;
+    fs4::available_space(&downloads_dir).map_err(|source| {
+        PedaruError::Io(IoError::ReadFailed {
+            path: downloads_dir.display().to_string(),
+            source,
         })
+    })
+}
+
+/// Count cloud items per download status
+pub fn count_items_by_status(app: &AppHandle) -> Result<BTreeMap<String, i64>, PedaruError> {
+    let conn = open_db(app)?;
+    let mut stmt = conn
+        .prepare("SELECT download_status, COUNT(*) FROM bookshelf_cloud GROUP BY download_status")
+        .db_err()?;
+    let counts = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
         .db_err()?
         .filter_map(|r| r.ok())
         .collect();
-
-    Ok(folders)
+    Ok(counts)
 }
 
-/// Update folder sync timestamp
-pub fn update_folder_sync_time(app: &AppHandle, folder_id: &str) -> Result<(), PedaruError> {
+/// Check there is room for the rest of a cloud item's download
+///
+/// Skipped (with a log message) when Drive didn't report the file size.
+pub fn check_download_space(app: &AppHandle, drive_file_id: &str) -> Result<(), PedaruError> {
     let conn = open_db(app)?;
-    conn.execute(
-        "UPDATE drive_folders SET last_synced = ?1 WHERE folder_id = ?2",
-        rusqlite::params![now_timestamp(), folder_id],
-    )
-    .db_err()?;
+    let (file_size, downloaded_bytes): (Option<i64>, i64) = conn
+        .query_row(
+            "SELECT file_size, downloaded_bytes FROM bookshelf_cloud WHERE drive_file_id = ?1",
+            [drive_file_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .db_err()?;
+    drop(conn);
+
+    let Some(file_size) = file_size else {
+        log::info!(
+            "Unknown size for {}, skipping disk space check",
+            drive_file_id
+        );
+        return Ok(());
+    };
+
+    let remaining = (file_size - downloaded_bytes).max(0) as u64;
+    let required = remaining + DISK_SPACE_MARGIN_BYTES;
+    let available = free_space_bytes(app)?;
+    if available < required {
+        return Err(GoogleDriveError::InsufficientDiskSpace {
+            required,
+            available,
+        }
+        .into());
+    }
     Ok(())
 }
 
 // ============================================================================
-// Cloud Item Operations (Google Drive)
+// Download File Names
 // ============================================================================
 
-/// Upsert cloud item from Drive file
-pub fn upsert_cloud_item(
-    app: &AppHandle,
+/// Template used when none is set: the file's name on Drive
+pub const DEFAULT_DOWNLOAD_NAME_TEMPLATE: &str = "{file_name}";
+
+/// Tokens a download name template can contain
+const DOWNLOAD_NAME_TOKENS: [&str; 3] = ["pdf_title", "file_name", "drive_file_id"];
+
+/// Longest file name written to disk, in bytes
+///
+/// Linux, macOS and NTFS allow 255, less the `.partial` suffix added while
+/// downloading. Windows also caps whole paths at 260 characters, so names
+/// are kept shorter there.
+#[cfg(windows)]
+const MAX_FILE_NAME_BYTES: usize = 150;
+#[cfg(not(windows))]
+const MAX_FILE_NAME_BYTES: usize = 255 - PARTIAL_DOWNLOAD_SUFFIX.len();
+
+/// Characters that aren't allowed in file names on at least one platform
+const RESERVED_FILE_NAME_CHARS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Device names Windows won't create files as, with or without an extension
+const WINDOWS_RESERVED_NAMES: [&str; 24] = [
+    "CON", "PRN", "AUX", "NUL", "CONIN$", "CONOUT$", "COM1", "COM2", "COM3", "COM4", "COM5",
+    "COM6", "COM7", "COM8", "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8",
+    "LPT9",
+];
+
+/// Make `name` safe to use as a file name on every platform
+///
+/// Drive allows names that can't be created on disk everywhere: reserved
+/// and control characters become `_`, trailing dots and spaces (dropped or
+/// rejected by Windows) are removed, and Windows device names such as
+/// `CON` or `nul.txt` get a `_` after the name. The result may be empty.
+fn sanitize_file_name(name: &str) -> String {
+    let mut sanitized = name
+        .chars()
+        .map(|c| {
+            if c.is_control() || RESERVED_FILE_NAME_CHARS.contains(&c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect::<String>()
+        .trim_start()
+        .trim_end_matches(|c: char| c == '.' || c.is_whitespace())
+        .to_string();
+
+    let base_len = sanitized.find('.').unwrap_or(sanitized.len());
+    let base = sanitized[..base_len].trim_end();
+    if WINDOWS_RESERVED_NAMES
+        .iter()
+        .any(|reserved| base.eq_ignore_ascii_case(reserved))
+    {
+        sanitized.insert(base_len, '_');
+    }
+    sanitized
+}
+
+/// Cut `s` to at most `max_bytes` bytes without splitting a character
+fn truncate_to_bytes(s: &str, max_bytes: usize) -> &str {
+    let mut end = s.len().min(max_bytes);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// Split a file name into its stem and extension ("" if there is none)
+fn split_extension(file_name: &str) -> (&str, &str) {
+    match file_name.rsplit_once('.') {
+        Some((stem, extension))
+            if !stem.is_empty()
+                && !extension.is_empty()
+                && extension.chars().all(|c| c.is_ascii_alphanumeric()) =>
+        {
+            (stem, extension)
+        }
+        _ => (file_name, ""),
+    }
+}
+
+/// Check a download name template, returning it trimmed
+///
+/// Every `{...}` must be a known token, there must be at least one, and the
+/// template can't point into a folder.
+fn parse_download_name_template(template: &str) -> Result<String, PedaruError> {
+    let template = template.trim();
+    let invalid = || ConfigError::InvalidFileNameTemplate(template.to_string()).into();
+
+    if template.contains(['/', '\\']) {
+        return Err(invalid());
+    }
+    let mut has_token = false;
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').ok_or_else(invalid)? + start;
+        if rest[..start].contains('}') || !DOWNLOAD_NAME_TOKENS.contains(&&rest[start + 1..end]) {
+            return Err(invalid());
+        }
+        has_token = true;
+        rest = &rest[end + 1..];
+    }
+    if !has_token || rest.contains('}') {
+        return Err(invalid());
+    }
+    Ok(template.to_string())
+}
+
+/// Fill in a download name template for one item
+///
+/// Returns the sanitized stem and the extension of `file_name`, which is
+/// always kept. `{file_name}` is the Drive name without its extension, and
+/// `{pdf_title}` falls back to it until the title has been read from the
+/// PDF (after its first download). A name that comes out empty falls back
+/// to the Drive file id.
+fn render_download_name(
+    template: &str,
     drive_file_id: &str,
-    folder_id: &str,
     file_name: &str,
-    file_size: Option<i64>,
-    modified_time: Option<&str>,
-) -> Result<(), PedaruError> {
-    let conn = open_db(app)?;
-    let now = now_timestamp();
+    pdf_title: Option<&str>,
+) -> (String, String) {
+    let (stem, extension) = split_extension(file_name);
+    let pdf_title = pdf_title
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .unwrap_or(stem);
+
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|end| end + start) else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        match &rest[start + 1..end] {
+            "pdf_title" => rendered.push_str(pdf_title),
+            "file_name" => rendered.push_str(stem),
+            "drive_file_id" => rendered.push_str(drive_file_id),
+            _ => rendered.push_str(&rest[start..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    rendered.push_str(rest);
 
-    conn.execute(
-        "INSERT INTO bookshelf_cloud (
-           drive_file_id, drive_folder_id, file_name, file_size,
-           drive_modified_time, created_at, updated_at
-         )
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)
-         ON CONFLICT(drive_file_id) DO UPDATE SET
-           file_name = excluded.file_name,
-           file_size = excluded.file_size,
-           drive_modified_time = excluded.drive_modified_time,
-           updated_at = excluded.updated_at",
-        rusqlite::params![
+    let mut stem = sanitize_file_name(&rendered);
+    if stem.is_empty() {
+        stem = sanitize_file_name(drive_file_id);
+    }
+    (stem, extension.to_string())
+}
+
+/// Build a file name within `MAX_FILE_NAME_BYTES`, adding a `_n` suffix
+/// when `n` > 0
+fn numbered_file_name(stem: &str, extension: &str, n: usize) -> String {
+    let suffix = if n > 0 {
+        format!("_{}", n)
+    } else {
+        String::new()
+    };
+    let extension = if extension.is_empty() {
+        String::new()
+    } else {
+        format!(".{}", extension)
+    };
+    let max_stem = MAX_FILE_NAME_BYTES.saturating_sub(suffix.len() + extension.len());
+    format!(
+        "{}{}{}",
+        truncate_to_bytes(stem, max_stem).trim_end_matches(|c: char| c == '.' || c.is_whitespace()),
+        suffix,
+        extension
+    )
+}
+
+/// Get the template download file names are built from
+pub fn get_download_name_template(app: &AppHandle) -> Result<String, PedaruError> {
+    Ok(
+        settings::get_setting(app, settings::KEY_DOWNLOAD_NAME_TEMPLATE)?
+            .and_then(|v| parse_download_name_template(&v).ok())
+            .unwrap_or_else(|| DEFAULT_DOWNLOAD_NAME_TEMPLATE.to_string()),
+    )
+}
+
+/// Set the template download file names are built from (blank restores the
+/// default, the Drive name), returning the template now in effect
+///
+/// Only affects later downloads; files already on disk keep their names.
+pub fn set_download_name_template(app: &AppHandle, template: &str) -> Result<String, PedaruError> {
+    if template.trim().is_empty() {
+        settings::delete_setting(app, settings::KEY_DOWNLOAD_NAME_TEMPLATE)?;
+    } else {
+        let template = parse_download_name_template(template)?;
+        settings::set_setting(app, settings::KEY_DOWNLOAD_NAME_TEMPLATE, &template)?;
+    }
+    get_download_name_template(app)
+}
+
+/// Where a cloud item is downloaded to
+///
+/// The name comes from the download name template, made safe for every
+/// file system; the item's `file_name` stays as it is on Drive for display.
+/// If another item's download or any other file already has that name, a
+/// counter is added (`name_1.pdf`, `name_2.pdf`, ...). The item's own
+/// `local_path` is reused when it matches.
+///
+/// The path is reserved on the item until its download completes, so a
+/// concurrent download whose name renders the same gets the next counter,
+/// and a resumed download keeps writing to its `.partial` file.
+pub fn reserve_download_path(
+    app: &AppHandle,
+    drive_file_id: &str,
+    file_name: &str,
+) -> Result<std::path::PathBuf, PedaruError> {
+    let downloads_dir = get_downloads_dir(app)?;
+    let template = get_download_name_template(app)?;
+    retry_busy(|| {
+        reserve_download_path_on(
+            &mut open_db(app)?,
+            &downloads_dir,
+            &template,
             drive_file_id,
-            folder_id,
             file_name,
-            file_size,
-            modified_time,
-            now
-        ],
+        )
+    })
+}
+
+/// Pick and reserve a download path in one write transaction, so no other
+/// connection can pick the same path in between
+fn reserve_download_path_on(
+    conn: &mut rusqlite::Connection,
+    downloads_dir: &std::path::Path,
+    template: &str,
+    drive_file_id: &str,
+    file_name: &str,
+) -> Result<std::path::PathBuf, PedaruError> {
+    let tx = conn
+        .transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)
+        .db_err()?;
+    let path = download_path_on(&tx, downloads_dir, template, drive_file_id, file_name)?;
+    tx.execute(
+        "UPDATE bookshelf_cloud SET reserved_path = ?1 WHERE drive_file_id = ?2",
+        rusqlite::params![path.to_string_lossy(), drive_file_id],
     )
     .db_err()?;
-
-    Ok(())
+    tx.commit().db_err()?;
+    Ok(path)
 }
 
-/// Get all cloud items
-/// Sorted by last_opened (most recent first), then by file_name for items never opened
-pub fn get_cloud_items(app: &AppHandle) -> Result<Vec<CloudItem>, PedaruError> {
-    let conn = open_db(app)?;
+/// Where a cloud item would be downloaded to, without reserving the path
+///
+/// A path is taken when another item downloaded or reserved it, a local
+/// item points at it, or it or its `.partial` file exists on disk. The
+/// item's own reserved path comes first, then its `local_path`.
+fn download_path_on(
+    conn: &rusqlite::Connection,
+    downloads_dir: &std::path::Path,
+    template: &str,
+    drive_file_id: &str,
+    file_name: &str,
+) -> Result<std::path::PathBuf, PedaruError> {
+    let (pdf_title, own_path, reserved_path): (Option<String>, Option<String>, Option<String>) =
+        conn.query_row(
+            "SELECT pdf_title, local_path, reserved_path FROM bookshelf_cloud
+             WHERE drive_file_id = ?1",
+            [drive_file_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .db_err()?
+        .unwrap_or_default();
+    if let Some(reserved_path) = reserved_path {
+        return Ok(reserved_path.into());
+    }
+    let (stem, extension) =
+        render_download_name(template, drive_file_id, file_name, pdf_title.as_deref());
+
     let mut stmt = conn
         .prepare(
-            "SELECT id, drive_file_id, drive_folder_id, file_name, file_size,
-                    thumbnail_data, local_path, download_status, download_progress,
-                    pdf_title, pdf_author, is_favorite, last_opened
-             FROM bookshelf_cloud
-             ORDER BY last_opened IS NULL, last_opened DESC, file_name ASC",
+            "SELECT 1 FROM bookshelf_cloud
+             WHERE (local_path = ?1 OR reserved_path = ?1) AND drive_file_id != ?2
+             UNION ALL SELECT 1 FROM bookshelf_local WHERE file_path = ?1",
         )
         .db_err()?;
+    let mut n = 0;
+    loop {
+        let path = downloads_dir.join(numbered_file_name(&stem, &extension, n));
+        let path_str = path.to_string_lossy();
+        if own_path.as_deref() == Some(&*path_str) {
+            return Ok(path);
+        }
+        let taken = stmt
+            .exists(rusqlite::params![path_str, drive_file_id])
+            .db_err()?
+            || path.exists()
+            || partial_download_path(&path).exists();
+        if !taken {
+            return Ok(path);
+        }
+        n += 1;
+    }
+}
 
-    let items = stmt
-        .query_map([], |row| {
-            let status_str: String = row.get(7)?;
-            let download_status = status_str.parse().unwrap_or_default();
-            Ok(CloudItem {
-                id: row.get(0)?,
-                drive_file_id: row.get(1)?,
-                drive_folder_id: row.get(2)?,
-                file_name: row.get(3)?,
-                file_size: row.get(4)?,
-                thumbnail_data: row.get(5)?,
-                local_path: row.get(6)?,
-                download_status,
-                download_progress: row.get(8)?,
-                pdf_title: row.get(9)?,
-                pdf_author: row.get(10)?,
-                is_favorite: row.get::<_, i64>(11)? != 0,
-                last_opened: row.get(12)?,
-            })
-        })
-        .db_err()?
-        .filter_map(|r| r.ok())
-        .collect();
+// ============================================================================
+// Bandwidth Throttle
+// ============================================================================
 
-    Ok(items)
+/// Global download speed limit in bytes per second (0 = unlimited)
+static MAX_DOWNLOAD_BYTES_PER_SEC: AtomicU64 = AtomicU64::new(0);
+
+/// Point in time when the bandwidth already handed out is used up, shared by
+/// every active download so the limit is global
+static BANDWIDTH_NEXT_FREE: OnceLock<Mutex<Instant>> = OnceLock::new();
+
+fn get_bandwidth_next_free() -> &'static Mutex<Instant> {
+    BANDWIDTH_NEXT_FREE.get_or_init(|| Mutex::new(Instant::now()))
 }
 
-/// Update download status for cloud item
-pub fn update_download_status(
+/// Load the download speed limit from settings (called on app startup)
+pub fn init_bandwidth_limit(app: &AppHandle) {
+    let limit = settings::get_setting(app, settings::KEY_MAX_DOWNLOAD_BYTES_PER_SEC)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(0);
+    MAX_DOWNLOAD_BYTES_PER_SEC.store(limit, Ordering::SeqCst);
+}
+
+/// Get the download speed limit in bytes per second (0 = unlimited)
+pub fn get_max_download_speed() -> u64 {
+    MAX_DOWNLOAD_BYTES_PER_SEC.load(Ordering::SeqCst)
+}
+
+/// Change the download speed limit (0 = unlimited); applies to running downloads
+pub fn set_max_download_speed(app: &AppHandle, bytes_per_sec: u64) -> Result<(), PedaruError> {
+    settings::set_setting(
+        app,
+        settings::KEY_MAX_DOWNLOAD_BYTES_PER_SEC,
+        &bytes_per_sec.to_string(),
+    )?;
+    MAX_DOWNLOAD_BYTES_PER_SEC.store(bytes_per_sec, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Reserve transfer time for `bytes` and return how long to wait before
+/// continuing
+fn reserve_bandwidth(next_free: &mut Instant, now: Instant, bytes: u64, limit: u64) -> Duration {
+    let start = (*next_free).max(now);
+    *next_free = start + Duration::from_secs_f64(bytes as f64 / limit as f64);
+    start - now
+}
+
+/// Wait as needed to keep all downloads under the speed limit
+///
+/// Called by the download loop after writing each chunk.
+pub async fn throttle_download(bytes: usize) {
+    let limit = MAX_DOWNLOAD_BYTES_PER_SEC.load(Ordering::SeqCst);
+    if limit == 0 {
+        return;
+    }
+
+    let wait = {
+        let mut next_free = get_bandwidth_next_free()
+            .lock()
+            .expect("BANDWIDTH_NEXT_FREE mutex poisoned");
+        reserve_bandwidth(&mut next_free, Instant::now(), bytes as u64, limit)
+    };
+    if !wait.is_zero() {
+        tokio::time::sleep(wait).await;
+    }
+}
+
+// ============================================================================
+// Download Progress Events
+// ============================================================================
+
+/// Accepted range for the progress event interval in milliseconds
+const DOWNLOAD_PROGRESS_INTERVAL_MS_RANGE: std::ops::RangeInclusive<u64> = 16..=2000;
+
+/// Minimum milliseconds between progress events of one download
+static DOWNLOAD_PROGRESS_INTERVAL_MS: AtomicU64 =
+    AtomicU64::new(settings::DEFAULT_DOWNLOAD_PROGRESS_INTERVAL_MS);
+
+/// Load the progress event interval from settings (called on app startup)
+pub fn init_download_progress_interval(app: &AppHandle) {
+    let interval = settings::get_setting(app, settings::KEY_DOWNLOAD_PROGRESS_INTERVAL_MS)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|ms| DOWNLOAD_PROGRESS_INTERVAL_MS_RANGE.contains(ms))
+        .unwrap_or(settings::DEFAULT_DOWNLOAD_PROGRESS_INTERVAL_MS);
+    DOWNLOAD_PROGRESS_INTERVAL_MS.store(interval, Ordering::SeqCst);
+}
+
+/// Get the minimum time between progress events in milliseconds
+pub fn get_download_progress_interval() -> u64 {
+    DOWNLOAD_PROGRESS_INTERVAL_MS.load(Ordering::SeqCst)
+}
+
+/// Change the minimum time between progress events (clamped to 16-2000ms);
+/// applies to running downloads from their next chunk
+pub fn set_download_progress_interval(app: &AppHandle, ms: u64) -> Result<(), PedaruError> {
+    let ms = ms.clamp(
+        *DOWNLOAD_PROGRESS_INTERVAL_MS_RANGE.start(),
+        *DOWNLOAD_PROGRESS_INTERVAL_MS_RANGE.end(),
+    );
+    settings::set_setting(
+        app,
+        settings::KEY_DOWNLOAD_PROGRESS_INTERVAL_MS,
+        &ms.to_string(),
+    )?;
+    DOWNLOAD_PROGRESS_INTERVAL_MS.store(ms, Ordering::SeqCst);
+    Ok(())
+}
+
+// ============================================================================
+// Download Retries
+// ============================================================================
+
+/// Number of automatic retries before a download is marked as failed
+pub const MAX_DOWNLOAD_RETRIES: u32 = 3;
+
+/// Delay before the first automatic retry; doubles on each further attempt
+const DOWNLOAD_RETRY_BASE_DELAY_MS: u64 = 2000;
+
+/// Record how many automatic retries a download has used
+pub fn set_download_retry_count(
     app: &AppHandle,
     drive_file_id: &str,
-    status: &str,
-    progress: f64,
-    local_path: Option<&str>,
+    count: u32,
 ) -> Result<(), PedaruError> {
     let conn = open_db(app)?;
     conn.execute(
-        "UPDATE bookshelf_cloud SET
-           download_status = ?1,
-           download_progress = ?2,
-           local_path = COALESCE(?3, local_path),
-           updated_at = ?4
-         WHERE drive_file_id = ?5",
-        rusqlite::params![status, progress, local_path, now_timestamp(), drive_file_id],
+        "UPDATE bookshelf_cloud SET download_retry_count = ?1 WHERE drive_file_id = ?2",
+        rusqlite::params![count, drive_file_id],
     )
     .db_err()?;
     Ok(())
 }
 
-/// Update thumbnail data for cloud item
-pub fn update_cloud_thumbnail(
+/// Whether a failed download attempt is worth retrying
+///
+/// Cancellations and failures that another attempt can't fix are not retried.
+fn is_retryable_download_error(error: &PedaruError, cancelled: bool) -> bool {
+    !cancelled && error.is_retryable()
+}
+
+/// Run a download, retrying transient failures with exponential backoff
+///
+/// The retry count is stored on the item so the UI can show it, and reset
+/// when a new download starts. A cancel during the backoff wait stops the
+/// retries with `DownloadCancelled`.
+pub async fn download_with_retries<F, Fut>(
     app: &AppHandle,
     drive_file_id: &str,
-    thumbnail_data: &str,
-) -> Result<(), PedaruError> {
-    let conn = open_db(app)?;
-    conn.execute(
-        "UPDATE bookshelf_cloud SET thumbnail_data = ?1, updated_at = ?2 WHERE drive_file_id = ?3",
-        rusqlite::params![thumbnail_data, now_timestamp(), drive_file_id],
+    cancel_flag: &AtomicBool,
+    mut attempt: F,
+) -> Result<(), PedaruError>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<(), PedaruError>>,
+{
+    set_download_retry_count(app, drive_file_id, 0)?;
+
+    let mut retries = 0;
+    loop {
+        let error = match attempt().await {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+
+        // Offline: wait for the network without using up a retry
+        if matches!(error, PedaruError::Network(NetworkError::Offline { .. }))
+            && !cancel_flag.load(Ordering::SeqCst)
+        {
+            log::info!(
+                "Download of {} queued until the network is reachable",
+                drive_file_id
+            );
+            update_download_status(app, drive_file_id, "queued", 0.0, None, None)?;
+            if !network::wait_until_online(cancel_flag).await {
+                return Err(GoogleDriveError::DownloadCancelled(drive_file_id.to_string()).into());
+            }
+            update_download_status(app, drive_file_id, "downloading", 0.0, None, None)?;
+            continue;
+        }
+
+        if retries >= MAX_DOWNLOAD_RETRIES
+            || !is_retryable_download_error(&error, cancel_flag.load(Ordering::SeqCst))
+        {
+            return Err(error);
+        }
+
+        retries += 1;
+        set_download_retry_count(app, drive_file_id, retries)?;
+        let delay = DOWNLOAD_RETRY_BASE_DELAY_MS * (1 << (retries - 1));
+        log::warn!(
+            "Download of {} failed ({}), retry {}/{} in {}ms",
+            drive_file_id,
+            error,
+            retries,
+            MAX_DOWNLOAD_RETRIES,
+            delay
+        );
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+
+        if cancel_flag.load(Ordering::SeqCst) {
+            return Err(GoogleDriveError::DownloadCancelled(drive_file_id.to_string()).into());
+        }
+        // The next attempt may start over from 0, which the stale-update
+        // guard would otherwise reject until it passes the old progress
+        retry_busy(|| restart_download_progress_on(&open_db(app)?, drive_file_id))?;
+    }
+}
+
+// ============================================================================
+// Prefetch
+// ============================================================================
+
+/// Whether the next item in a folder is downloaded ahead of time (off by
+/// default, since it spends bandwidth on metered connections)
+static PREFETCH_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Drive file IDs of prefetches that hold a download slot
+static RUNNING_PREFETCHES: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+fn get_running_prefetches() -> &'static Mutex<HashSet<String>> {
+    RUNNING_PREFETCHES.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// An item worth downloading ahead of time
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrefetchCandidate {
+    pub drive_file_id: String,
+    pub file_name: String,
+    pub file_size: Option<u64>,
+}
+
+/// Load the prefetch setting (called on app startup)
+pub fn init_prefetch(app: &AppHandle) {
+    let enabled = settings::get_setting(app, settings::KEY_PREFETCH_ENABLED)
+        .ok()
+        .flatten()
+        .is_some_and(|v| v == "true");
+    PREFETCH_ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+pub fn is_prefetch_enabled() -> bool {
+    PREFETCH_ENABLED.load(Ordering::SeqCst)
+}
+
+/// Turn prefetching on or off
+///
+/// Turning it off cancels queued and running prefetches; returns how many
+/// were cancelled.
+pub fn set_prefetch_enabled(app: &AppHandle, enabled: bool) -> Result<usize, PedaruError> {
+    settings::set_setting(
+        app,
+        settings::KEY_PREFETCH_ENABLED,
+        if enabled { "true" } else { "false" },
+    )?;
+    PREFETCH_ENABLED.store(enabled, Ordering::SeqCst);
+    if enabled {
+        return Ok(0);
+    }
+    Ok(cancel_prefetches())
+}
+
+/// Cancel every queued and running prefetch
+pub fn cancel_prefetches() -> usize {
+    let queued = std::mem::take(
+        &mut get_download_queue()
+            .lock()
+            .expect("DOWNLOAD_QUEUE mutex poisoned")
+            .prefetching,
     )
-    .db_err()?;
-    Ok(())
+    .len();
+    if queued > 0 {
+        get_download_queue_changed().notify_waiters();
+    }
+
+    let running: Vec<String> = get_running_prefetches()
+        .lock()
+        .expect("RUNNING_PREFETCHES mutex poisoned")
+        .iter()
+        .cloned()
+        .collect();
+    let running = running.iter().filter(|id| cancel_download(id)).count();
+
+    queued + running
 }
 
-/// Update PDF metadata for cloud item
-pub fn update_cloud_metadata(
+/// Record that a prefetch holds a download slot
+pub fn register_prefetch(file_id: &str) {
+    get_running_prefetches()
+        .lock()
+        .expect("RUNNING_PREFETCHES mutex poisoned")
+        .insert(file_id.to_string());
+}
+
+pub fn unregister_prefetch(file_id: &str) {
+    get_running_prefetches()
+        .lock()
+        .expect("RUNNING_PREFETCHES mutex poisoned")
+        .remove(file_id);
+}
+
+/// Get the Drive file ID of the cloud item downloaded to `local_path`
+pub fn get_cloud_file_id_by_path(
     app: &AppHandle,
-    drive_file_id: &str,
-    pdf_title: Option<&str>,
-    pdf_author: Option<&str>,
-) -> Result<(), PedaruError> {
+    local_path: &str,
+) -> Result<Option<String>, PedaruError> {
     let conn = open_db(app)?;
-    conn.execute(
-        "UPDATE bookshelf_cloud SET pdf_title = ?1, pdf_author = ?2, updated_at = ?3 WHERE drive_file_id = ?4",
-        rusqlite::params![pdf_title, pdf_author, now_timestamp(), drive_file_id],
+    conn.query_row(
+        "SELECT drive_file_id FROM bookshelf_cloud WHERE local_path = ?1 LIMIT 1",
+        [local_path],
+        |row| row.get(0),
     )
-    .db_err()?;
-    Ok(())
+    .optional()
+    .db_err()
 }
 
-/// Delete local copy of a cloud item (deletes file and resets database)
-pub fn delete_cloud_local_copy(app: &AppHandle, drive_file_id: &str) -> Result<(), PedaruError> {
-    let conn = open_db(app)?;
+/// Get the item after `drive_file_id` by name in the same folder, if it
+/// still needs downloading
+pub fn next_prefetch_candidate(
+    app: &AppHandle,
+    drive_file_id: &str,
+) -> Result<Option<PrefetchCandidate>, PedaruError> {
+    next_prefetch_candidate_on(&open_db(app)?, drive_file_id)
+}
 
-    // Get current local path
-    let local_path: Option<String> = conn
+fn next_prefetch_candidate_on(
+    conn: &rusqlite::Connection,
+    drive_file_id: &str,
+) -> Result<Option<PrefetchCandidate>, PedaruError> {
+    type Neighbor = (String, String, Option<i64>, String, Option<String>);
+    let next: Option<Neighbor> = conn
         .query_row(
-            "SELECT local_path FROM bookshelf_cloud WHERE drive_file_id = ?1",
+            "SELECT n.drive_file_id, n.file_name, n.file_size, n.download_status,
+                    n.download_failure
+             FROM bookshelf_cloud c
+             JOIN bookshelf_cloud n ON n.drive_folder_id = c.drive_folder_id
+             WHERE c.drive_file_id = ?1
+               AND (lower(n.file_name), n.drive_file_id) > (lower(c.file_name), c.drive_file_id)
+             ORDER BY lower(n.file_name), n.drive_file_id
+             LIMIT 1",
             [drive_file_id],
-            |row| row.get(0),
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            },
         )
-        .ok()
-        .flatten();
+        .optional()
+        .db_err()?;
 
-    // Delete file if exists
-    if let Some(path) = local_path {
-        let path = std::path::Path::new(&path);
-        if path.exists() {
-            std::fs::remove_file(path).map_err(|e| {
-                PedaruError::Io(IoError::ReadFailed {
-                    path: path.display().to_string(),
-                    source: e,
-                })
-            })?;
-        }
+    // Downloaded, in progress, failed or cancelled items are left alone
+    let cancelled = DownloadFailure::Cancelled.to_string();
+    Ok(next
+        .filter(|(_, _, _, status, failure)| {
+            status == "pending" && failure.as_ref() != Some(&cancelled)
+        })
+        .map(
+            |(drive_file_id, file_name, file_size, _, _)| PrefetchCandidate {
+                drive_file_id,
+                file_name,
+                file_size: file_size.and_then(|size| u64::try_from(size).ok()),
+            },
+        ))
+}
+
+/// Whether a file of `file_size` bytes fits under `cap` on top of `usage`
+///
+/// A prefetch never evicts other downloads, so it must fit as is.
+fn fits_storage_cap(usage: u64, file_size: Option<u64>, cap: Option<u64>) -> bool {
+    match cap {
+        Some(cap) => usage.saturating_add(file_size.unwrap_or(0)) <= cap,
+        None => true,
     }
+}
+
+/// Whether `candidate` can be prefetched without going over the storage cap
+pub fn prefetch_fits_storage_cap(
+    app: &AppHandle,
+    candidate: &PrefetchCandidate,
+) -> Result<bool, PedaruError> {
+    let usage = storage_usage(app)?;
+    Ok(fits_storage_cap(
+        usage.total_bytes,
+        candidate.file_size,
+        usage.cap_bytes,
+    ))
+}
 
-    // Update database
+// ============================================================================
+// Folder Operations (Google Drive)
+// ============================================================================
+
+/// Add an account's folder to the sync list (optionally including its subfolders)
+///
+/// Without a name the folder is named after its id until
+/// `sync::resolve_folder_names` fetches its Drive name; re-adding a folder
+/// without a name keeps the one it had. Re-adding a folder also clears its
+/// changes token, so its next sync is a full one, which records the shared
+/// drive the folder is on if `drive_id` isn't known yet.
+pub fn add_sync_folder(
+    app: &AppHandle,
+    account_id: i64,
+    folder_id: &str,
+    folder_name: Option<&str>,
+    recursive: bool,
+    drive_id: Option<&str>,
+) -> Result<(), PedaruError> {
+    let conn = open_db(app)?;
     conn.execute(
-        "UPDATE bookshelf_cloud SET
-           local_path = NULL,
-           download_status = 'pending',
-           download_progress = 0,
-           updated_at = ?1
-         WHERE drive_file_id = ?2",
-        rusqlite::params![now_timestamp(), drive_file_id],
+        "INSERT INTO drive_folders
+           (folder_id, folder_name, name_pending, recursive, account_id, drive_id, created_at)
+         VALUES (?1, COALESCE(?2, ?1), ?2 IS NULL, ?3, ?4, ?5, ?6)
+         ON CONFLICT(folder_id) DO UPDATE SET
+           folder_name = COALESCE(?2, folder_name),
+           name_pending = ?2 IS NULL AND name_pending,
+           recursive = excluded.recursive,
+           account_id = excluded.account_id,
+           drive_id = COALESCE(?5, drive_id),
+           is_active = 1,
+           changes_page_token = NULL",
+        rusqlite::params![
+            folder_id,
+            folder_name,
+            recursive as i32,
+            account_id,
+            drive_id,
+            now_timestamp()
+        ],
     )
     .db_err()?;
-
     Ok(())
 }
 
-/// Reset download status for cloud item without deleting the file
-pub fn reset_cloud_download_status(
+/// Active folders still named after their id, with their account
+pub fn get_folders_pending_name(app: &AppHandle) -> Result<Vec<(String, i64)>, PedaruError> {
+    let conn = open_db(app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT folder_id, account_id FROM drive_folders
+             WHERE name_pending = 1 AND is_active = 1",
+        )
+        .db_err()?;
+    let folders = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .db_err()?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(folders)
+}
+
+/// Set a folder's name, or keep its current one (`None`), and stop waiting
+/// for its Drive name
+pub fn set_folder_name(
     app: &AppHandle,
-    drive_file_id: &str,
+    folder_id: &str,
+    folder_name: Option<&str>,
 ) -> Result<(), PedaruError> {
     let conn = open_db(app)?;
-
     conn.execute(
-        "UPDATE bookshelf_cloud SET
+        "UPDATE drive_folders SET folder_name = COALESCE(?1, folder_name), name_pending = 0
+         WHERE folder_id = ?2",
+        rusqlite::params![folder_name, folder_id],
+    )
+    .db_err()?;
+    Ok(())
+}
+
+/// Record the shared drive a folder is on (None for My Drive)
+pub fn set_folder_drive_id(
+    app: &AppHandle,
+    folder_id: &str,
+    drive_id: Option<&str>,
+) -> Result<(), PedaruError> {
+    let conn = open_db(app)?;
+    conn.execute(
+        "UPDATE drive_folders SET drive_id = ?1 WHERE folder_id = ?2",
+        rusqlite::params![drive_id, folder_id],
+    )
+    .db_err()?;
+    Ok(())
+}
+
+/// Remove a folder from the sync list (marks as inactive)
+pub fn remove_sync_folder(app: &AppHandle, folder_id: &str) -> Result<(), PedaruError> {
+    let conn = open_db(app)?;
+    conn.execute(
+        "UPDATE drive_folders SET is_active = 0, changes_page_token = NULL WHERE folder_id = ?1",
+        [folder_id],
+    )
+    .db_err()?;
+    Ok(())
+}
+
+/// Get all active sync folders
+pub fn get_sync_folders(app: &AppHandle) -> Result<Vec<StoredFolder>, PedaruError> {
+    let conn = open_db(app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT folder_id, folder_name, is_active, last_synced, recursive, account_id, auto_sync,
+                    drive_id
+             FROM drive_folders
+             WHERE is_active = 1
+             ORDER BY folder_name",
+        )
+        .db_err()?;
+
+    let folders = stmt
+        .query_map([], |row| {
+            Ok(StoredFolder {
+                folder_id: row.get(0)?,
+                account_id: row.get(5)?,
+                folder_name: row.get(1)?,
+                is_active: row.get::<_, i32>(2)? != 0,
+                last_synced: row.get(3)?,
+                recursive: row.get::<_, i32>(4)? != 0,
+                auto_sync: row.get::<_, i32>(6)? != 0,
+                drive_id: row.get(7)?,
+            })
+        })
+        .db_err()?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(folders)
+}
+
+/// Include or exclude a folder from automatic syncs
+pub fn set_folder_auto_sync(
+    app: &AppHandle,
+    folder_id: &str,
+    enabled: bool,
+) -> Result<(), PedaruError> {
+    let conn = open_db(app)?;
+    conn.execute(
+        "UPDATE drive_folders SET auto_sync = ?1 WHERE folder_id = ?2",
+        rusqlite::params![enabled as i32, folder_id],
+    )
+    .db_err()?;
+    Ok(())
+}
+
+/// Update folder sync timestamp
+pub fn update_folder_sync_time(app: &AppHandle, folder_id: &str) -> Result<(), PedaruError> {
+    let conn = open_db(app)?;
+    conn.execute(
+        "UPDATE drive_folders SET last_synced = ?1 WHERE folder_id = ?2",
+        rusqlite::params![now_timestamp(), folder_id],
+    )
+    .db_err()?;
+    Ok(())
+}
+
+/// Get the Drive changes page token saved by a folder's last sync
+pub fn get_folder_page_token(
+    app: &AppHandle,
+    folder_id: &str,
+) -> Result<Option<String>, PedaruError> {
+    let conn = open_db(app)?;
+    conn.query_row(
+        "SELECT changes_page_token FROM drive_folders WHERE folder_id = ?1",
+        [folder_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .db_err()
+    .map(Option::flatten)
+}
+
+/// Save the Drive changes page token for a folder's next sync
+pub fn set_folder_page_token(
+    app: &AppHandle,
+    folder_id: &str,
+    page_token: &str,
+) -> Result<(), PedaruError> {
+    let conn = open_db(app)?;
+    conn.execute(
+        "UPDATE drive_folders SET changes_page_token = ?1 WHERE folder_id = ?2",
+        [page_token, folder_id],
+    )
+    .db_err()?;
+    Ok(())
+}
+
+/// Get a watched folder's known subfolders, mapped to their paths
+///
+/// Always includes the watched folder itself with an empty path.
+pub fn get_folder_tree(
+    app: &AppHandle,
+    root_folder_id: &str,
+) -> Result<HashMap<String, String>, PedaruError> {
+    let conn = open_db(app)?;
+    let mut stmt = conn
+        .prepare("SELECT folder_id, folder_path FROM drive_subfolders WHERE root_folder_id = ?1")
+        .db_err()?;
+    let mut folders: HashMap<String, String> = stmt
+        .query_map([root_folder_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .db_err()?
+        .filter_map(|r| r.ok())
+        .collect();
+    folders.insert(root_folder_id.to_string(), String::new());
+    Ok(folders)
+}
+
+/// Replace a watched folder's subfolder tree after a full listing
+pub fn set_folder_tree(
+    app: &AppHandle,
+    root_folder_id: &str,
+    folders: &[(String, String)],
+) -> Result<(), PedaruError> {
+    let mut conn = open_db(app)?;
+    let tx = conn.transaction().db_err()?;
+    tx.execute(
+        "DELETE FROM drive_subfolders WHERE root_folder_id = ?1",
+        [root_folder_id],
+    )
+    .db_err()?;
+    for (folder_id, folder_path) in folders {
+        tx.execute(
+            "INSERT OR REPLACE INTO drive_subfolders (root_folder_id, folder_id, folder_path)
+             VALUES (?1, ?2, ?3)",
+            [root_folder_id, folder_id, folder_path],
+        )
+        .db_err()?;
+    }
+    tx.commit().db_err()
+}
+
+// ============================================================================
+// Cloud Item Operations (Google Drive)
+// ============================================================================
+
+/// Upsert cloud item from Drive file
+///
+/// `root_folder_id` is the watched folder the file was synced through.
+pub fn upsert_cloud_item(
+    app: &AppHandle,
+    account_id: i64,
+    root_folder_id: &str,
+    item: &DriveFileInput,
+) -> Result<UpsertOutcome, PedaruError> {
+    upsert_cloud_item_on(&open_db(app)?, account_id, root_folder_id, item)
+}
+
+/// Upsert many cloud items from one watched folder in a single transaction
+///
+/// On large folders this is far faster than one `upsert_cloud_item` per
+/// file, which opens a connection and commits (syncing to disk) each time.
+/// On error nothing is written. Returns each item's outcome, in order.
+pub fn upsert_cloud_items(
+    app: &AppHandle,
+    account_id: i64,
+    root_folder_id: &str,
+    items: &[DriveFileInput],
+) -> Result<Vec<UpsertOutcome>, PedaruError> {
+    upsert_cloud_items_on(&mut open_db(app)?, account_id, root_folder_id, items)
+}
+
+/// `upsert_cloud_items` on an existing connection
+fn upsert_cloud_items_on(
+    conn: &mut rusqlite::Connection,
+    account_id: i64,
+    root_folder_id: &str,
+    items: &[DriveFileInput],
+) -> Result<Vec<UpsertOutcome>, PedaruError> {
+    let tx = conn.transaction().db_err()?;
+    let outcomes = items
+        .iter()
+        .map(|item| upsert_cloud_item_on(&tx, account_id, root_folder_id, item))
+        .collect::<Result<Vec<_>, _>>()?;
+    tx.commit().db_err()?;
+    Ok(outcomes)
+}
+
+/// `upsert_cloud_item` on an existing connection (or transaction)
+fn upsert_cloud_item_on(
+    conn: &rusqlite::Connection,
+    account_id: i64,
+    root_folder_id: &str,
+    item: &DriveFileInput,
+) -> Result<UpsertOutcome, PedaruError> {
+    type Listing = (String, Option<String>, String, Option<i64>, Option<String>);
+    let existing: Option<Listing> = conn
+        .prepare_cached(
+            "SELECT drive_folder_id, folder_path, file_name, file_size, drive_modified_time
+             FROM bookshelf_cloud WHERE drive_file_id = ?1",
+        )
+        .db_err()?
+        .query_row([&item.file.id], |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+            ))
+        })
+        .optional()
+        .db_err()?;
+    let outcome = match existing {
+        None => UpsertOutcome::Added,
+        Some(listing)
+            if listing
+                == (
+                    item.folder_id.to_string(),
+                    item.folder_path.map(String::from),
+                    item.file.local_name(),
+                    item.file.size_bytes(),
+                    item.file.modified_time.clone(),
+                ) =>
+        {
+            UpsertOutcome::Unchanged
+        }
+        Some(_) => UpsertOutcome::Updated,
+    };
+
+    conn.prepare_cached(
+        "INSERT INTO bookshelf_cloud (
+           drive_file_id, drive_folder_id, root_folder_id, folder_path, file_name,
+           file_size, drive_modified_time, account_id, created_at, updated_at
+         )
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)
+         ON CONFLICT(drive_file_id) DO UPDATE SET
+           drive_folder_id = excluded.drive_folder_id,
+           account_id = excluded.account_id,
+           root_folder_id = excluded.root_folder_id,
+           folder_path = excluded.folder_path,
+           file_name = excluded.file_name,
+           file_size = excluded.file_size,
+           drive_modified_time = excluded.drive_modified_time,
+           updated_at = excluded.updated_at",
+    )
+    .db_err()?
+    .execute(rusqlite::params![
+        item.file.id,
+        item.folder_id,
+        root_folder_id,
+        item.folder_path,
+        item.file.local_name(),
+        item.file.size_bytes(),
+        item.file.modified_time,
+        account_id,
+        now_timestamp()
+    ])
+    .db_err()?;
+
+    Ok(outcome)
+}
+
+/// IDs of the active watched folders and their known subfolders
+pub fn get_watched_folder_ids(app: &AppHandle) -> Result<HashSet<String>, PedaruError> {
+    let conn = open_db(app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT folder_id FROM drive_folders WHERE is_active = 1
+             UNION
+             SELECT s.folder_id FROM drive_subfolders s
+             JOIN drive_folders f ON f.folder_id = s.root_folder_id
+             WHERE f.is_active = 1",
+        )
+        .db_err()?;
+    let ids = stmt
+        .query_map([], |row| row.get(0))
+        .db_err()?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(ids)
+}
+
+/// Move a cloud item to another watched folder (or one of its subfolders)
+///
+/// The row is updated in place, so the download, tags, reading progress and
+/// thumbnail stay with it. Returns false if the item doesn't exist or is
+/// already in that folder.
+pub fn move_item(
+    app: &AppHandle,
+    drive_file_id: &str,
+    new_folder_id: &str,
+) -> Result<bool, PedaruError> {
+    move_item_on(&open_db(app)?, drive_file_id, new_folder_id)
+}
+
+/// `move_item` on an existing connection
+fn move_item_on(
+    conn: &rusqlite::Connection,
+    drive_file_id: &str,
+    new_folder_id: &str,
+) -> Result<bool, PedaruError> {
+    let location: Option<(String, Option<String>)> = conn
+        .query_row(
+            "SELECT folder_id, NULL FROM drive_folders
+             WHERE folder_id = ?1 AND is_active = 1
+             UNION ALL
+             SELECT s.root_folder_id, NULLIF(s.folder_path, '') FROM drive_subfolders s
+             JOIN drive_folders f ON f.folder_id = s.root_folder_id
+             WHERE s.folder_id = ?1 AND f.is_active = 1
+             LIMIT 1",
+            [new_folder_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .db_err()?;
+    let Some((root_folder_id, folder_path)) = location else {
+        return Err(GoogleDriveError::InvalidFolderId(new_folder_id.to_string()).into());
+    };
+
+    let moved = conn
+        .execute(
+            "UPDATE bookshelf_cloud SET
+               drive_folder_id = ?1,
+               root_folder_id = ?2,
+               folder_path = ?3,
+               updated_at = ?4
+             WHERE drive_file_id = ?5
+               AND (drive_folder_id != ?1 OR root_folder_id IS NOT ?2)",
+            rusqlite::params![
+                new_folder_id,
+                root_folder_id,
+                folder_path,
+                now_timestamp(),
+                drive_file_id
+            ],
+        )
+        .db_err()?;
+    if moved > 0 {
+        log::info!("Moved {} to Drive folder {}", drive_file_id, new_folder_id);
+    }
+    Ok(moved > 0)
+}
+
+/// Get the Google account a cloud item belongs to
+pub fn get_item_account(app: &AppHandle, drive_file_id: &str) -> Result<i64, PedaruError> {
+    let conn = open_db(app)?;
+    conn.query_row(
+        "SELECT account_id FROM bookshelf_cloud WHERE drive_file_id = ?1",
+        [drive_file_id],
+        |row| row.get(0),
+    )
+    .optional()
+    .db_err()?
+    .ok_or_else(|| GoogleDriveError::FileNotFound(drive_file_id.to_string()).into())
+}
+
+/// Get all cloud items
+/// Sorted by last_opened (most recent first), then by file_name for items never opened
+pub fn get_cloud_items(app: &AppHandle) -> Result<Vec<CloudItem>, PedaruError> {
+    let conn = open_db(app)?;
+    let thumbnails_dir = get_thumbnails_dir(app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT c.id, c.drive_file_id, c.drive_folder_id, c.file_name, c.file_size,
+                    c.thumbnail_data, c.local_path, c.download_status, c.download_progress,
+                    c.pdf_title, c.pdf_author, c.is_favorite, c.last_opened,
+                    c.root_folder_id, c.folder_path, p.page, c.page_count, c.download_error,
+                    c.download_retry_count, c.drive_modified_time, c.account_id, c.thumbnail_path,
+                    c.custom_name, c.created_at, c.updated_at, c.download_failure
+             FROM bookshelf_cloud c
+             LEFT JOIN reading_progress p ON p.drive_file_id = c.drive_file_id
+             ORDER BY c.last_opened IS NULL, c.last_opened DESC, c.file_name ASC",
+        )
+        .db_err()?;
+
+    let items = stmt
+        .query_map([], |row| {
+            let status_str: String = row.get(7)?;
+            let download_status = status_str.parse().unwrap_or_default();
+            let file_name: String = row.get(3)?;
+            let pdf_title: Option<String> = row.get(9)?;
+            let custom_name: Option<String> = row.get(22)?;
+            let item = CloudItem {
+                id: row.get(0)?,
+                drive_file_id: row.get(1)?,
+                account_id: row.get(20)?,
+                drive_folder_id: row.get(2)?,
+                root_folder_id: row.get(13)?,
+                folder_path: row.get(14)?,
+                display_name: display_name(
+                    custom_name.as_deref(),
+                    pdf_title.as_deref(),
+                    &file_name,
+                ),
+                file_name,
+                file_size: row.get(4)?,
+                drive_modified_time: row.get(19)?,
+                thumbnail_data: row.get(5)?,
+                thumbnail_path: row
+                    .get::<_, Option<String>>(21)?
+                    .map(|path| thumbnails_dir.join(path).to_string_lossy().into_owned()),
+                local_path: row.get(6)?,
+                download_status,
+                download_progress: row.get(8)?,
+                download_error: row.get(17)?,
+                download_failure: row
+                    .get::<_, Option<String>>(25)?
+                    .and_then(|s| s.parse().ok()),
+                retry_count: row.get(18)?,
+                pdf_title,
+                pdf_author: row.get(10)?,
+                page_count: row.get(16)?,
+                custom_name,
+                is_favorite: row.get::<_, i64>(11)? != 0,
+                last_opened: row.get(12)?,
+                last_read_page: row.get(15)?,
+                tags: Vec::new(),
+                created_at: row.get(23)?,
+                updated_at: row.get(24)?,
+            };
+            Ok(item)
+        })
+        .db_err()?
+        .filter_map(|r| r.ok())
+        .collect::<Vec<_>>();
+    drop(stmt);
+
+    // Attach tags
+    let mut tags_by_file: HashMap<String, Vec<String>> = HashMap::new();
+    let mut stmt = conn
+        .prepare(
+            "SELECT bt.drive_file_id, t.name
+             FROM bookshelf_tags bt
+             JOIN tags t ON t.id = bt.tag_id
+             ORDER BY t.name COLLATE NOCASE",
+        )
+        .db_err()?;
+    for (drive_file_id, name) in stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))
+        .db_err()?
+        .filter_map(|r| r.ok())
+    {
+        tags_by_file.entry(drive_file_id).or_default().push(name);
+    }
+
+    let items = items
+        .into_iter()
+        .map(|mut item| {
+            item.tags = tags_by_file.remove(&item.drive_file_id).unwrap_or_default();
+            item
+        })
+        .collect();
+
+    Ok(items)
+}
+
+/// Update download status for cloud item
+///
+/// Clears the last failure unless `failure` says why the download stopped
+/// (e.g. a cancel, which puts the item back to pending). Stale updates from
+/// a download that already finished or was cancelled are ignored (see
+/// `update_download_status_on`).
+pub fn update_download_status(
+    app: &AppHandle,
+    drive_file_id: &str,
+    status: &str,
+    progress: f64,
+    local_path: Option<&str>,
+    failure: Option<DownloadFailure>,
+) -> Result<(), PedaruError> {
+    let applied = retry_busy(|| {
+        update_download_status_on(
+            &open_db(app)?,
+            drive_file_id,
+            status,
+            progress,
+            local_path,
+            failure,
+        )
+    })?;
+    if !applied {
+        log::debug!(
+            "Ignored stale '{}' status update for {}",
+            status,
+            drive_file_id
+        );
+    }
+    Ok(())
+}
+
+/// Update download status, returning false when the update was stale
+///
+/// Updates can arrive out of order, so a download never moves an item back
+/// to `downloading` once it completed or was cancelled (a new download
+/// starts from `queued`), and progress only moves forward while downloading.
+fn update_download_status_on(
+    conn: &rusqlite::Connection,
+    drive_file_id: &str,
+    status: &str,
+    progress: f64,
+    local_path: Option<&str>,
+    failure: Option<DownloadFailure>,
+) -> Result<bool, PedaruError> {
+    let updated = conn
+        .prepare_cached(
+            "UPDATE bookshelf_cloud SET
+               download_status = ?1,
+               download_progress = ?2,
+               local_path = COALESCE(?3, local_path),
+               reserved_path = CASE WHEN ?1 = 'completed' THEN NULL ELSE reserved_path END,
+               download_error = NULL,
+               download_failure = ?4,
+               updated_at = ?5
+             WHERE drive_file_id = ?6
+               AND NOT (?1 = 'downloading' AND (
+                 download_status = 'completed'
+                 OR (download_status = 'pending' AND download_failure = 'cancelled')
+                 OR (download_status = 'downloading' AND download_progress > ?2)
+               ))",
+        )
+        .db_err()?
+        .execute(rusqlite::params![
+            status,
+            progress,
+            local_path,
+            failure.map(|f| f.to_string()),
+            now_timestamp(),
+            drive_file_id
+        ])
+        .db_err()?;
+    Ok(updated > 0)
+}
+
+/// Reset a running download's progress before a retry
+///
+/// Bypasses the forward-only check of `update_download_status_on`, but only
+/// touches items still downloading, so a cancel or completion stays put.
+fn restart_download_progress_on(
+    conn: &rusqlite::Connection,
+    drive_file_id: &str,
+) -> Result<(), PedaruError> {
+    conn.execute(
+        "UPDATE bookshelf_cloud SET download_progress = 0, updated_at = ?1
+         WHERE drive_file_id = ?2 AND download_status = 'downloading'",
+        rusqlite::params![now_timestamp(), drive_file_id],
+    )
+    .db_err()?;
+    Ok(())
+}
+
+/// Record whether the user asked for an item to be downloaded
+///
+/// Requested downloads that haven't completed are resumed on startup.
+pub fn set_download_requested(
+    app: &AppHandle,
+    drive_file_id: &str,
+    requested: bool,
+) -> Result<(), PedaruError> {
+    retry_busy(|| {
+        let conn = open_db(app)?;
+        conn.execute(
+            "UPDATE bookshelf_cloud SET download_requested = ?1 WHERE drive_file_id = ?2",
+            rusqlite::params![requested as i32, drive_file_id],
+        )
+        .db_err()?;
+        Ok(())
+    })
+}
+
+/// Forget every download request (e.g. when the user stops all downloads)
+pub fn clear_download_requests(app: &AppHandle) -> Result<(), PedaruError> {
+    retry_busy(|| {
+        let conn = open_db(app)?;
+        conn.execute(
+            "UPDATE bookshelf_cloud SET download_requested = 0 WHERE download_requested = 1",
+            [],
+        )
+        .db_err()?;
+        Ok(())
+    })
+}
+
+/// Get requested downloads that haven't completed, as (drive_file_id, file_name)
+pub fn get_requested_downloads(app: &AppHandle) -> Result<Vec<(String, String)>, PedaruError> {
+    requested_downloads_on(&open_db(app)?)
+}
+
+fn requested_downloads_on(
+    conn: &rusqlite::Connection,
+) -> Result<Vec<(String, String)>, PedaruError> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT drive_file_id, file_name FROM bookshelf_cloud
+             WHERE download_requested = 1 AND download_status != 'completed'
+             ORDER BY updated_at",
+        )
+        .db_err()?;
+    let downloads = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .db_err()?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(downloads)
+}
+
+/// Mark a cloud item's download as failed, recording why
+///
+/// The download stays requested (and is resumed on the next start) only
+/// when `failure` is retryable.
+pub fn mark_download_failed(
+    app: &AppHandle,
+    drive_file_id: &str,
+    failure: DownloadFailure,
+    reason: &str,
+) -> Result<(), PedaruError> {
+    retry_busy(|| mark_download_failed_on(&open_db(app)?, drive_file_id, failure, reason))
+}
+
+fn mark_download_failed_on(
+    conn: &rusqlite::Connection,
+    drive_file_id: &str,
+    failure: DownloadFailure,
+    reason: &str,
+) -> Result<(), PedaruError> {
+    conn.execute(
+        "UPDATE bookshelf_cloud SET
+           download_status = 'error',
+           download_progress = 0,
+           download_error = ?1,
+           download_failure = ?2,
+           download_requested = download_requested AND ?3,
+           updated_at = ?4
+         WHERE drive_file_id = ?5",
+        rusqlite::params![
+            reason,
+            failure.to_string(),
+            failure.is_retryable(),
+            now_timestamp(),
+            drive_file_id
+        ],
+    )
+    .db_err()?;
+    Ok(())
+}
+
+/// Get the number of bytes already written for a partial download
+pub fn get_downloaded_bytes(app: &AppHandle, drive_file_id: &str) -> Result<u64, PedaruError> {
+    let conn = open_db(app)?;
+    let bytes: i64 = conn
+        .query_row(
+            "SELECT downloaded_bytes FROM bookshelf_cloud WHERE drive_file_id = ?1",
+            [drive_file_id],
+            |row| row.get(0),
+        )
+        .db_err()?;
+    Ok(bytes.max(0) as u64)
+}
+
+/// Record the number of bytes written so far (the resume offset)
+pub fn update_downloaded_bytes(
+    app: &AppHandle,
+    drive_file_id: &str,
+    bytes: u64,
+) -> Result<(), PedaruError> {
+    retry_busy(|| {
+        let conn = open_db(app)?;
+        conn.execute(
+            "UPDATE bookshelf_cloud SET downloaded_bytes = ?1 WHERE drive_file_id = ?2",
+            rusqlite::params![bytes as i64, drive_file_id],
+        )
+        .db_err()?;
+        Ok(())
+    })
+}
+
+/// Point a cloud item at an existing download of the same PDF
+///
+/// If another completed item has the same Drive MD5 checksum and its file
+/// still exists, the item shares that file (and its metadata) instead of
+/// downloading a copy of its own. Items that already have a local copy or are
+/// queued for download are left alone. Returns the shared path if the item was linked.
+pub fn link_duplicate_download(
+    app: &AppHandle,
+    drive_file_id: &str,
+    md5_checksum: &str,
+) -> Result<Option<String>, PedaruError> {
+    if get_cancel_flag(drive_file_id).is_some() {
+        return Ok(None);
+    }
+    link_duplicate_download_on(&open_db(app)?, drive_file_id, md5_checksum)
+}
+
+fn link_duplicate_download_on(
+    conn: &rusqlite::Connection,
+    drive_file_id: &str,
+    md5_checksum: &str,
+) -> Result<Option<String>, PedaruError> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT drive_file_id, local_path FROM bookshelf_cloud
+             WHERE md5_checksum = ?1 AND drive_file_id != ?2
+               AND download_status = 'completed' AND local_path IS NOT NULL",
+        )
+        .db_err()?;
+    let source = stmt
+        .query_map([md5_checksum, drive_file_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .db_err()?
+        .filter_map(|r| r.ok())
+        .find(|(_, path)| std::path::Path::new(path).exists());
+    drop(stmt);
+
+    let Some((source_id, local_path)) = source else {
+        return Ok(None);
+    };
+    let linked = conn
+        .execute(
+            "UPDATE bookshelf_cloud SET
+               local_path = src.local_path,
+               reserved_path = NULL,
+               download_status = 'completed',
+               download_progress = 100,
+               downloaded_bytes = src.downloaded_bytes,
+               download_error = NULL,
+               download_failure = NULL,
+               md5_checksum = src.md5_checksum,
+               pdf_title = COALESCE(bookshelf_cloud.pdf_title, src.pdf_title),
+               pdf_author = COALESCE(bookshelf_cloud.pdf_author, src.pdf_author),
+               page_count = COALESCE(bookshelf_cloud.page_count, src.page_count),
+               updated_at = ?3
+             FROM (SELECT * FROM bookshelf_cloud WHERE drive_file_id = ?2) AS src
+             WHERE bookshelf_cloud.drive_file_id = ?1
+               AND bookshelf_cloud.local_path IS NULL
+               AND bookshelf_cloud.download_status NOT IN ('downloading', 'queued')",
+            rusqlite::params![drive_file_id, source_id, now_timestamp()],
+        )
+        .db_err()?;
+
+    if linked == 0 {
+        return Ok(None);
+    }
+    log::info!(
+        "{} is a duplicate of {}, sharing its download",
+        drive_file_id,
+        source_id
+    );
+    Ok(Some(local_path))
+}
+
+/// Store Drive's MD5 checksum for a downloaded cloud item
+pub fn update_md5_checksum(
+    app: &AppHandle,
+    drive_file_id: &str,
+    md5_checksum: Option<&str>,
+) -> Result<(), PedaruError> {
+    retry_busy(|| {
+        let conn = open_db(app)?;
+        conn.execute(
+            "UPDATE bookshelf_cloud SET md5_checksum = ?1 WHERE drive_file_id = ?2",
+            rusqlite::params![md5_checksum, drive_file_id],
+        )
+        .db_err()?;
+        Ok(())
+    })
+}
+
+// ============================================================================
+// Thumbnails (cloud items)
+// ============================================================================
+
+/// Directory holding cloud item thumbnails, under the app config dir
+const THUMBNAILS_DIR_NAME: &str = "thumbnails";
+
+/// Get thumbnails directory path
+pub fn get_thumbnails_dir(app: &AppHandle) -> Result<std::path::PathBuf, PedaruError> {
+    Ok(get_downloads_dir(app)?.with_file_name(THUMBNAILS_DIR_NAME))
+}
+
+/// Thumbnail file name for a cloud item (relative to the thumbnails dir)
+fn thumbnail_file_name(drive_file_id: &str) -> String {
+    let safe_id: String = drive_file_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}.png", safe_id)
+}
+
+/// Write a thumbnail PNG and return its path relative to `thumbnails_dir`
+fn write_thumbnail_file(
+    thumbnails_dir: &std::path::Path,
+    drive_file_id: &str,
+    png: &[u8],
+) -> Result<String, PedaruError> {
+    std::fs::create_dir_all(thumbnails_dir).map_err(|source| IoError::CreateDirFailed {
+        path: thumbnails_dir.display().to_string(),
+        source,
+    })?;
+    let file_name = thumbnail_file_name(drive_file_id);
+    let path = thumbnails_dir.join(&file_name);
+    std::fs::write(&path, png).map_err(|source| IoError::WriteFailed {
+        path: path.display().to_string(),
+        source,
+    })?;
+    Ok(file_name)
+}
+
+/// Delete a cloud item's thumbnail file (succeeds if it doesn't exist)
+fn remove_thumbnail_file(app: &AppHandle, drive_file_id: &str) -> Result<(), PedaruError> {
+    let path = get_thumbnails_dir(app)?.join(thumbnail_file_name(drive_file_id));
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(source) => Err(IoError::WriteFailed {
+            path: path.display().to_string(),
+            source,
+        }
+        .into()),
+    }
+}
+
+/// Thumbnail image sent by the frontend: a base64 string or raw PNG bytes
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ThumbnailInput {
+    Base64(String),
+    Bytes(Vec<u8>),
+}
+
+impl ThumbnailInput {
+    fn into_bytes(self) -> Result<Vec<u8>, PedaruError> {
+        match self {
+            ThumbnailInput::Base64(data) => general_purpose::STANDARD
+                .decode(data)
+                .map_err(|e| PdfError::InvalidThumbnail(e.to_string()).into()),
+            ThumbnailInput::Bytes(bytes) => Ok(bytes),
+        }
+    }
+}
+
+/// Downscale, compress and store a thumbnail PNG for a cloud item
+///
+/// Returns the PNG as stored.
+pub fn save_cloud_thumbnail(
+    app: &AppHandle,
+    drive_file_id: &str,
+    png: &[u8],
+) -> Result<Vec<u8>, PedaruError> {
+    let png = thumbnail::compress_thumbnail(png)?;
+    let thumbnail_path = write_thumbnail_file(&get_thumbnails_dir(app)?, drive_file_id, &png)?;
+    let conn = open_db(app)?;
+    conn.execute(
+        "UPDATE bookshelf_cloud SET thumbnail_path = ?1, thumbnail_data = NULL, updated_at = ?2
+         WHERE drive_file_id = ?3",
+        rusqlite::params![thumbnail_path, now_timestamp(), drive_file_id],
+    )
+    .db_err()?;
+    Ok(png)
+}
+
+/// Update thumbnail for cloud item
+pub fn update_cloud_thumbnail(
+    app: &AppHandle,
+    drive_file_id: &str,
+    thumbnail_data: ThumbnailInput,
+) -> Result<(), PedaruError> {
+    save_cloud_thumbnail(app, drive_file_id, &thumbnail_data.into_bytes()?)?;
+    Ok(())
+}
+
+/// Move thumbnails stored inline in the database out to files
+///
+/// Runs on startup until every row is moved; returns how many were moved.
+/// Data that isn't a valid base64 PNG is dropped, so the thumbnail is rendered
+/// again.
+pub fn migrate_thumbnails_to_files(app: &AppHandle) -> Result<usize, PedaruError> {
+    let thumbnails_dir = get_thumbnails_dir(app)?;
+    let conn = open_db(app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT drive_file_id, thumbnail_data FROM bookshelf_cloud
+             WHERE thumbnail_data IS NOT NULL",
+        )
+        .db_err()?;
+    let thumbnails: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .db_err()?
+        .filter_map(|r| r.ok())
+        .collect();
+    drop(stmt);
+
+    for (drive_file_id, thumbnail_data) in &thumbnails {
+        let png = general_purpose::STANDARD
+            .decode(thumbnail_data)
+            .ok()
+            .and_then(|png| thumbnail::compress_thumbnail(&png).ok());
+        let thumbnail_path = match png {
+            Some(png) => Some(write_thumbnail_file(&thumbnails_dir, drive_file_id, &png)?),
+            None => None,
+        };
+        conn.execute(
+            "UPDATE bookshelf_cloud SET thumbnail_path = ?1, thumbnail_data = NULL
+             WHERE drive_file_id = ?2",
+            rusqlite::params![thumbnail_path, drive_file_id],
+        )
+        .db_err()?;
+    }
+
+    if !thumbnails.is_empty() {
+        log::info!("Moved {} thumbnails out of the database", thumbnails.len());
+    }
+    Ok(thumbnails.len())
+}
+
+/// Set or clear (`None` or blank) the user's name for a cloud item
+pub fn set_custom_name(
+    app: &AppHandle,
+    drive_file_id: &str,
+    custom_name: Option<&str>,
+) -> Result<(), PedaruError> {
+    let custom_name = custom_name.map(str::trim).filter(|name| !name.is_empty());
+    let conn = open_db(app)?;
+    let updated = conn
+        .execute(
+            "UPDATE bookshelf_cloud SET custom_name = ?1, updated_at = ?2 WHERE drive_file_id = ?3",
+            rusqlite::params![custom_name, now_timestamp(), drive_file_id],
+        )
+        .db_err()?;
+    if updated == 0 {
+        return Err(DatabaseError::NotFound.into());
+    }
+    Ok(())
+}
+
+/// Update PDF metadata for cloud item
+///
+/// A `None` page count leaves the stored value unchanged.
+pub fn update_cloud_metadata(
+    app: &AppHandle,
+    drive_file_id: &str,
+    pdf_title: Option<&str>,
+    pdf_author: Option<&str>,
+    page_count: Option<i64>,
+) -> Result<(), PedaruError> {
+    let conn = open_db(app)?;
+    conn.execute(
+        "UPDATE bookshelf_cloud SET
+           pdf_title = ?1,
+           pdf_author = ?2,
+           page_count = COALESCE(?3, page_count),
+           updated_at = ?4
+         WHERE drive_file_id = ?5",
+        rusqlite::params![
+            pdf_title,
+            pdf_author,
+            page_count,
+            now_timestamp(),
+            drive_file_id
+        ],
+    )
+    .db_err()?;
+    Ok(())
+}
+
+/// Look up the local path of a cloud item's downloaded copy
+fn get_cloud_local_path(
+    conn: &rusqlite::Connection,
+    drive_file_id: &str,
+) -> Result<Option<String>, PedaruError> {
+    Ok(conn
+        .query_row(
+            "SELECT local_path FROM bookshelf_cloud WHERE drive_file_id = ?1",
+            [drive_file_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .db_err()?
+        .flatten())
+}
+
+/// Delete a downloaded file if it exists
+fn remove_downloaded_file(path: &str) -> Result<(), PedaruError> {
+    let path = std::path::Path::new(path);
+    if path.exists() {
+        std::fs::remove_file(path).map_err(|e| {
+            PedaruError::Io(IoError::ReadFailed {
+                path: path.display().to_string(),
+                source: e,
+            })
+        })?;
+    }
+    Ok(())
+}
+
+/// Reset a cloud item's row after its local copy was deleted
+///
+/// The caller deletes the thumbnail file once this is committed.
+fn clear_cloud_local_copy(
+    conn: &rusqlite::Connection,
+    drive_file_id: &str,
+) -> Result<(), PedaruError> {
+    conn.execute(
+        "UPDATE bookshelf_cloud SET
+           local_path = NULL,
+           download_status = 'pending',
+           download_progress = 0,
+           downloaded_bytes = 0,
+           thumbnail_path = NULL,
+           thumbnail_data = NULL,
+           updated_at = ?1
+         WHERE drive_file_id = ?2",
+        rusqlite::params![now_timestamp(), drive_file_id],
+    )
+    .db_err()?;
+    Ok(())
+}
+
+/// Get the file to delete along with a cloud item's local copy
+///
+/// Duplicates of the same PDF can share one local file (see
+/// `link_duplicate_download`), so the path is only returned when no other
+/// item still references it.
+fn local_copy_to_remove(
+    conn: &rusqlite::Connection,
+    drive_file_id: &str,
+) -> Result<Option<String>, PedaruError> {
+    let Some(path) = get_cloud_local_path(conn, drive_file_id)? else {
+        return Ok(None);
+    };
+    let shared: bool = conn
+        .query_row(
+            "SELECT EXISTS(
+               SELECT 1 FROM bookshelf_cloud WHERE local_path = ?1 AND drive_file_id != ?2
+             )",
+            [&path, drive_file_id],
+            |row| row.get(0),
+        )
+        .db_err()?;
+    Ok((!shared).then_some(path))
+}
+
+/// Reset a cloud item whose local copy is corrupt, along with every item
+/// sharing that file
+///
+/// Returns the items reset and the file to delete, which no item references
+/// once they are all reset.
+fn reset_corrupt_local_copy(
+    conn: &rusqlite::Connection,
+    drive_file_id: &str,
+) -> Result<(Vec<String>, Option<String>), PedaruError> {
+    let Some(path) = get_cloud_local_path(conn, drive_file_id)? else {
+        return Ok((Vec::new(), None));
+    };
+    let mut stmt = conn
+        .prepare("SELECT drive_file_id FROM bookshelf_cloud WHERE local_path = ?1 AND drive_file_id != ?2")
+        .db_err()?;
+    let mut reset: Vec<String> = stmt
+        .query_map([&path, drive_file_id], |row| row.get(0))
+        .db_err()?
+        .collect::<Result<_, _>>()
+        .db_err()?;
+    for other in &reset {
+        clear_cloud_local_copy(conn, other)?;
+    }
+
+    let to_remove = local_copy_to_remove(conn, drive_file_id)?;
+    clear_cloud_local_copy(conn, drive_file_id)?;
+    reset.push(drive_file_id.to_string());
+    Ok((reset, to_remove))
+}
+
+/// Delete local copy of a cloud item (deletes file and resets database)
+pub fn delete_cloud_local_copy(app: &AppHandle, drive_file_id: &str) -> Result<(), PedaruError> {
+    let conn = open_db(app)?;
+
+    // Reset the row first, so a failed delete leaves an unreferenced file
+    // rather than an item pointing at a missing one
+    let to_remove = local_copy_to_remove(&conn, drive_file_id)?;
+    clear_cloud_local_copy(&conn, drive_file_id)?;
+    if let Some(path) = to_remove {
+        remove_downloaded_file(&path)?;
+    }
+    remove_thumbnail_file(app, drive_file_id)
+}
+
+/// Delete the local copies of several cloud items at once
+///
+/// All row updates share one transaction and files are only deleted once it
+/// is committed, so a database error leaves every item and file as it was.
+/// Files or thumbnails that can't be removed (e.g. locked) are reported per
+/// item and the other items carry on; their rows are already reset, so the
+/// files are left over as unreferenced.
+pub fn delete_cloud_local_copies(
+    app: &AppHandle,
+    drive_file_ids: &[String],
+) -> Result<Vec<DeleteResult>, PedaruError> {
+    let to_remove = clear_cloud_local_copies_on(&mut open_db(app)?, drive_file_ids)?;
+
+    let mut results = Vec::with_capacity(drive_file_ids.len());
+    for (drive_file_id, path) in drive_file_ids.iter().zip(to_remove) {
+        let removed = match path {
+            Some(path) => remove_downloaded_file(&path),
+            None => Ok(()),
+        };
+        let error = removed
+            .and(remove_thumbnail_file(app, drive_file_id))
+            .err()
+            .map(|e| e.to_string());
+        results.push(DeleteResult {
+            drive_file_id: drive_file_id.clone(),
+            success: error.is_none(),
+            error,
+        });
+    }
+    Ok(results)
+}
+
+/// Reset several cloud items' rows in one transaction, returning for each
+/// item the file to delete (see `local_copy_to_remove`)
+///
+/// Items sharing a file return it only for the last of them, once no row
+/// references it.
+fn clear_cloud_local_copies_on(
+    conn: &mut rusqlite::Connection,
+    drive_file_ids: &[String],
+) -> Result<Vec<Option<String>>, PedaruError> {
+    let tx = conn.transaction().db_err()?;
+    let mut to_remove = Vec::with_capacity(drive_file_ids.len());
+    for drive_file_id in drive_file_ids {
+        to_remove.push(local_copy_to_remove(&tx, drive_file_id)?);
+        clear_cloud_local_copy(&tx, drive_file_id)?;
+    }
+    tx.commit().db_err()?;
+    Ok(to_remove)
+}
+
+/// Delete every cloud item's local copy and partial download
+///
+/// Every item goes back to 'pending', as if it had never been downloaded.
+pub fn delete_all_cloud_local_copies(app: &AppHandle) -> Result<Vec<DeleteResult>, PedaruError> {
+    let drive_file_ids: Vec<String> = {
+        let conn = open_db(app)?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT drive_file_id FROM bookshelf_cloud
+                 WHERE local_path IS NOT NULL OR downloaded_bytes > 0
+                    OR download_status != 'pending'",
+            )
+            .db_err()?;
+        stmt.query_map([], |row| row.get(0))
+            .db_err()?
+            .filter_map(|r| r.ok())
+            .collect()
+    };
+
+    let results = delete_cloud_local_copies(app, &drive_file_ids)?;
+    // Partial files are orphaned now that no item records their progress
+    remove_orphaned_partial_downloads(app)?;
+    Ok(results)
+}
+
+/// Reset download status for cloud item without deleting the file
+pub fn reset_cloud_download_status(
+    app: &AppHandle,
+    drive_file_id: &str,
+) -> Result<(), PedaruError> {
+    let conn = open_db(app)?;
+
+    conn.execute(
+        "UPDATE bookshelf_cloud SET
            local_path = NULL,
            download_status = 'pending',
            download_progress = 0,
+           downloaded_bytes = 0,
+           thumbnail_path = NULL,
            thumbnail_data = NULL,
            updated_at = ?1
          WHERE drive_file_id = ?2",
         rusqlite::params![now_timestamp(), drive_file_id],
     )
     .db_err()?;
-
+
+    remove_thumbnail_file(app, drive_file_id)
+}
+
+/// Reset stale "downloading"/"queued" statuses to "pending" on app startup
+pub fn reset_stale_downloads(app: &AppHandle) -> Result<(), PedaruError> {
+    let conn = open_db(app)?;
+    conn.execute(
+        "UPDATE bookshelf_cloud SET download_status = 'pending', download_progress = 0 WHERE download_status IN ('downloading', 'queued')",
+        [],
+    )
+    .db_err()?;
+    drop(conn);
+
+    let removed = remove_orphaned_partial_downloads(app)?;
+    if removed > 0 {
+        log::info!("Removed {} leftover partial downloads", removed);
+    }
+    Ok(())
+}
+
+/// Verify that local files exist for completed cloud downloads
+/// Resets status to "pending" for items where the file no longer exists
+pub fn verify_cloud_files(app: &AppHandle) -> Result<i32, PedaruError> {
+    let conn = open_db(app)?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT drive_file_id, local_path FROM bookshelf_cloud
+             WHERE download_status = 'completed' AND local_path IS NOT NULL",
+        )
+        .db_err()?;
+
+    let items: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .db_err()?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut reset_count = 0;
+
+    for (drive_file_id, local_path) in items {
+        let path = std::path::Path::new(&local_path);
+        if !path.exists() {
+            log::info!("Cloud file missing, resetting status: {}", local_path);
+            conn.execute(
+                "UPDATE bookshelf_cloud SET
+                   download_status = 'pending',
+                   download_progress = 0,
+                   downloaded_bytes = 0,
+                   local_path = NULL,
+                   thumbnail_path = NULL,
+                   thumbnail_data = NULL,
+                   updated_at = ?1
+                 WHERE drive_file_id = ?2",
+                rusqlite::params![now_timestamp(), drive_file_id],
+            )
+            .db_err()?;
+            remove_thumbnail_file(app, &drive_file_id)?;
+            reset_count += 1;
+        }
+    }
+
+    if reset_count > 0 {
+        log::info!("Reset {} cloud items with missing files", reset_count);
+    }
+
+    Ok(reset_count)
+}
+
+/// Compute the MD5 of a file as lowercase hex, reading it in chunks
+pub fn compute_file_md5(path: &std::path::Path) -> std::io::Result<String> {
+    use md5::{Digest, Md5};
+    use std::io::Read;
+
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Md5::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// Verify completed cloud downloads against Drive's MD5 checksum
+///
+/// Corrupted files are deleted and their items (including duplicates that
+/// share the file) reset to "pending" so they can be downloaded again. Items
+/// without a stored checksum are skipped. Returns the number of corrupted
+/// files found.
+pub fn verify_cloud_integrity(app: &AppHandle) -> Result<i32, PedaruError> {
+    let conn = open_db(app)?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT drive_file_id, local_path, md5_checksum FROM bookshelf_cloud
+             WHERE download_status = 'completed' AND local_path IS NOT NULL
+               AND md5_checksum IS NOT NULL",
+        )
+        .db_err()?;
+
+    let items: Vec<(String, String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .db_err()?
+        .filter_map(|r| r.ok())
+        .collect();
+    drop(stmt);
+    drop(conn);
+
+    let mut corrupted_count = 0;
+
+    for (drive_file_id, local_path, expected) in items {
+        let path = std::path::Path::new(&local_path);
+        // Missing files are handled by verify_cloud_files
+        let Ok(actual) = compute_file_md5(path) else {
+            continue;
+        };
+        if !actual.eq_ignore_ascii_case(&expected) {
+            log::warn!("Checksum mismatch for {}, resetting status", local_path);
+            let mut conn = open_db(app)?;
+            let tx = conn.transaction().db_err()?;
+            let (reset, to_remove) = reset_corrupt_local_copy(&tx, &drive_file_id)?;
+            tx.commit().db_err()?;
+
+            if let Some(path) = to_remove {
+                remove_downloaded_file(&path)?;
+            }
+            for drive_file_id in &reset {
+                remove_thumbnail_file(app, drive_file_id)?;
+            }
+            corrupted_count += 1;
+        }
+    }
+
+    Ok(corrupted_count)
+}
+
+/// Remove cloud items from inactive (removed) folders
+/// Only removes items that are not downloaded (pending status)
+/// Returns the items removed
+pub fn remove_items_from_inactive_folders(app: &AppHandle) -> Result<Vec<SyncedFile>, PedaruError> {
+    let conn = open_db(app)?;
+
+    // Get list of active folder IDs
+    let mut stmt = conn
+        .prepare("SELECT folder_id FROM drive_folders WHERE is_active = 1")
+        .db_err()?;
+    let active_folder_ids: Vec<String> = stmt
+        .query_map([], |row| row.get(0))
+        .db_err()?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    if active_folder_ids.is_empty() {
+        // No active folders - remove all non-downloaded cloud items
+        let removed = select_synced_files(&conn, "download_status != 'completed'", &[])?;
+        for table in ["reading_progress", "reading_sessions", "annotations"] {
+            conn.execute(
+                &format!(
+                    "DELETE FROM {table} WHERE drive_file_id IN (
+                       SELECT drive_file_id FROM bookshelf_cloud WHERE download_status != 'completed'
+                     )"
+                ),
+                [],
+            )
+            .db_err()?;
+        }
+        conn.execute(
+            "DELETE FROM bookshelf_cloud WHERE download_status != 'completed'",
+            [],
+        )
+        .db_err()?;
+        log::info!("Removed {} cloud items (no active folders)", removed.len());
+        return Ok(removed);
+    }
+
+    // Build placeholders for IN clause
+    let placeholders: Vec<String> = (0..active_folder_ids.len())
+        .map(|i| format!("?{}", i + 1))
+        .collect();
+    let in_clause = placeholders.join(", ");
+
+    // Delete items from inactive folders that are not downloaded, along with
+    // their reading progress, sessions and annotations
+    // (items in subfolders belong to the watched folder in root_folder_id)
+    let condition = format!(
+        "COALESCE(root_folder_id, drive_folder_id) NOT IN ({})
+           AND download_status != 'completed'",
+        in_clause
+    );
+
+    let params: Vec<&dyn rusqlite::ToSql> = active_folder_ids
+        .iter()
+        .map(|s| s as &dyn rusqlite::ToSql)
+        .collect();
+
+    let removed = select_synced_files(&conn, &condition, params.as_slice())?;
+    for table in ["reading_progress", "reading_sessions", "annotations"] {
+        conn.execute(
+            &format!(
+                "DELETE FROM {table} WHERE drive_file_id IN (
+                   SELECT drive_file_id FROM bookshelf_cloud WHERE {condition}
+                 )"
+            ),
+            params.as_slice(),
+        )
+        .db_err()?;
+    }
+    conn.execute(
+        &format!("DELETE FROM bookshelf_cloud WHERE {}", condition),
+        params.as_slice(),
+    )
+    .db_err()?;
+
+    if !removed.is_empty() {
+        log::info!(
+            "Removed {} cloud items from inactive folders",
+            removed.len()
+        );
+    }
+
+    Ok(removed)
+}
+
+/// Cloud items matching a `WHERE` condition, as sync result entries
+fn select_synced_files(
+    conn: &rusqlite::Connection,
+    condition: &str,
+    params: &[&dyn rusqlite::ToSql],
+) -> Result<Vec<SyncedFile>, PedaruError> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT drive_file_id, file_name FROM bookshelf_cloud WHERE {}",
+            condition
+        ))
+        .db_err()?;
+    let files = stmt
+        .query_map(params, |row| {
+            Ok(SyncedFile {
+                drive_file_id: row.get(0)?,
+                file_name: row.get(1)?,
+            })
+        })
+        .db_err()?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(files)
+}
+
+/// Remove cloud items of a synced folder that no longer exist on Drive
+///
+/// `drive_file_ids` must be the complete listing of the folder (including
+/// subfolders for recursive folders). Local copies are deleted along with the
+/// rows; reading progress is kept in case the file comes back. Items that are
+/// currently downloading are left alone. Returns the items removed.
+pub fn reconcile_folder(
+    app: &AppHandle,
+    folder_id: &str,
+    drive_file_ids: &[String],
+) -> Result<Vec<SyncedFile>, PedaruError> {
+    let present: HashSet<&str> = drive_file_ids.iter().map(String::as_str).collect();
+    remove_folder_items_where(app, folder_id, |id| !present.contains(id))
+}
+
+/// IDs of a synced folder's items that are missing from `drive_file_ids`
+/// (a full listing of the folder)
+pub fn get_missing_folder_items(
+    app: &AppHandle,
+    folder_id: &str,
+    drive_file_ids: &[String],
+) -> Result<Vec<String>, PedaruError> {
+    missing_folder_items_on(&open_db(app)?, folder_id, drive_file_ids)
+}
+
+fn missing_folder_items_on(
+    conn: &rusqlite::Connection,
+    folder_id: &str,
+    drive_file_ids: &[String],
+) -> Result<Vec<String>, PedaruError> {
+    let present: HashSet<&str> = drive_file_ids.iter().map(String::as_str).collect();
+    Ok(select_synced_files(
+        conn,
+        "COALESCE(root_folder_id, drive_folder_id) = ?1",
+        &[&folder_id],
+    )?
+    .into_iter()
+    .map(|file| file.drive_file_id)
+    .filter(|id| !present.contains(id.as_str()))
+    .collect())
+}
+
+/// Remove the given cloud items of a synced folder (incremental sync)
+///
+/// Like `reconcile_folder`, but `drive_file_ids` lists the files that left
+/// the folder. IDs that aren't in the folder are ignored.
+pub fn remove_folder_items(
+    app: &AppHandle,
+    folder_id: &str,
+    drive_file_ids: &[String],
+) -> Result<Vec<SyncedFile>, PedaruError> {
+    let gone: HashSet<&str> = drive_file_ids.iter().map(String::as_str).collect();
+    remove_folder_items_where(app, folder_id, |id| gone.contains(id))
+}
+
+fn remove_folder_items_where(
+    app: &AppHandle,
+    folder_id: &str,
+    should_remove: impl Fn(&str) -> bool,
+) -> Result<Vec<SyncedFile>, PedaruError> {
+    let conn = open_db(app)?;
+    let candidates: Vec<SyncedFile> = select_synced_files(
+        &conn,
+        "COALESCE(root_folder_id, drive_folder_id) = ?1",
+        &[&folder_id],
+    )?
+    .into_iter()
+    .filter(|file| should_remove(&file.drive_file_id))
+    .collect();
+
+    let mut removed = Vec::new();
+    for file in candidates {
+        if get_cancel_flag(&file.drive_file_id).is_some() {
+            continue;
+        }
+        cancel_queued_download(&file.drive_file_id);
+
+        delete_cloud_local_copy(app, &file.drive_file_id)?;
+        conn.execute(
+            "DELETE FROM bookshelf_cloud WHERE drive_file_id = ?1",
+            [&file.drive_file_id],
+        )
+        .db_err()?;
+        removed.push(file);
+    }
+
+    if !removed.is_empty() {
+        log::info!(
+            "Removed {} items deleted from Drive folder {}",
+            removed.len(),
+            folder_id
+        );
+    }
+
+    Ok(removed)
+}
+
+/// Toggle favorite status for cloud item
+pub fn toggle_cloud_favorite(app: &AppHandle, item_id: i64) -> Result<bool, PedaruError> {
+    let conn = open_db(app)?;
+
+    let current: i64 = conn
+        .query_row(
+            "SELECT is_favorite FROM bookshelf_cloud WHERE id = ?1",
+            [item_id],
+            |row| row.get(0),
+        )
+        .db_err()?;
+
+    let new_status = if current == 0 { 1 } else { 0 };
+
+    conn.execute(
+        "UPDATE bookshelf_cloud SET is_favorite = ?1, updated_at = ?2 WHERE id = ?3",
+        rusqlite::params![new_status, now_timestamp(), item_id],
+    )
+    .db_err()?;
+
+    Ok(new_status == 1)
+}
+
+/// Set favorite status for a cloud item
+pub fn set_cloud_favorite(
+    app: &AppHandle,
+    drive_file_id: &str,
+    is_favorite: bool,
+) -> Result<(), PedaruError> {
+    let conn = open_db(app)?;
+    conn.execute(
+        "UPDATE bookshelf_cloud SET is_favorite = ?1, updated_at = ?2 WHERE drive_file_id = ?3",
+        rusqlite::params![is_favorite as i32, now_timestamp(), drive_file_id],
+    )
+    .db_err()?;
     Ok(())
 }
 
-/// Reset stale "downloading" statuses to "pending" on app startup
-pub fn reset_stale_downloads(app: &AppHandle) -> Result<(), PedaruError> {
+/// Update last_opened timestamp for cloud item (by local_path)
+pub fn update_cloud_last_opened(app: &AppHandle, local_path: &str) -> Result<(), PedaruError> {
     let conn = open_db(app)?;
+    let now = now_timestamp();
+
     conn.execute(
-        "UPDATE bookshelf_cloud SET download_status = 'pending', download_progress = 0 WHERE download_status = 'downloading'",
-        [],
+        "UPDATE bookshelf_cloud SET last_opened = ?1, updated_at = ?1 WHERE local_path = ?2",
+        rusqlite::params![now, local_path],
     )
     .db_err()?;
+
     Ok(())
 }
 
-/// Verify that local files exist for completed cloud downloads
-/// Resets status to "pending" for items where the file no longer exists
-pub fn verify_cloud_files(app: &AppHandle) -> Result<i32, PedaruError> {
-    let conn = open_db(app)?;
+// ============================================================================
+// Storage Management
+// ============================================================================
+
+/// Get the storage cap for downloaded PDFs (None = unlimited)
+pub fn get_storage_cap(app: &AppHandle) -> Result<Option<u64>, PedaruError> {
+    Ok(settings::get_setting(app, settings::KEY_STORAGE_CAP_BYTES)?
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|&cap| cap > 0))
+}
+
+/// Set the storage cap for downloaded PDFs (None or 0 removes the cap)
+pub fn set_storage_cap(app: &AppHandle, cap_bytes: Option<u64>) -> Result<(), PedaruError> {
+    match cap_bytes.filter(|&cap| cap > 0) {
+        Some(cap) => settings::set_setting(app, settings::KEY_STORAGE_CAP_BYTES, &cap.to_string()),
+        None => settings::delete_setting(app, settings::KEY_STORAGE_CAP_BYTES),
+    }
+}
 
+/// Delete least-recently-read downloads until usage is back under the cap
+///
+/// Favorites, active downloads and `keep_file_id` are never evicted. Recency
+/// is the last reading-progress save, falling back to last_opened/updated_at.
+pub fn enforce_storage_cap(
+    app: &AppHandle,
+    keep_file_id: Option<&str>,
+) -> Result<CleanupResult, PedaruError> {
+    let mut result = CleanupResult::default();
+    let Some(cap) = get_storage_cap(app)? else {
+        return Ok(result);
+    };
+
+    let conn = open_db(app)?;
     let mut stmt = conn
         .prepare(
-            "SELECT drive_file_id, local_path FROM bookshelf_cloud
-             WHERE download_status = 'completed' AND local_path IS NOT NULL",
+            "SELECT c.drive_file_id, c.local_path, c.is_favorite
+             FROM bookshelf_cloud c
+             LEFT JOIN reading_progress p ON p.drive_file_id = c.drive_file_id
+             WHERE c.download_status = 'completed' AND c.local_path IS NOT NULL
+             ORDER BY COALESCE(p.updated_at, c.last_opened, c.updated_at) ASC",
         )
         .db_err()?;
-
-    let items: Vec<(String, String)> = stmt
-        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+    let downloads: Vec<(String, String, u64, bool)> = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)? != 0,
+            ))
+        })
         .db_err()?
         .filter_map(|r| r.ok())
+        .map(|(file_id, path, is_favorite)| {
+            let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            (file_id, path, size, is_favorite)
+        })
         .collect();
+    drop(stmt);
+    drop(conn);
 
-    let mut reset_count = 0;
+    // Duplicates sharing one file only take up space once
+    let mut counted = HashSet::new();
+    let mut usage: u64 = downloads
+        .iter()
+        .filter(|(_, path, _, _)| counted.insert(path.as_str()))
+        .map(|(_, _, size, _)| size)
+        .sum();
+    for (file_id, path, size, is_favorite) in &downloads {
+        if usage <= cap {
+            break;
+        }
+        if *is_favorite
+            || keep_file_id == Some(file_id.as_str())
+            || get_cancel_flag(file_id).is_some()
+        {
+            continue;
+        }
 
-    for (drive_file_id, local_path) in items {
-        let path = std::path::Path::new(&local_path);
-        if !path.exists() {
-            eprintln!(
-                "[Pedaru] Cloud file missing, resetting status: {}",
-                local_path
-            );
-            conn.execute(
-                "UPDATE bookshelf_cloud SET
-                   download_status = 'pending',
-                   download_progress = 0,
-                   local_path = NULL,
-                   thumbnail_data = NULL,
-                   updated_at = ?1
-                 WHERE drive_file_id = ?2",
-                rusqlite::params![now_timestamp(), drive_file_id],
-            )
-            .db_err()?;
-            reset_count += 1;
+        delete_cloud_local_copy(app, file_id)?;
+        result.files_removed += 1;
+        // Nothing is freed while another item still shares the file
+        if !std::path::Path::new(path).exists() {
+            usage = usage.saturating_sub(*size);
+            result.bytes_freed += size;
         }
     }
 
-    if reset_count > 0 {
-        eprintln!(
-            "[Pedaru] Reset {} cloud items with missing files",
-            reset_count
+    if result.files_removed > 0 {
+        log::info!(
+            "Evicted {} downloads ({} bytes) to stay under storage cap",
+            result.files_removed,
+            result.bytes_freed
         );
     }
 
-    Ok(reset_count)
+    Ok(result)
 }
 
-/// Remove cloud items from inactive (removed) folders
-/// Only removes items that are not downloaded (pending status)
-/// Returns the number of items removed
-pub fn remove_items_from_inactive_folders(app: &AppHandle) -> Result<i32, PedaruError> {
-    let conn = open_db(app)?;
+/// A completed download as recorded in the bookshelf
+struct StoredDownload {
+    folder_id: String,
+    folder_name: Option<String>,
+    local_path: String,
+    file_size: Option<i64>,
+}
 
-    // Get list of active folder IDs
+/// Total up downloads per folder, calling `stat` only for unknown sizes
+fn summarize_storage(
+    downloads: &[StoredDownload],
+    stat: impl Fn(&str) -> Option<u64>,
+) -> StorageUsage {
+    let mut usage = StorageUsage::default();
+    let mut folders: BTreeMap<&str, FolderStorageUsage> = BTreeMap::new();
+    // Duplicates sharing one file only take up space once
+    let mut counted = HashSet::new();
+
+    for download in downloads {
+        let size = match download.file_size {
+            Some(size) => Some(size.max(0) as u64),
+            None => stat(&download.local_path),
+        };
+        let Some(size) = size else {
+            usage.missing_count += 1;
+            continue;
+        };
+
+        let folder = folders
+            .entry(&download.folder_id)
+            .or_insert_with(|| FolderStorageUsage {
+                folder_id: download.folder_id.clone(),
+                folder_name: download.folder_name.clone(),
+                total_bytes: 0,
+                item_count: 0,
+            });
+        folder.item_count += 1;
+        usage.item_count += 1;
+        if counted.insert(download.local_path.as_str()) {
+            folder.total_bytes += size;
+            usage.total_bytes += size;
+        }
+    }
+
+    usage.folders = folders.into_values().collect();
+    usage
+        .folders
+        .sort_by_key(|folder| std::cmp::Reverse(folder.total_bytes));
+    usage
+}
+
+/// Report disk space used by downloads, in total and per watched folder
+///
+/// Sizes come from the stored `file_size`; files are only stat-ed when it is
+/// unknown, so a file deleted outside Pedaru is only noticed then (or by
+/// `verify_cloud_files`).
+pub fn storage_usage(app: &AppHandle) -> Result<StorageUsage, PedaruError> {
+    let conn = open_db(app)?;
     let mut stmt = conn
-        .prepare("SELECT folder_id FROM drive_folders WHERE is_active = 1")
+        .prepare(
+            "SELECT COALESCE(c.root_folder_id, c.drive_folder_id), f.folder_name,
+                    c.local_path, c.file_size
+             FROM bookshelf_cloud c
+             LEFT JOIN drive_folders f
+               ON f.folder_id = COALESCE(c.root_folder_id, c.drive_folder_id)
+             WHERE c.download_status = 'completed' AND c.local_path IS NOT NULL",
+        )
         .db_err()?;
-    let active_folder_ids: Vec<String> = stmt
-        .query_map([], |row| row.get(0))
+    let downloads: Vec<StoredDownload> = stmt
+        .query_map([], |row| {
+            Ok(StoredDownload {
+                folder_id: row.get(0)?,
+                folder_name: row.get(1)?,
+                local_path: row.get(2)?,
+                file_size: row.get(3)?,
+            })
+        })
         .db_err()?
         .filter_map(|r| r.ok())
         .collect();
+    drop(stmt);
+    drop(conn);
 
-    if active_folder_ids.is_empty() {
-        // No active folders - remove all non-downloaded cloud items
-        let count = conn
-            .execute(
-                "DELETE FROM bookshelf_cloud WHERE download_status != 'completed'",
-                [],
-            )
-            .db_err()?;
-        eprintln!("[Pedaru] Removed {} cloud items (no active folders)", count);
-        return Ok(count as i32);
-    }
-
-    // Build placeholders for IN clause
-    let placeholders: Vec<String> = (0..active_folder_ids.len())
-        .map(|i| format!("?{}", i + 1))
-        .collect();
-    let in_clause = placeholders.join(", ");
-
-    // Delete items from inactive folders that are not downloaded
-    let query = format!(
-        "DELETE FROM bookshelf_cloud WHERE drive_folder_id NOT IN ({}) AND download_status != 'completed'",
-        in_clause
-    );
-
-    let params: Vec<&dyn rusqlite::ToSql> = active_folder_ids
-        .iter()
-        .map(|s| s as &dyn rusqlite::ToSql)
-        .collect();
+    let mut usage = summarize_storage(&downloads, |path| {
+        std::fs::metadata(path).map(|m| m.len()).ok()
+    });
+    usage.cap_bytes = get_storage_cap(app)?;
+    Ok(usage)
+}
 
-    let count = conn.execute(&query, params.as_slice()).db_err()?;
+// ============================================================================
+// Tags
+// ============================================================================
 
-    if count > 0 {
-        eprintln!(
-            "[Pedaru] Removed {} cloud items from inactive folders",
-            count
-        );
+/// Tag a cloud item, creating the tag if it doesn't exist yet
+pub fn add_tag(app: &AppHandle, drive_file_id: &str, tag: &str) -> Result<(), PedaruError> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Ok(());
     }
 
-    Ok(count as i32)
+    let conn = open_db(app)?;
+    let now = now_timestamp();
+    conn.execute(
+        "INSERT INTO tags (name, created_at) VALUES (?1, ?2) ON CONFLICT(name) DO NOTHING",
+        rusqlite::params![tag, now],
+    )
+    .db_err()?;
+    conn.execute(
+        "INSERT OR IGNORE INTO bookshelf_tags (drive_file_id, tag_id, created_at)
+         SELECT ?1, id, ?3 FROM tags WHERE name = ?2",
+        rusqlite::params![drive_file_id, tag, now],
+    )
+    .db_err()?;
+    Ok(())
 }
 
-/// Toggle favorite status for cloud item
-pub fn toggle_cloud_favorite(app: &AppHandle, item_id: i64) -> Result<bool, PedaruError> {
+/// Remove a tag from a cloud item, deleting the tag once nothing uses it
+pub fn remove_tag(app: &AppHandle, drive_file_id: &str, tag: &str) -> Result<(), PedaruError> {
     let conn = open_db(app)?;
+    conn.execute(
+        "DELETE FROM bookshelf_tags
+         WHERE drive_file_id = ?1 AND tag_id IN (SELECT id FROM tags WHERE name = ?2)",
+        rusqlite::params![drive_file_id, tag.trim()],
+    )
+    .db_err()?;
+    conn.execute(
+        "DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM bookshelf_tags)",
+        [],
+    )
+    .db_err()?;
+    Ok(())
+}
 
-    let current: i64 = conn
-        .query_row(
-            "SELECT is_favorite FROM bookshelf_cloud WHERE id = ?1",
-            [item_id],
-            |row| row.get(0),
-        )
+/// Get all tag names, sorted alphabetically
+pub fn get_tags(app: &AppHandle) -> Result<Vec<String>, PedaruError> {
+    let conn = open_db(app)?;
+    let mut stmt = conn
+        .prepare("SELECT name FROM tags ORDER BY name COLLATE NOCASE")
         .db_err()?;
+    let tags = stmt
+        .query_map([], |row| row.get(0))
+        .db_err()?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(tags)
+}
 
-    let new_status = if current == 0 { 1 } else { 0 };
+// ============================================================================
+// Reading Progress
+// ============================================================================
 
+/// Save where the user left off in a cloud document
+pub fn save_reading_position(
+    app: &AppHandle,
+    drive_file_id: &str,
+    page: i64,
+    scroll_offset: f64,
+) -> Result<(), PedaruError> {
+    let conn = open_db(app)?;
     conn.execute(
-        "UPDATE bookshelf_cloud SET is_favorite = ?1, updated_at = ?2 WHERE id = ?3",
-        rusqlite::params![new_status, now_timestamp(), item_id],
+        "INSERT INTO reading_progress (drive_file_id, page, scroll_offset, updated_at)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(drive_file_id) DO UPDATE SET
+           page = excluded.page,
+           scroll_offset = excluded.scroll_offset,
+           updated_at = excluded.updated_at",
+        rusqlite::params![drive_file_id, page, scroll_offset, now_timestamp()],
     )
     .db_err()?;
-
-    Ok(new_status == 1)
+    Ok(())
 }
 
-/// Update last_opened timestamp for cloud item (by local_path)
-pub fn update_cloud_last_opened(app: &AppHandle, local_path: &str) -> Result<(), PedaruError> {
+/// Start recording a reading session for a cloud document
+///
+/// Returns the session id for `update_reading_session`.
+pub fn start_reading_session(
+    app: &AppHandle,
+    drive_file_id: &str,
+    page: i64,
+) -> Result<i64, PedaruError> {
     let conn = open_db(app)?;
     let now = now_timestamp();
-
     conn.execute(
-        "UPDATE bookshelf_cloud SET last_opened = ?1, updated_at = ?1 WHERE local_path = ?2",
-        rusqlite::params![now, local_path],
+        "INSERT INTO reading_sessions (drive_file_id, started_at, ended_at, max_page)
+         VALUES (?1, ?2, ?2, ?3)",
+        rusqlite::params![drive_file_id, now, page],
     )
     .db_err()?;
+    Ok(conn.last_insert_rowid())
+}
 
+/// Extend a reading session to now, recording the page being read
+///
+/// Called on page changes and when the document is closed.
+pub fn update_reading_session(
+    app: &AppHandle,
+    session_id: i64,
+    page: i64,
+) -> Result<(), PedaruError> {
+    let conn = open_db(app)?;
+    let updated = conn
+        .execute(
+            "UPDATE reading_sessions SET ended_at = MAX(ended_at, ?1), max_page = MAX(max_page, ?2)
+             WHERE id = ?3",
+            rusqlite::params![now_timestamp(), page, session_id],
+        )
+        .db_err()?;
+    if updated == 0 {
+        return Err(DatabaseError::NotFound.into());
+    }
     Ok(())
 }
 
+/// Aggregate reading progress and sessions into statistics
+pub fn reading_stats(app: &AppHandle) -> Result<ReadingStats, PedaruError> {
+    reading_stats_on(&open_db(app)?)
+}
+
+fn reading_stats_on(conn: &rusqlite::Connection) -> Result<ReadingStats, PedaruError> {
+    // Documents read before sessions were recorded still count their saved page
+    let mut stmt = conn
+        .prepare(
+            "WITH s AS (
+               SELECT drive_file_id, COUNT(*) AS session_count,
+                      SUM(ended_at - started_at) AS time_spent, MAX(max_page) AS max_page,
+                      MAX(ended_at) AS last_read_at
+               FROM reading_sessions GROUP BY drive_file_id
+             )
+             SELECT c.drive_file_id, c.custom_name, c.pdf_title, c.file_name, c.page_count,
+                    MAX(COALESCE(s.max_page, 0), COALESCE(p.page, 0)),
+                    COALESCE(s.time_spent, 0), COALESCE(s.session_count, 0),
+                    MAX(COALESCE(s.last_read_at, 0), COALESCE(p.updated_at, 0))
+             FROM bookshelf_cloud c
+             LEFT JOIN s ON s.drive_file_id = c.drive_file_id
+             LEFT JOIN reading_progress p ON p.drive_file_id = c.drive_file_id
+             WHERE s.drive_file_id IS NOT NULL OR p.drive_file_id IS NOT NULL
+             ORDER BY 9 DESC",
+        )
+        .db_err()?;
+    let documents: Vec<DocumentReadingStats> = stmt
+        .query_map([], |row| {
+            let custom_name: Option<String> = row.get(1)?;
+            let pdf_title: Option<String> = row.get(2)?;
+            let file_name: String = row.get(3)?;
+            let page_count: Option<i64> = row.get(4)?;
+            let furthest_page: i64 = row.get(5)?;
+            let last_read_at: i64 = row.get(8)?;
+            Ok(DocumentReadingStats {
+                drive_file_id: row.get(0)?,
+                display_name: display_name(
+                    custom_name.as_deref(),
+                    pdf_title.as_deref(),
+                    &file_name,
+                ),
+                pages_read: page_count.map_or(furthest_page, |count| furthest_page.min(count)),
+                page_count,
+                completed: page_count.is_some_and(|count| count > 0 && furthest_page >= count),
+                time_spent_secs: row.get(6)?,
+                session_count: row.get(7)?,
+                last_read_at: (last_read_at > 0).then_some(last_read_at),
+            })
+        })
+        .db_err()?
+        .collect::<Result<_, _>>()
+        .db_err()?;
+
+    Ok(ReadingStats {
+        total_pages_read: documents.iter().map(|d| d.pages_read).sum(),
+        documents_completed: documents.iter().filter(|d| d.completed).count() as i64,
+        total_time_secs: documents.iter().map(|d| d.time_spent_secs).sum(),
+        documents,
+    })
+}
+
+/// Get the saved reading position for a cloud document
+pub fn get_reading_position(
+    app: &AppHandle,
+    drive_file_id: &str,
+) -> Result<Option<ReadingPosition>, PedaruError> {
+    let conn = open_db(app)?;
+    conn.query_row(
+        "SELECT drive_file_id, page, scroll_offset, updated_at
+         FROM reading_progress WHERE drive_file_id = ?1",
+        [drive_file_id],
+        |row| {
+            Ok(ReadingPosition {
+                drive_file_id: row.get(0)?,
+                page: row.get(1)?,
+                scroll_offset: row.get(2)?,
+                updated_at: row.get(3)?,
+            })
+        },
+    )
+    .optional()
+    .db_err()
+}
+
 // ============================================================================
 // Local Item Operations
 // ============================================================================
@@ -632,7 +3650,8 @@ pub fn get_local_items(app: &AppHandle) -> Result<Vec<LocalItem>, PedaruError> {
     let mut stmt = conn
         .prepare(
             "SELECT id, file_path, original_path, file_name, file_size,
-                    thumbnail_data, pdf_title, pdf_author, is_favorite, last_opened
+                    thumbnail_data, pdf_title, pdf_author, is_favorite, last_opened,
+                    imported_at, updated_at
              FROM bookshelf_local
              ORDER BY last_opened IS NULL, last_opened DESC, file_name ASC",
         )
@@ -651,6 +3670,8 @@ pub fn get_local_items(app: &AppHandle) -> Result<Vec<LocalItem>, PedaruError> {
                 pdf_author: row.get(7)?,
                 is_favorite: row.get::<_, i64>(8)? != 0,
                 last_opened: row.get(9)?,
+                created_at: row.get(10)?,
+                updated_at: row.get(11)?,
             })
         })
         .db_err()?
@@ -711,7 +3732,7 @@ pub fn verify_local_files(app: &AppHandle) -> Result<i32, PedaruError> {
     for (id, file_path) in items {
         let path = std::path::Path::new(&file_path);
         if !path.exists() {
-            eprintln!("[Pedaru] Local file missing, deleting entry: {}", file_path);
+            log::info!("Local file missing, deleting entry: {}", file_path);
             conn.execute("DELETE FROM bookshelf_local WHERE id = ?1", [id])
                 .db_err()?;
             deleted_count += 1;
@@ -719,10 +3740,7 @@ pub fn verify_local_files(app: &AppHandle) -> Result<i32, PedaruError> {
     }
 
     if deleted_count > 0 {
-        eprintln!(
-            "[Pedaru] Deleted {} local items with missing files",
-            deleted_count
-        );
+        log::info!("Deleted {} local items with missing files", deleted_count);
     }
 
     Ok(deleted_count)
@@ -879,6 +3897,8 @@ pub fn import_local_file(app: &AppHandle, source_path: &str) -> Result<LocalItem
         pdf_author: None,
         is_favorite: false,
         last_opened: None,
+        created_at: now,
+        updated_at: now,
     })
 }
 
@@ -921,7 +3941,7 @@ pub fn import_local_directory(
                     if error_str.contains("already imported") {
                         skipped_count += 1;
                     } else {
-                        eprintln!("[Pedaru] Failed to import {:?}: {:?}", path, e);
+                        log::warn!("Failed to import {:?}: {:?}", path, e);
                         error_count += 1;
                     }
                 }
@@ -991,28 +4011,47 @@ pub fn verify_all_local_files(app: &AppHandle) -> Result<i32, PedaruError> {
 // ============================================================================
 
 /// Extract PDF metadata and save it to the bookshelf database (cloud item)
+///
+/// Stores title, author and page count. When the document has no Title the
+/// Drive file name (without extension) is used instead.
 pub fn extract_and_save_cloud_metadata(
     app: &AppHandle,
     file_path: &str,
     drive_file_id: &str,
 ) -> Result<(), PedaruError> {
-    if let Ok(pdf_info) = crate::get_pdf_info_impl(file_path) {
-        let title = pdf_info
-            .title
-            .as_ref()
-            .filter(|t| !t.trim().is_empty())
-            .map(|s| s.as_str());
-        let author = pdf_info
-            .author
-            .as_ref()
-            .filter(|a| !a.trim().is_empty())
-            .map(|s| s.as_str());
-
-        if title.is_some() || author.is_some() {
-            update_cloud_metadata(app, drive_file_id, title, author)?;
+    let doc = lopdf::Document::load(file_path).map_err(|source| PdfError::LoadFailed {
+        path: file_path.to_string(),
+        source,
+    })?;
+    let metadata = crate::pdf::extract_metadata(&doc);
+
+    let title = match metadata.title {
+        Some(title) => Some(title),
+        None => {
+            let conn = open_db(app)?;
+            conn.query_row(
+                "SELECT file_name FROM bookshelf_cloud WHERE drive_file_id = ?1",
+                [drive_file_id],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .db_err()?
+            .map(|name| {
+                std::path::Path::new(&name)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or(name)
+            })
         }
-    }
-    Ok(())
+    };
+
+    update_cloud_metadata(
+        app,
+        drive_file_id,
+        title.as_deref(),
+        metadata.author.as_deref(),
+        Some(metadata.page_count as i64),
+    )
 }
 
 /// Extract PDF metadata and save it to the bookshelf database (local item)
@@ -1054,20 +4093,36 @@ pub fn extract_and_save_local_metadata(
 pub struct BookshelfItem {
     pub id: i64,
     pub drive_file_id: Option<String>,
+    pub account_id: Option<i64>,
     pub drive_folder_id: Option<String>,
+    pub root_folder_id: Option<String>,
+    pub folder_path: Option<String>,
     pub file_name: String,
     pub file_size: Option<i64>,
+    pub drive_modified_time: Option<String>,
     pub thumbnail_data: Option<String>,
+    pub thumbnail_path: Option<String>,
     pub local_path: Option<String>,
     pub download_status: String,
     pub download_progress: f64,
+    pub download_error: Option<String>,
+    pub download_failure: Option<DownloadFailure>,
+    pub retry_count: i64,
     pub pdf_title: Option<String>,
     pub pdf_author: Option<String>,
+    pub page_count: Option<i64>,
+    pub custom_name: Option<String>,
+    pub display_name: String,
     pub source_type: String,
     pub original_path: Option<String>,
+    /// When the item was added to the bookshelf (Unix timestamp)
     pub created_at: i64,
+    /// When the item was last changed (Unix timestamp)
+    pub updated_at: i64,
     pub is_favorite: bool,
     pub last_opened: Option<i64>,
+    pub last_read_page: Option<i64>,
+    pub tags: Vec<String>,
 }
 
 impl From<CloudItem> for BookshelfItem {
@@ -1075,70 +4130,385 @@ impl From<CloudItem> for BookshelfItem {
         BookshelfItem {
             id: item.id,
             drive_file_id: Some(item.drive_file_id),
+            account_id: Some(item.account_id),
             drive_folder_id: Some(item.drive_folder_id),
+            root_folder_id: item.root_folder_id,
+            folder_path: item.folder_path,
             file_name: item.file_name,
             file_size: item.file_size,
+            drive_modified_time: item.drive_modified_time,
             thumbnail_data: item.thumbnail_data,
+            thumbnail_path: item.thumbnail_path,
             local_path: item.local_path,
             download_status: item.download_status.to_string(),
             download_progress: item.download_progress,
+            download_error: item.download_error,
+            download_failure: item.download_failure,
+            retry_count: item.retry_count,
             pdf_title: item.pdf_title,
             pdf_author: item.pdf_author,
+            page_count: item.page_count,
+            custom_name: item.custom_name,
+            display_name: item.display_name,
             source_type: "google_drive".to_string(),
             original_path: None,
-            created_at: 0, // Not available in new schema
+            created_at: item.created_at,
+            updated_at: item.updated_at,
             is_favorite: item.is_favorite,
             last_opened: item.last_opened,
+            last_read_page: item.last_read_page,
+            tags: item.tags,
         }
     }
 }
 
 impl From<LocalItem> for BookshelfItem {
     fn from(item: LocalItem) -> Self {
+        let display_name = display_name(None, item.pdf_title.as_deref(), &item.file_name);
         BookshelfItem {
             id: item.id,
             drive_file_id: None,
+            account_id: None,
             drive_folder_id: None,
+            root_folder_id: None,
+            folder_path: None,
             file_name: item.file_name,
             file_size: item.file_size,
+            drive_modified_time: None,
             thumbnail_data: item.thumbnail_data,
+            thumbnail_path: None,
             local_path: Some(item.file_path),
             download_status: "completed".to_string(),
             download_progress: 100.0,
+            download_error: None,
+            download_failure: None,
+            retry_count: 0,
             pdf_title: item.pdf_title,
             pdf_author: item.pdf_author,
+            page_count: None,
+            custom_name: None,
+            display_name,
             source_type: "local".to_string(),
             original_path: Some(item.original_path),
-            created_at: 0, // Not available in new schema
+            created_at: item.created_at,
+            updated_at: item.updated_at,
             is_favorite: item.is_favorite,
             last_opened: item.last_opened,
+            last_read_page: None,
+            tags: Vec::new(),
+        }
+    }
+}
+
+/// How favorites are treated when listing bookshelf items
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FavoritesMode {
+    /// No special treatment
+    #[default]
+    Mixed,
+    /// Favorites before everything else
+    First,
+    /// Only favorites
+    Only,
+}
+
+/// Column to sort bookshelf items by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SortColumn {
+    Name,
+    Size,
+    ModifiedTime,
+    DownloadStatus,
+    #[default]
+    LastRead,
+    /// When the item was added to the bookshelf
+    CreatedAt,
+    /// When the item was last changed
+    UpdatedAt,
+}
+
+impl SortColumn {
+    /// Direction used when the caller doesn't specify one
+    fn default_direction(self) -> SortDirection {
+        match self {
+            SortColumn::Name | SortColumn::DownloadStatus => SortDirection::Asc,
+            SortColumn::Size
+            | SortColumn::ModifiedTime
+            | SortColumn::LastRead
+            | SortColumn::CreatedAt
+            | SortColumn::UpdatedAt => SortDirection::Desc,
+        }
+    }
+}
+
+/// Sort direction for bookshelf items
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SortDirection {
+    Asc,
+    Desc,
+}
+
+/// Options for listing bookshelf items
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ItemQuery {
+    pub favorites: FavoritesMode,
+    /// Only items carrying at least one of these tags (empty = no filter)
+    pub tags: Vec<String>,
+    pub sort_by: SortColumn,
+    /// Defaults to ascending for name/status and descending otherwise
+    pub sort_direction: Option<SortDirection>,
+    /// Maximum number of items to return (None = all)
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+/// One page of bookshelf items
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemPage {
+    pub items: Vec<BookshelfItem>,
+    /// Number of items matching the query across all pages
+    pub total_count: usize,
+}
+
+/// Cloud and local items as one listing, with the columns of `BookshelfItem`
+const ITEM_LISTING_SQL: &str = "
+    SELECT 'google_drive' AS source_type, c.id, c.drive_file_id, c.account_id,
+           c.drive_folder_id, c.root_folder_id, c.folder_path, c.file_name, c.file_size,
+           c.drive_modified_time, c.thumbnail_data, c.thumbnail_path, c.local_path,
+           c.download_status, c.download_progress, c.download_error, c.download_failure,
+           c.download_retry_count, c.pdf_title, c.pdf_author, c.page_count, c.custom_name,
+           NULL AS original_path, c.created_at, c.updated_at, c.is_favorite,
+           c.last_opened, p.page AS last_read_page
+    FROM bookshelf_cloud c
+    LEFT JOIN reading_progress p ON p.drive_file_id = c.drive_file_id
+    UNION ALL
+    SELECT 'local', id, NULL, NULL, NULL, NULL, NULL, file_name, file_size, NULL,
+           thumbnail_data, NULL, file_path, 'completed', 100.0, NULL, NULL, 0,
+           pdf_title, pdf_author, NULL, NULL, original_path, imported_at, updated_at,
+           is_favorite, last_opened, NULL
+    FROM bookshelf_local";
+
+impl ItemQuery {
+    /// WHERE clause for the favorites and tag filters, with its parameters
+    fn filter(&self) -> (String, Vec<String>) {
+        let mut conditions = vec!["1".to_string()];
+        if self.favorites == FavoritesMode::Only {
+            conditions.push("is_favorite != 0".to_string());
+        }
+        if !self.tags.is_empty() {
+            // Tag names compare case-insensitively (the column is NOCASE)
+            let placeholders = vec!["?"; self.tags.len()].join(", ");
+            conditions.push(format!(
+                "drive_file_id IN (
+                   SELECT bt.drive_file_id FROM bookshelf_tags bt
+                   JOIN tags t ON t.id = bt.tag_id
+                   WHERE t.name IN ({})
+                 )",
+                placeholders
+            ));
+        }
+        (conditions.join(" AND "), self.tags.clone())
+    }
+
+    /// ORDER BY clause; missing values always sort last and ties go by name
+    fn order_by(&self) -> String {
+        let direction = match self
+            .sort_direction
+            .unwrap_or_else(|| self.sort_by.default_direction())
+        {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        };
+        let nulls_last = |column: &str| format!("{column} IS NULL, {column} {direction}");
+        let column = match self.sort_by {
+            SortColumn::Name => format!("file_name COLLATE NOCASE {direction}"),
+            SortColumn::Size => nulls_last("file_size"),
+            SortColumn::ModifiedTime => nulls_last("drive_modified_time"),
+            SortColumn::DownloadStatus => format!(
+                "CASE download_status
+                   WHEN 'completed' THEN 0 WHEN 'downloading' THEN 1
+                   WHEN 'queued' THEN 2 WHEN 'pending' THEN 3 ELSE 4
+                 END {direction}"
+            ),
+            SortColumn::LastRead => nulls_last("last_opened"),
+            SortColumn::CreatedAt => format!("created_at {direction}"),
+            SortColumn::UpdatedAt => format!("updated_at {direction}"),
+        };
+        let favorites_first = if self.favorites == FavoritesMode::First {
+            "is_favorite DESC, "
+        } else {
+            ""
+        };
+        // The source and id keep pages stable when everything else ties
+        format!("{favorites_first}{column}, file_name COLLATE NOCASE ASC, source_type, id")
+    }
+}
+
+/// Read one row of `ITEM_LISTING_SQL`, resolving thumbnail paths against
+/// `thumbnails_dir`
+fn listing_row_to_item(
+    row: &rusqlite::Row,
+    thumbnails_dir: &std::path::Path,
+) -> rusqlite::Result<BookshelfItem> {
+    let file_name: String = row.get("file_name")?;
+    let pdf_title: Option<String> = row.get("pdf_title")?;
+    let custom_name: Option<String> = row.get("custom_name")?;
+    Ok(BookshelfItem {
+        id: row.get("id")?,
+        drive_file_id: row.get("drive_file_id")?,
+        account_id: row.get("account_id")?,
+        drive_folder_id: row.get("drive_folder_id")?,
+        root_folder_id: row.get("root_folder_id")?,
+        folder_path: row.get("folder_path")?,
+        display_name: display_name(custom_name.as_deref(), pdf_title.as_deref(), &file_name),
+        file_name,
+        file_size: row.get("file_size")?,
+        drive_modified_time: row.get("drive_modified_time")?,
+        thumbnail_data: row.get("thumbnail_data")?,
+        thumbnail_path: row
+            .get::<_, Option<String>>("thumbnail_path")?
+            .map(|path| thumbnails_dir.join(path).to_string_lossy().into_owned()),
+        local_path: row.get("local_path")?,
+        download_status: row
+            .get::<_, String>("download_status")?
+            .parse::<DownloadStatus>()
+            .unwrap_or_default()
+            .to_string(),
+        download_progress: row.get("download_progress")?,
+        download_error: row.get("download_error")?,
+        download_failure: row
+            .get::<_, Option<String>>("download_failure")?
+            .and_then(|s| s.parse().ok()),
+        retry_count: row.get("download_retry_count")?,
+        pdf_title,
+        pdf_author: row.get("pdf_author")?,
+        page_count: row.get("page_count")?,
+        custom_name,
+        source_type: row.get("source_type")?,
+        original_path: row.get("original_path")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+        is_favorite: row.get::<_, i64>("is_favorite")? != 0,
+        last_opened: row.get("last_opened")?,
+        last_read_page: row.get("last_read_page")?,
+        tags: Vec::new(),
+    })
+}
+
+/// Attach tags to the cloud items in `items`
+fn attach_tags_on(
+    conn: &rusqlite::Connection,
+    items: &mut [BookshelfItem],
+) -> Result<(), PedaruError> {
+    let file_ids: Vec<&str> = items
+        .iter()
+        .filter_map(|item| item.drive_file_id.as_deref())
+        .collect();
+    if file_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT bt.drive_file_id, t.name
+             FROM bookshelf_tags bt
+             JOIN tags t ON t.id = bt.tag_id
+             WHERE bt.drive_file_id IN ({})
+             ORDER BY t.name COLLATE NOCASE",
+            vec!["?"; file_ids.len()].join(", ")
+        ))
+        .db_err()?;
+    let mut tags_by_file: HashMap<String, Vec<String>> = HashMap::new();
+    for (drive_file_id, name) in stmt
+        .query_map(rusqlite::params_from_iter(&file_ids), |row| {
+            Ok((row.get::<_, String>(0)?, row.get(1)?))
+        })
+        .db_err()?
+        .filter_map(|r| r.ok())
+    {
+        tags_by_file.entry(drive_file_id).or_default().push(name);
+    }
+
+    for item in items {
+        if let Some(tags) = item
+            .drive_file_id
+            .as_ref()
+            .and_then(|id| tags_by_file.remove(id))
+        {
+            item.tags = tags;
         }
     }
+    Ok(())
+}
+
+/// Get one page of bookshelf items; filtering, sorting and pagination all
+/// happen in SQL
+fn get_items_with_query_on(
+    conn: &rusqlite::Connection,
+    thumbnails_dir: &std::path::Path,
+    query: &ItemQuery,
+) -> Result<ItemPage, PedaruError> {
+    let (filter, filter_params) = query.filter();
+
+    let total_count: i64 = conn
+        .query_row(
+            &format!("SELECT COUNT(*) FROM ({ITEM_LISTING_SQL}) WHERE {filter}"),
+            rusqlite::params_from_iter(&filter_params),
+            |row| row.get(0),
+        )
+        .db_err()?;
+
+    // A negative LIMIT means no limit
+    let limit = query
+        .limit
+        .map_or(-1, |limit| i64::try_from(limit).unwrap_or(i64::MAX));
+    let offset = i64::try_from(query.offset).unwrap_or(i64::MAX);
+    let mut params: Vec<&dyn rusqlite::ToSql> = filter_params
+        .iter()
+        .map(|tag| tag as &dyn rusqlite::ToSql)
+        .collect();
+    params.push(&limit);
+    params.push(&offset);
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT * FROM ({ITEM_LISTING_SQL}) WHERE {filter}
+             ORDER BY {} LIMIT ? OFFSET ?",
+            query.order_by()
+        ))
+        .db_err()?;
+    let mut items = stmt
+        .query_map(params.as_slice(), |row| {
+            listing_row_to_item(row, thumbnails_dir)
+        })
+        .db_err()?
+        .filter_map(|r| r.ok())
+        .collect::<Vec<_>>();
+    attach_tags_on(conn, &mut items)?;
+
+    Ok(ItemPage {
+        items,
+        total_count: total_count as usize,
+    })
 }
 
 /// Get all bookshelf items (combines cloud and local)
 /// @deprecated Use get_cloud_items() and get_local_items() instead
 pub fn get_items(app: &AppHandle) -> Result<Vec<BookshelfItem>, PedaruError> {
-    let cloud_items: Vec<BookshelfItem> = get_cloud_items(app)?
-        .into_iter()
-        .map(BookshelfItem::from)
-        .collect();
-    let local_items: Vec<BookshelfItem> = get_local_items(app)?
-        .into_iter()
-        .map(BookshelfItem::from)
-        .collect();
-
-    // Merge and sort by last_opened
-    let mut all_items = cloud_items;
-    all_items.extend(local_items);
-    all_items.sort_by(|a, b| match (a.last_opened, b.last_opened) {
-        (Some(a_time), Some(b_time)) => b_time.cmp(&a_time),
-        (Some(_), None) => std::cmp::Ordering::Less,
-        (None, Some(_)) => std::cmp::Ordering::Greater,
-        (None, None) => a.file_name.cmp(&b.file_name),
-    });
+    Ok(get_items_with_query(app, &ItemQuery::default())?.items)
+}
 
-    Ok(all_items)
+/// Get a page of bookshelf items (combines cloud and local)
+///
+/// Items are filtered, sorted and then paginated; `total_count` is the
+/// number of items before pagination.
+pub fn get_items_with_query(app: &AppHandle, query: &ItemQuery) -> Result<ItemPage, PedaruError> {
+    get_items_with_query_on(&open_db(app)?, &get_thumbnails_dir(app)?, query)
 }
 
 /// Toggle favorite status (determines table from source_type)
@@ -1156,7 +4526,7 @@ pub fn toggle_favorite(app: &AppHandle, item_id: i64, is_cloud: bool) -> Result<
 pub fn update_thumbnail(
     app: &AppHandle,
     drive_file_id: &str,
-    thumbnail_data: &str,
+    thumbnail_data: ThumbnailInput,
 ) -> Result<(), PedaruError> {
     update_cloud_thumbnail(app, drive_file_id, thumbnail_data)
 }
@@ -1169,7 +4539,7 @@ pub fn update_pdf_metadata(
     pdf_title: Option<&str>,
     pdf_author: Option<&str>,
 ) -> Result<(), PedaruError> {
-    update_cloud_metadata(app, drive_file_id, pdf_title, pdf_author)
+    update_cloud_metadata(app, drive_file_id, pdf_title, pdf_author, None)
 }
 
 /// Delete local copy (for backward compatibility)
@@ -1194,21 +4564,21 @@ pub fn verify_local_files_compat(app: &AppHandle) -> Result<i32, PedaruError> {
 /// @deprecated Use upsert_cloud_item() instead
 pub fn upsert_item(
     app: &AppHandle,
-    drive_file_id: &str,
+    account_id: i64,
     folder_id: &str,
-    file_name: &str,
-    file_size: Option<i64>,
-    _mime_type: &str,
-    modified_time: Option<&str>,
+    file: &DriveFile,
 ) -> Result<(), PedaruError> {
     upsert_cloud_item(
         app,
-        drive_file_id,
+        account_id,
         folder_id,
-        file_name,
-        file_size,
-        modified_time,
-    )
+        &DriveFileInput {
+            file,
+            folder_id,
+            folder_path: None,
+        },
+    )?;
+    Ok(())
 }
 
 /// Extract and save PDF metadata (for backward compatibility)
@@ -1220,3 +4590,1066 @@ pub fn extract_and_save_pdf_metadata(
 ) -> Result<(), PedaruError> {
     extract_and_save_cloud_metadata(app, file_path, item_id)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_reserve_bandwidth_spaces_out_chunks() {
+        let now = Instant::now();
+        let mut next_free = now;
+
+        // First chunk goes straight through, the next waits for its share
+        assert_eq!(
+            reserve_bandwidth(&mut next_free, now, 1000, 1000),
+            Duration::ZERO
+        );
+        assert_eq!(
+            reserve_bandwidth(&mut next_free, now, 500, 1000),
+            Duration::from_secs(1)
+        );
+        assert_eq!(next_free - now, Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn test_is_retryable_download_error() {
+        let interrupted: PedaruError = GoogleDriveError::DownloadFailed("reset".into()).into();
+        assert!(is_retryable_download_error(&interrupted, false));
+        assert!(!is_retryable_download_error(&interrupted, true));
+
+        let no_space: PedaruError = GoogleDriveError::InsufficientDiskSpace {
+            required: 2,
+            available: 1,
+        }
+        .into();
+        assert!(!is_retryable_download_error(&no_space, false));
+
+        let forbidden: PedaruError = GoogleDriveError::ApiRequestFailed {
+            status: Some(403),
+            message: "forbidden".into(),
+        }
+        .into();
+        assert!(!is_retryable_download_error(&forbidden, false));
+    }
+
+    #[test]
+    fn test_reserve_bandwidth_after_idle() {
+        let now = Instant::now();
+        let mut next_free = now;
+        let later = now + Duration::from_secs(10);
+
+        // Idle time isn't banked as burst credit
+        assert_eq!(
+            reserve_bandwidth(&mut next_free, later, 1000, 1000),
+            Duration::ZERO
+        );
+        assert_eq!(next_free, later + Duration::from_secs(1));
+    }
+
+    fn migrated_db() -> rusqlite::Connection {
+        let mut conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::migrations::apply_migrations(&mut conn, crate::migrations::MIGRATIONS).unwrap();
+        conn
+    }
+
+    fn insert_download(
+        conn: &rusqlite::Connection,
+        drive_file_id: &str,
+        local_path: Option<&str>,
+        md5_checksum: Option<&str>,
+    ) {
+        conn.execute(
+            "INSERT INTO bookshelf_cloud
+               (drive_file_id, drive_folder_id, file_name, local_path, download_status,
+                md5_checksum, created_at, updated_at)
+             VALUES (?1, 'folder', ?1, ?2, ?3, ?4, 0, 0)",
+            rusqlite::params![
+                drive_file_id,
+                local_path,
+                if local_path.is_some() {
+                    "completed"
+                } else {
+                    "pending"
+                },
+                md5_checksum
+            ],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_shared_local_copy_is_removed_with_last_reference() {
+        let conn = migrated_db();
+        insert_download(&conn, "a", Some("/downloads/shared.pdf"), None);
+        insert_download(&conn, "b", Some("/downloads/shared.pdf"), None);
+        insert_download(&conn, "c", Some("/downloads/own.pdf"), None);
+
+        // "b" still uses the file, so deleting "a" must keep it
+        assert_eq!(local_copy_to_remove(&conn, "a").unwrap(), None);
+        clear_cloud_local_copy(&conn, "a").unwrap();
+        assert_eq!(
+            local_copy_to_remove(&conn, "b").unwrap().as_deref(),
+            Some("/downloads/shared.pdf")
+        );
+        assert_eq!(
+            local_copy_to_remove(&conn, "c").unwrap().as_deref(),
+            Some("/downloads/own.pdf")
+        );
+        assert_eq!(local_copy_to_remove(&conn, "a").unwrap(), None);
+        assert_eq!(local_copy_to_remove(&conn, "missing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_clear_local_copies_returns_files_to_delete() {
+        let mut conn = migrated_db();
+        insert_download(&conn, "a", Some("/downloads/shared.pdf"), None);
+        insert_download(&conn, "b", Some("/downloads/shared.pdf"), None);
+        insert_download(&conn, "c", None, None);
+        let ids = ["a", "b", "c"].map(str::to_string);
+
+        let to_remove = clear_cloud_local_copies_on(&mut conn, &ids).unwrap();
+
+        assert_eq!(
+            to_remove,
+            vec![None, Some("/downloads/shared.pdf".to_string()), None]
+        );
+        let remaining: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM bookshelf_cloud
+                 WHERE local_path IS NOT NULL OR download_status != 'pending'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(remaining, 0);
+    }
+
+    #[test]
+    fn test_corrupt_shared_local_copy_resets_every_item() {
+        let conn = migrated_db();
+        insert_download(&conn, "a", Some("/downloads/shared.pdf"), None);
+        insert_download(&conn, "b", Some("/downloads/shared.pdf"), None);
+        insert_download(&conn, "c", Some("/downloads/own.pdf"), None);
+
+        let (reset, to_remove) = reset_corrupt_local_copy(&conn, "a").unwrap();
+        assert_eq!(reset, ["b", "a"]);
+        assert_eq!(to_remove.as_deref(), Some("/downloads/shared.pdf"));
+        for drive_file_id in ["a", "b"] {
+            assert_eq!(get_cloud_local_path(&conn, drive_file_id).unwrap(), None);
+        }
+        assert_eq!(
+            get_cloud_local_path(&conn, "c").unwrap().as_deref(),
+            Some("/downloads/own.pdf")
+        );
+
+        // Already reset: nothing left to do
+        assert_eq!(
+            reset_corrupt_local_copy(&conn, "b").unwrap(),
+            (Vec::new(), None)
+        );
+    }
+
+    #[test]
+    fn test_link_duplicate_download_shares_existing_file() {
+        let conn = migrated_db();
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let path = file.path().to_str().unwrap();
+        insert_download(&conn, "original", Some(path), Some("abc"));
+        conn.execute(
+            "UPDATE bookshelf_cloud SET page_count = 12 WHERE drive_file_id = 'original'",
+            [],
+        )
+        .unwrap();
+        insert_download(&conn, "copy", None, None);
+
+        assert_eq!(
+            link_duplicate_download_on(&conn, "copy", "other").unwrap(),
+            None
+        );
+        assert_eq!(
+            link_duplicate_download_on(&conn, "copy", "abc")
+                .unwrap()
+                .as_deref(),
+            Some(path)
+        );
+        let (status, page_count): (String, i64) = conn
+            .query_row(
+                "SELECT download_status, page_count FROM bookshelf_cloud
+                 WHERE drive_file_id = 'copy'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((status.as_str(), page_count), ("completed", 12));
+
+        // Already linked, and the original still needs the shared file
+        assert_eq!(
+            link_duplicate_download_on(&conn, "copy", "abc").unwrap(),
+            None
+        );
+        assert_eq!(local_copy_to_remove(&conn, "original").unwrap(), None);
+    }
+
+    #[test]
+    fn test_upsert_cloud_item_reports_changes() {
+        let conn = migrated_db();
+        let upsert = |name: &str, modified: &str| {
+            let file = drive_file("file-1", name, modified);
+            upsert_cloud_item_on(&conn, 1, "folder", &top_level(&file)).unwrap()
+        };
+
+        assert_eq!(upsert("a.pdf", "2024-01-01"), UpsertOutcome::Added);
+        assert_eq!(upsert("a.pdf", "2024-01-01"), UpsertOutcome::Unchanged);
+        assert_eq!(upsert("a.pdf", "2024-02-01"), UpsertOutcome::Updated);
+        assert_eq!(upsert("b.pdf", "2024-02-01"), UpsertOutcome::Updated);
+    }
+
+    fn drive_file(id: &str, name: &str, modified: &str) -> DriveFile {
+        DriveFile {
+            id: id.to_string(),
+            name: name.to_string(),
+            size: Some("10".to_string()),
+            mime_type: "application/pdf".to_string(),
+            modified_time: Some(modified.to_string()),
+            thumbnail_link: None,
+            md5_checksum: None,
+        }
+    }
+
+    /// `count` files named after their index
+    fn drive_files(count: usize) -> Vec<DriveFile> {
+        (0..count)
+            .map(|i| drive_file(&format!("file-{}", i), &format!("{}.pdf", i), "2024-01-01"))
+            .collect()
+    }
+
+    /// A file at the top level of the watched folder "folder"
+    fn top_level(file: &DriveFile) -> DriveFileInput<'_> {
+        DriveFileInput {
+            file,
+            folder_id: "folder",
+            folder_path: None,
+        }
+    }
+
+    fn count_cloud_items(conn: &rusqlite::Connection) -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM bookshelf_cloud", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_upsert_cloud_items_in_one_transaction() {
+        let mut conn = migrated_db();
+        let mut files = drive_files(500);
+        let items: Vec<_> = files.iter().map(top_level).collect();
+
+        let outcomes = upsert_cloud_items_on(&mut conn, 1, "folder", &items).unwrap();
+        assert!(outcomes.iter().all(|o| *o == UpsertOutcome::Added));
+        assert_eq!(count_cloud_items(&conn), 500);
+
+        files[7].name = "renamed.pdf".to_string();
+        let items: Vec<_> = files.iter().map(top_level).collect();
+        let outcomes = upsert_cloud_items_on(&mut conn, 1, "folder", &items).unwrap();
+        assert_eq!(outcomes[7], UpsertOutcome::Updated);
+        assert_eq!(
+            outcomes
+                .iter()
+                .filter(|o| **o == UpsertOutcome::Unchanged)
+                .count(),
+            499
+        );
+    }
+
+    #[test]
+    fn test_upsert_cloud_items_writes_nothing_on_error() {
+        let mut conn = migrated_db();
+        conn.execute_batch(
+            "CREATE TRIGGER reject_file BEFORE INSERT ON bookshelf_cloud
+             WHEN NEW.drive_file_id = 'file-250'
+             BEGIN SELECT RAISE(ABORT, 'rejected'); END;",
+        )
+        .unwrap();
+        let files = drive_files(300);
+        let items: Vec<_> = files.iter().map(top_level).collect();
+
+        assert!(upsert_cloud_items_on(&mut conn, 1, "folder", &items).is_err());
+        assert_eq!(count_cloud_items(&conn), 0);
+    }
+
+    #[test]
+    fn test_missing_folder_items() {
+        let conn = migrated_db();
+        // insert_download puts every item in 'folder'
+        for id in ["listed", "missing"] {
+            insert_download(&conn, id, None, None);
+        }
+        conn.execute(
+            "INSERT INTO bookshelf_cloud
+               (drive_file_id, drive_folder_id, file_name, created_at, updated_at)
+             VALUES ('elsewhere', 'other', 'x.pdf', 0, 0)",
+            [],
+        )
+        .unwrap();
+
+        assert_eq!(
+            missing_folder_items_on(&conn, "folder", &["listed".to_string()]).unwrap(),
+            vec!["missing".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_move_item_keeps_download_and_tags() {
+        let conn = migrated_db();
+        insert_download(&conn, "file-1", Some("/downloads/a.pdf"), None);
+        conn.execute_batch(
+            "INSERT INTO drive_folders (folder_id, folder_name, created_at)
+               VALUES ('folder', 'Old', 0), ('other', 'New', 0);
+             INSERT INTO drive_subfolders (root_folder_id, folder_id, folder_path)
+               VALUES ('other', 'other-sub', 'Chapter 1');
+             INSERT INTO tags (name, created_at) VALUES ('kept', 0);
+             INSERT INTO bookshelf_tags (drive_file_id, tag_id, created_at)
+               SELECT 'file-1', id, 0 FROM tags;",
+        )
+        .unwrap();
+
+        assert!(move_item_on(&conn, "file-1", "other-sub").unwrap());
+        // Already there, or not in the bookshelf
+        assert!(!move_item_on(&conn, "file-1", "other-sub").unwrap());
+        assert!(!move_item_on(&conn, "missing", "other").unwrap());
+        assert_eq!(
+            move_item_on(&conn, "file-1", "unwatched")
+                .unwrap_err()
+                .code(),
+            "drive.invalid_folder_id"
+        );
+
+        let row: (String, String, Option<String>, Option<String>, i64) = conn
+            .query_row(
+                "SELECT drive_folder_id, root_folder_id, folder_path, local_path,
+                        (SELECT COUNT(*) FROM bookshelf_tags WHERE drive_file_id = 'file-1')
+                 FROM bookshelf_cloud WHERE drive_file_id = 'file-1'",
+                [],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            row,
+            (
+                "other-sub".to_string(),
+                "other".to_string(),
+                Some("Chapter 1".to_string()),
+                Some("/downloads/a.pdf".to_string()),
+                1
+            )
+        );
+    }
+
+    #[test]
+    fn test_cancel_all_downloads() {
+        let running = register_download("cancel_all_running");
+        let already_cancelled = register_download("cancel_all_cancelled");
+        already_cancelled.store(true, Ordering::SeqCst);
+        get_download_queue().lock().unwrap().waiting.extend([
+            "cancel_all_queued_1".to_string(),
+            "cancel_all_queued_2".to_string(),
+        ]);
+
+        assert_eq!(cancel_all_downloads(), 3);
+        assert!(running.load(Ordering::SeqCst));
+        assert_eq!(get_download_queue_depth(), 0);
+
+        unregister_download("cancel_all_running");
+        unregister_download("cancel_all_cancelled");
+    }
+
+    #[test]
+    fn test_prefetches_wait_behind_downloads() {
+        let mut queue = DownloadQueue::default();
+        queue.prefetching.push_back("prefetch".to_string());
+        queue.waiting.push_back("requested".to_string());
+
+        assert!(!queue.try_start("prefetch", 2));
+        assert!(queue.try_start("requested", 2));
+        assert!(queue.try_start("prefetch", 2));
+        assert_eq!(queue.active, 2);
+
+        // No slot free, even with nothing else waiting
+        queue.prefetching.push_back("next".to_string());
+        assert!(!queue.try_start("next", 2));
+        assert!(queue.remove("next"));
+        assert!(!queue.contains("next"));
+    }
+
+    #[test]
+    fn test_next_prefetch_candidate_by_name() {
+        let conn = migrated_db();
+        for (file_id, folder_id, name, status) in [
+            ("a", "folder", "Chapter 1.pdf", "completed"),
+            ("b", "folder", "chapter 2.pdf", "pending"),
+            ("c", "folder", "Chapter 3.pdf", "completed"),
+            ("d", "folder", "Chapter 4.pdf", "failed"),
+            ("x", "other", "Chapter 1a.pdf", "pending"),
+        ] {
+            conn.execute(
+                "INSERT INTO bookshelf_cloud
+                   (drive_file_id, drive_folder_id, file_name, file_size, download_status,
+                    created_at, updated_at)
+                 VALUES (?1, ?2, ?3, 100, ?4, 0, 0)",
+                rusqlite::params![file_id, folder_id, name, status],
+            )
+            .unwrap();
+        }
+
+        assert_eq!(
+            next_prefetch_candidate_on(&conn, "a").unwrap(),
+            Some(PrefetchCandidate {
+                drive_file_id: "b".to_string(),
+                file_name: "chapter 2.pdf".to_string(),
+                file_size: Some(100),
+            })
+        );
+        // The next item is already downloaded, failed, or there is none
+        assert_eq!(next_prefetch_candidate_on(&conn, "b").unwrap(), None);
+        assert_eq!(next_prefetch_candidate_on(&conn, "c").unwrap(), None);
+        assert_eq!(next_prefetch_candidate_on(&conn, "d").unwrap(), None);
+
+        // A download the user cancelled isn't restarted by prefetching
+        conn.execute(
+            "UPDATE bookshelf_cloud SET download_failure = 'cancelled' WHERE drive_file_id = 'b'",
+            [],
+        )
+        .unwrap();
+        assert_eq!(next_prefetch_candidate_on(&conn, "a").unwrap(), None);
+    }
+
+    #[test]
+    fn test_stale_download_status_updates_are_ignored() {
+        let conn = migrated_db();
+        insert_download(&conn, "file", None, None);
+        let status = |conn: &rusqlite::Connection| -> (String, f64) {
+            conn.query_row(
+                "SELECT download_status, download_progress FROM bookshelf_cloud
+                 WHERE drive_file_id = 'file'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+        };
+        let update = |status: &str, progress: f64, failure: Option<DownloadFailure>| {
+            update_download_status_on(&conn, "file", status, progress, None, failure).unwrap()
+        };
+
+        assert!(update("queued", 0.0, None));
+        assert!(update("downloading", 40.0, None));
+        // Progress only moves forward
+        assert!(!update("downloading", 20.0, None));
+        assert_eq!(status(&conn), ("downloading".to_string(), 40.0));
+
+        // A late update from a cancelled download doesn't resurrect it
+        assert!(update("pending", 0.0, Some(DownloadFailure::Cancelled)));
+        assert!(!update("downloading", 0.0, None));
+        assert_eq!(status(&conn), ("pending".to_string(), 0.0));
+
+        // A new download starts from queued
+        assert!(update("queued", 0.0, None));
+        assert!(update("downloading", 0.0, None));
+        assert!(update("completed", 100.0, None));
+        assert!(!update("downloading", 0.0, None));
+        assert_eq!(status(&conn), ("completed".to_string(), 100.0));
+    }
+
+    #[test]
+    fn test_retry_restarts_download_progress() {
+        let conn = migrated_db();
+        insert_download(&conn, "file", None, None);
+        let progress = || -> f64 {
+            conn.query_row(
+                "SELECT download_progress FROM bookshelf_cloud WHERE drive_file_id = 'file'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        let update = |status: &str, progress: f64, failure: Option<DownloadFailure>| {
+            update_download_status_on(&conn, "file", status, progress, None, failure).unwrap()
+        };
+
+        // The first attempt fails at 60% and the retry starts over
+        assert!(update("queued", 0.0, None));
+        assert!(update("downloading", 60.0, None));
+        assert!(!update("downloading", 10.0, None));
+        restart_download_progress_on(&conn, "file").unwrap();
+        assert_eq!(progress(), 0.0);
+        assert!(update("downloading", 10.0, None));
+        assert_eq!(progress(), 10.0);
+
+        // A download that already completed isn't touched
+        assert!(update("completed", 100.0, None));
+        restart_download_progress_on(&conn, "file").unwrap();
+        assert_eq!(progress(), 100.0);
+    }
+
+    #[test]
+    fn test_fits_storage_cap() {
+        assert!(fits_storage_cap(900, Some(100), Some(1000)));
+        assert!(!fits_storage_cap(901, Some(100), Some(1000)));
+        assert!(!fits_storage_cap(1001, None, Some(1000)));
+        assert!(fits_storage_cap(u64::MAX, Some(1), None));
+    }
+
+    #[test]
+    fn test_reading_stats_aggregates_sessions() {
+        let conn = migrated_db();
+        insert_download(&conn, "finished", None, None);
+        insert_download(&conn, "started", None, None);
+        insert_download(&conn, "progress_only", None, None);
+        insert_download(&conn, "unread", None, None);
+        conn.execute_batch(
+            "UPDATE bookshelf_cloud SET page_count = 10;
+             INSERT INTO reading_sessions (drive_file_id, started_at, ended_at, max_page) VALUES
+               ('finished', 100, 400, 6),
+               ('finished', 500, 700, 12),
+               ('started', 1000, 1060, 3);
+             INSERT INTO reading_progress (drive_file_id, page, updated_at)
+               VALUES ('progress_only', 4, 50);",
+        )
+        .unwrap();
+
+        let stats = reading_stats_on(&conn).unwrap();
+        assert_eq!(stats.total_pages_read, 10 + 3 + 4);
+        assert_eq!(stats.documents_completed, 1);
+        assert_eq!(stats.total_time_secs, 300 + 200 + 60);
+
+        let ids: Vec<_> = stats
+            .documents
+            .iter()
+            .map(|d| d.drive_file_id.as_str())
+            .collect();
+        assert_eq!(ids, ["started", "finished", "progress_only"]);
+        let finished = &stats.documents[1];
+        assert!(finished.completed);
+        assert_eq!(finished.session_count, 2);
+        assert_eq!(finished.last_read_at, Some(700));
+    }
+
+    #[test]
+    fn test_display_name_precedence() {
+        assert_eq!(
+            display_name(Some("Mine"), Some("Title"), "file.pdf"),
+            "Mine"
+        );
+        assert_eq!(display_name(None, Some("Title"), "file.pdf"), "Title");
+        assert_eq!(display_name(Some("  "), Some(""), "file.pdf"), "file.pdf");
+        assert_eq!(display_name(None, None, "file.pdf"), "file.pdf");
+    }
+
+    fn insert_local(
+        conn: &rusqlite::Connection,
+        file_name: &str,
+        created_at: i64,
+        updated_at: i64,
+    ) {
+        conn.execute(
+            "INSERT INTO bookshelf_local
+               (file_path, original_path, file_name, imported_at, updated_at)
+             VALUES ('/library/' || ?1, '/imports/' || ?1, ?1, ?2, ?3)",
+            rusqlite::params![file_name, created_at, updated_at],
+        )
+        .unwrap();
+    }
+
+    fn page_names(conn: &rusqlite::Connection, query: &ItemQuery) -> Vec<String> {
+        get_items_with_query_on(conn, std::path::Path::new("/thumbnails"), query)
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|item| item.file_name)
+            .collect()
+    }
+
+    #[test]
+    fn test_items_sorted_by_timestamps() {
+        let conn = migrated_db();
+        insert_local(&conn, "b.pdf", 200, 300);
+        insert_local(&conn, "c.pdf", 100, 400);
+        insert_local(&conn, "a.pdf", 200, 200);
+
+        let by = |sort_by, sort_direction| ItemQuery {
+            sort_by,
+            sort_direction,
+            ..ItemQuery::default()
+        };
+        assert_eq!(
+            page_names(&conn, &by(SortColumn::CreatedAt, None)),
+            ["a.pdf", "b.pdf", "c.pdf"]
+        );
+        assert_eq!(
+            page_names(&conn, &by(SortColumn::UpdatedAt, Some(SortDirection::Asc))),
+            ["a.pdf", "b.pdf", "c.pdf"]
+        );
+        assert_eq!(
+            page_names(&conn, &by(SortColumn::UpdatedAt, Some(SortDirection::Desc))),
+            ["c.pdf", "b.pdf", "a.pdf"]
+        );
+    }
+
+    #[test]
+    fn test_items_filtered_and_paginated_in_sql() {
+        let conn = migrated_db();
+        insert_download(&conn, "cloud-b.pdf", Some("/downloads/cloud-b.pdf"), None);
+        insert_download(&conn, "cloud-d.pdf", None, None);
+        insert_local(&conn, "local-a.pdf", 1, 1);
+        insert_local(&conn, "local-c.pdf", 1, 1);
+        conn.execute_batch(
+            "UPDATE bookshelf_cloud SET is_favorite = 1 WHERE drive_file_id = 'cloud-d.pdf';
+             UPDATE bookshelf_local SET is_favorite = 1 WHERE file_name = 'local-c.pdf';
+             INSERT INTO tags (id, name, created_at) VALUES (1, 'Work', 0), (2, 'Home', 0);
+             INSERT INTO bookshelf_tags (drive_file_id, tag_id, created_at)
+             VALUES ('cloud-b.pdf', 1, 0), ('cloud-b.pdf', 2, 0);",
+        )
+        .unwrap();
+
+        let by_name = ItemQuery {
+            sort_by: SortColumn::Name,
+            limit: Some(3),
+            ..ItemQuery::default()
+        };
+        let page =
+            get_items_with_query_on(&conn, std::path::Path::new("/thumbnails"), &by_name).unwrap();
+        assert_eq!(page.total_count, 4);
+        let names: Vec<&str> = page
+            .items
+            .iter()
+            .map(|item| item.file_name.as_str())
+            .collect();
+        assert_eq!(names, ["cloud-b.pdf", "cloud-d.pdf", "local-a.pdf"]);
+        assert_eq!(page.items[0].tags, ["Home", "Work"]);
+        assert_eq!(page.items[0].download_status, "completed");
+        assert_eq!(page.items[2].source_type, "local");
+
+        let second_page = ItemQuery {
+            offset: 3,
+            ..by_name.clone()
+        };
+        assert_eq!(page_names(&conn, &second_page), ["local-c.pdf"]);
+
+        let favorites_first = ItemQuery {
+            favorites: FavoritesMode::First,
+            sort_by: SortColumn::Name,
+            ..ItemQuery::default()
+        };
+        assert_eq!(
+            page_names(&conn, &favorites_first),
+            ["cloud-d.pdf", "local-c.pdf", "cloud-b.pdf", "local-a.pdf"]
+        );
+
+        let favorites_only = ItemQuery {
+            favorites: FavoritesMode::Only,
+            ..favorites_first
+        };
+        assert_eq!(
+            page_names(&conn, &favorites_only),
+            ["cloud-d.pdf", "local-c.pdf"]
+        );
+
+        let tagged = ItemQuery {
+            tags: vec!["work".to_string()],
+            ..ItemQuery::default()
+        };
+        let page =
+            get_items_with_query_on(&conn, std::path::Path::new("/thumbnails"), &tagged).unwrap();
+        assert_eq!(page.total_count, 1);
+        assert_eq!(page.items[0].file_name, "cloud-b.pdf");
+    }
+
+    #[test]
+    fn test_requested_downloads_skip_completed() {
+        let conn = migrated_db();
+        insert_download(&conn, "requested", None, None);
+        insert_download(&conn, "finished", Some("/downloads/finished.pdf"), None);
+        insert_download(&conn, "incidental", None, None);
+        conn.execute(
+            "UPDATE bookshelf_cloud SET download_requested = 1
+             WHERE drive_file_id IN ('requested', 'finished')",
+            [],
+        )
+        .unwrap();
+
+        assert_eq!(
+            requested_downloads_on(&conn).unwrap(),
+            vec![("requested".to_string(), "requested".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_failed_downloads_resume_only_when_retryable() {
+        let conn = migrated_db();
+        insert_download(&conn, "offline", None, None);
+        insert_download(&conn, "disk_full", None, None);
+        conn.execute("UPDATE bookshelf_cloud SET download_requested = 1", [])
+            .unwrap();
+
+        mark_download_failed_on(&conn, "offline", DownloadFailure::Network, "reset").unwrap();
+        mark_download_failed_on(&conn, "disk_full", DownloadFailure::DiskFull, "full").unwrap();
+
+        assert_eq!(
+            requested_downloads_on(&conn).unwrap(),
+            vec![("offline".to_string(), "offline".to_string())]
+        );
+        let failure: String = conn
+            .query_row(
+                "SELECT download_failure FROM bookshelf_cloud WHERE drive_file_id = 'disk_full'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(failure.parse(), Ok(DownloadFailure::DiskFull));
+    }
+
+    #[test]
+    fn test_download_failure_of_error() {
+        let of = |e: PedaruError| DownloadFailure::of(&e);
+        assert_eq!(
+            of(GoogleDriveError::DownloadCancelled("a".into()).into()),
+            DownloadFailure::Cancelled
+        );
+        assert_eq!(
+            of(GoogleDriveError::InsufficientDiskSpace {
+                required: 2,
+                available: 1
+            }
+            .into()),
+            DownloadFailure::DiskFull
+        );
+        assert_eq!(
+            of(IoError::WriteFailed {
+                path: "a.pdf".into(),
+                source: std::io::ErrorKind::StorageFull.into(),
+            }
+            .into()),
+            DownloadFailure::DiskFull
+        );
+        assert_eq!(
+            of(GoogleDriveError::IntegrityCheckFailed("size".into()).into()),
+            DownloadFailure::Integrity
+        );
+        assert_eq!(
+            of(GoogleDriveError::ApiRequestFailed {
+                status: Some(503),
+                message: "unavailable".into(),
+            }
+            .into()),
+            DownloadFailure::Network
+        );
+        assert_eq!(
+            of(GoogleDriveError::ApiRequestFailed {
+                status: Some(403),
+                message: "forbidden".into(),
+            }
+            .into()),
+            DownloadFailure::Other
+        );
+    }
+
+    #[test]
+    fn test_thumbnail_files_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let thumbnails_dir = dir.path().join(THUMBNAILS_DIR_NAME);
+
+        let file_name = write_thumbnail_file(&thumbnails_dir, "abc/../x", b"png").unwrap();
+        assert_eq!(file_name, "abc____x.png");
+        assert_eq!(
+            std::fs::read(thumbnails_dir.join(&file_name)).unwrap(),
+            b"png"
+        );
+    }
+
+    #[test]
+    fn test_compute_file_md5() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"abc").unwrap();
+        assert_eq!(
+            compute_file_md5(file.path()).unwrap(),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+    }
+
+    #[test]
+    fn test_compute_file_md5_empty_file() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        assert_eq!(
+            compute_file_md5(file.path()).unwrap(),
+            "d41d8cd98f00b204e9800998ecf8427e"
+        );
+    }
+
+    #[test]
+    fn test_partial_download_path() {
+        assert_eq!(
+            partial_download_path(std::path::Path::new("/downloads/book.pdf")),
+            std::path::PathBuf::from("/downloads/book.pdf.partial")
+        );
+    }
+
+    #[test]
+    fn test_orphaned_partials_exclude_resumable_downloads() {
+        let resumable = HashSet::from(["resuming.pdf".to_string()]);
+        assert!(is_orphaned_partial("abandoned.pdf.partial", &resumable));
+        assert!(!is_orphaned_partial("resuming.pdf.partial", &resumable));
+        assert!(!is_orphaned_partial("abandoned.pdf", &resumable));
+    }
+
+    #[test]
+    fn test_parse_download_name_template() {
+        for template in [
+            "{file_name}",
+            " {pdf_title} ",
+            "{pdf_title} [{drive_file_id}]",
+            "scan-{file_name}",
+        ] {
+            assert_eq!(
+                parse_download_name_template(template).unwrap(),
+                template.trim()
+            );
+        }
+        for template in [
+            "",
+            "book",
+            "{title}",
+            "{file_name",
+            "file_name}",
+            "{file_name}}",
+            "{{file_name}",
+            "{pdf_title}/{file_name}",
+            "{pdf_title}\\{file_name}",
+        ] {
+            assert!(
+                parse_download_name_template(template).is_err(),
+                "{template:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_download_name() {
+        let render = |template, pdf_title| {
+            render_download_name(template, "id123", "Report 2024.pdf", pdf_title)
+        };
+        let name = |stem: &str, extension: &str| (stem.to_string(), extension.to_string());
+
+        assert_eq!(render("{file_name}", None), name("Report 2024", "pdf"));
+        // The title isn't known before the first download
+        assert_eq!(render("{pdf_title}", None), name("Report 2024", "pdf"));
+        assert_eq!(
+            render("{pdf_title} ({drive_file_id})", Some("Annual: Q1/Q2")),
+            name("Annual_ Q1_Q2 (id123)", "pdf")
+        );
+        assert_eq!(
+            render("{pdf_title}", Some("  ")),
+            name("Report 2024", "pdf")
+        );
+        // A value that looks like a token isn't expanded again
+        assert_eq!(
+            render("{pdf_title}", Some("{drive_file_id}")),
+            name("{drive_file_id}", "pdf")
+        );
+        assert_eq!(
+            render_download_name("{file_name}", "id123", "notes", None),
+            name("notes", "")
+        );
+        assert_eq!(
+            render_download_name("{pdf_title}", "id123", ".pdf", Some("\t")),
+            name(".pdf", "")
+        );
+    }
+
+    #[test]
+    fn test_sanitize_file_name() {
+        for (name, sanitized) in [
+            ("Report 2024", "Report 2024"),
+            // Reserved on Windows, or path separators everywhere
+            ("a/b\\c", "a_b_c"),
+            ("Q1: Plan?", "Q1_ Plan_"),
+            ("<draft> \"final\" *v2*|", "_draft_ _final_ _v2__"),
+            ("line\nbreak\u{7f}", "line_break_"),
+            // Windows drops trailing dots and spaces
+            ("etc.", "etc"),
+            ("  notes . . ", "notes"),
+            ("...", ""),
+            // Windows device names, in any case and with any extension
+            ("CON", "CON_"),
+            ("nul", "nul_"),
+            ("Aux.backup", "Aux_.backup"),
+            ("com1 .txt", "com1 _.txt"),
+            ("LPT9", "LPT9_"),
+            ("CONOUT$", "CONOUT$_"),
+            ("COM10", "COM10"),
+            ("Console", "Console"),
+            ("prn_list", "prn_list"),
+        ] {
+            assert_eq!(sanitize_file_name(name), sanitized, "{name:?}");
+        }
+    }
+
+    #[test]
+    fn test_download_name_of_unsafe_drive_names() {
+        let render = |file_name| {
+            render_download_name(DEFAULT_DOWNLOAD_NAME_TEMPLATE, "id123", file_name, None)
+        };
+        let name = |stem: &str, extension: &str| (stem.to_string(), extension.to_string());
+
+        assert_eq!(
+            render("2024/01/31 scan.pdf"),
+            name("2024_01_31 scan", "pdf")
+        );
+        assert_eq!(render("CON.pdf"), name("CON_", "pdf"));
+        assert_eq!(render("aux.tar.pdf"), name("aux_.tar", "pdf"));
+        assert_eq!(render("draft..pdf"), name("draft", "pdf"));
+        assert_eq!(render("notes."), name("notes", ""));
+        assert_eq!(render("?.pdf"), name("_", "pdf"));
+        assert_eq!(render(". .pdf"), name("id123", "pdf"));
+
+        // A long name is cut on a character boundary, keeping the extension
+        let long = format!("{}.pdf", "書".repeat(200));
+        let (stem, extension) = render(&long);
+        let file_name = numbered_file_name(&stem, &extension, 0);
+        assert!(file_name.len() <= MAX_FILE_NAME_BYTES);
+        assert!(file_name.ends_with("書.pdf"));
+    }
+
+    #[test]
+    fn test_numbered_file_name_length() {
+        assert_eq!(numbered_file_name("book", "pdf", 0), "book.pdf");
+        assert_eq!(numbered_file_name("book", "pdf", 2), "book_2.pdf");
+        assert_eq!(numbered_file_name("book", "", 1), "book_1");
+
+        let long = "é".repeat(MAX_FILE_NAME_BYTES);
+        for n in [0, 1, 100] {
+            let name = numbered_file_name(&long, "pdf", n);
+            assert!(name.len() <= MAX_FILE_NAME_BYTES, "{n}");
+            assert!(name.ends_with(".pdf"));
+        }
+        assert!(numbered_file_name(&long, "pdf", 3).ends_with("é_3.pdf"));
+    }
+
+    #[test]
+    fn test_download_path_adds_counter_on_collision() {
+        let conn = migrated_db();
+        let dir = tempfile::tempdir().unwrap();
+        let path_of = |name: &str| dir.path().join(name).to_string_lossy().into_owned();
+        let download_path = |drive_file_id: &str| {
+            download_path_on(&conn, dir.path(), "{pdf_title}", drive_file_id, "x.pdf")
+                .unwrap()
+                .to_string_lossy()
+                .into_owned()
+        };
+        for id in ["a", "b", "c"] {
+            insert_download(&conn, id, None, None);
+        }
+        conn.execute("UPDATE bookshelf_cloud SET pdf_title = 'Book'", [])
+            .unwrap();
+
+        assert_eq!(download_path("a"), path_of("Book.pdf"));
+
+        // Taken by another item's download
+        conn.execute(
+            "UPDATE bookshelf_cloud SET local_path = ?1 WHERE drive_file_id = 'a'",
+            [path_of("Book.pdf")],
+        )
+        .unwrap();
+        assert_eq!(download_path("b"), path_of("Book_1.pdf"));
+        // ...but an item keeps its own path
+        assert_eq!(download_path("a"), path_of("Book.pdf"));
+
+        // Taken by a file that's already on disk
+        std::fs::write(path_of("Book_1.pdf"), b"").unwrap();
+        assert_eq!(download_path("c"), path_of("Book_2.pdf"));
+    }
+
+    #[test]
+    fn test_reserved_download_paths_dont_collide() {
+        let mut conn = migrated_db();
+        let dir = tempfile::tempdir().unwrap();
+        let path_of = |name: &str| dir.path().join(name).to_string_lossy().into_owned();
+        for id in ["a", "b", "c"] {
+            insert_download(&conn, id, None, None);
+        }
+        conn.execute("UPDATE bookshelf_cloud SET pdf_title = 'Book'", [])
+            .unwrap();
+        let mut reserve = |drive_file_id: &str| {
+            reserve_download_path_on(&mut conn, dir.path(), "{pdf_title}", drive_file_id, "x.pdf")
+                .unwrap()
+                .to_string_lossy()
+                .into_owned()
+        };
+
+        // Two downloads in flight, neither file on disk yet
+        assert_eq!(reserve("a"), path_of("Book.pdf"));
+        assert_eq!(reserve("b"), path_of("Book_1.pdf"));
+        // A resumed download keeps its reservation
+        assert_eq!(reserve("a"), path_of("Book.pdf"));
+
+        // A partial file left on disk counts as taken
+        std::fs::write(path_of("Book_2.pdf.partial"), b"").unwrap();
+        assert_eq!(reserve("c"), path_of("Book_3.pdf"));
+
+        // Completing the download releases the reservation; local_path keeps
+        // the name taken
+        let local_path = path_of("Book.pdf");
+        assert!(
+            update_download_status_on(&conn, "a", "completed", 100.0, Some(&local_path), None)
+                .unwrap()
+        );
+        let reserved: Option<String> = conn
+            .query_row(
+                "SELECT reserved_path FROM bookshelf_cloud WHERE drive_file_id = 'a'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(reserved, None);
+        assert_eq!(
+            download_path_on(&conn, dir.path(), "{pdf_title}", "b", "x.pdf").unwrap(),
+            dir.path().join("Book_1.pdf")
+        );
+    }
+
+    #[test]
+    fn test_storage_usage_by_folder() {
+        let download = |folder_id: &str, local_path: &str, file_size: Option<i64>| StoredDownload {
+            folder_id: folder_id.to_string(),
+            folder_name: None,
+            local_path: local_path.to_string(),
+            file_size,
+        };
+        let downloads = [
+            download("a", "/d/one.pdf", Some(100)),
+            download("a", "/d/one.pdf", Some(100)),
+            download("b", "/d/two.pdf", None),
+            download("b", "/d/three.pdf", Some(500)),
+            download("b", "/d/missing.pdf", None),
+        ];
+
+        let usage = summarize_storage(&downloads, |path| (path == "/d/two.pdf").then_some(20));
+
+        assert_eq!(usage.total_bytes, 620);
+        assert_eq!(usage.item_count, 4);
+        assert_eq!(usage.missing_count, 1);
+        let folders: Vec<_> = usage
+            .folders
+            .iter()
+            .map(|f| (f.folder_id.as_str(), f.total_bytes, f.item_count))
+            .collect();
+        assert_eq!(folders, [("b", 520, 2), ("a", 100, 2)]);
+    }
+}
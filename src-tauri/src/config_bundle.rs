@@ -0,0 +1,554 @@
+//! Portable configuration export and import
+//!
+//! Bundles synced folders, non-secret settings, tags and favorites into one
+//! JSON document so a setup survives a reinstall or a move to another
+//! machine. API keys and OAuth tokens live in the keychain and are never
+//! included. Drive file IDs are stable across machines, so imported items
+//! can simply be downloaded again.
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::AppHandle;
+
+use crate::db::{ToDbError, now_timestamp, open_db};
+use crate::error::{ConfigError, PedaruError};
+use crate::oauth;
+use crate::settings;
+
+/// Current bundle format version (bumped on incompatible changes)
+pub const CONFIG_BUNDLE_VERSION: u32 = 1;
+
+/// Settings that are user preferences and carry over to another machine
+///
+/// Anything not listed here (account state, the proxy, sign-in pages, new
+/// keys nobody has classified yet) stays out of bundles.
+const PORTABLE_SETTINGS: &[&str] = &[
+    settings::KEY_TRANSLATION_PROVIDER,
+    settings::KEY_TARGET_LANGUAGE,
+    settings::KEY_TRANSLATION_PROMPT_WORD,
+    settings::KEY_TRANSLATION_PROMPT_SENTENCE,
+    settings::KEY_TRANSLATION_PROMPT_PARAGRAPH,
+    settings::KEY_PROMPT_WORD_MAX_WORDS,
+    settings::KEY_PROMPT_PARAGRAPH_MIN_WORDS,
+    settings::KEY_GEMINI_MODEL,
+    settings::KEY_GEMINI_EXPLANATION_MODEL,
+    settings::KEY_OPENAI_MODEL,
+    settings::KEY_OPENAI_EXPLANATION_MODEL,
+    settings::KEY_ANTHROPIC_MODEL,
+    settings::KEY_ANTHROPIC_EXPLANATION_MODEL,
+    settings::KEY_GEMINI_TEMPERATURE,
+    settings::KEY_GEMINI_MAX_OUTPUT_TOKENS,
+    settings::KEY_OPENAI_TEMPERATURE,
+    settings::KEY_OPENAI_MAX_OUTPUT_TOKENS,
+    settings::KEY_ANTHROPIC_TEMPERATURE,
+    settings::KEY_ANTHROPIC_MAX_OUTPUT_TOKENS,
+    settings::KEY_MAX_CONCURRENT_DOWNLOADS,
+    settings::KEY_MAX_DOWNLOAD_BYTES_PER_SEC,
+    settings::KEY_TRANSLATION_CACHE_MAX_AGE_SECS,
+    settings::KEY_GEMINI_REQUESTS_PER_MINUTE,
+    settings::KEY_STORAGE_CAP_BYTES,
+    settings::KEY_PREFETCH_ENABLED,
+    settings::KEY_SYNC_MIME_TYPES,
+    settings::KEY_AUTO_SYNC_INTERVAL_MINUTES,
+    settings::KEY_TOKEN_REFRESH_MARGIN_SECS,
+    settings::KEY_DOWNLOAD_NAME_TEMPLATE,
+    settings::KEY_DOWNLOAD_PROGRESS_INTERVAL_MS,
+    settings::KEY_HTTP_CONNECT_TIMEOUT_SECS,
+    settings::KEY_HTTP_REQUEST_TIMEOUT_SECS,
+    settings::KEY_LOG_LEVEL,
+];
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// A synced Drive folder
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleFolder {
+    pub folder_id: String,
+    pub folder_name: String,
+    pub recursive: bool,
+    /// Label of the Google account the folder belongs to
+    pub account_label: String,
+}
+
+/// A tag and the Drive files it is applied to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleTag {
+    pub name: String,
+    pub drive_file_ids: Vec<String>,
+}
+
+/// A favorite cloud item, with enough detail to recreate it before a sync
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleItem {
+    pub drive_file_id: String,
+    pub drive_folder_id: String,
+    pub root_folder_id: Option<String>,
+    pub folder_path: Option<String>,
+    pub file_name: String,
+    pub file_size: Option<i64>,
+    pub drive_modified_time: Option<String>,
+    pub account_label: String,
+}
+
+/// Exported configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigBundle {
+    pub version: u32,
+    pub exported_at: i64,
+    pub drive_folders: Vec<BundleFolder>,
+    pub settings: BTreeMap<String, String>,
+    pub tags: Vec<BundleTag>,
+    pub favorites: Vec<BundleItem>,
+}
+
+/// What to do when an imported entry already exists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImportMode {
+    /// Keep the existing entry
+    #[default]
+    Skip,
+    /// Replace the existing entry
+    Overwrite,
+}
+
+/// Number of entries applied by an import
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub folders: i32,
+    pub settings: i32,
+    /// Tag assignments added (tags are always merged)
+    pub tags: i32,
+    pub favorites: i32,
+    /// Entries left alone because they already existed
+    pub skipped: i32,
+}
+
+// ============================================================================
+// Export
+// ============================================================================
+
+/// Whether a setting belongs in an exported bundle
+fn is_portable_setting(key: &str) -> bool {
+    PORTABLE_SETTINGS.contains(&key)
+}
+
+fn export_bundle(conn: &Connection) -> Result<ConfigBundle, PedaruError> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT f.folder_id, f.folder_name, f.recursive, COALESCE(a.label, 'Default')
+             FROM drive_folders f
+             LEFT JOIN google_accounts a ON a.id = f.account_id
+             WHERE f.is_active = 1
+             ORDER BY f.folder_name",
+        )
+        .db_err()?;
+    let drive_folders = stmt
+        .query_map([], |row| {
+            Ok(BundleFolder {
+                folder_id: row.get(0)?,
+                folder_name: row.get(1)?,
+                recursive: row.get::<_, i32>(2)? != 0,
+                account_label: row.get(3)?,
+            })
+        })
+        .db_err()?
+        .collect::<Result<Vec<_>, _>>()
+        .db_err()?;
+
+    let mut stmt = conn
+        .prepare("SELECT key, value FROM settings ORDER BY key")
+        .db_err()?;
+    let settings = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))
+        .db_err()?
+        .collect::<Result<Vec<(String, String)>, _>>()
+        .db_err()?
+        .into_iter()
+        .filter(|(key, _)| is_portable_setting(key))
+        .collect();
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT t.name, bt.drive_file_id
+             FROM tags t
+             LEFT JOIN bookshelf_tags bt ON bt.tag_id = t.id
+             ORDER BY t.name COLLATE NOCASE, bt.drive_file_id",
+        )
+        .db_err()?;
+    let mut tags: Vec<BundleTag> = Vec::new();
+    for row in stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+        })
+        .db_err()?
+    {
+        let (name, drive_file_id) = row.db_err()?;
+        if tags.last().is_none_or(|tag| tag.name != name) {
+            tags.push(BundleTag {
+                name,
+                drive_file_ids: Vec::new(),
+            });
+        }
+        if let (Some(tag), Some(drive_file_id)) = (tags.last_mut(), drive_file_id) {
+            tag.drive_file_ids.push(drive_file_id);
+        }
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT c.drive_file_id, c.drive_folder_id, c.root_folder_id, c.folder_path,
+                    c.file_name, c.file_size, c.drive_modified_time,
+                    COALESCE(a.label, 'Default')
+             FROM bookshelf_cloud c
+             LEFT JOIN google_accounts a ON a.id = c.account_id
+             WHERE c.is_favorite = 1
+             ORDER BY c.file_name",
+        )
+        .db_err()?;
+    let favorites = stmt
+        .query_map([], |row| {
+            Ok(BundleItem {
+                drive_file_id: row.get(0)?,
+                drive_folder_id: row.get(1)?,
+                root_folder_id: row.get(2)?,
+                folder_path: row.get(3)?,
+                file_name: row.get(4)?,
+                file_size: row.get(5)?,
+                drive_modified_time: row.get(6)?,
+                account_label: row.get(7)?,
+            })
+        })
+        .db_err()?
+        .collect::<Result<Vec<_>, _>>()
+        .db_err()?;
+
+    Ok(ConfigBundle {
+        version: CONFIG_BUNDLE_VERSION,
+        exported_at: now_timestamp(),
+        drive_folders,
+        settings,
+        tags,
+        favorites,
+    })
+}
+
+/// Export folders, non-secret settings, tags and favorites as JSON
+pub fn export_config(app: &AppHandle) -> Result<String, PedaruError> {
+    let bundle = export_bundle(&open_db(app)?)?;
+    serde_json::to_string_pretty(&bundle)
+        .map_err(|e| ConfigError::InvalidBundle(e.to_string()).into())
+}
+
+// ============================================================================
+// Import
+// ============================================================================
+
+/// Parse a bundle, checking its version before the rest of the document
+fn parse_bundle(json: &str) -> Result<ConfigBundle, PedaruError> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| ConfigError::InvalidBundle(e.to_string()))?;
+
+    let version = value
+        .get("version")
+        .and_then(serde_json::Value::as_u64)
+        .ok_or_else(|| ConfigError::InvalidBundle("missing version".to_string()))?;
+    if version == 0 || version > u64::from(CONFIG_BUNDLE_VERSION) {
+        return Err(ConfigError::IncompatibleBundle {
+            found: version,
+            supported: CONFIG_BUNDLE_VERSION,
+        }
+        .into());
+    }
+
+    serde_json::from_value(value).map_err(|e| ConfigError::InvalidBundle(e.to_string()).into())
+}
+
+fn import_bundle(
+    conn: &mut Connection,
+    bundle: &ConfigBundle,
+    mode: ImportMode,
+) -> Result<ImportSummary, PedaruError> {
+    let overwrite = mode == ImportMode::Overwrite;
+    let mut summary = ImportSummary::default();
+    let now = now_timestamp();
+    let tx = conn.transaction().db_err()?;
+
+    for folder in &bundle.drive_folders {
+        let account_id = oauth::get_or_create_account_on(&tx, &folder.account_label)?.id;
+        let changed = tx
+            .execute(
+                if overwrite {
+                    "INSERT INTO drive_folders (folder_id, folder_name, recursive, account_id, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)
+                     ON CONFLICT(folder_id) DO UPDATE SET
+                       folder_name = excluded.folder_name,
+                       recursive = excluded.recursive,
+                       account_id = excluded.account_id,
+                       is_active = 1,
+                       changes_page_token = NULL"
+                } else {
+                    "INSERT INTO drive_folders (folder_id, folder_name, recursive, account_id, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)
+                     ON CONFLICT(folder_id) DO NOTHING"
+                },
+                rusqlite::params![
+                    folder.folder_id,
+                    folder.folder_name,
+                    folder.recursive as i32,
+                    account_id,
+                    now
+                ],
+            )
+            .db_err()?;
+        if changed > 0 {
+            summary.folders += 1;
+        } else {
+            summary.skipped += 1;
+        }
+    }
+
+    for (key, value) in bundle
+        .settings
+        .iter()
+        .filter(|(key, _)| is_portable_setting(key))
+    {
+        let changed = tx
+            .execute(
+                if overwrite {
+                    "INSERT INTO settings (key, value, updated_at) VALUES (?1, ?2, ?3)
+                     ON CONFLICT(key) DO UPDATE SET value = ?2, updated_at = ?3"
+                } else {
+                    "INSERT INTO settings (key, value, updated_at) VALUES (?1, ?2, ?3)
+                     ON CONFLICT(key) DO NOTHING"
+                },
+                rusqlite::params![key, value, now],
+            )
+            .db_err()?;
+        if changed > 0 {
+            summary.settings += 1;
+        } else {
+            summary.skipped += 1;
+        }
+    }
+
+    for tag in &bundle.tags {
+        let name = tag.name.trim();
+        if name.is_empty() {
+            continue;
+        }
+        tx.execute(
+            "INSERT INTO tags (name, created_at) VALUES (?1, ?2) ON CONFLICT(name) DO NOTHING",
+            rusqlite::params![name, now],
+        )
+        .db_err()?;
+        for drive_file_id in &tag.drive_file_ids {
+            summary.tags += tx
+                .execute(
+                    "INSERT OR IGNORE INTO bookshelf_tags (drive_file_id, tag_id, created_at)
+                     SELECT ?1, id, ?3 FROM tags WHERE name = ?2",
+                    rusqlite::params![drive_file_id, name, now],
+                )
+                .db_err()? as i32;
+        }
+    }
+
+    for item in &bundle.favorites {
+        let account_id = oauth::get_or_create_account_on(&tx, &item.account_label)?.id;
+        let changed = tx
+            .execute(
+                if overwrite {
+                    "INSERT INTO bookshelf_cloud (
+                       drive_file_id, drive_folder_id, root_folder_id, folder_path, file_name,
+                       file_size, drive_modified_time, account_id, is_favorite, created_at, updated_at
+                     )
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 1, ?9, ?9)
+                     ON CONFLICT(drive_file_id) DO UPDATE SET
+                       is_favorite = 1,
+                       updated_at = excluded.updated_at"
+                } else {
+                    "INSERT INTO bookshelf_cloud (
+                       drive_file_id, drive_folder_id, root_folder_id, folder_path, file_name,
+                       file_size, drive_modified_time, account_id, is_favorite, created_at, updated_at
+                     )
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 1, ?9, ?9)
+                     ON CONFLICT(drive_file_id) DO NOTHING"
+                },
+                rusqlite::params![
+                    item.drive_file_id,
+                    item.drive_folder_id,
+                    item.root_folder_id,
+                    item.folder_path,
+                    item.file_name,
+                    item.file_size,
+                    item.drive_modified_time,
+                    account_id,
+                    now
+                ],
+            )
+            .db_err()?;
+        if changed > 0 {
+            summary.favorites += 1;
+        } else {
+            summary.skipped += 1;
+        }
+    }
+
+    tx.commit().db_err()?;
+    Ok(summary)
+}
+
+/// Merge an exported bundle into this installation
+///
+/// Runs in one transaction, so a failing import changes nothing. Tags are
+/// always merged; for folders, settings and favorites `mode` decides what
+/// happens to entries that already exist.
+pub fn import_config(
+    app: &AppHandle,
+    json: &str,
+    mode: ImportMode,
+) -> Result<ImportSummary, PedaruError> {
+    let bundle = parse_bundle(json)?;
+    let summary = import_bundle(&mut open_db(app)?, &bundle, mode)?;
+    log::info!(
+        "Imported configuration: {} folders, {} settings, {} tag assignments, {} favorites ({} skipped)",
+        summary.folders,
+        summary.settings,
+        summary.tags,
+        summary.favorites,
+        summary.skipped
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::migrations;
+
+    fn migrated_db() -> Connection {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrations::apply_migrations(&mut conn, migrations::MIGRATIONS).unwrap();
+        conn
+    }
+
+    /// Settings that describe this machine or an account's sign-in state
+    const MACHINE_SETTINGS: &[&str] = &[
+        settings::KEY_GOOGLE_GRANTED_SCOPES,
+        settings::KEY_GOOGLE_NEEDS_REAUTH,
+        settings::KEY_OAUTH_SUCCESS_PAGE,
+        settings::KEY_OAUTH_FAILURE_PAGE,
+        settings::KEY_OAUTH_STATE_MISMATCH_PAGE,
+        settings::KEY_PROXY_URL,
+        settings::KEY_DB_SIZE_AFTER_COMPACTION,
+    ];
+
+    #[test]
+    fn test_machine_settings_are_not_portable() {
+        assert!(is_portable_setting(settings::KEY_GEMINI_MODEL));
+        for key in MACHINE_SETTINGS {
+            assert!(!is_portable_setting(key), "{}", key);
+        }
+        assert!(!is_portable_setting("google_granted_scopes:2"));
+        assert!(!is_portable_setting("google_needs_reauth:2"));
+        assert!(!is_portable_setting("some_future_setting"));
+    }
+
+    #[test]
+    fn test_every_setting_key_is_classified() {
+        // Every `KEY_*` constant in settings.rs must be either portable or
+        // a machine setting, so a new key can't be forgotten
+        let keys: Vec<&str> = include_str!("settings.rs")
+            .lines()
+            .filter(|line| line.starts_with("pub const KEY_"))
+            .filter_map(|line| line.split('"').nth(1))
+            .collect();
+        assert!(keys.len() > MACHINE_SETTINGS.len());
+        for key in keys {
+            assert!(
+                PORTABLE_SETTINGS.contains(&key) ^ MACHINE_SETTINGS.contains(&key),
+                "setting '{}' must be listed in exactly one of PORTABLE_SETTINGS or MACHINE_SETTINGS",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_bundle_rejects_newer_version() {
+        let err = parse_bundle(r#"{"version": 99, "unknown": true}"#).unwrap_err();
+        assert!(matches!(
+            err,
+            PedaruError::Config(ConfigError::IncompatibleBundle { found: 99, .. })
+        ));
+        assert!(matches!(
+            parse_bundle("{}").unwrap_err(),
+            PedaruError::Config(ConfigError::InvalidBundle(_))
+        ));
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let source = migrated_db();
+        source
+            .execute_batch(
+                "INSERT INTO drive_folders (folder_id, folder_name, recursive, created_at)
+                 VALUES ('folder', 'Papers', 1, 0);
+                 INSERT INTO settings (key, value, updated_at) VALUES
+                   ('gemini_model', 'gemini-pro', 0),
+                   ('db_size_after_compaction', '123', 0);
+                 INSERT INTO tags (name, created_at) VALUES ('ml', 0), ('unused', 0);
+                 INSERT INTO bookshelf_tags (drive_file_id, tag_id, created_at)
+                 SELECT 'file', id, 0 FROM tags WHERE name = 'ml';
+                 INSERT INTO bookshelf_cloud
+                   (drive_file_id, drive_folder_id, file_name, is_favorite, created_at, updated_at)
+                 VALUES ('file', 'folder', 'paper.pdf', 1, 0, 0);",
+            )
+            .unwrap();
+
+        let bundle = export_bundle(&source).unwrap();
+        assert_eq!(bundle.drive_folders.len(), 1);
+        assert_eq!(bundle.drive_folders[0].account_label, "Default");
+        assert!(!bundle.settings.contains_key("db_size_after_compaction"));
+        assert_eq!(bundle.tags.len(), 2);
+        assert_eq!(bundle.favorites.len(), 1);
+
+        let json = serde_json::to_string(&bundle).unwrap();
+        let mut target = migrated_db();
+        target
+            .execute(
+                "INSERT INTO settings (key, value, updated_at) VALUES ('gemini_model', 'mine', 0)",
+                [],
+            )
+            .unwrap();
+
+        let summary =
+            import_bundle(&mut target, &parse_bundle(&json).unwrap(), ImportMode::Skip).unwrap();
+        assert_eq!(summary.folders, 1);
+        assert_eq!(summary.settings, 0);
+        assert_eq!(summary.tags, 1);
+        assert_eq!(summary.favorites, 1);
+        assert_eq!(summary.skipped, 1);
+        let model: String = target
+            .query_row(
+                "SELECT value FROM settings WHERE key = 'gemini_model'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(model, "mine");
+
+        import_bundle(&mut target, &bundle, ImportMode::Overwrite).unwrap();
+        let model: String = target
+            .query_row(
+                "SELECT value FROM settings WHERE key = 'gemini_model'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(model, "gemini-pro");
+    }
+}
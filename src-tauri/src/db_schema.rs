@@ -1,11 +0,0 @@
-use tauri_plugin_sql::{Migration, MigrationKind};
-
-/// Returns the database migrations for the application
-pub fn get_migrations() -> Vec<Migration> {
-    vec![Migration {
-        version: 1,
-        description: "initial_schema",
-        sql: include_str!("migrations/001_initial_schema.sql"),
-        kind: MigrationKind::Up,
-    }]
-}
@@ -0,0 +1,160 @@
+//! Health checks for support requests
+//!
+//! `run_diagnostics` checks each subsystem independently and reports every
+//! result, so one broken subsystem never hides the state of the others.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tauri::AppHandle;
+
+use crate::bookshelf;
+use crate::db::{get_db_path, now_timestamp, open_db};
+use crate::error::{IoError, PedaruError};
+use crate::gemini;
+use crate::migrations;
+use crate::oauth;
+use crate::secrets;
+use crate::settings;
+
+/// File written and removed to check the downloads directory is writable
+const WRITE_PROBE_FILE_NAME: &str = ".pedaru-write-test";
+
+/// Outcome of one check
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum Check<T> {
+    Ok { value: T },
+    Err { code: String, message: String },
+}
+
+impl<T> From<Result<T, PedaruError>> for Check<T> {
+    fn from(result: Result<T, PedaruError>) -> Self {
+        match result {
+            Ok(value) => Check::Ok { value },
+            Err(e) => Check::Err {
+                code: e.code().to_string(),
+                message: e.to_string(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseStatus {
+    pub path: String,
+    pub schema_version: i64,
+    /// Newest migration this build knows about
+    pub latest_version: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeminiStatus {
+    pub key_present: bool,
+    pub model: String,
+    /// Result of a live request (None when not requested or no key is set)
+    pub live_check: Option<Check<()>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadsStatus {
+    pub path: String,
+    pub free_space_bytes: u64,
+}
+
+/// Structured health report
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsReport {
+    pub generated_at: i64,
+    pub database: Check<DatabaseStatus>,
+    /// Name of the secrets backend in use
+    pub secrets_backend: Check<&'static str>,
+    pub google_auth: Check<oauth::AuthStatus>,
+    pub gemini: Check<GeminiStatus>,
+    pub downloads: Check<DownloadsStatus>,
+    /// Cloud items per download status
+    pub download_status_counts: Check<BTreeMap<String, i64>>,
+}
+
+fn check_database(app: &AppHandle) -> Result<DatabaseStatus, PedaruError> {
+    let conn = open_db(app)?;
+    Ok(DatabaseStatus {
+        path: get_db_path(app)?.display().to_string(),
+        schema_version: migrations::current_version(&conn)?,
+        latest_version: migrations::MIGRATIONS.last().map_or(0, |m| m.version),
+    })
+}
+
+async fn check_gemini(app: &AppHandle, live: bool) -> Result<GeminiStatus, PedaruError> {
+    let gemini_settings = settings::get_gemini_settings(app)?;
+    let key_present = !gemini_settings.api_key.trim().is_empty();
+    let live_check = if live && key_present {
+        Some(
+            gemini::test_gemini_key(&gemini_settings.api_key, &gemini_settings.model)
+                .await
+                .into(),
+        )
+    } else {
+        None
+    };
+
+    Ok(GeminiStatus {
+        key_present,
+        model: gemini_settings.model,
+        live_check,
+    })
+}
+
+/// Check the downloads directory can be written to and report its free space
+fn check_downloads(app: &AppHandle) -> Result<DownloadsStatus, PedaruError> {
+    let downloads_dir = bookshelf::get_downloads_dir(app)?;
+    let free_space_bytes = bookshelf::free_space_bytes(app)?;
+
+    let probe_path = downloads_dir.join(WRITE_PROBE_FILE_NAME);
+    std::fs::write(&probe_path, b"").map_err(|source| IoError::WriteFailed {
+        path: probe_path.display().to_string(),
+        source,
+    })?;
+    let _ = std::fs::remove_file(&probe_path);
+
+    Ok(DownloadsStatus {
+        path: downloads_dir.display().to_string(),
+        free_space_bytes,
+    })
+}
+
+/// Check every subsystem (`live` also sends a test request to Gemini)
+pub async fn run_diagnostics(app: &AppHandle, live: bool) -> DiagnosticsReport {
+    DiagnosticsReport {
+        generated_at: now_timestamp(),
+        database: check_database(app).into(),
+        secrets_backend: secrets::check_backend(app).into(),
+        google_auth: oauth::get_auth_status(app).into(),
+        gemini: check_gemini(app, live).await.into(),
+        downloads: check_downloads(app).into(),
+        download_status_counts: bookshelf::count_items_by_status(app).into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::DatabaseError;
+
+    #[test]
+    fn test_check_serializes_status() {
+        let ok: Check<i64> = Ok(3).into();
+        assert_eq!(
+            serde_json::to_value(ok).unwrap(),
+            serde_json::json!({ "status": "ok", "value": 3 })
+        );
+
+        let err: Check<i64> = Err(DatabaseError::NotFound.into()).into();
+        let value = serde_json::to_value(err).unwrap();
+        assert_eq!(value["status"], "err");
+        assert_eq!(value["code"], "database.not_found");
+    }
+}
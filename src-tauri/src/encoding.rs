@@ -104,8 +104,8 @@ fn try_japanese_encodings_with_scoring(bytes: &[u8], debug: bool) -> Option<Stri
             valid_japanese * 10 - (replacement_count as i32 * 100) - (control_count as i32 * 50);
 
         if debug {
-            eprintln!(
-                "[Pedaru] Trying {}: had_errors={}, replacement={}, control={}, japanese={}, score={}, result={:?}",
+            log::debug!(
+                "Trying {}: had_errors={}, replacement={}, control={}, japanese={}, score={}, result={:?}",
                 name,
                 had_errors,
                 replacement_count,
@@ -167,35 +167,35 @@ fn decode_latin1(bytes: &[u8]) -> String {
 pub fn decode_pdf_string(obj: &lopdf::Object) -> Option<String> {
     match obj {
         lopdf::Object::String(bytes, _) => {
-            eprintln!(
-                "[Pedaru] decode_pdf_string: bytes len={}, first bytes={:?}",
+            log::debug!(
+                "decode_pdf_string: bytes len={}, first bytes={:?}",
                 bytes.len(),
                 &bytes[..std::cmp::min(20, bytes.len())]
             );
 
             // Try UTF-16BE first (starts with BOM 0xFE 0xFF)
             if is_utf16be(bytes) {
-                eprintln!("[Pedaru] Detected UTF-16BE");
+                log::debug!("Detected UTF-16BE");
                 let result = decode_utf16be(bytes);
-                eprintln!("[Pedaru] UTF-16BE result: {:?}", result);
+                log::debug!("UTF-16BE result: {:?}", result);
                 return result;
             }
 
             // Try UTF-8
             if let Ok(s) = String::from_utf8(bytes.clone()) {
-                eprintln!("[Pedaru] Detected UTF-8: {:?}", s);
+                log::debug!("Detected UTF-8: {:?}", s);
                 return Some(s);
             }
 
             // Try Japanese encodings with scoring
             if let Some(result) = try_japanese_encodings_with_scoring(bytes, true) {
-                eprintln!("[Pedaru] Best encoding result: {:?}", result);
+                log::debug!("Best encoding result: {:?}", result);
                 return Some(result);
             }
 
             // Fall back to Latin-1/PDFDocEncoding
             let result = decode_latin1(bytes);
-            eprintln!("[Pedaru] Fallback to Latin-1: {:?}", result);
+            log::debug!("Fallback to Latin-1: {:?}", result);
             Some(result)
         }
         _ => None,
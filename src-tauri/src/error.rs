@@ -3,6 +3,7 @@
 //! This module defines a structured error hierarchy using thiserror,
 //! organized by error category for better error handling and reporting.
 
+use serde::Serialize;
 use thiserror::Error;
 
 /// Top-level application error type
@@ -32,8 +33,14 @@ pub enum PedaruError {
     #[error("Gemini API error: {0}")]
     Gemini(#[from] GeminiError),
 
+    #[error("Translation error: {0}")]
+    Translation(#[from] TranslationError),
+
     #[error("Secrets storage error: {0}")]
-    Secrets(String),
+    Secrets(#[from] SecretsError),
+
+    #[error("Network error: {0}")]
+    Network(#[from] NetworkError),
 }
 
 /// PDF-specific errors (loading, parsing, metadata extraction)
@@ -45,6 +52,22 @@ pub enum PdfError {
         #[source]
         source: lopdf::Error,
     },
+
+    #[error("Failed to render PDF file '{path}': {message}")]
+    RenderFailed { path: String, message: String },
+
+    #[error("Failed to save PDF file '{path}': {source}")]
+    SaveFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Invalid thumbnail image: {0}")]
+    InvalidThumbnail(String),
+
+    #[error("Invalid annotation: {0}")]
+    InvalidAnnotation(String),
 }
 
 /// File I/O errors
@@ -63,6 +86,13 @@ pub enum IoError {
         #[source]
         source: std::io::Error,
     },
+
+    #[error("Failed to write file '{path}': {source}")]
+    WriteFailed {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
 }
 
 /// Database errors (SQLite operations)
@@ -82,6 +112,23 @@ pub enum DatabaseError {
 
     #[error("Database not found at expected location")]
     NotFound,
+
+    #[error("Database is busy: {0}")]
+    Busy(String),
+
+    #[error("Database could not be decrypted: the encryption key is missing or wrong")]
+    WrongKey,
+
+    /// The key store was wiped or switched, so the key can't be recreated
+    #[error("Database is encrypted, but its key is missing from the key store")]
+    KeyMissing,
+
+    #[error("Migration {version} ({description}) failed: {message}")]
+    MigrationFailed {
+        version: i64,
+        description: &'static str,
+        message: String,
+    },
 }
 
 /// Menu construction errors
@@ -99,6 +146,24 @@ pub enum MenuError {
 pub enum ConfigError {
     #[error("Failed to resolve app config directory: {0}")]
     ConfigDirResolutionFailed(String),
+
+    #[error("Invalid configuration bundle: {0}")]
+    InvalidBundle(String),
+
+    #[error("Configuration bundle version {found} is not supported (expected 1 to {supported})")]
+    IncompatibleBundle { found: u64, supported: u32 },
+
+    #[error("Invalid log level '{0}' (expected off, error, warn, info, debug or trace)")]
+    InvalidLogLevel(String),
+
+    #[error(
+        "Invalid file type '{0}' (expected a MIME type such as application/pdf; Google Docs files can't be synced)"
+    )]
+    InvalidMimeType(String),
+    #[error(
+        "Invalid file name template '{0}' (use {{pdf_title}}, {{file_name}} and {{drive_file_id}}, without folders)"
+    )]
+    InvalidFileNameTemplate(String),
 }
 
 /// OAuth authentication errors
@@ -107,9 +172,18 @@ pub enum OAuthError {
     #[error("OAuth not configured: client credentials not set")]
     NotConfigured,
 
+    #[error("Invalid OAuth client credentials: {0}")]
+    InvalidCredentials(String),
+
+    #[error("No Google account with id {0}")]
+    UnknownAccount(i64),
+
     #[error("OAuth callback server failed to start: {0}")]
     CallbackServerFailed(String),
 
+    #[error("OAuth callback timed out after {0} seconds")]
+    CallbackTimeout(u64),
+
     #[error("OAuth authorization failed: {0}")]
     AuthorizationFailed(String),
 
@@ -119,11 +193,27 @@ pub enum OAuthError {
     #[error("Token refresh failed: {0}")]
     TokenRefreshFailed(String),
 
-    #[error("HTTP request failed: {0}")]
-    HttpRequestFailed(String),
+    /// Google rejected the refresh token (`invalid_grant`): it was revoked or
+    /// expired, and only signing in again helps
+    #[error("Google no longer accepts the sign-in of account {0}; reconnect it to continue")]
+    ReauthRequired(i64),
+
+    /// `status` is None when no response was received (network failure)
+    #[error("HTTP request failed: {message}")]
+    HttpRequestFailed {
+        status: Option<u16>,
+        message: String,
+    },
 
     #[error("Invalid response: {0}")]
     InvalidResponse(String),
+
+    #[error("Token refresh margin must be between 0 and {max} seconds, got {secs}")]
+    InvalidRefreshMargin { secs: i64, max: i64 },
+
+    /// Every logout step ran, but some of them failed
+    #[error("Signed out, but some data could not be cleared: {0}")]
+    LogoutIncomplete(String),
 }
 
 /// Google Drive API errors
@@ -132,8 +222,15 @@ pub enum GoogleDriveError {
     #[error("Not authenticated with Google")]
     NotAuthenticated,
 
-    #[error("API request failed: {0}")]
-    ApiRequestFailed(String),
+    /// `status` is None when no response was received (network failure)
+    #[error("API request failed: {message}")]
+    ApiRequestFailed {
+        status: Option<u16>,
+        message: String,
+    },
+
+    #[error("Invalid response from Google Drive: {0}")]
+    InvalidResponse(String),
 
     #[error("Failed to list files: {0}")]
     ListFilesFailed(String),
@@ -144,11 +241,36 @@ pub enum GoogleDriveError {
     #[error("Download cancelled: {0}")]
     DownloadCancelled(String),
 
+    /// The downloaded file's size or checksum doesn't match Drive's
+    #[error("Downloaded file is corrupt: {0}")]
+    IntegrityCheckFailed(String),
+
     #[error("File not found: {0}")]
     FileNotFound(String),
 
+    /// Drive refused to export a Google Docs/Slides file because it's too big
+    #[error("File is too large for Google Drive to export as a PDF")]
+    ExportTooLarge,
+
+    #[error("Not enough disk space: {required} bytes needed, {available} bytes available")]
+    InsufficientDiskSpace { required: u64, available: u64 },
+
     #[error("Invalid folder ID: {0}")]
     InvalidFolderId(String),
+
+    /// Drive kept rate-limiting a request after every retry
+    #[error("Google Drive is limiting requests right now. Please try again later.")]
+    RateLimited { retry_after_secs: Option<u64> },
+
+    /// The account never granted a scope the operation needs
+    #[error(
+        "Pedaru isn't allowed to {action}. Please sign in to Google again and grant access to your Drive files."
+    )]
+    InsufficientScope {
+        action: &'static str,
+        /// Scope to request when signing in again
+        required_scope: &'static str,
+    },
 }
 
 /// Gemini API errors
@@ -157,29 +279,740 @@ pub enum GeminiError {
     #[error("API key not configured")]
     ApiKeyMissing,
 
-    #[error("API request failed: {0}")]
-    ApiRequestFailed(String),
+    #[error("Invalid API key. Please check your Gemini API key in Settings.")]
+    InvalidApiKey,
+
+    #[error("Rate limit or quota exceeded. Please wait a moment and try again.")]
+    RateLimited,
+
+    #[error(
+        "Too many Gemini requests (limit: {limit} per minute). Try again in {retry_after_secs} seconds."
+    )]
+    TooManyRequests { limit: u32, retry_after_secs: u64 },
+
+    #[error("Model '{0}' not found. Please check the model name in Settings.")]
+    ModelNotFound(String),
+
+    /// `status` is None when no response was received (network failure)
+    #[error("API request failed: {message}")]
+    ApiRequestFailed {
+        status: Option<u16>,
+        message: String,
+    },
 
     #[error("Invalid response: {0}")]
     InvalidResponse(String),
 }
 
+/// Translation provider errors (OpenAI, Anthropic, provider selection)
+#[derive(Error, Debug)]
+pub enum TranslationError {
+    #[error("Unsupported translation provider '{0}' (expected gemini, openai or anthropic)")]
+    UnsupportedProvider(String),
+
+    #[error("Invalid translation settings: {0}")]
+    InvalidSettings(String),
+
+    #[error("{provider} API key not configured")]
+    ApiKeyMissing { provider: &'static str },
+
+    /// `status` is None when no response was received (network failure)
+    #[error("{provider} API request failed: {message}")]
+    ApiRequestFailed {
+        provider: &'static str,
+        status: Option<u16>,
+        message: String,
+    },
+
+    #[error("Invalid response from {provider}: {message}")]
+    InvalidResponse {
+        provider: &'static str,
+        message: String,
+    },
+}
+
+/// Secure storage errors (OS keychain access)
+#[derive(Error, Debug)]
+pub enum SecretsError {
+    #[error("{backend} is not available: {message}")]
+    BackendUnavailable {
+        backend: &'static str,
+        message: String,
+    },
+
+    #[error("Failed to open {backend} entry: {message}")]
+    EntryFailed {
+        backend: &'static str,
+        message: String,
+    },
+
+    #[error("Failed to load secrets from {backend}: {message}")]
+    LoadFailed {
+        backend: &'static str,
+        message: String,
+    },
+
+    #[error("Failed to save secrets to {backend}: {message}")]
+    SaveFailed {
+        backend: &'static str,
+        message: String,
+    },
+
+    #[error("Failed to delete secrets from {backend}: {message}")]
+    DeleteFailed {
+        backend: &'static str,
+        message: String,
+    },
+
+    /// Deleting every secret failed in some stores; the others were cleared
+    #[error("Some secrets could not be deleted: {0}")]
+    DeleteIncomplete(String),
+
+    #[error("Failed to serialize secrets: {0}")]
+    SerializeFailed(String),
+}
+
+/// Connectivity errors
+#[derive(Error, Debug)]
+pub enum NetworkError {
+    #[error("You appear to be offline ({service} is unreachable): {message}")]
+    Offline {
+        service: &'static str,
+        message: String,
+    },
+
+    #[error("{service} didn't respond in time. Please try again.")]
+    TimedOut { service: &'static str },
+
+    #[error("Invalid proxy URL: {0}")]
+    InvalidProxy(String),
+
+    #[error("Failed to create HTTP client: {0}")]
+    ClientFailed(String),
+}
+
+// ============================================================================
+// Error Codes
+// ============================================================================
+
+impl PedaruError {
+    /// Stable machine-readable identifier for this error, e.g.
+    /// `drive.not_authenticated`
+    pub fn code(&self) -> &'static str {
+        match self {
+            PedaruError::Pdf(e) => e.code(),
+            PedaruError::Io(e) => e.code(),
+            PedaruError::Database(e) => e.code(),
+            PedaruError::Menu(e) => e.code(),
+            PedaruError::Config(e) => e.code(),
+            PedaruError::OAuth(e) => e.code(),
+            PedaruError::GoogleDrive(e) => e.code(),
+            PedaruError::Gemini(e) => e.code(),
+            PedaruError::Translation(e) => e.code(),
+            PedaruError::Secrets(e) => e.code(),
+            PedaruError::Network(e) => e.code(),
+        }
+    }
+}
+
+impl PdfError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            PdfError::LoadFailed { .. } => "pdf.load_failed",
+            PdfError::RenderFailed { .. } => "pdf.render_failed",
+            PdfError::SaveFailed { .. } => "pdf.save_failed",
+            PdfError::InvalidThumbnail(_) => "pdf.invalid_thumbnail",
+            PdfError::InvalidAnnotation(_) => "pdf.invalid_annotation",
+        }
+    }
+}
+
+impl IoError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            IoError::ReadFailed { .. } => "io.read_failed",
+            IoError::CreateDirFailed { .. } => "io.create_dir_failed",
+            IoError::WriteFailed { .. } => "io.write_failed",
+        }
+    }
+}
+
+impl DatabaseError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            DatabaseError::PathResolutionFailed(_) => "database.path_resolution_failed",
+            DatabaseError::OpenFailed { .. } => "database.open_failed",
+            DatabaseError::QueryFailed(_) => "database.query_failed",
+            DatabaseError::NotFound => "database.not_found",
+            DatabaseError::Busy(_) => "database.busy",
+            DatabaseError::WrongKey => "database.wrong_key",
+            DatabaseError::KeyMissing => "database.key_missing",
+            DatabaseError::MigrationFailed { .. } => "database.migration_failed",
+        }
+    }
+}
+
+impl MenuError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            MenuError::BuildFailed(_) => "menu.build_failed",
+            MenuError::SetMenuFailed(_) => "menu.set_menu_failed",
+        }
+    }
+}
+
+impl ConfigError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConfigError::ConfigDirResolutionFailed(_) => "config.dir_resolution_failed",
+            ConfigError::InvalidBundle(_) => "config.invalid_bundle",
+            ConfigError::IncompatibleBundle { .. } => "config.incompatible_bundle",
+            ConfigError::InvalidLogLevel(_) => "config.invalid_log_level",
+            ConfigError::InvalidMimeType(_) => "config.invalid_mime_type",
+            ConfigError::InvalidFileNameTemplate(_) => "config.invalid_file_name_template",
+        }
+    }
+}
+
+impl OAuthError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            OAuthError::NotConfigured => "oauth.not_configured",
+            OAuthError::InvalidCredentials(_) => "oauth.invalid_credentials",
+            OAuthError::UnknownAccount(_) => "oauth.unknown_account",
+            OAuthError::CallbackServerFailed(_) => "oauth.callback_server_failed",
+            OAuthError::CallbackTimeout(_) => "oauth.callback_timeout",
+            OAuthError::AuthorizationFailed(_) => "oauth.authorization_failed",
+            OAuthError::TokenExchangeFailed(_) => "oauth.token_exchange_failed",
+            OAuthError::TokenRefreshFailed(_) => "oauth.token_refresh_failed",
+            OAuthError::ReauthRequired(_) => "oauth.reauth_required",
+            OAuthError::HttpRequestFailed { .. } => "oauth.http_request_failed",
+            OAuthError::InvalidResponse(_) => "oauth.invalid_response",
+            OAuthError::InvalidRefreshMargin { .. } => "oauth.invalid_refresh_margin",
+            OAuthError::LogoutIncomplete(_) => "oauth.logout_incomplete",
+        }
+    }
+}
+
+impl GoogleDriveError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            GoogleDriveError::NotAuthenticated => "drive.not_authenticated",
+            GoogleDriveError::ApiRequestFailed { .. } => "drive.api_request_failed",
+            GoogleDriveError::InvalidResponse(_) => "drive.invalid_response",
+            GoogleDriveError::ListFilesFailed(_) => "drive.list_files_failed",
+            GoogleDriveError::DownloadFailed(_) => "drive.download_failed",
+            GoogleDriveError::DownloadCancelled(_) => "drive.download_cancelled",
+            GoogleDriveError::IntegrityCheckFailed(_) => "drive.integrity_check_failed",
+            GoogleDriveError::FileNotFound(_) => "drive.file_not_found",
+            GoogleDriveError::ExportTooLarge => "drive.export_too_large",
+            GoogleDriveError::InsufficientDiskSpace { .. } => "drive.insufficient_disk_space",
+            GoogleDriveError::InvalidFolderId(_) => "drive.invalid_folder_id",
+            GoogleDriveError::RateLimited { .. } => "drive.rate_limited",
+            GoogleDriveError::InsufficientScope { .. } => "drive.insufficient_scope",
+        }
+    }
+}
+
+impl GeminiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            GeminiError::ApiKeyMissing => "gemini.api_key_missing",
+            GeminiError::InvalidApiKey => "gemini.invalid_api_key",
+            GeminiError::RateLimited => "gemini.rate_limited",
+            GeminiError::TooManyRequests { .. } => "gemini.too_many_requests",
+            GeminiError::ModelNotFound(_) => "gemini.model_not_found",
+            GeminiError::ApiRequestFailed { .. } => "gemini.api_request_failed",
+            GeminiError::InvalidResponse(_) => "gemini.invalid_response",
+        }
+    }
+}
+
+impl TranslationError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            TranslationError::UnsupportedProvider(_) => "translation.unsupported_provider",
+            TranslationError::InvalidSettings(_) => "translation.invalid_settings",
+            TranslationError::ApiKeyMissing { .. } => "translation.api_key_missing",
+            TranslationError::ApiRequestFailed { .. } => "translation.api_request_failed",
+            TranslationError::InvalidResponse { .. } => "translation.invalid_response",
+        }
+    }
+}
+
+impl SecretsError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            SecretsError::BackendUnavailable { .. } => "secrets.backend_unavailable",
+            SecretsError::EntryFailed { .. } => "secrets.entry_failed",
+            SecretsError::LoadFailed { .. } => "secrets.load_failed",
+            SecretsError::SaveFailed { .. } => "secrets.save_failed",
+            SecretsError::DeleteFailed { .. } => "secrets.delete_failed",
+            SecretsError::DeleteIncomplete(_) => "secrets.delete_incomplete",
+            SecretsError::SerializeFailed(_) => "secrets.serialize_failed",
+        }
+    }
+}
+
+impl NetworkError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            NetworkError::Offline { .. } => "network.offline",
+            NetworkError::TimedOut { .. } => "network.timed_out",
+            NetworkError::InvalidProxy(_) => "network.invalid_proxy",
+            NetworkError::ClientFailed(_) => "network.client_failed",
+        }
+    }
+}
+
+// ============================================================================
+// Retry Classification
+// ============================================================================
+
+/// Whether an HTTP status is worth retrying (server errors and rate limits)
+pub fn is_retryable_status(status: u16) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
+/// A request with no response (network failure) or a retryable status
+fn is_retryable_request(status: Option<u16>) -> bool {
+    status.is_none_or(is_retryable_status)
+}
+
+impl PedaruError {
+    /// Whether the failed operation may succeed if tried again
+    ///
+    /// True for transient network failures, HTTP 5xx and rate limits; false
+    /// for auth, configuration and parse errors.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            PedaruError::Pdf(_)
+            | PedaruError::Io(_)
+            | PedaruError::Database(_)
+            | PedaruError::Menu(_)
+            | PedaruError::Config(_)
+            | PedaruError::Secrets(_) => false,
+            PedaruError::Network(e) => e.is_retryable(),
+            PedaruError::Translation(e) => e.is_retryable(),
+            PedaruError::OAuth(e) => e.is_retryable(),
+            PedaruError::GoogleDrive(e) => e.is_retryable(),
+            PedaruError::Gemini(e) => e.is_retryable(),
+        }
+    }
+}
+
+impl OAuthError {
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            OAuthError::HttpRequestFailed { status, .. } => is_retryable_request(*status),
+            _ => false,
+        }
+    }
+}
+
+impl GoogleDriveError {
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            GoogleDriveError::ApiRequestFailed { status, .. } => is_retryable_request(*status),
+            // Interrupted transfers and bad resumes start over on the next attempt
+            GoogleDriveError::DownloadFailed(_) | GoogleDriveError::IntegrityCheckFailed(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl NetworkError {
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            NetworkError::TimedOut { .. } => true,
+            // Retrying right away won't help; callers wait for connectivity
+            // instead (see `network::wait_until_online`)
+            NetworkError::Offline { .. } => false,
+            NetworkError::InvalidProxy(_) | NetworkError::ClientFailed(_) => false,
+        }
+    }
+}
+
+impl GeminiError {
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            GeminiError::RateLimited | GeminiError::TooManyRequests { .. } => true,
+            GeminiError::ApiRequestFailed { status, .. } => is_retryable_request(*status),
+            _ => false,
+        }
+    }
+}
+
+impl TranslationError {
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            TranslationError::ApiRequestFailed { status, .. } => is_retryable_request(*status),
+            _ => false,
+        }
+    }
+}
+
 /// Convenience type alias for internal use
 pub type Result<T> = std::result::Result<T, PedaruError>;
 
-/// Extension trait for converting errors to Tauri-compatible String format
+// ============================================================================
+// Tauri Command Errors
+// ============================================================================
+
+/// Error returned by Tauri commands, so the frontend can branch on `code`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandError {
+    /// Stable machine-readable identifier (see `PedaruError::code`)
+    pub code: String,
+    /// Human-readable message, including the error chain
+    pub message: String,
+    /// OAuth scope to request when reconnecting (`drive.insufficient_scope`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub required_scope: Option<String>,
+}
+
+impl CommandError {
+    /// Code for failures that don't come from a `PedaruError`
+    pub const INTERNAL: &'static str = "internal";
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self {
+            code: Self::INTERNAL.to_string(),
+            message: message.into(),
+            required_scope: None,
+        }
+    }
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        Self::internal(message)
+    }
+}
+
+/// Extension trait for converting errors to the structured form returned by
+/// Tauri commands
 pub trait IntoTauriError {
-    fn into_tauri_error(self) -> String;
+    fn into_tauri_error(self) -> CommandError;
 }
 
 impl IntoTauriError for PedaruError {
-    fn into_tauri_error(self) -> String {
-        format!("{:#}", anyhow::Error::from(self))
+    fn into_tauri_error(self) -> CommandError {
+        let required_scope = match &self {
+            PedaruError::GoogleDrive(GoogleDriveError::InsufficientScope {
+                required_scope,
+                ..
+            }) => Some(required_scope.to_string()),
+            _ => None,
+        };
+        CommandError {
+            code: self.code().to_string(),
+            message: format!("{:#}", anyhow::Error::from(self)),
+            required_scope,
+        }
     }
 }
 
 impl IntoTauriError for anyhow::Error {
-    fn into_tauri_error(self) -> String {
-        format!("{:#}", self)
+    fn into_tauri_error(self) -> CommandError {
+        CommandError::internal(format!("{:#}", self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// One value of every variant; `code()` matches exhaustively, so adding a
+    /// variant without a code fails to compile
+    fn all_errors() -> Vec<PedaruError> {
+        let io = || std::io::Error::other("io");
+        let s = || "x".to_string();
+        vec![
+            PdfError::LoadFailed {
+                path: s(),
+                source: lopdf::Error::CharacterEncoding,
+            }
+            .into(),
+            PdfError::RenderFailed {
+                path: s(),
+                message: s(),
+            }
+            .into(),
+            PdfError::SaveFailed {
+                path: s(),
+                source: io(),
+            }
+            .into(),
+            PdfError::InvalidThumbnail(s()).into(),
+            PdfError::InvalidAnnotation(s()).into(),
+            IoError::ReadFailed {
+                path: s(),
+                source: io(),
+            }
+            .into(),
+            IoError::CreateDirFailed {
+                path: s(),
+                source: io(),
+            }
+            .into(),
+            IoError::WriteFailed {
+                path: s(),
+                source: io(),
+            }
+            .into(),
+            DatabaseError::PathResolutionFailed(s()).into(),
+            DatabaseError::OpenFailed {
+                source: rusqlite::Error::InvalidQuery,
+            }
+            .into(),
+            DatabaseError::QueryFailed(s()).into(),
+            DatabaseError::NotFound.into(),
+            DatabaseError::Busy(s()).into(),
+            DatabaseError::WrongKey.into(),
+            DatabaseError::KeyMissing.into(),
+            DatabaseError::MigrationFailed {
+                version: 1,
+                description: "x",
+                message: s(),
+            }
+            .into(),
+            MenuError::BuildFailed(s()).into(),
+            MenuError::SetMenuFailed(s()).into(),
+            ConfigError::ConfigDirResolutionFailed(s()).into(),
+            ConfigError::InvalidBundle(s()).into(),
+            ConfigError::IncompatibleBundle {
+                found: 2,
+                supported: 1,
+            }
+            .into(),
+            ConfigError::InvalidLogLevel(s()).into(),
+            ConfigError::InvalidMimeType(s()).into(),
+            ConfigError::InvalidFileNameTemplate(s()).into(),
+            OAuthError::NotConfigured.into(),
+            OAuthError::InvalidCredentials(s()).into(),
+            OAuthError::UnknownAccount(2).into(),
+            OAuthError::CallbackServerFailed(s()).into(),
+            OAuthError::CallbackTimeout(1).into(),
+            OAuthError::AuthorizationFailed(s()).into(),
+            OAuthError::TokenExchangeFailed(s()).into(),
+            OAuthError::TokenRefreshFailed(s()).into(),
+            OAuthError::ReauthRequired(2).into(),
+            OAuthError::HttpRequestFailed {
+                status: None,
+                message: s(),
+            }
+            .into(),
+            OAuthError::InvalidResponse(s()).into(),
+            OAuthError::InvalidRefreshMargin { secs: -1, max: 1 }.into(),
+            OAuthError::LogoutIncomplete(s()).into(),
+            GoogleDriveError::NotAuthenticated.into(),
+            GoogleDriveError::ApiRequestFailed {
+                status: Some(500),
+                message: s(),
+            }
+            .into(),
+            GoogleDriveError::InvalidResponse(s()).into(),
+            GoogleDriveError::ListFilesFailed(s()).into(),
+            GoogleDriveError::DownloadFailed(s()).into(),
+            GoogleDriveError::DownloadCancelled(s()).into(),
+            GoogleDriveError::IntegrityCheckFailed(s()).into(),
+            GoogleDriveError::FileNotFound(s()).into(),
+            GoogleDriveError::ExportTooLarge.into(),
+            GoogleDriveError::InsufficientDiskSpace {
+                required: 2,
+                available: 1,
+            }
+            .into(),
+            GoogleDriveError::InvalidFolderId(s()).into(),
+            GoogleDriveError::RateLimited {
+                retry_after_secs: None,
+            }
+            .into(),
+            GoogleDriveError::InsufficientScope {
+                action: "x",
+                required_scope: "x",
+            }
+            .into(),
+            GeminiError::ApiKeyMissing.into(),
+            GeminiError::InvalidApiKey.into(),
+            GeminiError::RateLimited.into(),
+            GeminiError::TooManyRequests {
+                limit: 1,
+                retry_after_secs: 1,
+            }
+            .into(),
+            GeminiError::ModelNotFound(s()).into(),
+            GeminiError::ApiRequestFailed {
+                status: None,
+                message: s(),
+            }
+            .into(),
+            GeminiError::InvalidResponse(s()).into(),
+            TranslationError::UnsupportedProvider(s()).into(),
+            TranslationError::InvalidSettings(s()).into(),
+            TranslationError::ApiKeyMissing { provider: "p" }.into(),
+            TranslationError::ApiRequestFailed {
+                provider: "p",
+                status: None,
+                message: s(),
+            }
+            .into(),
+            TranslationError::InvalidResponse {
+                provider: "p",
+                message: s(),
+            }
+            .into(),
+            SecretsError::BackendUnavailable {
+                backend: "b",
+                message: s(),
+            }
+            .into(),
+            SecretsError::EntryFailed {
+                backend: "b",
+                message: s(),
+            }
+            .into(),
+            SecretsError::LoadFailed {
+                backend: "b",
+                message: s(),
+            }
+            .into(),
+            SecretsError::SaveFailed {
+                backend: "b",
+                message: s(),
+            }
+            .into(),
+            SecretsError::DeleteFailed {
+                backend: "b",
+                message: s(),
+            }
+            .into(),
+            SecretsError::DeleteIncomplete(s()).into(),
+            SecretsError::SerializeFailed(s()).into(),
+            NetworkError::Offline {
+                service: "x",
+                message: s(),
+            }
+            .into(),
+            NetworkError::TimedOut { service: "x" }.into(),
+            NetworkError::InvalidProxy(s()).into(),
+            NetworkError::ClientFailed(s()).into(),
+        ]
+    }
+
+    #[test]
+    fn test_error_codes_are_unique() {
+        let errors = all_errors();
+        let codes: HashSet<&str> = errors.iter().map(|e| e.code()).collect();
+        assert_eq!(codes.len(), errors.len());
+        assert!(!codes.contains(CommandError::INTERNAL));
+    }
+
+    #[test]
+    fn test_error_codes_are_namespaced() {
+        for error in all_errors() {
+            let code = error.code();
+            let (category, name) = code.split_once('.').unwrap();
+            assert!(!category.is_empty() && !name.is_empty(), "{}", code);
+            assert!(
+                code.chars()
+                    .all(|c| c.is_ascii_lowercase() || c == '_' || c == '.'),
+                "{}",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_retryable_http_status() {
+        let api = |status| -> PedaruError {
+            GoogleDriveError::ApiRequestFailed {
+                status,
+                message: String::new(),
+            }
+            .into()
+        };
+        let gemini = |status| -> PedaruError {
+            GeminiError::ApiRequestFailed {
+                status,
+                message: String::new(),
+            }
+            .into()
+        };
+        let translation = |status| -> PedaruError {
+            TranslationError::ApiRequestFailed {
+                provider: "OpenAI",
+                status,
+                message: String::new(),
+            }
+            .into()
+        };
+        for request in [api, gemini, translation] {
+            assert!(request(None).is_retryable());
+            assert!(request(Some(503)).is_retryable());
+            assert!(request(Some(500)).is_retryable());
+            assert!(request(Some(429)).is_retryable());
+            assert!(!request(Some(400)).is_retryable());
+            assert!(!request(Some(403)).is_retryable());
+            assert!(!request(Some(404)).is_retryable());
+        }
+    }
+
+    #[test]
+    fn test_is_retryable_fatal_errors() {
+        let fatal: Vec<PedaruError> = vec![
+            GoogleDriveError::NotAuthenticated.into(),
+            GoogleDriveError::InvalidResponse(String::new()).into(),
+            OAuthError::NotConfigured.into(),
+            OAuthError::TokenRefreshFailed("invalid_client".to_string()).into(),
+            OAuthError::ReauthRequired(1).into(),
+            GeminiError::InvalidApiKey.into(),
+            DatabaseError::NotFound.into(),
+        ];
+        for error in fatal {
+            assert!(!error.is_retryable(), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_into_tauri_error_keeps_message() {
+        let error = PedaruError::from(GoogleDriveError::NotAuthenticated).into_tauri_error();
+        assert_eq!(error.code, "drive.not_authenticated");
+        assert!(
+            error
+                .message
+                .starts_with("Google Drive error: Not authenticated with Google")
+        );
+        assert_eq!(error.required_scope, None);
+    }
+
+    #[test]
+    fn test_into_tauri_error_exposes_required_scope() {
+        let error = PedaruError::from(GoogleDriveError::InsufficientScope {
+            action: "upload files",
+            required_scope: "https://www.googleapis.com/auth/drive.file",
+        })
+        .into_tauri_error();
+        assert_eq!(error.code, "drive.insufficient_scope");
+        assert_eq!(
+            error.required_scope.as_deref(),
+            Some("https://www.googleapis.com/auth/drive.file")
+        );
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(
+            json["requiredScope"],
+            "https://www.googleapis.com/auth/drive.file"
+        );
     }
 }
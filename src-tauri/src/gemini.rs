@@ -1,130 +1,48 @@
 //! Gemini API client for translation
 //!
-//! This module provides functionality to translate text using Google's Gemini API.
+//! This module provides the Google Gemini backend for `translation`.
 
-use reqwest::Client;
+use reqwest::Response;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use crate::error::{GeminiError, PedaruError};
+use crate::network;
+use crate::secrets;
+use crate::settings;
+use crate::translation::{self, GenerationParams, TranslationProvider};
 
 /// Gemini API base URL
 const GEMINI_API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta";
 
-// ============================================================================
-// Default Prompts (hardcoded in backend)
-// ============================================================================
-
-// System instruction for translation (behavioral guidelines)
-const TRANSLATION_SYSTEM_INSTRUCTION: &str = r#"You are a professional English-to-Japanese translator and language teacher.
-
-## Your Task
-Translate ONLY the "SELECTED TEXT" provided by the user. The context is for understanding only.
-
-## Output Format (STRICT - follow exactly):
-- Output MUST be valid JSON only. No markdown code blocks, no extra text.
-- The JSON structure MUST be:
-{
-  "translation": "Translation result in Japanese (string)",
-  "points": ["Point 1 (string)", "Point 2 (string)", "Point 3 (string)"]
-}
-
-## Critical Rules:
-- The "points" field MUST be a flat array of strings. DO NOT use nested objects.
-- Each element in points must be a simple string, not an object.
-- All output text MUST be in Japanese.
-- IMPORTANT: Translate ONLY the SELECTED TEXT, not the context.
-
-## Translation Rules:
-- For single words, idioms, or short phrases (no spaces, or 2-3 words):
-  - translation: Only the meaning of the word/idiom. NOT a translation of the entire sentence.
-  - points: A flat array of strings containing:
-    1. "単語の意味: [explanation of the word in Japanese]"
-    2. "原文: [Extract the COMPLETE English sentence containing the word from the context, with ***highlighted*** word]"
-    3. "訳: [Japanese translation of that complete sentence, with ***highlighted*** translation of the word]"
-    4. "類語・言い換え: [synonyms in English with Japanese meanings]"
-  - Example output:
-    {
-      "translation": "活用する、利用する",
-      "points": [
-        "単語の意味: 何かの力や資源を有効に使うこと",
-        "原文: The goal is to ***harness*** the power of AI.",
-        "訳: 目標はAIの力を***活用する***ことです。",
-        "類語・言い換え: utilize（活用する）, leverage（活かす）, exploit（利用する）"
-      ]
-    }
-  - CRITICAL: How to find the 原文 (original sentence):
-    - The selected word appears at the EXACT BOUNDARY between "Context before" and "Context after".
-    - The 原文 containing the selected word is: (end of "Context before") + (selected word) + (beginning of "Context after")
-    - If the same word appears multiple times in the context, you MUST use ONLY the occurrence at the boundary position.
-    - DO NOT pick a sentence from earlier in Context before that happens to contain the same word.
-
-- For sentences or longer text:
-  - translation: Full Japanese translation of the text
-  - points: A flat array of strings with grammatical explanations:
-    1. Each point is a single string explaining one grammar structure
-    2. Focus on challenging structures: relative clauses, participle constructions, etc.
-    3. Include synonyms or alternative expressions where helpful"#;
-
-// User prompt for translation (actual content - data only)
-const TRANSLATION_PROMPT: &str = r#"SELECTED TEXT (translate this):
-{text}
-
-Context before:
-{context_before}
-
-Context after:
-{context_after}"#;
-
-// System instruction for explanation (behavioral guidelines)
-const EXPLANATION_SYSTEM_INSTRUCTION: &str = r#"You are an expert at explaining complex concepts in simple, easy-to-understand terms.
-
-## Output Format (STRICT - follow exactly):
-- Output MUST be valid JSON only. No markdown code blocks, no extra text.
-- The JSON structure MUST be:
-{
-  "summary": "One-sentence summary (string)",
-  "points": ["Point 1 (string)", "Point 2 (string)", "Point 3 (string)"]
-}
-
-## Critical Rules:
-- The "points" field MUST be a flat array of strings. DO NOT use nested objects.
-- All output text MUST be in Japanese.
-
-## Explanation Guidelines:
+/// Service name used in offline errors
+const GEMINI_SERVICE: &str = "Gemini";
 
-### Summary (summary field):
-- Summarize the essence in ONE sentence
-- Use phrases like "要するに〜ということ" or "つまり〜"
-- Make it understandable even for someone unfamiliar with the topic
+/// Header carrying the API key
+const API_KEY_HEADER: &str = "x-goog-api-key";
 
-### Explanation points (points field):
-- Rephrase technical terms in plain language: "〇〇（つまり△△のこと）"
-- Use familiar analogies or metaphors to explain abstract concepts
-- Add context about "why this matters" or "what benefit does this provide"
-- For technical content, explain practical use cases and benefits concretely
-- For academic content, explain the importance in the field and application examples
-- Each point should be independently understandable
-- Keep each point to 2-3 sentences"#;
+/// How long a fetched model list is reused
+const MODEL_LIST_TTL: Duration = Duration::from_secs(10 * 60);
 
-// User prompt for explanation (actual content)
-const EXPLANATION_PROMPT: &str = r#"Explain the following text.
+/// Models offered when the model list cannot be fetched
+const FALLBACK_MODELS: &[(&str, &str)] = &[
+    ("gemini-2.0-flash", "Gemini 2.0 Flash"),
+    ("gemini-2.0-flash-lite", "Gemini 2.0 Flash-Lite"),
+    ("gemini-2.5-flash", "Gemini 2.5 Flash"),
+    ("gemini-2.5-flash-lite", "Gemini 2.5 Flash-Lite"),
+    ("gemini-2.5-pro", "Gemini 2.5 Pro"),
+];
 
-The user has selected text from a PDF document. The context shows the surrounding text:
-- "Context before" = text that appears BEFORE the selected text in the document
-- "Text to explain" = the actual text the user selected
-- "Context after" = text that appears AFTER the selected text in the document
+/// Last model list fetched from the API, with the key it was fetched for
+static MODEL_LIST_CACHE: Mutex<Option<CachedModelList>> = Mutex::new(None);
 
-## Context before (for understanding only):
-{context_before}
-
-## Text to explain:
-{text}
-
-## Context after (for understanding only):
-{context_after}
-
-Use the context to understand the meaning, but explain only the selected text."#;
+struct CachedModelList {
+    api_key: String,
+    fetched_at: Instant,
+    models: Vec<GeminiModelInfo>,
+}
 
 // ============================================================================
 // Request/Response Types
@@ -136,6 +54,7 @@ struct GeminiRequest {
     contents: Vec<GeminiContent>,
     #[serde(skip_serializing_if = "Option::is_none")]
     system_instruction: Option<GeminiContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     generation_config: Option<GenerationConfig>,
 }
 
@@ -143,6 +62,10 @@ struct GeminiRequest {
 #[serde(rename_all = "camelCase")]
 struct GenerationConfig {
     response_mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
 }
 
 #[derive(Debug, Serialize)]
@@ -181,50 +104,120 @@ struct GeminiApiError {
     message: String,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListModelsResponse {
+    #[serde(default)]
+    models: Vec<ApiModel>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiModel {
+    /// Resource name, e.g. "models/gemini-2.0-flash"
+    name: String,
+    display_name: Option<String>,
+    #[serde(default)]
+    supported_generation_methods: Vec<String>,
+}
+
 // ============================================================================
 // Public Types
 // ============================================================================
 
-/// Structured translation result
+/// A Gemini model usable for translation
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TranslationResponse {
-    pub translation: String,
-    pub points: Vec<String>,
+#[serde(rename_all = "camelCase")]
+pub struct GeminiModelInfo {
+    /// Model id as used in settings, e.g. "gemini-2.0-flash"
+    pub id: String,
+    pub display_name: String,
 }
 
-/// Structured explanation result
+/// Outcome of replacing the Gemini API key
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ExplanationResponse {
-    pub summary: String,
-    pub points: Vec<String>,
+#[serde(rename_all = "camelCase")]
+pub struct KeyRotation {
+    /// Whether the replaced key still worked (None when there was no key or
+    /// it couldn't be checked, e.g. because of a rate limit)
+    pub previous_key_valid: Option<bool>,
 }
 
 // ============================================================================
 // API Functions
 // ============================================================================
 
-/// Call Gemini API with the given prompt and optional system instruction
-async fn call_gemini_api(
-    api_key: &str,
-    model: &str,
-    prompt: &str,
-    system_instruction: Option<&str>,
-) -> Result<String, PedaruError> {
-    if api_key.is_empty() {
-        return Err(PedaruError::Gemini(GeminiError::ApiKeyMissing));
+/// Gemini backend for translation and explanation
+pub struct GeminiProvider {
+    api_key: String,
+    model: String,
+    params: GenerationParams,
+}
+
+impl GeminiProvider {
+    pub fn new(api_key: &str, model: &str, params: GenerationParams) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            params,
+        }
     }
+}
 
-    let client = Client::builder()
-        .timeout(std::time::Duration::from_secs(60))
-        .build()
-        .map_err(|e| {
-            PedaruError::Gemini(GeminiError::ApiRequestFailed(format!(
-                "Failed to create HTTP client: {}",
-                e
-            )))
-        })?;
+impl TranslationProvider for GeminiProvider {
+    fn name(&self) -> &'static str {
+        "Gemini"
+    }
 
-    let request = GeminiRequest {
+    async fn generate(
+        &self,
+        prompt: &str,
+        system_instruction: &str,
+    ) -> Result<String, PedaruError> {
+        call_gemini_api(
+            &self.api_key,
+            &self.model,
+            prompt,
+            Some(system_instruction),
+            self.params,
+        )
+        .await
+    }
+
+    async fn start_stream(
+        &self,
+        prompt: &str,
+        system_instruction: &str,
+    ) -> Result<Response, PedaruError> {
+        let request = build_request(prompt, Some(system_instruction), self.params);
+        send_gemini_request(&self.api_key, &self.model, &request, true).await
+    }
+
+    /// Each streamed event is a partial GenerateContentResponse
+    fn stream_delta(&self, event: &Value) -> Option<String> {
+        let parts = event
+            .get("candidates")?
+            .get(0)?
+            .get("content")?
+            .get("parts")?
+            .as_array()?;
+        Some(
+            parts
+                .iter()
+                .filter_map(|part| part.get("text").and_then(Value::as_str))
+                .collect(),
+        )
+    }
+}
+
+/// Build a generateContent request body
+fn build_request(
+    prompt: &str,
+    system_instruction: Option<&str>,
+    params: GenerationParams,
+) -> GeminiRequest {
+    GeminiRequest {
         contents: vec![GeminiContent {
             parts: vec![GeminiPart {
                 text: prompt.to_string(),
@@ -237,58 +230,22 @@ async fn call_gemini_api(
         }),
         generation_config: Some(GenerationConfig {
             response_mime_type: "application/json".to_string(),
+            temperature: params.temperature,
+            max_output_tokens: params.max_output_tokens,
         }),
-    };
-
-    let url = format!(
-        "{}/models/{}:generateContent?key={}",
-        GEMINI_API_BASE, model, api_key
-    );
-
-    let response = client
-        .post(&url)
-        .header("Content-Type", "application/json")
-        .json(&request)
-        .send()
-        .await
-        .map_err(|e| {
-            let err_msg = if e.is_timeout() {
-                "Request timed out. Please try again.".to_string()
-            } else if e.is_connect() {
-                "Failed to connect to Gemini API. Check your internet connection.".to_string()
-            } else {
-                format!("Network error: {}", e.without_url())
-            };
-            PedaruError::Gemini(GeminiError::ApiRequestFailed(err_msg))
-        })?;
-
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_default();
-
-        let error_message = if status.as_u16() == 429 {
-            "Rate limit exceeded. Please wait a moment and try again.".to_string()
-        } else if status.as_u16() == 401 || status.as_u16() == 403 {
-            "Invalid API key. Please check your Gemini API key in Settings.".to_string()
-        } else {
-            format!("API error ({}): {}", status, error_text)
-        };
-
-        return Err(PedaruError::Gemini(GeminiError::ApiRequestFailed(
-            error_message,
-        )));
     }
+}
 
-    let gemini_response: GeminiResponse = response
-        .json()
-        .await
-        .map_err(|e| PedaruError::Gemini(GeminiError::InvalidResponse(e.to_string())))?;
-
-    if let Some(error) = gemini_response.error {
-        return Err(PedaruError::Gemini(GeminiError::ApiRequestFailed(
-            error.message,
-        )));
-    }
+/// Call Gemini API with the given prompt and optional system instruction
+async fn call_gemini_api(
+    api_key: &str,
+    model: &str,
+    prompt: &str,
+    system_instruction: Option<&str>,
+    params: GenerationParams,
+) -> Result<String, PedaruError> {
+    let request = build_request(prompt, system_instruction, params);
+    let gemini_response = send_generate_content(api_key, model, &request).await?;
 
     let text = gemini_response
         .candidates
@@ -304,190 +261,370 @@ async fn call_gemini_api(
     Ok(text)
 }
 
-/// Parse JSON response from Gemini, with fallback for markdown code blocks
-fn parse_translation_response(text: &str) -> Result<TranslationResponse, PedaruError> {
-    eprintln!("[Gemini] Raw API response: {}", text);
+/// Send a generateContent request and return the decoded response
+async fn send_generate_content(
+    api_key: &str,
+    model: &str,
+    request: &GeminiRequest,
+) -> Result<GeminiResponse, PedaruError> {
+    let response = send_gemini_request(api_key, model, request, false).await?;
 
-    // Try to parse directly first
-    if let Ok(response) = serde_json::from_str::<TranslationResponse>(text) {
-        eprintln!("[Gemini] Parsed directly: {:?}", response);
-        return Ok(response);
-    }
+    let gemini_response: GeminiResponse = response
+        .json()
+        .await
+        .map_err(|e| PedaruError::Gemini(GeminiError::InvalidResponse(e.to_string())))?;
 
-    // Try to extract JSON from markdown code block
-    let cleaned = text
-        .trim()
-        .trim_start_matches("```json")
-        .trim_start_matches("```")
-        .trim_end_matches("```")
-        .trim();
+    // An error reported in the body of a successful response
+    if let Some(error) = gemini_response.error {
+        return Err(PedaruError::Gemini(GeminiError::ApiRequestFailed {
+            status: Some(200),
+            message: error.message,
+        }));
+    }
 
-    eprintln!("[Gemini] Cleaned text: {}", cleaned);
+    Ok(gemini_response)
+}
 
-    if let Ok(response) = serde_json::from_str::<TranslationResponse>(cleaned) {
-        eprintln!("[Gemini] Parsed from cleaned: {:?}", response);
-        return Ok(response);
+/// Send a generateContent (or streamGenerateContent, as server-sent events)
+/// request and return the successful HTTP response
+async fn send_gemini_request(
+    api_key: &str,
+    model: &str,
+    request: &GeminiRequest,
+    stream: bool,
+) -> Result<Response, PedaruError> {
+    if api_key.is_empty() {
+        return Err(PedaruError::Gemini(GeminiError::ApiKeyMissing));
     }
+    translation::acquire_gemini_slot().await?;
 
-    // Try to parse as a more flexible JSON structure
-    if let Ok(value) = serde_json::from_str::<Value>(cleaned) {
-        eprintln!("[Gemini] Parsed as Value: {:?}", value);
-
-        // Handle both object and array responses
-        let obj = if value.is_array() {
-            // If it's an array, take the first element
-            value.as_array().and_then(|arr| arr.first()).cloned()
-        } else {
-            Some(value)
-        };
-
-        if let Some(obj) = obj {
-            let translation = obj
-                .get("translation")
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
+    // Streams only time out when the server goes quiet, not on total length
+    let builder = if stream {
+        network::client_builder()
+    } else {
+        network::client_builder().timeout(network::http_timeouts().request())
+    };
+    let client = builder.build().map_err(|e| {
+        PedaruError::Gemini(GeminiError::ApiRequestFailed {
+            status: None,
+            message: format!("Failed to create HTTP client: {}", e),
+        })
+    })?;
+
+    let method = if stream {
+        "streamGenerateContent?alt=sse"
+    } else {
+        "generateContent"
+    };
+    let url = format!("{}/models/{}:{}", GEMINI_API_BASE, model, method);
 
-            let points = obj
-                .get("points")
-                .and_then(|v| v.as_array())
-                .map(|arr| {
-                    arr.iter()
-                        .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                        .collect()
+    // The key goes in a header so it never appears in a URL (error messages
+    // and logs include the request URL)
+    network::ensure_online(GEMINI_SERVICE)?;
+    let response = client
+        .post(&url)
+        .header(API_KEY_HEADER, api_key)
+        .header("Content-Type", "application/json")
+        .json(request)
+        .send()
+        .await
+        .map_err(|e| {
+            network::network_error(GEMINI_SERVICE, &e).unwrap_or_else(|| {
+                PedaruError::Gemini(GeminiError::ApiRequestFailed {
+                    status: None,
+                    message: format!("Network error: {}", e.without_url()),
                 })
-                .unwrap_or_default();
-
-            let response = TranslationResponse {
-                translation,
-                points,
-            };
-            eprintln!("[Gemini] Flexible parse result: {:?}", response);
-            return Ok(response);
-        }
+            })
+        })?;
+
+    if !response.status().is_success() {
+        let status = response.status().as_u16();
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(PedaruError::Gemini(status_error(
+            status,
+            model,
+            &error_text,
+        )));
     }
 
-    eprintln!("[Gemini] All parsing failed, returning raw text");
-    // If all parsing fails, return the raw text as translation
-    Ok(TranslationResponse {
-        translation: text.to_string(),
-        points: vec![],
-    })
+    Ok(response)
 }
 
-/// Parse JSON response for explanation, with fallback for markdown code blocks
-fn parse_explanation_response(text: &str) -> Result<ExplanationResponse, PedaruError> {
-    eprintln!("[Gemini] Raw API response (explanation): {}", text);
-
-    // Try to parse directly first
-    if let Ok(response) = serde_json::from_str::<ExplanationResponse>(text) {
-        eprintln!("[Gemini] Parsed directly: {:?}", response);
-        return Ok(response);
+/// Map a non-success HTTP status from Gemini to an error
+///
+/// Gemini reports malformed keys as 400 with `API_KEY_INVALID`, so those are
+/// treated like 401/403.
+fn status_error(status: u16, model: &str, body: &str) -> GeminiError {
+    match status {
+        401 | 403 => GeminiError::InvalidApiKey,
+        400 if body.contains("API_KEY_INVALID") => GeminiError::InvalidApiKey,
+        429 => GeminiError::RateLimited,
+        404 => GeminiError::ModelNotFound(model.to_string()),
+        _ => GeminiError::ApiRequestFailed {
+            status: Some(status),
+            message: format!("API error ({}): {}", status, body),
+        },
     }
+}
 
-    // Try to extract JSON from markdown code block
-    let cleaned = text
-        .trim()
-        .trim_start_matches("```json")
-        .trim_start_matches("```")
-        .trim_end_matches("```")
-        .trim();
+/// Check that an API key and model work by sending a minimal request
+///
+/// Succeeds as soon as Gemini accepts the request; the generated text is not
+/// inspected.
+pub async fn test_gemini_key(api_key: &str, model: &str) -> Result<(), PedaruError> {
+    let request = GeminiRequest {
+        contents: vec![GeminiContent {
+            parts: vec![GeminiPart {
+                text: "ping".to_string(),
+            }],
+        }],
+        system_instruction: None,
+        generation_config: None,
+    };
 
-    eprintln!("[Gemini] Cleaned text: {}", cleaned);
+    send_generate_content(api_key.trim(), model, &request).await?;
+    Ok(())
+}
 
-    if let Ok(response) = serde_json::from_str::<ExplanationResponse>(cleaned) {
-        eprintln!("[Gemini] Parsed from cleaned: {:?}", response);
-        return Ok(response);
+/// List models that support generateContent for the model dropdown
+///
+/// Results are cached per API key for `MODEL_LIST_TTL`. When the key is
+/// missing or the request fails, a hardcoded list of known-good models is
+/// returned instead.
+pub async fn list_models(api_key: &str) -> Vec<GeminiModelInfo> {
+    let api_key = api_key.trim();
+    if api_key.is_empty() {
+        return fallback_models();
     }
 
-    // Try to parse as a more flexible JSON structure
-    if let Ok(value) = serde_json::from_str::<Value>(cleaned) {
-        eprintln!("[Gemini] Parsed as Value: {:?}", value);
-
-        // Handle both object and array responses
-        let obj = if value.is_array() {
-            value.as_array().and_then(|arr| arr.first()).cloned()
-        } else {
-            Some(value)
-        };
-
-        if let Some(obj) = obj {
-            let summary = obj
-                .get("summary")
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
+    if let Some(cached) = MODEL_LIST_CACHE.lock().unwrap().as_ref()
+        && cached.api_key == api_key
+        && cached.fetched_at.elapsed() < MODEL_LIST_TTL
+    {
+        return cached.models.clone();
+    }
+
+    match fetch_models(api_key).await {
+        Ok(models) if !models.is_empty() => {
+            *MODEL_LIST_CACHE.lock().unwrap() = Some(CachedModelList {
+                api_key: api_key.to_string(),
+                fetched_at: Instant::now(),
+                models: models.clone(),
+            });
+            models
+        }
+        Ok(_) => fallback_models(),
+        Err(e) => {
+            log::warn!("Failed to list Gemini models: {}", e);
+            fallback_models()
+        }
+    }
+}
+
+/// Fetch every page of the models.list endpoint
+async fn fetch_models(api_key: &str) -> Result<Vec<GeminiModelInfo>, PedaruError> {
+    network::ensure_online(GEMINI_SERVICE)?;
+    let client = network::client_builder()
+        .timeout(network::http_timeouts().request())
+        .build()
+        .map_err(|e| {
+            PedaruError::Gemini(GeminiError::ApiRequestFailed {
+                status: None,
+                message: format!("Failed to create HTTP client: {}", e),
+            })
+        })?;
+
+    let mut models = Vec::new();
+    let mut page_token: Option<String> = None;
+    loop {
+        let mut request = client
+            .get(format!("{}/models", GEMINI_API_BASE))
+            .header(API_KEY_HEADER, api_key)
+            .query(&[("pageSize", "1000")]);
+        if let Some(token) = &page_token {
+            request = request.query(&[("pageToken", token)]);
+        }
 
-            let points = obj
-                .get("points")
-                .and_then(|v| v.as_array())
-                .map(|arr| {
-                    arr.iter()
-                        .filter_map(|v| v.as_str().map(|s| s.to_string()))
-                        .collect()
+        let response = request.send().await.map_err(|e| {
+            network::network_error(GEMINI_SERVICE, &e).unwrap_or_else(|| {
+                PedaruError::Gemini(GeminiError::ApiRequestFailed {
+                    status: None,
+                    message: format!("Network error: {}", e.without_url()),
                 })
-                .unwrap_or_default();
+            })
+        })?;
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(PedaruError::Gemini(status_error(status, "", &error_text)));
+        }
 
-            let response = ExplanationResponse { summary, points };
-            eprintln!("[Gemini] Flexible parse result: {:?}", response);
-            return Ok(response);
+        let page: ListModelsResponse = response
+            .json()
+            .await
+            .map_err(|e| PedaruError::Gemini(GeminiError::InvalidResponse(e.to_string())))?;
+        models.extend(generate_content_models(page.models));
+
+        match page.next_page_token.filter(|token| !token.is_empty()) {
+            Some(token) => page_token = Some(token),
+            None => break,
         }
     }
 
-    eprintln!("[Gemini] All parsing failed, returning raw text");
-    Ok(ExplanationResponse {
-        summary: text.to_string(),
-        points: vec![],
-    })
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(models)
 }
 
-/// Translate text using Gemini API
-///
-/// Returns a structured response with translation and explanation points.
-pub async fn translate_text(
-    api_key: &str,
-    model: &str,
-    text: &str,
-    context_before: &str,
-    context_after: &str,
-) -> Result<TranslationResponse, PedaruError> {
-    let prompt = TRANSLATION_PROMPT
-        .replace("{text}", text)
-        .replace("{context_before}", context_before)
-        .replace("{context_after}", context_after);
-
-    let response_text = call_gemini_api(
-        api_key,
-        model,
-        &prompt,
-        Some(TRANSLATION_SYSTEM_INSTRUCTION),
-    )
-    .await?;
-    parse_translation_response(&response_text)
+/// Keep models that support generateContent, stripping the "models/" prefix
+fn generate_content_models(models: Vec<ApiModel>) -> Vec<GeminiModelInfo> {
+    models
+        .into_iter()
+        .filter(|model| {
+            model
+                .supported_generation_methods
+                .iter()
+                .any(|method| method == "generateContent")
+        })
+        .map(|model| {
+            let id = model
+                .name
+                .strip_prefix("models/")
+                .unwrap_or(&model.name)
+                .to_string();
+            GeminiModelInfo {
+                display_name: model.display_name.unwrap_or_else(|| id.clone()),
+                id,
+            }
+        })
+        .collect()
+}
+
+fn fallback_models() -> Vec<GeminiModelInfo> {
+    FALLBACK_MODELS
+        .iter()
+        .map(|(id, display_name)| GeminiModelInfo {
+            id: id.to_string(),
+            display_name: display_name.to_string(),
+        })
+        .collect()
+}
+
+// ============================================================================
+// API Key Rotation
+// ============================================================================
+
+/// What a key check says about the key itself (None = inconclusive)
+fn key_validity(check: &Result<(), PedaruError>) -> Option<bool> {
+    match check {
+        Ok(()) => Some(true),
+        Err(PedaruError::Gemini(GeminiError::InvalidApiKey)) => Some(false),
+        Err(_) => None,
+    }
 }
 
-/// Get explanation of text
+/// Replace the saved Gemini API key with `new_key` once it is known to work
 ///
-/// Returns a summary and explanation points.
-/// The context parameters help understand the text but are not included in output.
-pub async fn explain_text(
-    api_key: &str,
-    model: &str,
-    text: &str,
-    context_before: &str,
-    context_after: &str,
-) -> Result<ExplanationResponse, PedaruError> {
-    let prompt = EXPLANATION_PROMPT
-        .replace("{text}", text)
-        .replace("{context_before}", context_before)
-        .replace("{context_after}", context_after);
-
-    let response_text = call_gemini_api(
-        api_key,
-        model,
-        &prompt,
-        Some(EXPLANATION_SYSTEM_INSTRUCTION),
-    )
-    .await?;
-    parse_explanation_response(&response_text)
+/// The new key is checked against the saved model first; if that fails the
+/// error is returned and the saved key is left alone. The key has a single
+/// slot in the secrets store, so saving the new key replaces the old one in
+/// one write and there's never a moment without a key.
+pub async fn rotate_gemini_key(
+    app: &tauri::AppHandle,
+    new_key: &str,
+) -> Result<KeyRotation, PedaruError> {
+    let new_key = new_key.trim();
+    if new_key.is_empty() {
+        return Err(GeminiError::ApiKeyMissing.into());
+    }
+
+    let saved = settings::get_gemini_settings(app)?;
+    test_gemini_key(new_key, &saved.model).await?;
+
+    let previous_key_valid = if saved.api_key.trim().is_empty() {
+        None
+    } else {
+        key_validity(&test_gemini_key(&saved.api_key, &saved.model).await)
+    };
+
+    secrets::store_secret(app, secrets::keys::GEMINI_API_KEY, new_key)?;
+    log::info!("Rotated the Gemini API key");
+
+    Ok(KeyRotation { previous_key_valid })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_validity_only_trusts_key_errors() {
+        assert_eq!(key_validity(&Ok(())), Some(true));
+        assert_eq!(
+            key_validity(&Err(GeminiError::InvalidApiKey.into())),
+            Some(false)
+        );
+        assert_eq!(key_validity(&Err(GeminiError::RateLimited.into())), None);
+        assert_eq!(
+            key_validity(&Err(GeminiError::ModelNotFound("m".to_string()).into())),
+            None
+        );
+    }
+
+    #[test]
+    fn test_status_error_distinguishes_key_and_quota() {
+        assert!(matches!(
+            status_error(403, "m", ""),
+            GeminiError::InvalidApiKey
+        ));
+        assert!(matches!(
+            status_error(
+                400,
+                "m",
+                r#"{"error": {"details": [{"reason": "API_KEY_INVALID"}]}}"#
+            ),
+            GeminiError::InvalidApiKey
+        ));
+        assert!(matches!(
+            status_error(429, "m", ""),
+            GeminiError::RateLimited
+        ));
+        assert!(matches!(
+            status_error(404, "gemini-2.0-flsh", ""),
+            GeminiError::ModelNotFound(model) if model == "gemini-2.0-flsh"
+        ));
+        assert!(matches!(
+            status_error(500, "m", "boom"),
+            GeminiError::ApiRequestFailed {
+                status: Some(500),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_generate_content_models_filters_and_strips_prefix() {
+        let page: ListModelsResponse = serde_json::from_str(
+            r#"{"models": [
+                {"name": "models/gemini-2.0-flash", "displayName": "Gemini 2.0 Flash",
+                 "supportedGenerationMethods": ["generateContent", "countTokens"]},
+                {"name": "models/text-embedding-004",
+                 "supportedGenerationMethods": ["embedContent"]}
+            ]}"#,
+        )
+        .unwrap();
+
+        let models = generate_content_models(page.models);
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].id, "gemini-2.0-flash");
+        assert_eq!(models[0].display_name, "Gemini 2.0 Flash");
+    }
+
+    #[test]
+    fn test_stream_delta_joins_parts() {
+        let provider = GeminiProvider::new("key", "m", GenerationParams::default());
+        let event = serde_json::json!({
+            "candidates": [{ "content": { "parts": [{ "text": "{\"tr" }, { "text": "ans" }] } }]
+        });
+        assert_eq!(provider.stream_delta(&event).as_deref(), Some("{\"trans"));
+    }
 }
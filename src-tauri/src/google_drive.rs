@@ -5,21 +5,61 @@
 
 use futures_util::StreamExt;
 use reqwest::Client;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::fs::File;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::{File, OpenOptions};
 use std::io::Write;
 use std::path::Path;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
 
-use crate::bookshelf::{DownloadProgress, get_cancel_flag};
+use crate::bookshelf::{self, DownloadProgress, DownloadStatus, get_cancel_flag};
 use crate::error::{GoogleDriveError, IoError, PedaruError};
-use crate::oauth::get_valid_access_token;
+use crate::network;
+use crate::oauth::{self, get_valid_access_token};
 
 /// Google Drive API base URL
 const DRIVE_API_BASE: &str = "https://www.googleapis.com/drive/v3";
 
+/// MIME types of Drive folders and PDFs
+const FOLDER_MIME_TYPE: &str = "application/vnd.google-apps.folder";
+pub const PDF_MIME_TYPE: &str = "application/pdf";
+
+/// Prefix of Google-native types (Docs, Sheets, Slides, ...), which have no
+/// file content to download
+const NATIVE_MIME_PREFIX: &str = "application/vnd.google-apps.";
+
+/// Google-native types downloaded by exporting them as PDF (Docs and Slides)
+const EXPORTABLE_MIME_TYPES: &[&str] = &[
+    "application/vnd.google-apps.document",
+    "application/vnd.google-apps.presentation",
+];
+
+/// Maximum subfolder depth walked by `list_files_recursive`
+const MAX_FOLDER_DEPTH: usize = 32;
+
+/// Window over which download speed is averaged for progress events
+const RATE_WINDOW: Duration = Duration::from_secs(3);
+
+/// Progress change (in percent) that is worth another event
+const PROGRESS_EVENT_MIN_STEP: f64 = 1.0;
+
+/// Longest gap between progress events, so speed and ETA stay current on
+/// downloads that take a while to move a whole percent
+const PROGRESS_EVENT_MAX_GAP: Duration = Duration::from_secs(1);
+
+/// Accepted folder id lengths (shared drive ids are the shortest at 19)
+const FOLDER_ID_LENGTHS: std::ops::RangeInclusive<usize> = 10..=128;
+
+/// Sent with every request so files on shared drives are found
+const SUPPORTS_ALL_DRIVES: (&str, &str) = ("supportsAllDrives", "true");
+
+/// Sent with every list request so results include shared drive items
+const INCLUDE_ALL_DRIVES: (&str, &str) = ("includeItemsFromAllDrives", "true");
+
 // ============================================================================
 // Types
 // ============================================================================
@@ -31,6 +71,8 @@ pub struct DriveFolder {
     pub id: String,
     pub name: String,
     pub modified_time: Option<String>,
+    /// Shared drive the folder is on (None for My Drive)
+    pub drive_id: Option<String>,
 }
 
 /// A file from Google Drive
@@ -43,6 +85,34 @@ pub struct DriveFile {
     pub mime_type: String,
     pub modified_time: Option<String>,
     pub thumbnail_link: Option<String>,
+    /// Content checksum, used to spot the same PDF in several folders
+    pub md5_checksum: Option<String>,
+}
+
+/// Whether a MIME type is a Google-native file rather than an uploaded one
+pub fn is_native_file(mime_type: &str) -> bool {
+    mime_type.starts_with(NATIVE_MIME_PREFIX)
+}
+
+/// Whether a Google-native file is downloaded by exporting it as PDF
+pub fn is_exportable(mime_type: &str) -> bool {
+    EXPORTABLE_MIME_TYPES.contains(&mime_type)
+}
+
+impl DriveFile {
+    /// Size in bytes (Drive reports it as a string; None for Google-native files)
+    pub fn size_bytes(&self) -> Option<i64> {
+        self.size.as_ref().and_then(|size| size.parse().ok())
+    }
+
+    /// Name on the bookshelf (exported files get a `.pdf` extension)
+    pub fn local_name(&self) -> String {
+        if is_exportable(&self.mime_type) {
+            format!("{}.pdf", self.name)
+        } else {
+            self.name.clone()
+        }
+    }
 }
 
 /// Combined item that can be either a folder or file
@@ -58,25 +128,469 @@ pub struct DriveItem {
     pub is_folder: bool,
 }
 
-/// Response from Drive files.list API
+impl From<DriveItem> for DriveFile {
+    fn from(item: DriveItem) -> Self {
+        DriveFile {
+            id: item.id,
+            name: item.name,
+            size: item.size,
+            mime_type: item.mime_type,
+            modified_time: item.modified_time,
+            thumbnail_link: item.thumbnail_link,
+            md5_checksum: None,
+        }
+    }
+}
+
+/// A file found while walking a folder tree, with the folder it was found in
+#[derive(Debug, Clone)]
+pub struct NestedDriveFile {
+    pub file: DriveFile,
+    /// ID of the folder that directly contains the file
+    pub parent_id: String,
+    /// Subfolder path relative to the watched folder ("" at the top level)
+    pub folder_path: String,
+}
+
+/// Every file in a folder tree, plus the folders that were walked
+#[derive(Debug, Clone)]
+pub struct FolderListing {
+    pub files: Vec<NestedDriveFile>,
+    /// (folder ID, path relative to the watched folder), including the
+    /// watched folder itself with an empty path
+    pub folders: Vec<(String, String)>,
+}
+
+/// A file with its parents, as reported by changes.list or files.get
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangedFile {
+    #[serde(flatten)]
+    pub file: DriveFile,
+    #[serde(default)]
+    pub parents: Vec<String>,
+    #[serde(default)]
+    pub trashed: bool,
+}
+
+/// One entry from changes.list
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DriveChange {
+    /// Absent for shared drive changes
+    pub file_id: Option<String>,
+    /// Whether the file was deleted or access to it was lost
+    #[serde(default)]
+    pub removed: bool,
+    pub file: Option<ChangedFile>,
+}
+
+/// Changes since a page token, and the token for the next sync
+#[derive(Debug, Clone)]
+pub struct DriveChanges {
+    pub changes: Vec<DriveChange>,
+    pub new_start_page_token: String,
+}
+
+/// What a synced folder's changes mean for the bookshelf
+#[derive(Debug, Clone, Default)]
+pub struct FolderChanges {
+    /// Files that are new, modified or moved into the folder (of any type;
+    /// sync picks the ones it keeps)
+    pub upserts: Vec<NestedDriveFile>,
+    /// Files that may have left the folder (deleted or trashed); IDs the
+    /// bookshelf doesn't have for the folder are ignored
+    pub removed: Vec<String>,
+    /// Files still on Drive whose parents are outside the folder, which may
+    /// have moved to another watched folder
+    pub moved_out: Vec<ChangedFile>,
+}
+
+/// Response from Drive changes.getStartPageToken API
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StartPageTokenResponse {
+    start_page_token: String,
+}
+
+/// Response from Drive changes.list API
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct FilesListResponse {
-    files: Vec<DriveFile>,
+struct ChangesListResponse {
+    changes: Vec<DriveChange>,
     next_page_token: Option<String>,
+    new_start_page_token: Option<String>,
+}
+
+// ============================================================================
+// Folder IDs
+// ============================================================================
+
+/// Extract a folder id from a raw id or a pasted Drive folder URL
+///
+/// Accepts `https://drive.google.com/drive/folders/<id>` (with or without
+/// `/u/<n>/` and query parameters) and the older `open?id=<id>` and
+/// `folderview?id=<id>` forms.
+pub fn parse_folder_id(input: &str) -> Result<String, PedaruError> {
+    let input = input.trim();
+    let invalid = || GoogleDriveError::InvalidFolderId(input.to_string());
+
+    let folder_id = if input.contains('/') || input.contains('?') {
+        let with_scheme = if input.contains("://") {
+            input.to_string()
+        } else {
+            format!("https://{}", input)
+        };
+        let url = reqwest::Url::parse(&with_scheme).map_err(|_| invalid())?;
+        if !url
+            .host_str()
+            .is_some_and(|host| host == "google.com" || host.ends_with(".google.com"))
+        {
+            return Err(invalid().into());
+        }
+        let from_path = url
+            .path_segments()
+            .and_then(|mut segments| {
+                segments.by_ref().find(|segment| *segment == "folders")?;
+                segments.next()
+            })
+            .map(str::to_string);
+        from_path
+            .or_else(|| {
+                url.query_pairs()
+                    .find(|(key, _)| key == "id")
+                    .map(|(_, value)| value.into_owned())
+            })
+            .ok_or_else(invalid)?
+    } else {
+        input.to_string()
+    };
+
+    let well_formed = folder_id == "root"
+        || (FOLDER_ID_LENGTHS.contains(&folder_id.len())
+            && folder_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    if !well_formed {
+        return Err(invalid().into());
+    }
+    Ok(folder_id)
 }
 
 // ============================================================================
 // API Functions
 // ============================================================================
 
-/// List folders in Google Drive root or a specific folder
+/// Service name used in offline errors
+const DRIVE_SERVICE: &str = "Google Drive";
+
+/// Map a request that got no response (network failure) to a Drive error
+fn request_failed(e: reqwest::Error) -> PedaruError {
+    network::network_error(DRIVE_SERVICE, &e).unwrap_or_else(|| {
+        PedaruError::GoogleDrive(GoogleDriveError::ApiRequestFailed {
+            status: None,
+            message: e.to_string(),
+        })
+    })
+}
+
+/// Attempts at a request that Drive rate-limits before giving up
+const MAX_RATE_LIMITED_ATTEMPTS: u32 = 5;
+
+/// Base delay between attempts at a rate-limited request
+const RATE_LIMIT_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest `Retry-After` worth waiting for; past this the user is asked to
+/// try again later
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Error reasons Drive reports on a 403 that is really a rate limit
+const RATE_LIMIT_REASONS: &[&str] = &[
+    "rateLimitExceeded",
+    "userRateLimitExceeded",
+    "RATE_LIMIT_EXCEEDED",
+    "USER_RATE_LIMIT_EXCEEDED",
+];
+
+/// Error reason Drive reports when a file is too large to export
+const EXPORT_SIZE_LIMIT_REASON: &str = "exportSizeLimitExceeded";
+
+/// Whether a Drive error body reports one of `reasons`
+///
+/// Drive puts the reason in `error.errors[].reason` (and, in newer
+/// responses, `error.details[].reason`).
+fn has_error_reason(body: &str, reasons: &[&str]) -> bool {
+    let Ok(body) = serde_json::from_str::<serde_json::Value>(body) else {
+        return false;
+    };
+    ["errors", "details"].iter().any(|list| {
+        body["error"][list].as_array().is_some_and(|entries| {
+            entries.iter().any(|entry| {
+                entry["reason"]
+                    .as_str()
+                    .is_some_and(|reason| reasons.contains(&reason))
+            })
+        })
+    })
+}
+
+/// Whether a 403 error body reports a rate limit rather than a permission
+/// problem
+fn is_rate_limit_body(body: &str) -> bool {
+    has_error_reason(body, RATE_LIMIT_REASONS)
+}
+
+/// Parse a `Retry-After` header given in seconds
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// Send a Drive request, retrying while Drive rate-limits it
+///
+/// `build` makes a fresh request for each attempt, and `send_failed` maps
+/// requests that got no response. A 429, or a 403 whose reason is a rate
+/// limit, is retried after the `Retry-After` delay or an exponential backoff
+/// with jitter; other 403s (permission denied) are returned as errors right
+/// away. Every other response is returned for the caller to check.
+async fn send_drive_request(
+    build: impl Fn() -> reqwest::RequestBuilder,
+    send_failed: fn(reqwest::Error) -> PedaruError,
+) -> Result<reqwest::Response, PedaruError> {
+    let mut attempt = 0;
+    loop {
+        let response = build().send().await.map_err(send_failed)?;
+        let status = response.status().as_u16();
+        if !matches!(status, 403 | 429) {
+            return Ok(response);
+        }
+
+        let wait = retry_after(response.headers());
+        if status == 403 {
+            let body = response.text().await.unwrap_or_default();
+            if has_error_reason(&body, &[EXPORT_SIZE_LIMIT_REASON]) {
+                return Err(GoogleDriveError::ExportTooLarge.into());
+            }
+            if !is_rate_limit_body(&body) {
+                return Err(GoogleDriveError::ApiRequestFailed {
+                    status: Some(status),
+                    message: body,
+                }
+                .into());
+            }
+        }
+
+        attempt += 1;
+        let delay =
+            wait.unwrap_or_else(|| oauth::backoff_delay(RATE_LIMIT_BASE_DELAY, attempt - 1));
+        if attempt >= MAX_RATE_LIMITED_ATTEMPTS || delay > MAX_RETRY_AFTER {
+            return Err(GoogleDriveError::RateLimited {
+                retry_after_secs: wait.map(|wait| wait.as_secs()),
+            }
+            .into());
+        }
+        log::warn!(
+            "Google Drive rate-limited a request (attempt {}/{}), retrying in {:?}",
+            attempt,
+            MAX_RATE_LIMITED_ATTEMPTS,
+            delay
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// Full access to Drive, which covers every operation
+const SCOPE_DRIVE: &str = "https://www.googleapis.com/auth/drive";
+const SCOPE_DRIVE_READONLY: &str = "https://www.googleapis.com/auth/drive.readonly";
+const SCOPE_DRIVE_METADATA: &str = "https://www.googleapis.com/auth/drive.metadata";
+const SCOPE_DRIVE_METADATA_READONLY: &str =
+    "https://www.googleapis.com/auth/drive.metadata.readonly";
+
+/// What a Drive request needs access to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DriveAccess {
+    /// Listing files and folders, and reading changes
+    Metadata,
+    /// Downloading file contents
+    Content,
+}
+
+impl DriveAccess {
+    /// Scopes that allow this access (any one is enough; the first is the
+    /// one to ask for)
+    fn accepted_scopes(self) -> &'static [&'static str] {
+        match self {
+            DriveAccess::Metadata => &[
+                SCOPE_DRIVE_READONLY,
+                SCOPE_DRIVE,
+                SCOPE_DRIVE_METADATA,
+                SCOPE_DRIVE_METADATA_READONLY,
+            ],
+            DriveAccess::Content => &[SCOPE_DRIVE_READONLY, SCOPE_DRIVE],
+        }
+    }
+
+    fn action(self) -> &'static str {
+        match self {
+            DriveAccess::Metadata => "list your Google Drive files",
+            DriveAccess::Content => "download files from Google Drive",
+        }
+    }
+}
+
+/// Whether the `granted` scopes allow `access`
+///
+/// No recorded scopes (tokens saved before scopes were stored) counts as
+/// allowed, leaving the decision to Drive.
+fn scopes_allow(granted: &[String], access: DriveAccess) -> bool {
+    granted.is_empty()
+        || granted
+            .iter()
+            .any(|scope| access.accepted_scopes().contains(&scope.as_str()))
+}
+
+/// Get an access token for a request, first checking that the account
+/// granted the scope it needs
+///
+/// Without the check, a token with too narrow a scope only gets an opaque
+/// 403 from Drive.
+async fn access_token_for(
+    app: &AppHandle,
+    account_id: i64,
+    access: DriveAccess,
+) -> Result<String, PedaruError> {
+    let granted = oauth::load_granted_scopes(app, account_id)?;
+    if !scopes_allow(&granted, access) {
+        return Err(GoogleDriveError::InsufficientScope {
+            action: access.action(),
+            required_scope: access.accepted_scopes()[0],
+        }
+        .into());
+    }
+    get_valid_access_token(app, account_id).await
+}
+
+/// Files per page requested from files.list
+const LIST_PAGE_SIZE: &str = "100";
+
+/// Most pages read for one listing; past this, a page token loop is more
+/// likely than a real folder that large
+const MAX_LIST_PAGES: usize = 1000;
+
+/// One page of a files.list response
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileListPage<T> {
+    // Not plain `default`, which would require `T: Default`
+    #[serde(default = "Vec::new")]
+    files: Vec<T>,
+    next_page_token: Option<String>,
+}
+
+/// Run a files.list query, following `nextPageToken` until every page is read
+///
+/// `params` are sent with every page; `fields` must include `nextPageToken`.
+/// Items on shared drives are always included.
+/// Stopping early would make sync treat the missing files as removed, so
+/// hitting `MAX_LIST_PAGES` is an error rather than a partial result.
+async fn list_all_files<T: DeserializeOwned>(
+    client: &Client,
+    api_base: &str,
+    access_token: &str,
+    params: &[(&str, &str)],
+) -> Result<Vec<T>, PedaruError> {
+    let mut files = Vec::new();
+    let mut page_token: Option<String> = None;
+
+    for _ in 0..MAX_LIST_PAGES {
+        let page: FileListPage<T> = fetch_list_page(
+            client,
+            api_base,
+            access_token,
+            params,
+            page_token.as_deref(),
+        )
+        .await?;
+        files.extend(page.files);
+        match page.next_page_token {
+            Some(token) => page_token = Some(token),
+            None => return Ok(files),
+        }
+    }
+
+    Err(GoogleDriveError::ListFilesFailed(format!(
+        "listing has more than {} pages",
+        MAX_LIST_PAGES
+    ))
+    .into())
+}
+
+/// files.list parameters that restrict a query to one shared drive, which
+/// Drive answers faster than a search of every drive the user can see
+fn drive_corpus(drive_id: Option<&str>) -> Vec<(&'static str, &str)> {
+    match drive_id {
+        Some(drive_id) => vec![("corpora", "drive"), ("driveId", drive_id)],
+        None => Vec::new(),
+    }
+}
+
+/// Fetch one page of a files.list query
+async fn fetch_list_page<T: DeserializeOwned>(
+    client: &Client,
+    api_base: &str,
+    access_token: &str,
+    params: &[(&str, &str)],
+    page_token: Option<&str>,
+) -> Result<FileListPage<T>, PedaruError> {
+    let response = send_drive_request(
+        || {
+            let request = client
+                .get(format!("{}/files", api_base))
+                .bearer_auth(access_token)
+                .query(params)
+                .query(&[
+                    ("pageSize", LIST_PAGE_SIZE),
+                    SUPPORTS_ALL_DRIVES,
+                    INCLUDE_ALL_DRIVES,
+                ]);
+            match page_token {
+                Some(token) => request.query(&[("pageToken", token)]),
+                None => request,
+            }
+        },
+        request_failed,
+    )
+    .await?;
+
+    if !response.status().is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(GoogleDriveError::ListFilesFailed(error_text).into());
+    }
+
+    response
+        .json()
+        .await
+        .map_err(|e| GoogleDriveError::InvalidResponse(e.to_string()).into())
+}
+
+/// List folders in Google Drive root or a specific folder (handles pagination)
+///
+/// `drive_id` is the shared drive the parent is on, if known.
 pub async fn list_folders(
     app: &AppHandle,
+    account_id: i64,
     parent_id: Option<&str>,
+    drive_id: Option<&str>,
 ) -> Result<Vec<DriveFolder>, PedaruError> {
-    let access_token = get_valid_access_token(app).await?;
-    let client = Client::new();
+    network::ensure_online(DRIVE_SERVICE)?;
+    let access_token = access_token_for(app, account_id, DriveAccess::Metadata).await?;
+    let client = network::http_client()?;
 
     let parent = parent_id.unwrap_or("root");
     let query = format!(
@@ -84,46 +598,137 @@ pub async fn list_folders(
         parent
     );
 
-    let response = client
-        .get(format!("{}/files", DRIVE_API_BASE))
-        .bearer_auth(&access_token)
-        .query(&[
-            ("q", query.as_str()),
-            ("fields", "files(id,name,modifiedTime)"),
-            ("orderBy", "name"),
-            ("pageSize", "100"),
-        ])
-        .send()
-        .await
-        .map_err(|e| PedaruError::GoogleDrive(GoogleDriveError::ApiRequestFailed(e.to_string())))?;
+    let mut params = vec![
+        ("q", query.as_str()),
+        (
+            "fields",
+            "files(id,name,modifiedTime,driveId),nextPageToken",
+        ),
+        ("orderBy", "name"),
+    ];
+    params.extend(drive_corpus(drive_id));
+    list_all_files(&client, DRIVE_API_BASE, &access_token, &params).await
+}
 
-    if !response.status().is_success() {
+/// Look up a folder, failing unless it exists, is a folder and isn't trashed
+pub async fn get_folder(
+    app: &AppHandle,
+    account_id: i64,
+    folder_id: &str,
+) -> Result<DriveFolder, PedaruError> {
+    network::ensure_online(DRIVE_SERVICE)?;
+    let access_token = access_token_for(app, account_id, DriveAccess::Metadata).await?;
+    let client = network::http_client()?;
+    let response = send_drive_request(
+        || {
+            client
+                .get(format!("{}/files/{}", DRIVE_API_BASE, folder_id))
+                .bearer_auth(&access_token)
+                .query(&[
+                    ("fields", "id,name,mimeType,modifiedTime,trashed,driveId"),
+                    SUPPORTS_ALL_DRIVES,
+                ])
+        },
+        request_failed,
+    )
+    .await?;
+
+    let status = response.status();
+    if status.as_u16() == 404 {
+        return Err(GoogleDriveError::FileNotFound(folder_id.to_string()).into());
+    }
+    if !status.is_success() {
         let error_text = response.text().await.unwrap_or_default();
-        return Err(PedaruError::GoogleDrive(GoogleDriveError::ListFilesFailed(
-            error_text,
-        )));
+        return Err(GoogleDriveError::ApiRequestFailed {
+            status: Some(status.as_u16()),
+            message: format!("Folder request failed ({}): {}", status, error_text),
+        }
+        .into());
     }
 
     #[derive(Deserialize)]
-    struct FolderListResponse {
-        files: Vec<DriveFolder>,
+    #[serde(rename_all = "camelCase")]
+    struct FolderMetadata {
+        id: String,
+        name: String,
+        mime_type: String,
+        modified_time: Option<String>,
+        #[serde(default)]
+        trashed: bool,
+        drive_id: Option<String>,
     }
 
-    let folder_list: FolderListResponse = response
+    let metadata: FolderMetadata = response
         .json()
         .await
-        .map_err(|e| PedaruError::GoogleDrive(GoogleDriveError::ApiRequestFailed(e.to_string())))?;
+        .map_err(|e| GoogleDriveError::InvalidResponse(e.to_string()))?;
+    if metadata.mime_type != FOLDER_MIME_TYPE || metadata.trashed {
+        return Err(GoogleDriveError::InvalidFolderId(folder_id.to_string()).into());
+    }
 
-    Ok(folder_list.files)
+    Ok(DriveFolder {
+        id: metadata.id,
+        name: metadata.name,
+        modified_time: metadata.modified_time,
+        drive_id: metadata.drive_id,
+    })
+}
+
+/// Look up a file with its parents, or `None` if Drive can't find it
+pub async fn get_file(
+    app: &AppHandle,
+    account_id: i64,
+    file_id: &str,
+) -> Result<Option<ChangedFile>, PedaruError> {
+    network::ensure_online(DRIVE_SERVICE)?;
+    let access_token = access_token_for(app, account_id, DriveAccess::Metadata).await?;
+    let client = network::http_client()?;
+    let response = send_drive_request(
+        || {
+            client
+                .get(format!("{}/files/{}", DRIVE_API_BASE, file_id))
+                .bearer_auth(&access_token)
+                .query(&[
+                    (
+                        "fields",
+                        "id,name,size,mimeType,modifiedTime,thumbnailLink,md5Checksum,parents,trashed",
+                    ),
+                    SUPPORTS_ALL_DRIVES,
+                ])
+        },
+        request_failed,
+    )
+    .await?;
+
+    let status = response.status();
+    if status.as_u16() == 404 {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(GoogleDriveError::ApiRequestFailed {
+            status: Some(status.as_u16()),
+            message: format!("File request failed ({}): {}", status, error_text),
+        }
+        .into());
+    }
+
+    response
+        .json()
+        .await
+        .map(Some)
+        .map_err(|e| GoogleDriveError::InvalidResponse(e.to_string()).into())
 }
 
 /// List both folders and PDF files in a parent folder (handles pagination)
 pub async fn list_drive_items(
     app: &AppHandle,
+    account_id: i64,
     parent_id: Option<&str>,
 ) -> Result<Vec<DriveItem>, PedaruError> {
-    let access_token = get_valid_access_token(app).await?;
-    let client = Client::new();
+    network::ensure_online(DRIVE_SERVICE)?;
+    let access_token = access_token_for(app, account_id, DriveAccess::Metadata).await?;
+    let client = network::http_client()?;
 
     let parent = parent_id.unwrap_or("root");
     // Get folders OR PDF files
@@ -144,136 +749,310 @@ pub async fn list_drive_items(
         thumbnail_link: Option<String>,
     }
 
-    #[derive(Deserialize)]
-    #[serde(rename_all = "camelCase")]
-    struct RawListResponse {
-        files: Vec<RawItem>,
-        next_page_token: Option<String>,
-    }
+    let raw_items: Vec<RawItem> = list_all_files(
+        &client,
+        DRIVE_API_BASE,
+        &access_token,
+        &[
+            ("q", query.as_str()),
+            (
+                "fields",
+                "files(id,name,size,mimeType,modifiedTime,thumbnailLink),nextPageToken",
+            ),
+            ("orderBy", "folder,name"),
+        ],
+    )
+    .await?;
+
+    // Convert to DriveItem with is_folder flag
+    Ok(raw_items
+        .into_iter()
+        .map(|item| DriveItem {
+            id: item.id,
+            name: item.name,
+            size: item.size,
+            is_folder: item.mime_type == FOLDER_MIME_TYPE,
+            mime_type: item.mime_type,
+            modified_time: item.modified_time,
+            thumbnail_link: item.thumbnail_link,
+        })
+        .collect())
+}
 
-    let mut all_items = Vec::new();
-    let mut page_token: Option<String> = None;
+/// List the files (not subfolders) in a folder (handles pagination)
+///
+/// Files of every type are returned; sync picks the ones it keeps.
+/// `drive_id` is the shared drive the folder is on, if known.
+pub async fn list_files(
+    app: &AppHandle,
+    account_id: i64,
+    folder_id: &str,
+    drive_id: Option<&str>,
+) -> Result<Vec<DriveFile>, PedaruError> {
+    network::ensure_online(DRIVE_SERVICE)?;
+    let access_token = access_token_for(app, account_id, DriveAccess::Metadata).await?;
+    let client = network::http_client()?;
 
-    loop {
-        let mut request = client
-            .get(format!("{}/files", DRIVE_API_BASE))
-            .bearer_auth(&access_token)
-            .query(&[
-                ("q", query.as_str()),
-                (
-                    "fields",
-                    "files(id,name,size,mimeType,modifiedTime,thumbnailLink),nextPageToken",
-                ),
-                ("orderBy", "folder,name"),
-                ("pageSize", "100"),
-            ]);
-
-        if let Some(token) = &page_token {
-            request = request.query(&[("pageToken", token.as_str())]);
-        }
-
-        let response = request.send().await.map_err(|e| {
-            PedaruError::GoogleDrive(GoogleDriveError::ApiRequestFailed(e.to_string()))
-        })?;
+    let query = format!(
+        "'{}' in parents and mimeType!='{}' and trashed=false",
+        folder_id, FOLDER_MIME_TYPE
+    );
+    let mut params = vec![
+        ("q", query.as_str()),
+        (
+            "fields",
+            "files(id,name,size,mimeType,modifiedTime,thumbnailLink,md5Checksum),nextPageToken",
+        ),
+        ("orderBy", "name"),
+    ];
+    params.extend(drive_corpus(drive_id));
+    list_all_files(&client, DRIVE_API_BASE, &access_token, &params).await
+}
 
-        if !response.status().is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(PedaruError::GoogleDrive(GoogleDriveError::ListFilesFailed(
-                error_text,
-            )));
+/// List the files in a folder and all of its subfolders
+///
+/// Only real folders are descended into; shortcuts are never followed, and
+/// each folder is visited once so multi-parent folders can't cause cycles.
+pub async fn list_files_recursive(
+    app: &AppHandle,
+    account_id: i64,
+    root_folder_id: &str,
+    drive_id: Option<&str>,
+) -> Result<FolderListing, PedaruError> {
+    let mut results = Vec::new();
+    let mut folders = Vec::new();
+    let mut visited = HashSet::new();
+    let mut pending = vec![(root_folder_id.to_string(), String::new(), 0usize)];
+
+    while let Some((folder_id, folder_path, depth)) = pending.pop() {
+        if !visited.insert(folder_id.clone()) {
+            continue;
+        }
+        folders.push((folder_id.clone(), folder_path.clone()));
+
+        for file in list_files(app, account_id, &folder_id, drive_id).await? {
+            results.push(NestedDriveFile {
+                file,
+                parent_id: folder_id.clone(),
+                folder_path: folder_path.clone(),
+            });
         }
 
-        let raw_response: RawListResponse = response.json().await.map_err(|e| {
-            PedaruError::GoogleDrive(GoogleDriveError::ApiRequestFailed(e.to_string()))
-        })?;
+        if depth >= MAX_FOLDER_DEPTH {
+            log::info!(
+                "Not descending below {:?}: maximum folder depth reached",
+                folder_path
+            );
+            continue;
+        }
 
-        // Convert to DriveItem with is_folder flag
-        let items: Vec<DriveItem> = raw_response
-            .files
-            .into_iter()
-            .map(|item| DriveItem {
-                id: item.id,
-                name: item.name,
-                size: item.size,
-                is_folder: item.mime_type == "application/vnd.google-apps.folder",
-                mime_type: item.mime_type,
-                modified_time: item.modified_time,
-                thumbnail_link: item.thumbnail_link,
-            })
-            .collect();
+        for child in list_folders(app, account_id, Some(&folder_id), drive_id).await? {
+            let child_path = if folder_path.is_empty() {
+                child.name
+            } else {
+                format!("{}/{}", folder_path, child.name)
+            };
+            pending.push((child.id, child_path, depth + 1));
+        }
+    }
 
-        all_items.extend(items);
+    Ok(FolderListing {
+        files: results,
+        folders,
+    })
+}
 
-        match raw_response.next_page_token {
-            Some(token) => page_token = Some(token),
-            None => break,
-        }
+// ============================================================================
+// Changes (incremental sync)
+// ============================================================================
+
+/// Get the page token for changes made from now on
+///
+/// Shared drives keep their own change log, so folders on one pass its
+/// `drive_id`; without it the token is for the account's My Drive log.
+pub async fn get_start_page_token(
+    app: &AppHandle,
+    account_id: i64,
+    drive_id: Option<&str>,
+) -> Result<String, PedaruError> {
+    network::ensure_online(DRIVE_SERVICE)?;
+    let access_token = access_token_for(app, account_id, DriveAccess::Metadata).await?;
+
+    let client = network::http_client()?;
+    let response = send_drive_request(
+        || {
+            let request = client
+                .get(format!("{}/changes/startPageToken", DRIVE_API_BASE))
+                .bearer_auth(&access_token)
+                .query(&[SUPPORTS_ALL_DRIVES]);
+            match drive_id {
+                Some(drive_id) => request.query(&[("driveId", drive_id)]),
+                None => request,
+            }
+        },
+        request_failed,
+    )
+    .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(PedaruError::GoogleDrive(
+            GoogleDriveError::ApiRequestFailed {
+                status: Some(status.as_u16()),
+                message: error_text,
+            },
+        ));
     }
 
-    Ok(all_items)
+    let token: StartPageTokenResponse = response
+        .json()
+        .await
+        .map_err(|e| PedaruError::GoogleDrive(GoogleDriveError::InvalidResponse(e.to_string())))?;
+    Ok(token.start_page_token)
 }
 
-/// List PDF files in a folder (handles pagination)
-pub async fn list_pdf_files(
+/// List every change since `page_token` (handles pagination)
+///
+/// Returns `None` if Drive no longer accepts the token (404/410), in which
+/// case the folder has to be listed in full. `drive_id` must match the one
+/// the token was taken for (see `get_start_page_token`).
+pub async fn list_changes(
     app: &AppHandle,
-    folder_id: &str,
-) -> Result<Vec<DriveFile>, PedaruError> {
-    let access_token = get_valid_access_token(app).await?;
-    let client = Client::new();
+    account_id: i64,
+    page_token: &str,
+    drive_id: Option<&str>,
+) -> Result<Option<DriveChanges>, PedaruError> {
+    network::ensure_online(DRIVE_SERVICE)?;
+    let access_token = access_token_for(app, account_id, DriveAccess::Metadata).await?;
+    let client = network::http_client()?;
 
-    let mut all_files = Vec::new();
-    let mut page_token: Option<String> = None;
+    let mut changes = Vec::new();
+    let mut page_token = page_token.to_string();
 
     loop {
-        let query = format!(
-            "'{}' in parents and mimeType='application/pdf' and trashed=false",
-            folder_id
-        );
-
-        let mut request = client
-            .get(format!("{}/files", DRIVE_API_BASE))
-            .bearer_auth(&access_token)
-            .query(&[
-                ("q", query.as_str()),
-                (
-                    "fields",
-                    "files(id,name,size,mimeType,modifiedTime,thumbnailLink),nextPageToken",
-                ),
-                ("orderBy", "name"),
-                ("pageSize", "100"),
-            ]);
-
-        if let Some(token) = &page_token {
-            request = request.query(&[("pageToken", token.as_str())]);
-        }
-
-        let response = request.send().await.map_err(|e| {
-            PedaruError::GoogleDrive(GoogleDriveError::ApiRequestFailed(e.to_string()))
-        })?;
+        let response = send_drive_request(
+            || {
+                let request = client
+                    .get(format!("{}/changes", DRIVE_API_BASE))
+                    .bearer_auth(&access_token)
+                    .query(&[
+                        ("pageToken", page_token.as_str()),
+                        (
+                            "fields",
+                            "changes(fileId,removed,file(id,name,size,mimeType,modifiedTime,thumbnailLink,md5Checksum,parents,trashed)),nextPageToken,newStartPageToken",
+                        ),
+                        ("pageSize", "1000"),
+                        ("spaces", "drive"),
+                        SUPPORTS_ALL_DRIVES,
+                        INCLUDE_ALL_DRIVES,
+                    ]);
+                match drive_id {
+                    Some(drive_id) => request.query(&[("driveId", drive_id)]),
+                    None => request,
+                }
+            },
+            request_failed,
+        )
+        .await?;
 
-        if !response.status().is_success() {
+        let status = response.status();
+        if matches!(status.as_u16(), 404 | 410) {
+            log::info!("Drive changes token expired, falling back to a full sync");
+            return Ok(None);
+        }
+        if !status.is_success() {
             let error_text = response.text().await.unwrap_or_default();
-            return Err(PedaruError::GoogleDrive(GoogleDriveError::ListFilesFailed(
-                error_text,
-            )));
+            return Err(PedaruError::GoogleDrive(
+                GoogleDriveError::ApiRequestFailed {
+                    status: Some(status.as_u16()),
+                    message: error_text,
+                },
+            ));
         }
 
-        let list_response: FilesListResponse = response.json().await.map_err(|e| {
-            PedaruError::GoogleDrive(GoogleDriveError::ApiRequestFailed(e.to_string()))
+        let list_response: ChangesListResponse = response.json().await.map_err(|e| {
+            PedaruError::GoogleDrive(GoogleDriveError::InvalidResponse(e.to_string()))
         })?;
+        changes.extend(list_response.changes);
+
+        match (
+            list_response.next_page_token,
+            list_response.new_start_page_token,
+        ) {
+            (Some(next), _) => page_token = next,
+            (None, Some(new_start_page_token)) => {
+                return Ok(Some(DriveChanges {
+                    changes,
+                    new_start_page_token,
+                }));
+            }
+            (None, None) => {
+                return Err(PedaruError::GoogleDrive(GoogleDriveError::InvalidResponse(
+                    "changes.list returned no page token".to_string(),
+                )));
+            }
+        }
+    }
+}
 
-        all_files.extend(list_response.files);
+/// Match Drive changes against a synced folder
+///
+/// `folders` maps the watched folder and, for recursive folders, each known
+/// subfolder to its path. Returns `None` if a subfolder was added, moved,
+/// renamed or removed, since the tree then has to be listed again.
+pub fn match_changes(
+    changes: &[DriveChange],
+    root_folder_id: &str,
+    folders: &HashMap<String, String>,
+    recursive: bool,
+) -> Option<FolderChanges> {
+    let in_tree = |parents: &[String]| parents.iter().find(|p| folders.contains_key(*p)).cloned();
+    let mut matched = FolderChanges::default();
+
+    for change in changes {
+        let Some(file_id) = &change.file_id else {
+            continue;
+        };
+        let is_subfolder = file_id != root_folder_id && folders.contains_key(file_id);
+        let file = change.file.as_ref().filter(|_| !change.removed);
+
+        if let Some(changed) = file
+            && changed.file.mime_type == FOLDER_MIME_TYPE
+        {
+            if recursive && (is_subfolder || in_tree(&changed.parents).is_some()) {
+                return None;
+            }
+            continue;
+        }
+        if recursive && is_subfolder {
+            return None;
+        }
 
-        match list_response.next_page_token {
-            Some(token) => page_token = Some(token),
-            None => break,
+        match file {
+            Some(changed) if !changed.trashed => match in_tree(&changed.parents) {
+                Some(parent_id) => matched.upserts.push(NestedDriveFile {
+                    file: changed.file.clone(),
+                    folder_path: folders[&parent_id].clone(),
+                    parent_id,
+                }),
+                None => matched.moved_out.push(changed.clone()),
+            },
+            _ => matched.removed.push(file_id.clone()),
         }
     }
 
-    Ok(all_files)
+    Some(matched)
 }
 
-/// Download a file from Google Drive with cancellation support
+/// Download a file from Google Drive with cancellation and resume support
+///
+/// Google Docs and Slides files are exported as PDF instead; exports can't
+/// be resumed and have no checksum to verify. The file is written to a
+/// `.partial` path next to `dest_path` and only renamed into place once its
+/// size and checksum are verified. Partially downloaded bytes are kept on
+/// cancellation or failure and their count is recorded in the bookshelf, so
+/// the next attempt resumes with a `Range` request instead of starting over.
 pub async fn download_file(
     app: &AppHandle,
     file_id: &str,
@@ -283,11 +1062,8 @@ pub async fn download_file(
 
     let result = download_file_inner(app, file_id, dest_path, &cancel_flag).await;
 
-    // If cancelled, clean up partial file
+    // If cancelled, keep the partial file so the download can be resumed
     if cancel_flag.load(Ordering::SeqCst) {
-        if dest_path.exists() {
-            let _ = std::fs::remove_file(dest_path);
-        }
         return Err(PedaruError::GoogleDrive(
             GoogleDriveError::DownloadCancelled(file_id.to_string()),
         ));
@@ -296,6 +1072,19 @@ pub async fn download_file(
     result
 }
 
+/// Determine the byte offset to resume from, discarding untracked partial data
+fn resume_offset(app: &AppHandle, file_id: &str, dest_path: &Path) -> u64 {
+    let recorded = bookshelf::get_downloaded_bytes(app, file_id).unwrap_or(0);
+    let on_disk = std::fs::metadata(dest_path).map(|m| m.len()).unwrap_or(0);
+
+    // Only trust bytes that were both recorded and actually written
+    if recorded > 0 && on_disk >= recorded {
+        recorded
+    } else {
+        0
+    }
+}
+
 /// Inner download function that does the actual work
 async fn download_file_inner(
     app: &AppHandle,
@@ -303,30 +1092,66 @@ async fn download_file_inner(
     dest_path: &Path,
     cancel_flag: &Arc<AtomicBool>,
 ) -> Result<(), PedaruError> {
-    let access_token = get_valid_access_token(app).await?;
-    let client = Client::new();
+    let account_id = bookshelf::get_item_account(app, file_id)?;
+    network::ensure_online(DRIVE_SERVICE)?;
+    let access_token = access_token_for(app, account_id, DriveAccess::Content).await?;
+    let client = network::http_client()?;
 
     // Check for cancellation before starting
     if cancel_flag.load(Ordering::SeqCst) {
         return Ok(());
     }
 
-    // Get file metadata first to know total size
-    let total_bytes = get_file_size(&client, &access_token, file_id).await?;
+    // Get file metadata first to know total size and checksum
+    let metadata = get_file_metadata(&client, &access_token, file_id).await?;
+    let total_bytes = metadata.size;
+    let export = is_exportable(&metadata.mime_type);
 
     // Check for cancellation before downloading
     if cancel_flag.load(Ordering::SeqCst) {
         return Ok(());
     }
 
-    // Download the file content
-    let response = client
-        .get(format!("{}/files/{}", DRIVE_API_BASE, file_id))
-        .bearer_auth(&access_token)
-        .query(&[("alt", "media")])
-        .send()
-        .await
-        .map_err(|e| PedaruError::GoogleDrive(GoogleDriveError::DownloadFailed(e.to_string())))?;
+    let partial_path = bookshelf::partial_download_path(dest_path);
+    let mut offset = if export {
+        0
+    } else {
+        resume_offset(app, file_id, &partial_path)
+    };
+    if total_bytes > 0 && offset >= total_bytes {
+        // Recorded progress is stale (e.g. the file changed on Drive), start over
+        offset = 0;
+    }
+
+    // Download the file content (from the resume offset if we have one)
+    if offset > 0 {
+        log::debug!("Resuming download of {} from byte {}", file_id, offset);
+    }
+    let response = send_drive_request(
+        || {
+            let request = if export {
+                client
+                    .get(format!("{}/files/{}/export", DRIVE_API_BASE, file_id))
+                    .query(&[("mimeType", PDF_MIME_TYPE)])
+            } else {
+                client
+                    .get(format!("{}/files/{}", DRIVE_API_BASE, file_id))
+                    .query(&[("alt", "media"), SUPPORTS_ALL_DRIVES])
+            }
+            .bearer_auth(&access_token);
+            if offset > 0 {
+                request.header(reqwest::header::RANGE, format!("bytes={}-", offset))
+            } else {
+                request
+            }
+        },
+        |e| {
+            network::network_error(DRIVE_SERVICE, &e).unwrap_or_else(|| {
+                PedaruError::GoogleDrive(GoogleDriveError::DownloadFailed(e.to_string()))
+            })
+        },
+    )
+    .await?;
 
     if !response.status().is_success() {
         let status = response.status();
@@ -336,9 +1161,24 @@ async fn download_file_inner(
             )));
         }
         let error_text = response.text().await.unwrap_or_default();
-        return Err(PedaruError::GoogleDrive(GoogleDriveError::DownloadFailed(
-            error_text,
-        )));
+        if status.as_u16() == 416 {
+            // Range not satisfiable: forget the partial data so the retry starts fresh
+            let _ = bookshelf::update_downloaded_bytes(app, file_id, 0);
+            return Err(PedaruError::GoogleDrive(GoogleDriveError::DownloadFailed(
+                error_text,
+            )));
+        }
+        return Err(PedaruError::GoogleDrive(
+            GoogleDriveError::ApiRequestFailed {
+                status: Some(status.as_u16()),
+                message: format!("Download failed ({}): {}", status, error_text),
+            },
+        ));
+    }
+
+    // A 200 means the server ignored the Range header and sent the whole file
+    if offset > 0 && response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+        offset = 0;
     }
 
     // Create parent directories if needed
@@ -352,65 +1192,196 @@ async fn download_file_inner(
     }
 
     // Stream response to file with progress updates
-    stream_to_file(app, file_id, dest_path, response, total_bytes, cancel_flag).await
+    let downloaded = stream_to_file(
+        app,
+        file_id,
+        &partial_path,
+        response,
+        offset,
+        total_bytes,
+        cancel_flag,
+    )
+    .await?;
+
+    if cancel_flag.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    // Size check to catch corruption from a bad resume
+    if total_bytes > 0 && downloaded != total_bytes {
+        let _ = std::fs::remove_file(&partial_path);
+        let _ = bookshelf::update_downloaded_bytes(app, file_id, 0);
+        return Err(PedaruError::GoogleDrive(
+            GoogleDriveError::IntegrityCheckFailed(format!(
+                "Size mismatch: expected {} bytes, got {}",
+                total_bytes, downloaded
+            )),
+        ));
+    }
+
+    if let Some(expected) = metadata.md5_checksum.clone() {
+        let path = partial_path.clone();
+        let actual = tokio::task::spawn_blocking(move || bookshelf::compute_file_md5(&path))
+            .await
+            .map_err(|e| GoogleDriveError::DownloadFailed(e.to_string()))?
+            .map_err(|source| IoError::ReadFailed {
+                path: partial_path.display().to_string(),
+                source,
+            })?;
+        if !actual.eq_ignore_ascii_case(&expected) {
+            let _ = std::fs::remove_file(&partial_path);
+            let _ = bookshelf::update_downloaded_bytes(app, file_id, 0);
+            return Err(PedaruError::GoogleDrive(
+                GoogleDriveError::IntegrityCheckFailed(format!(
+                    "Checksum mismatch: expected {}, got {}",
+                    expected, actual
+                )),
+            ));
+        }
+    }
+
+    // Only a complete, verified file ever appears at the final path
+    std::fs::rename(&partial_path, dest_path).map_err(|source| IoError::WriteFailed {
+        path: dest_path.display().to_string(),
+        source,
+    })?;
+
+    // Remember Drive's checksum so the file can be verified later
+    bookshelf::update_md5_checksum(app, file_id, metadata.md5_checksum.as_deref())?;
+
+    emit_download_completed(app, file_id, downloaded);
+    Ok(())
 }
 
-/// Get file size from Google Drive API
-async fn get_file_size(
+/// Type, size and checksum of a Drive file
+struct RemoteFileMetadata {
+    mime_type: String,
+    size: u64,
+    md5_checksum: Option<String>,
+}
+
+/// Get file type, size and MD5 checksum from Google Drive API
+///
+/// Google-native files have no size or checksum; size is then 0.
+async fn get_file_metadata(
     client: &Client,
     access_token: &str,
     file_id: &str,
-) -> Result<u64, PedaruError> {
-    let response = client
-        .get(format!("{}/files/{}", DRIVE_API_BASE, file_id))
-        .bearer_auth(access_token)
-        .query(&[("fields", "size")])
-        .send()
-        .await
-        .map_err(|e| PedaruError::GoogleDrive(GoogleDriveError::ApiRequestFailed(e.to_string())))?;
+) -> Result<RemoteFileMetadata, PedaruError> {
+    let response = send_drive_request(
+        || {
+            client
+                .get(format!("{}/files/{}", DRIVE_API_BASE, file_id))
+                .bearer_auth(access_token)
+                .query(&[("fields", "mimeType,size,md5Checksum"), SUPPORTS_ALL_DRIVES])
+        },
+        request_failed,
+    )
+    .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        if status.as_u16() == 404 {
+            return Err(PedaruError::GoogleDrive(GoogleDriveError::FileNotFound(
+                file_id.to_string(),
+            )));
+        }
+        let error_text = response.text().await.unwrap_or_default();
+        return Err(PedaruError::GoogleDrive(
+            GoogleDriveError::ApiRequestFailed {
+                status: Some(status.as_u16()),
+                message: format!("File metadata request failed ({}): {}", status, error_text),
+            },
+        ));
+    }
 
     #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
     struct FileMetadata {
+        mime_type: String,
         size: Option<String>,
+        md5_checksum: Option<String>,
     }
 
     let metadata: FileMetadata = response
         .json()
         .await
-        .map_err(|e| PedaruError::GoogleDrive(GoogleDriveError::ApiRequestFailed(e.to_string())))?;
+        .map_err(|e| PedaruError::GoogleDrive(GoogleDriveError::InvalidResponse(e.to_string())))?;
 
-    Ok(metadata.size.and_then(|s| s.parse().ok()).unwrap_or(0))
+    Ok(RemoteFileMetadata {
+        mime_type: metadata.mime_type,
+        size: metadata.size.and_then(|s| s.parse().ok()).unwrap_or(0),
+        md5_checksum: metadata.md5_checksum,
+    })
 }
 
 /// Stream response body to file with progress updates
+///
+/// When `offset` is non-zero the body is appended to the existing partial
+/// file. Returns the total number of bytes in the file once streaming stops.
 async fn stream_to_file(
     app: &AppHandle,
     file_id: &str,
     dest_path: &Path,
     response: reqwest::Response,
+    offset: u64,
     total_bytes: u64,
     cancel_flag: &Arc<AtomicBool>,
-) -> Result<(), PedaruError> {
-    let mut file = File::create(dest_path).map_err(|e| {
+) -> Result<u64, PedaruError> {
+    let file = if offset > 0 {
+        OpenOptions::new()
+            .append(true)
+            .open(dest_path)
+            .and_then(|f| {
+                // Drop any bytes written after the last recorded offset
+                f.set_len(offset)?;
+                Ok(f)
+            })
+    } else {
+        File::create(dest_path)
+    };
+    let mut file = file.map_err(|e| {
         PedaruError::Io(IoError::ReadFailed {
             path: dest_path.display().to_string(),
             source: e,
         })
     })?;
 
-    let mut downloaded: u64 = 0;
+    let mut downloaded: u64 = offset;
     let mut stream = response.bytes_stream();
-    let mut last_progress_update = std::time::Instant::now();
+    let mut last_offset_save = Instant::now();
+    let mut rate = RateEstimator::new();
+    let mut throttle = ProgressThrottle::new();
+    let now = Instant::now();
+    rate.record(now, downloaded);
+
+    // Show the resumed position right away instead of starting the bar at 0
+    throttle.record(now, progress_percent(downloaded, total_bytes));
+    emit_progress(
+        app,
+        file_id,
+        downloaded,
+        total_bytes,
+        &rate,
+        DownloadStatus::Downloading,
+    );
 
     while let Some(chunk) = stream.next().await {
         // Check for cancellation during download
         if cancel_flag.load(Ordering::SeqCst) {
-            return Ok(());
+            let _ = bookshelf::update_downloaded_bytes(app, file_id, downloaded);
+            return Ok(downloaded);
         }
 
-        let chunk = chunk.map_err(|e| {
-            PedaruError::GoogleDrive(GoogleDriveError::DownloadFailed(e.to_string()))
-        })?;
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                let _ = bookshelf::update_downloaded_bytes(app, file_id, downloaded);
+                return Err(PedaruError::GoogleDrive(GoogleDriveError::DownloadFailed(
+                    e.to_string(),
+                )));
+            }
+        };
 
         file.write_all(&chunk).map_err(|e| {
             PedaruError::Io(IoError::ReadFailed {
@@ -421,34 +1392,570 @@ async fn stream_to_file(
 
         downloaded += chunk.len() as u64;
 
-        // Emit progress events every 100ms
-        if last_progress_update.elapsed() >= std::time::Duration::from_millis(100) {
-            emit_progress(app, file_id, downloaded, total_bytes);
-            last_progress_update = std::time::Instant::now();
+        // Stay under the global download speed limit
+        bookshelf::throttle_download(chunk.len()).await;
+
+        // Emit progress events at most every configured interval
+        let now = Instant::now();
+        let interval = Duration::from_millis(bookshelf::get_download_progress_interval());
+        if throttle.should_emit(now, progress_percent(downloaded, total_bytes), interval) {
+            rate.record(now, downloaded);
+            emit_progress(
+                app,
+                file_id,
+                downloaded,
+                total_bytes,
+                &rate,
+                DownloadStatus::Downloading,
+            );
+        }
+
+        // Record the resume offset every second (survives crashes)
+        if last_offset_save.elapsed() >= Duration::from_secs(1) {
+            let _ = bookshelf::update_downloaded_bytes(app, file_id, downloaded);
+            last_offset_save = Instant::now();
         }
     }
 
-    // Final progress update
-    emit_progress(app, file_id, downloaded, total_bytes);
+    // Final progress update, never throttled; `Completed` is only sent once
+    // the file is verified (see `emit_download_completed`)
+    rate.record(Instant::now(), downloaded);
+    emit_progress(
+        app,
+        file_id,
+        downloaded,
+        total_bytes,
+        &rate,
+        DownloadStatus::Downloading,
+    );
+    let _ = bookshelf::update_downloaded_bytes(app, file_id, downloaded);
 
-    Ok(())
+    Ok(downloaded)
 }
 
-/// Emit download progress event
-fn emit_progress(app: &AppHandle, file_id: &str, downloaded: u64, total_bytes: u64) {
-    let progress = if total_bytes > 0 {
+/// Percentage of `total_bytes` downloaded (0 when the size is unknown)
+fn progress_percent(downloaded: u64, total_bytes: u64) -> f64 {
+    if total_bytes > 0 {
         (downloaded as f64 / total_bytes as f64) * 100.0
     } else {
         0.0
-    };
+    }
+}
+
+/// Emit download progress event
+fn emit_progress(
+    app: &AppHandle,
+    file_id: &str,
+    downloaded: u64,
+    total_bytes: u64,
+    rate: &RateEstimator,
+    status: DownloadStatus,
+) {
+    let bytes_per_second = rate.bytes_per_second();
 
     let _ = app.emit(
         "download-progress",
         DownloadProgress {
             drive_file_id: file_id.to_string(),
-            progress,
+            progress: progress_percent(downloaded, total_bytes),
             downloaded_bytes: downloaded,
             total_bytes,
+            bytes_per_second,
+            eta_seconds: eta_seconds(downloaded, total_bytes, bytes_per_second),
+            status,
+        },
+    );
+}
+
+/// Emit the last progress event of a download whose file passed the size
+/// and checksum checks and was moved to its final path
+fn emit_download_completed(app: &AppHandle, file_id: &str, downloaded: u64) {
+    let _ = app.emit(
+        "download-progress",
+        DownloadProgress {
+            drive_file_id: file_id.to_string(),
+            progress: 100.0,
+            downloaded_bytes: downloaded,
+            total_bytes: downloaded,
+            bytes_per_second: 0.0,
+            eta_seconds: None,
+            status: DownloadStatus::Completed,
         },
     );
 }
+
+/// Emit the last progress event of a cancelled download
+///
+/// The item goes back to pending; its partial data is kept for resuming.
+pub fn emit_download_cancelled(app: &AppHandle, file_id: &str) {
+    let _ = app.emit(
+        "download-progress",
+        DownloadProgress {
+            drive_file_id: file_id.to_string(),
+            progress: 0.0,
+            downloaded_bytes: bookshelf::get_downloaded_bytes(app, file_id).unwrap_or(0),
+            total_bytes: 0,
+            bytes_per_second: 0.0,
+            eta_seconds: None,
+            status: DownloadStatus::Pending,
+        },
+    );
+}
+
+/// Decides which progress updates of a download are sent as events
+///
+/// Events are at least `interval` apart, and beyond that only sent once the
+/// progress moved by `PROGRESS_EVENT_MIN_STEP` or `PROGRESS_EVENT_MAX_GAP`
+/// passed.
+struct ProgressThrottle {
+    /// When the last event was sent and the progress it carried
+    last_emit: Option<(Instant, f64)>,
+}
+
+impl ProgressThrottle {
+    fn new() -> Self {
+        Self { last_emit: None }
+    }
+
+    /// Whether to send an event for `percent` now, recording it if so
+    fn should_emit(&mut self, now: Instant, percent: f64, interval: Duration) -> bool {
+        let emit = self.last_emit.is_none_or(|(at, last_percent)| {
+            let elapsed = now.duration_since(at);
+            elapsed >= interval
+                && (percent - last_percent >= PROGRESS_EVENT_MIN_STEP
+                    || elapsed >= PROGRESS_EVENT_MAX_GAP)
+        });
+        if emit {
+            self.record(now, percent);
+        }
+        emit
+    }
+
+    /// Note an event sent outside `should_emit`
+    fn record(&mut self, now: Instant, percent: f64) {
+        self.last_emit = Some((now, percent));
+    }
+}
+
+/// Download speed averaged over the samples from the last `RATE_WINDOW`
+struct RateEstimator {
+    /// (time, total bytes downloaded) pairs, oldest first
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl RateEstimator {
+    fn new() -> Self {
+        Self {
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Add a sample, dropping ones that fell out of the window
+    fn record(&mut self, now: Instant, downloaded: u64) {
+        self.samples.push_back((now, downloaded));
+        while self.samples.len() > 2
+            && self
+                .samples
+                .front()
+                .is_some_and(|&(t, _)| now.duration_since(t) > RATE_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    fn bytes_per_second(&self) -> f64 {
+        match (self.samples.front(), self.samples.back()) {
+            (Some(&(t0, b0)), Some(&(t1, b1))) if t1 > t0 => {
+                b1.saturating_sub(b0) as f64 / t1.duration_since(t0).as_secs_f64()
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+/// Seconds left at the current speed (None when size or speed is unknown)
+fn eta_seconds(downloaded: u64, total_bytes: u64, bytes_per_second: f64) -> Option<u64> {
+    if total_bytes == 0 || bytes_per_second <= 0.0 {
+        return None;
+    }
+    let remaining = total_bytes.saturating_sub(downloaded);
+    Some((remaining as f64 / bytes_per_second).ceil() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+
+    /// A canned response: status, extra header lines and JSON body
+    type MockResponse = (u16, &'static str, &'static str);
+
+    /// Answer successive requests on a local port with `responses`, returning
+    /// the base URL and a handle yielding the request lines received
+    fn mock_drive(responses: Vec<MockResponse>) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let mut request_lines = Vec::new();
+            for (status, headers, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                request_lines.push(line.trim_end().to_string());
+                // Skip the headers; requests have no body
+                while reader.read_line(&mut line).unwrap() > 0 && !line.ends_with("\r\n\r\n") {}
+                write!(
+                    stream,
+                    "HTTP/1.1 {} Mock\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    headers,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+            request_lines
+        });
+        (base_url, server)
+    }
+
+    #[test]
+    fn test_list_all_files_follows_page_tokens() {
+        let (base_url, server) = mock_drive(vec![
+            (
+                200,
+                "",
+                r#"{"files":[{"id":"1","name":"a"},{"id":"2","name":"b"}],"nextPageToken":"page-2"}"#,
+            ),
+            (200, "", r#"{"files":[{"id":"3","name":"c"}]}"#),
+        ]);
+        let client = Client::builder().no_proxy().build().unwrap();
+
+        let folders: Vec<DriveFolder> = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(async { list_all_files(&client, &base_url, "token", &[("q", "x")]).await })
+            .unwrap();
+
+        let ids: Vec<&str> = folders.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(ids, ["1", "2", "3"]);
+        let requests = server.join().unwrap();
+        assert!(!requests[0].contains("pageToken"));
+        assert!(requests[1].contains("pageToken=page-2"));
+        for request in &requests {
+            assert!(request.contains("supportsAllDrives=true"));
+            assert!(request.contains("includeItemsFromAllDrives=true"));
+        }
+    }
+
+    #[test]
+    fn test_drive_file_from_api_json() {
+        let file: DriveFile = serde_json::from_str(
+            r#"{"id":"1","name":"a.pdf","size":"2048","mimeType":"application/pdf",
+                "modifiedTime":"2024-01-01T00:00:00Z","md5Checksum":"abc"}"#,
+        )
+        .unwrap();
+        assert_eq!(file.size_bytes(), Some(2048));
+        assert_eq!(file.md5_checksum.as_deref(), Some("abc"));
+        assert_eq!(file.thumbnail_link, None);
+        assert_eq!(file.local_name(), "a.pdf");
+
+        let native: DriveFile = serde_json::from_str(
+            r#"{"id":"2","name":"Doc","mimeType":"application/vnd.google-apps.document"}"#,
+        )
+        .unwrap();
+        assert_eq!(native.size_bytes(), None);
+        assert_eq!(native.local_name(), "Doc.pdf");
+    }
+
+    #[test]
+    fn test_drive_corpus_restricts_shared_drive_listings() {
+        assert!(drive_corpus(None).is_empty());
+        assert_eq!(
+            drive_corpus(Some("0AbCdEfGhIjKlUk9PVA")),
+            [("corpora", "drive"), ("driveId", "0AbCdEfGhIjKlUk9PVA")]
+        );
+    }
+
+    const RATE_LIMIT_403: &str = r#"{"error":{"code":403,"errors":[{"domain":"usageLimits","reason":"userRateLimitExceeded"}]}}"#;
+    const PERMISSION_403: &str = r#"{"error":{"code":403,"errors":[{"domain":"global","reason":"insufficientFilePermissions"}]}}"#;
+    const EXPORT_TOO_LARGE_403: &str = r#"{"error":{"code":403,"message":"This file is too large to be exported.","errors":[{"domain":"global","reason":"exportSizeLimitExceeded"}]}}"#;
+
+    fn send_mock_request(base_url: &str) -> Result<reqwest::Response, PedaruError> {
+        let client = Client::builder().no_proxy().build().unwrap();
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(async {
+                send_drive_request(|| client.get(format!("{}/files", base_url)), request_failed)
+                    .await
+            })
+    }
+
+    #[test]
+    fn test_is_rate_limit_body() {
+        assert!(is_rate_limit_body(RATE_LIMIT_403));
+        assert!(is_rate_limit_body(
+            r#"{"error":{"code":403,"details":[{"reason":"RATE_LIMIT_EXCEEDED"}]}}"#
+        ));
+        assert!(!is_rate_limit_body(PERMISSION_403));
+        assert!(!is_rate_limit_body("Forbidden"));
+    }
+
+    #[test]
+    fn test_export_size_limit_body() {
+        assert!(has_error_reason(
+            EXPORT_TOO_LARGE_403,
+            &[EXPORT_SIZE_LIMIT_REASON]
+        ));
+        assert!(!is_rate_limit_body(EXPORT_TOO_LARGE_403));
+        assert!(!has_error_reason(
+            PERMISSION_403,
+            &[EXPORT_SIZE_LIMIT_REASON]
+        ));
+    }
+
+    #[test]
+    fn test_send_drive_request_retries_rate_limits() {
+        let (base_url, server) = mock_drive(vec![
+            (403, "Retry-After: 0\r\n", RATE_LIMIT_403),
+            (429, "Retry-After: 0\r\n", "{}"),
+            (200, "", "{}"),
+        ]);
+
+        let response = send_mock_request(&base_url).unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(server.join().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_send_drive_request_does_not_retry_permission_errors() {
+        let (base_url, server) = mock_drive(vec![(403, "", PERMISSION_403)]);
+
+        let error = send_mock_request(&base_url).unwrap_err();
+        assert_eq!(error.code(), "drive.api_request_failed");
+        assert!(!error.is_retryable());
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_send_drive_request_reports_export_too_large() {
+        let (base_url, server) = mock_drive(vec![(403, "", EXPORT_TOO_LARGE_403)]);
+
+        let error = send_mock_request(&base_url).unwrap_err();
+        assert_eq!(error.code(), "drive.export_too_large");
+        assert!(!error.is_retryable());
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_send_drive_request_gives_up_on_long_retry_after() {
+        let (base_url, server) = mock_drive(vec![(429, "Retry-After: 3600\r\n", "{}")]);
+
+        let error = send_mock_request(&base_url).unwrap_err();
+        assert!(matches!(
+            error,
+            PedaruError::GoogleDrive(GoogleDriveError::RateLimited {
+                retry_after_secs: Some(3600)
+            })
+        ));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_scopes_allow_operation() {
+        let metadata_only = vec![
+            "openid".to_string(),
+            SCOPE_DRIVE_METADATA_READONLY.to_string(),
+        ];
+        assert!(scopes_allow(&metadata_only, DriveAccess::Metadata));
+        assert!(!scopes_allow(&metadata_only, DriveAccess::Content));
+
+        let full = vec![SCOPE_DRIVE.to_string()];
+        assert!(scopes_allow(&full, DriveAccess::Metadata));
+        assert!(scopes_allow(&full, DriveAccess::Content));
+
+        // Scopes never recorded: leave it to Drive
+        assert!(scopes_allow(&[], DriveAccess::Content));
+    }
+
+    #[test]
+    fn test_parse_folder_id_from_urls() {
+        let id = "1AbC-dEf_GhIjKlMnOpQrStUvWxYz0123";
+        for input in [
+            id.to_string(),
+            format!("  {}  ", id),
+            format!("https://drive.google.com/drive/folders/{}", id),
+            format!("https://drive.google.com/drive/folders/{}?usp=sharing", id),
+            format!("https://drive.google.com/drive/u/1/folders/{}/", id),
+            format!("drive.google.com/drive/folders/{}", id),
+            format!("https://drive.google.com/open?id={}", id),
+            format!("https://drive.google.com/folderview?id={}&usp=sharing", id),
+        ] {
+            assert_eq!(parse_folder_id(&input).unwrap(), id, "{}", input);
+        }
+        assert_eq!(parse_folder_id("root").unwrap(), "root");
+
+        // Shared drives: the root folder id is the drive id
+        let drive_id = "0AbCdEfGhIjKlUk9PVA";
+        assert_eq!(parse_folder_id(drive_id).unwrap(), drive_id);
+        assert_eq!(
+            parse_folder_id(&format!(
+                "https://drive.google.com/drive/folders/{}",
+                drive_id
+            ))
+            .unwrap(),
+            drive_id
+        );
+    }
+
+    #[test]
+    fn test_parse_folder_id_rejects_garbage() {
+        for input in [
+            "",
+            "short",
+            "has spaces in the folder id",
+            "https://example.com/drive/folders/1AbC-dEf_GhIjKlMnOpQr",
+            "https://drive.google.com/drive/my-drive",
+            "https://drive.google.com/drive/folders/not*valid*id*chars",
+        ] {
+            let err = parse_folder_id(input).unwrap_err();
+            assert_eq!(err.code(), "drive.invalid_folder_id", "{}", input);
+        }
+    }
+
+    fn change(file_id: &str, mime_type: &str, parents: &[&str], trashed: bool) -> DriveChange {
+        DriveChange {
+            file_id: Some(file_id.to_string()),
+            removed: false,
+            file: Some(ChangedFile {
+                file: DriveFile {
+                    id: file_id.to_string(),
+                    name: format!("{}.pdf", file_id),
+                    size: None,
+                    mime_type: mime_type.to_string(),
+                    modified_time: None,
+                    thumbnail_link: None,
+                    md5_checksum: None,
+                },
+                parents: parents.iter().map(|p| p.to_string()).collect(),
+                trashed,
+            }),
+        }
+    }
+
+    fn tree() -> HashMap<String, String> {
+        HashMap::from([
+            ("root".to_string(), String::new()),
+            ("sub".to_string(), "Chapter 1".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_match_changes_upserts_and_removals() {
+        let deleted = DriveChange {
+            file_id: Some("deleted".to_string()),
+            removed: true,
+            file: None,
+        };
+        let changes = [
+            change("new", PDF_MIME_TYPE, &["sub"], false),
+            change("moved-out", PDF_MIME_TYPE, &["elsewhere"], false),
+            change("trashed", PDF_MIME_TYPE, &["root"], true),
+            change("notes", "text/plain", &["root"], false),
+            deleted,
+        ];
+
+        let matched = match_changes(&changes, "root", &tree(), true).unwrap();
+        assert_eq!(matched.upserts.len(), 2);
+        assert_eq!(matched.upserts[0].file.id, "new");
+        assert_eq!(matched.upserts[0].parent_id, "sub");
+        assert_eq!(matched.upserts[0].folder_path, "Chapter 1");
+        // Other types are left for sync's type filter
+        assert_eq!(matched.upserts[1].file.id, "notes");
+        assert_eq!(matched.removed, ["trashed", "deleted"]);
+        assert_eq!(matched.moved_out.len(), 1);
+        assert_eq!(matched.moved_out[0].file.id, "moved-out");
+        assert_eq!(matched.moved_out[0].parents, ["elsewhere"]);
+    }
+
+    #[test]
+    fn test_match_changes_needs_full_listing_when_tree_changes() {
+        // A new subfolder, a renamed subfolder and a deleted subfolder
+        let new_folder = change("new-sub", FOLDER_MIME_TYPE, &["root"], false);
+        let renamed = change("sub", FOLDER_MIME_TYPE, &["root"], false);
+        let deleted = DriveChange {
+            file_id: Some("sub".to_string()),
+            removed: true,
+            file: None,
+        };
+        for folder_change in [new_folder, renamed, deleted] {
+            assert!(
+                match_changes(std::slice::from_ref(&folder_change), "root", &tree(), true)
+                    .is_none()
+            );
+            // Non-recursive folders don't care about subfolders
+            assert!(match_changes(&[folder_change], "root", &tree(), false).is_some());
+        }
+
+        // Renaming the watched folder itself doesn't change any paths
+        let root_renamed = change("root", FOLDER_MIME_TYPE, &["my-drive"], false);
+        assert!(match_changes(&[root_renamed], "root", &tree(), true).is_some());
+    }
+
+    #[test]
+    fn test_rate_estimator_averages_over_window() {
+        let start = Instant::now();
+        let mut rate = RateEstimator::new();
+        rate.record(start, 0);
+        rate.record(start + Duration::from_secs(1), 1000);
+        rate.record(start + Duration::from_secs(2), 3000);
+        assert_eq!(rate.bytes_per_second(), 1500.0);
+
+        // Samples older than the window are dropped
+        rate.record(start + Duration::from_secs(5), 6000);
+        rate.record(start + Duration::from_secs(6), 7000);
+        assert_eq!(rate.bytes_per_second(), 1000.0);
+    }
+
+    #[test]
+    fn test_rate_estimator_single_sample() {
+        let mut rate = RateEstimator::new();
+        rate.record(Instant::now(), 500);
+        assert_eq!(rate.bytes_per_second(), 0.0);
+    }
+
+    #[test]
+    fn test_progress_throttle_limits_event_rate() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let interval = Duration::from_millis(100);
+        let mut throttle = ProgressThrottle::new();
+
+        assert!(throttle.should_emit(at(0), 0.0, interval));
+        // Too soon, however far the download got
+        assert!(!throttle.should_emit(at(50), 40.0, interval));
+        assert!(throttle.should_emit(at(100), 40.0, interval));
+        // Interval passed but less than a percent moved
+        assert!(!throttle.should_emit(at(300), 40.5, interval));
+        assert!(throttle.should_emit(at(400), 41.0, interval));
+        // Slow downloads still get an event every PROGRESS_EVENT_MAX_GAP
+        assert!(throttle.should_emit(at(1400), 41.2, interval));
+
+        // A longer interval spaces events out further
+        let interval = Duration::from_millis(500);
+        assert!(!throttle.should_emit(at(1800), 60.0, interval));
+        assert!(throttle.should_emit(at(1900), 60.0, interval));
+    }
+
+    #[test]
+    fn test_eta_seconds() {
+        assert_eq!(eta_seconds(500, 1000, 100.0), Some(5));
+        assert_eq!(eta_seconds(500, 1000, 300.0), Some(2));
+        assert_eq!(eta_seconds(500, 0, 100.0), None);
+        assert_eq!(eta_seconds(500, 1000, 0.0), None);
+    }
+}
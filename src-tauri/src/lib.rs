@@ -7,23 +7,37 @@ use lopdf::Document;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use tauri::Emitter;
+use tauri::Listener;
 use tauri::Manager;
-use tauri_plugin_sql::Builder as SqlBuilder;
 
 // Module declarations
+pub mod annotations;
+pub mod anthropic;
 pub mod bookshelf;
+pub mod config_bundle;
 pub mod db;
-mod db_schema;
+pub mod diagnostics;
 pub mod encoding;
 pub mod error;
 pub mod gemini;
 pub mod google_drive;
+pub mod logging;
 pub mod menu;
+pub mod migrations;
+pub mod network;
 pub mod oauth;
+pub mod openai;
 pub mod pdf;
+pub mod search;
 pub mod secrets;
+pub mod secrets_file;
 pub mod session;
 pub mod settings;
+pub mod sync;
+pub mod thumbnail;
+pub mod translation;
+pub mod translation_cache;
+pub mod translation_stream;
 pub mod types;
 
 // Re-export public types
@@ -31,20 +45,20 @@ pub use types::{PdfInfo, RecentFile, TocEntry};
 
 // Re-export functions for use in commands
 use encoding::decode_pdf_string;
-use error::{IntoTauriError, IoError, MenuError, PdfError};
+use error::{CommandError, IntoTauriError, IoError, MenuError, PdfError};
 use menu::{build_app_menu, decode_file_path_from_menu_id};
 use pdf::extract_toc;
 
 /// Internal implementation of get_pdf_info with typed errors
 fn get_pdf_info_impl(path: &str) -> error::Result<PdfInfo> {
-    eprintln!("[Pedaru] get_pdf_info called for: {}", path);
+    log::debug!("get_pdf_info called for: {}", path);
 
     // Load document from file
     let doc = Document::load(path).map_err(|source| PdfError::LoadFailed {
         path: path.to_string(),
         source,
     })?;
-    eprintln!("[Pedaru] PDF loaded successfully");
+    log::debug!("PDF loaded successfully");
 
     let mut title = None;
     let mut author = None;
@@ -70,7 +84,7 @@ fn get_pdf_info_impl(path: &str) -> error::Result<PdfInfo> {
 
 /// Extract PDF information including metadata and table of contents
 #[tauri::command]
-fn get_pdf_info(path: String) -> Result<PdfInfo, String> {
+fn get_pdf_info(path: String) -> Result<PdfInfo, CommandError> {
     get_pdf_info_impl(&path).map_err(|e| e.into_tauri_error())
 }
 
@@ -88,7 +102,7 @@ fn read_pdf_file_impl(path: &str) -> error::Result<Vec<u8>> {
 ///
 /// Returns the original file bytes - decryption is handled by pdf.js on the frontend.
 #[tauri::command]
-fn read_pdf_file(path: String) -> Result<Vec<u8>, String> {
+fn read_pdf_file(path: String) -> Result<Vec<u8>, CommandError> {
     read_pdf_file_impl(&path).map_err(|e| e.into_tauri_error())
 }
 
@@ -124,18 +138,18 @@ fn refresh_recent_menu_impl(_app: &tauri::AppHandle) -> error::Result<()> {
     // Windows and Linux use custom TitleBar component
     #[cfg(target_os = "macos")]
     {
-        eprintln!("[Pedaru] Refreshing recent files menu");
+        log::debug!("Refreshing recent files menu");
         let menu = build_app_menu(_app)?;
         _app.set_menu(menu)
             .map_err(|e| MenuError::SetMenuFailed(e.to_string()))?;
-        eprintln!("[Pedaru] Recent files menu refreshed successfully");
+        log::debug!("Recent files menu refreshed successfully");
     }
     Ok(())
 }
 
 /// Refresh the recent files menu
 #[tauri::command]
-fn refresh_recent_menu(app: tauri::AppHandle) -> Result<(), String> {
+fn refresh_recent_menu(app: tauri::AppHandle) -> Result<(), CommandError> {
     refresh_recent_menu_impl(&app).map_err(|e| e.into_tauri_error())
 }
 
@@ -144,53 +158,99 @@ fn refresh_recent_menu(app: tauri::AppHandle) -> Result<(), String> {
 // ============================================================================
 
 /// Save OAuth credentials
+///
+/// `strict` (default true) rejects client ids that don't look like Google's.
 #[tauri::command(rename_all = "camelCase")]
 fn save_oauth_credentials(
     app: tauri::AppHandle,
     client_id: String,
     client_secret: String,
-) -> Result<(), String> {
+    strict: Option<bool>,
+) -> Result<(), CommandError> {
     oauth::save_credentials(
         &app,
         &oauth::OAuthCredentials {
             client_id,
             client_secret,
         },
+        strict.unwrap_or(true),
     )
     .map_err(|e| e.into_tauri_error())
 }
 
 /// Get OAuth credentials
 #[tauri::command]
-fn get_oauth_credentials(app: tauri::AppHandle) -> Result<Option<oauth::OAuthCredentials>, String> {
+fn get_oauth_credentials(
+    app: tauri::AppHandle,
+) -> Result<Option<oauth::OAuthCredentials>, CommandError> {
     oauth::load_credentials(&app).map_err(|e| e.into_tauri_error())
 }
 
-/// Start Google OAuth flow
-#[tauri::command]
-fn start_google_auth(app: tauri::AppHandle) -> Result<String, String> {
-    oauth::start_auth_flow(&app).map_err(|e| e.into_tauri_error())
+/// Start Google OAuth flow (optionally requesting non-default scopes)
+///
+/// `account_label` connects an additional account (created if new); without
+/// it the default account is (re)connected.
+#[tauri::command(rename_all = "camelCase")]
+fn start_google_auth(
+    app: tauri::AppHandle,
+    scopes: Option<String>,
+    account_label: Option<String>,
+) -> Result<String, CommandError> {
+    oauth::start_auth_flow(&app, scopes.as_deref(), account_label.as_deref())
+        .map_err(|e| e.into_tauri_error())
+}
+
+/// Start a fresh sign-in for an account whose tokens Google rejected,
+/// keeping its folders and downloads (defaults to the default account)
+///
+/// `required_scope` is the `requiredScope` of a `drive.insufficient_scope`
+/// error, requested on top of the scopes the account already had.
+#[tauri::command(rename_all = "camelCase")]
+fn reauthenticate_google(
+    app: tauri::AppHandle,
+    account_id: Option<i64>,
+    required_scope: Option<String>,
+) -> Result<String, CommandError> {
+    oauth::reauthenticate(
+        &app,
+        account_id.unwrap_or(oauth::DEFAULT_ACCOUNT_ID),
+        required_scope.as_deref(),
+    )
+    .map_err(|e| e.into_tauri_error())
 }
 
 /// Get Google authentication status
 #[tauri::command]
-fn get_google_auth_status(app: tauri::AppHandle) -> Result<oauth::AuthStatus, String> {
+fn get_google_auth_status(app: tauri::AppHandle) -> Result<oauth::AuthStatus, CommandError> {
     oauth::get_auth_status(&app).map_err(|e| e.into_tauri_error())
 }
 
-/// Logout from Google
-#[tauri::command]
-fn logout_google(app: tauri::AppHandle) -> Result<(), String> {
-    oauth::clear_tokens(&app).map_err(|e| e.into_tauri_error())
+/// Logout a Google account (revokes tokens before clearing them)
+#[tauri::command(rename_all = "camelCase")]
+async fn logout_google(app: tauri::AppHandle, account_id: Option<i64>) -> Result<(), CommandError> {
+    oauth::revoke_tokens(&app, account_id.unwrap_or(oauth::DEFAULT_ACCOUNT_ID))
+        .await
+        .map_err(|e| e.into_tauri_error())
+}
+
+/// Sign out of every account and delete stored secrets (and, with
+/// `wipeDownloads`, downloaded PDFs)
+#[tauri::command(rename_all = "camelCase")]
+async fn logout(app: tauri::AppHandle, wipe_downloads: Option<bool>) -> Result<(), CommandError> {
+    oauth::logout(&app, wipe_downloads.unwrap_or(false))
+        .await
+        .map_err(|e| e.into_tauri_error())
 }
 
 /// List folders in Google Drive
 #[tauri::command(rename_all = "camelCase")]
 async fn list_drive_folders(
     app: tauri::AppHandle,
+    account_id: Option<i64>,
     parent_id: Option<String>,
-) -> Result<Vec<google_drive::DriveFolder>, String> {
-    google_drive::list_folders(&app, parent_id.as_deref())
+) -> Result<Vec<google_drive::DriveFolder>, CommandError> {
+    let account_id = account_id.unwrap_or(oauth::DEFAULT_ACCOUNT_ID);
+    google_drive::list_folders(&app, account_id, parent_id.as_deref(), None)
         .await
         .map_err(|e| e.into_tauri_error())
 }
@@ -199,9 +259,11 @@ async fn list_drive_folders(
 #[tauri::command(rename_all = "camelCase")]
 async fn list_drive_items(
     app: tauri::AppHandle,
+    account_id: Option<i64>,
     parent_id: Option<String>,
-) -> Result<Vec<google_drive::DriveItem>, String> {
-    google_drive::list_drive_items(&app, parent_id.as_deref())
+) -> Result<Vec<google_drive::DriveItem>, CommandError> {
+    let account_id = account_id.unwrap_or(oauth::DEFAULT_ACCOUNT_ID);
+    google_drive::list_drive_items(&app, account_id, parent_id.as_deref())
         .await
         .map_err(|e| e.into_tauri_error())
 }
@@ -210,9 +272,11 @@ async fn list_drive_items(
 #[tauri::command(rename_all = "camelCase")]
 fn import_drive_files(
     app: tauri::AppHandle,
+    account_id: Option<i64>,
     files: Vec<google_drive::DriveItem>,
     parent_folder_id: Option<String>,
-) -> Result<i32, String> {
+) -> Result<i32, CommandError> {
+    let account_id = account_id.unwrap_or(oauth::DEFAULT_ACCOUNT_ID);
     let folder_id = parent_folder_id.unwrap_or_else(|| "__imported__".to_string());
     let mut imported_count = 0;
 
@@ -220,150 +284,334 @@ fn import_drive_files(
         if file.is_folder {
             continue; // Skip folders, only import files
         }
-        let file_size: Option<i64> = file.size.as_ref().and_then(|s| s.parse().ok());
-        bookshelf::upsert_item(
-            &app,
-            &file.id,
-            &folder_id,
-            &file.name,
-            file_size,
-            &file.mime_type,
-            file.modified_time.as_deref(),
-        )
-        .map_err(|e| e.into_tauri_error())?;
+        bookshelf::upsert_item(&app, account_id, &folder_id, &file.into())
+            .map_err(|e| e.into_tauri_error())?;
         imported_count += 1;
     }
 
     Ok(imported_count)
 }
 
-/// Add a folder to sync list
+/// Add a folder to sync list (`recursive` also syncs its subfolders)
+///
+/// `folder_id` may also be a pasted Drive folder URL. Without `folder_name`
+/// the folder's Drive name is used, fetched after sign-in if need be.
 #[tauri::command(rename_all = "camelCase")]
-fn add_drive_folder(
+async fn add_drive_folder(
     app: tauri::AppHandle,
+    account_id: Option<i64>,
     folder_id: String,
-    folder_name: String,
-) -> Result<(), String> {
-    bookshelf::add_sync_folder(&app, &folder_id, &folder_name).map_err(|e| e.into_tauri_error())
+    folder_name: Option<String>,
+    recursive: Option<bool>,
+) -> Result<(), CommandError> {
+    let account_id = account_id.unwrap_or(oauth::DEFAULT_ACCOUNT_ID);
+    let folder_id = google_drive::parse_folder_id(&folder_id).map_err(|e| e.into_tauri_error())?;
+    let mut folder_name = folder_name.filter(|name| !name.trim().is_empty());
+    let mut drive_id = None;
+
+    // Check the folder is accessible when signed in (skipped while offline)
+    if oauth::is_authenticated(&app, account_id).map_err(|e| e.into_tauri_error())? {
+        match google_drive::get_folder(&app, account_id, &folder_id).await {
+            Ok(folder) => {
+                folder_name.get_or_insert(folder.name);
+                drive_id = folder.drive_id;
+            }
+            Err(error::PedaruError::Network(e)) => {
+                log::info!("Adding folder {} without checking it: {}", folder_id, e);
+            }
+            Err(e) => return Err(e.into_tauri_error()),
+        }
+    }
+
+    bookshelf::add_sync_folder(
+        &app,
+        account_id,
+        &folder_id,
+        folder_name.as_deref(),
+        recursive.unwrap_or(false),
+        drive_id.as_deref(),
+    )
+    .map_err(|e| e.into_tauri_error())
 }
 
 /// Remove a folder from sync list
 #[tauri::command(rename_all = "camelCase")]
-fn remove_drive_folder(app: tauri::AppHandle, folder_id: String) -> Result<(), String> {
+fn remove_drive_folder(app: tauri::AppHandle, folder_id: String) -> Result<(), CommandError> {
     bookshelf::remove_sync_folder(&app, &folder_id).map_err(|e| e.into_tauri_error())
 }
 
 /// Get all synced folders
 #[tauri::command]
-fn get_drive_folders(app: tauri::AppHandle) -> Result<Vec<bookshelf::StoredFolder>, String> {
+fn get_drive_folders(app: tauri::AppHandle) -> Result<Vec<bookshelf::StoredFolder>, CommandError> {
     bookshelf::get_sync_folders(&app).map_err(|e| e.into_tauri_error())
 }
 
-/// Sync bookshelf with Google Drive
+/// Include or exclude a folder from automatic syncs
+#[tauri::command(rename_all = "camelCase")]
+fn set_drive_folder_auto_sync(
+    app: tauri::AppHandle,
+    folder_id: String,
+    enabled: bool,
+) -> Result<(), CommandError> {
+    bookshelf::set_folder_auto_sync(&app, &folder_id, enabled).map_err(|e| e.into_tauri_error())
+}
+
+/// Check each subsystem for support requests (`live` also tests the Gemini key)
 #[tauri::command]
-async fn sync_bookshelf(app: tauri::AppHandle) -> Result<bookshelf::SyncResult, String> {
-    let folders = bookshelf::get_sync_folders(&app).map_err(|e| e.into_tauri_error())?;
+async fn run_diagnostics(
+    app: tauri::AppHandle,
+    live: Option<bool>,
+) -> diagnostics::DiagnosticsReport {
+    diagnostics::run_diagnostics(&app, live.unwrap_or(false)).await
+}
 
-    let mut new_files = 0i32;
-    let updated_files = 0i32;
+/// Get how many seconds before expiry Google tokens are refreshed
+#[tauri::command]
+fn get_token_refresh_margin() -> i64 {
+    oauth::get_token_refresh_margin()
+}
 
-    for folder in folders {
-        let files = google_drive::list_pdf_files(&app, &folder.folder_id)
-            .await
-            .map_err(|e| e.into_tauri_error())?;
+/// Set how many seconds before expiry Google tokens are refreshed (None = default)
+#[tauri::command]
+fn set_token_refresh_margin(app: tauri::AppHandle, secs: Option<i64>) -> Result<(), CommandError> {
+    oauth::set_token_refresh_margin(&app, secs).map_err(|e| e.into_tauri_error())
+}
 
-        for file in &files {
-            let file_size: Option<i64> = file.size.as_ref().and_then(|s| s.parse().ok());
-            bookshelf::upsert_item(
-                &app,
-                &file.id,
-                &folder.folder_id,
-                &file.name,
-                file_size,
-                &file.mime_type,
-                file.modified_time.as_deref(),
-            )
-            .map_err(|e| e.into_tauri_error())?;
-            new_files += 1;
-        }
+/// Get the HTML pages shown in the browser after signing in
+#[tauri::command]
+fn get_oauth_callback_pages(app: tauri::AppHandle) -> Result<oauth::CallbackPages, CommandError> {
+    oauth::get_callback_pages(&app).map_err(|e| e.into_tauri_error())
+}
 
-        bookshelf::update_folder_sync_time(&app, &folder.folder_id)
-            .map_err(|e| e.into_tauri_error())?;
-    }
+/// Save the HTML pages shown in the browser after signing in (blank = built-in)
+#[tauri::command]
+fn save_oauth_callback_pages(
+    app: tauri::AppHandle,
+    pages: oauth::CallbackPages,
+) -> Result<(), CommandError> {
+    oauth::save_callback_pages(&app, &pages).map_err(|e| e.into_tauri_error())
+}
 
-    // Remove items from folders that are no longer synced (but keep downloaded files)
-    let removed_files =
-        bookshelf::remove_items_from_inactive_folders(&app).map_err(|e| e.into_tauri_error())?;
+/// Get the auto-sync interval in minutes (None = auto-sync off)
+#[tauri::command]
+fn get_auto_sync_interval(app: tauri::AppHandle) -> Result<Option<u64>, CommandError> {
+    sync::get_auto_sync_interval(&app).map_err(|e| e.into_tauri_error())
+}
 
-    Ok(bookshelf::SyncResult {
-        new_files,
-        updated_files,
-        removed_files,
-    })
+/// Set the auto-sync interval in minutes (None or 0 turns auto-sync off)
+#[tauri::command]
+fn set_auto_sync_interval(app: tauri::AppHandle, minutes: Option<u64>) -> Result<(), CommandError> {
+    sync::set_auto_sync_interval(&app, minutes).map_err(|e| e.into_tauri_error())
+}
+
+/// Get the MIME types sync adds to the bookshelf
+#[tauri::command]
+fn get_sync_mime_types(app: tauri::AppHandle) -> Result<Vec<String>, CommandError> {
+    sync::get_sync_mime_types(&app).map_err(|e| e.into_tauri_error())
+}
+
+/// Set the MIME types sync adds to the bookshelf (empty restores PDFs only)
+#[tauri::command]
+fn set_sync_mime_types(
+    app: tauri::AppHandle,
+    mime_types: Vec<String>,
+) -> Result<Vec<String>, CommandError> {
+    sync::set_sync_mime_types(&app, &mime_types).map_err(|e| e.into_tauri_error())
+}
+
+/// Get when each synced folder is next synced automatically
+#[tauri::command]
+fn get_sync_schedule(app: tauri::AppHandle) -> Result<Vec<sync::ScheduledSync>, CommandError> {
+    sync::get_sync_schedule(&app).map_err(|e| e.into_tauri_error())
+}
+
+/// Sync bookshelf with Google Drive
+#[tauri::command]
+async fn sync_bookshelf(app: tauri::AppHandle) -> Result<bookshelf::SyncResult, CommandError> {
+    let folders = bookshelf::get_sync_folders(&app).map_err(|e| e.into_tauri_error())?;
+    sync::sync_folders(&app, folders)
+        .await
+        .map_err(|e| e.into_tauri_error())
 }
 
-/// Get all bookshelf items
+/// Get bookshelf items, optionally filtered or ordered by `query`
 #[tauri::command]
-fn get_bookshelf_items(app: tauri::AppHandle) -> Result<Vec<bookshelf::BookshelfItem>, String> {
+fn get_bookshelf_items(
+    app: tauri::AppHandle,
+    query: Option<bookshelf::ItemQuery>,
+) -> Result<Vec<bookshelf::BookshelfItem>, CommandError> {
     // Verify local files exist before returning items
     // This resets status for items where files are missing
     let _ = bookshelf::verify_local_files(&app);
 
-    bookshelf::get_items(&app).map_err(|e| e.into_tauri_error())
+    bookshelf::get_items_with_query(&app, &query.unwrap_or_default())
+        .map(|page| page.items)
+        .map_err(|e| e.into_tauri_error())
+}
+
+/// Get one sorted page of bookshelf items along with the total count
+#[tauri::command]
+fn get_bookshelf_page(
+    app: tauri::AppHandle,
+    query: bookshelf::ItemQuery,
+) -> Result<bookshelf::ItemPage, CommandError> {
+    let _ = bookshelf::verify_local_files(&app);
+
+    bookshelf::get_items_with_query(&app, &query).map_err(|e| e.into_tauri_error())
 }
 
 /// Download a bookshelf item
+///
+/// `user_initiated` (default true) records the request so the download
+/// resumes after a restart; bulk downloads pass false.
 #[tauri::command(rename_all = "camelCase")]
 async fn download_bookshelf_item(
     app: tauri::AppHandle,
     drive_file_id: String,
     file_name: String,
-) -> Result<String, String> {
-    // Register the download FIRST (before any async work)
-    bookshelf::register_download(&drive_file_id);
+    user_initiated: Option<bool>,
+) -> Result<String, CommandError> {
+    if user_initiated.unwrap_or(true) {
+        bookshelf::set_download_requested(&app, &drive_file_id, true)
+            .map_err(|e| e.into_tauri_error())?;
+    }
+    download_item(app, drive_file_id, file_name).await
+}
 
-    // Update status to downloading
-    bookshelf::update_download_status(&app, &drive_file_id, "downloading", 0.0, None)
+/// Queue, download and index a cloud item
+async fn download_item(
+    app: tauri::AppHandle,
+    drive_file_id: String,
+    file_name: String,
+) -> Result<String, CommandError> {
+    // Wait for a free slot in the download queue
+    bookshelf::update_download_status(&app, &drive_file_id, "queued", 0.0, None, None)
         .map_err(|e| e.into_tauri_error())?;
+    let _slot = match bookshelf::acquire_download_slot(&drive_file_id).await {
+        Ok(slot) => slot,
+        Err(e) => {
+            // Cancelled while still queued
+            bookshelf::update_download_status(
+                &app,
+                &drive_file_id,
+                "pending",
+                0.0,
+                None,
+                Some(bookshelf::DownloadFailure::Cancelled),
+            )
+            .map_err(|e| e.into_tauri_error())?;
+            google_drive::emit_download_cancelled(&app, &drive_file_id);
+            return Err(e.into_tauri_error());
+        }
+    };
 
-    // Get downloads directory
-    let downloads_dir = bookshelf::get_downloads_dir(&app).map_err(|e| {
-        bookshelf::unregister_download(&drive_file_id);
-        e.into_tauri_error()
-    })?;
-    let dest_path = downloads_dir.join(&file_name);
+    let path = run_download(&app, &drive_file_id, &file_name).await?;
+    // Get the next item in the folder ready while this one is read
+    schedule_prefetch(&app, &drive_file_id);
+    Ok(path)
+}
 
-    // Download file
-    let result = google_drive::download_file(&app, &drive_file_id, &dest_path).await;
+/// Download and index a cloud item (the caller holds a download slot)
+async fn run_download(
+    app: &tauri::AppHandle,
+    drive_file_id: &str,
+    file_name: &str,
+) -> Result<String, CommandError> {
+    // Make sure there is room for the file before starting
+    if let Err(e) = bookshelf::check_download_space(app, drive_file_id) {
+        let failure = bookshelf::DownloadFailure::of(&e);
+        let reason = e.into_tauri_error();
+        bookshelf::mark_download_failed(app, drive_file_id, failure, &reason.message)
+            .map_err(|e| e.into_tauri_error())?;
+        return Err(reason);
+    }
+
+    // Register the download (only active downloads are tracked)
+    let cancel_flag = bookshelf::register_download(drive_file_id);
+
+    // Update status to downloading (every early return unregisters, or the
+    // download would count as active for good)
+    bookshelf::update_download_status(app, drive_file_id, "downloading", 0.0, None, None).map_err(
+        |e| {
+            bookshelf::unregister_download(drive_file_id);
+            e.into_tauri_error()
+        },
+    )?;
+
+    // Work out where the file goes
+    let dest_path =
+        bookshelf::reserve_download_path(app, drive_file_id, file_name).map_err(|e| {
+            bookshelf::unregister_download(drive_file_id);
+            e.into_tauri_error()
+        })?;
+
+    // Download file, retrying transient failures
+    let result = bookshelf::download_with_retries(app, drive_file_id, &cancel_flag, || {
+        google_drive::download_file(app, drive_file_id, &dest_path)
+    })
+    .await;
 
     // Unregister the download
-    bookshelf::unregister_download(&drive_file_id);
+    bookshelf::unregister_download(drive_file_id);
 
     match result {
         Ok(()) => {
             let path_str = dest_path.to_string_lossy().to_string();
             bookshelf::update_download_status(
-                &app,
-                &drive_file_id,
+                app,
+                drive_file_id,
                 "completed",
                 100.0,
                 Some(&path_str),
+                None,
             )
             .map_err(|e| e.into_tauri_error())?;
+            bookshelf::set_download_requested(app, drive_file_id, false)
+                .map_err(|e| e.into_tauri_error())?;
+
+            // Extract and save PDF metadata; a failure here doesn't fail the download
+            if let Err(e) =
+                bookshelf::extract_and_save_cloud_metadata(app, &path_str, drive_file_id)
+            {
+                log::warn!("Failed to extract metadata for {}: {}", drive_file_id, e);
+            }
 
-            // Extract and save PDF metadata (title and author)
-            let _ = bookshelf::extract_and_save_pdf_metadata(&app, &path_str, &drive_file_id);
+            // Make room under the storage cap, keeping the file just downloaded
+            if let Err(e) = bookshelf::enforce_storage_cap(app, Some(drive_file_id)) {
+                log::warn!("Failed to enforce storage cap: {}", e);
+            }
+
+            // Render a thumbnail and add the document to the full-text search
+            // index in the background
+            let index_app = app.clone();
+            let index_file_id = drive_file_id.to_string();
+            tauri::async_runtime::spawn_blocking(move || {
+                if let Err(e) = thumbnail::generate_cloud_thumbnail(&index_app, &index_file_id) {
+                    log::warn!("Failed to generate thumbnail for {}: {}", index_file_id, e);
+                }
+                if let Err(e) = search::index_document(&index_app, &index_file_id) {
+                    log::warn!("Failed to index {} for search: {}", index_file_id, e);
+                }
+            });
 
             Ok(path_str)
         }
         Err(e) => {
-            // Check if it was cancelled
+            let failure = bookshelf::DownloadFailure::of(&e);
             let error_str = e.into_tauri_error();
-            if error_str.contains("cancelled") {
-                bookshelf::update_download_status(&app, &drive_file_id, "pending", 0.0, None)
-                    .map_err(|e| e.into_tauri_error())?;
+            if failure == bookshelf::DownloadFailure::Cancelled {
+                bookshelf::update_download_status(
+                    app,
+                    drive_file_id,
+                    "pending",
+                    0.0,
+                    None,
+                    Some(failure),
+                )
+                .map_err(|e| e.into_tauri_error())?;
+                google_drive::emit_download_cancelled(app, drive_file_id);
             } else {
-                bookshelf::update_download_status(&app, &drive_file_id, "error", 0.0, None)
+                bookshelf::mark_download_failed(app, drive_file_id, failure, &error_str.message)
                     .map_err(|e| e.into_tauri_error())?;
             }
             Err(error_str)
@@ -371,26 +619,219 @@ async fn download_bookshelf_item(
     }
 }
 
+/// Download the item after `drive_file_id` in its folder at low priority
+///
+/// Does nothing unless prefetching is on, and skips items that wouldn't fit
+/// under the storage cap. The prefetch waits behind every other download
+/// and can be cancelled like one.
+fn schedule_prefetch(app: &tauri::AppHandle, drive_file_id: &str) {
+    if !bookshelf::is_prefetch_enabled() {
+        return;
+    }
+    let candidate = match bookshelf::next_prefetch_candidate(app, drive_file_id) {
+        Ok(Some(candidate)) => candidate,
+        Ok(None) => return,
+        Err(e) => {
+            log::warn!("Failed to find the next item to prefetch: {}", e);
+            return;
+        }
+    };
+    match bookshelf::prefetch_fits_storage_cap(app, &candidate) {
+        Ok(true) => {}
+        Ok(false) => {
+            log::debug!(
+                "Not prefetching {}: it would exceed the storage cap",
+                candidate.drive_file_id
+            );
+            return;
+        }
+        Err(e) => {
+            log::warn!("Failed to check storage before prefetching: {}", e);
+            return;
+        }
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let file_id = &candidate.drive_file_id;
+        // Dropped from the queue when cancelled or requested directly
+        let Ok(_slot) = bookshelf::acquire_prefetch_slot(file_id).await else {
+            return;
+        };
+        log::info!("Prefetching {}", file_id);
+        bookshelf::register_prefetch(file_id);
+        let result = run_download(&app, file_id, &candidate.file_name).await;
+        bookshelf::unregister_prefetch(file_id);
+        if let Err(e) = result {
+            log::info!("Prefetch of {} stopped: {}", file_id, e.message);
+        }
+    });
+}
+
 /// Delete local copy of a bookshelf item
 #[tauri::command(rename_all = "camelCase")]
-fn delete_local_copy(app: tauri::AppHandle, drive_file_id: String) -> Result<(), String> {
+fn delete_local_copy(app: tauri::AppHandle, drive_file_id: String) -> Result<(), CommandError> {
     bookshelf::delete_local_copy(&app, &drive_file_id).map_err(|e| e.into_tauri_error())
 }
 
+/// Delete the local copies of several bookshelf items at once
+#[tauri::command(rename_all = "camelCase")]
+fn delete_local_copies(
+    app: tauri::AppHandle,
+    drive_file_ids: Vec<String>,
+) -> Result<Vec<bookshelf::DeleteResult>, CommandError> {
+    bookshelf::delete_cloud_local_copies(&app, &drive_file_ids).map_err(|e| e.into_tauri_error())
+}
+
 /// Reset download status without deleting the file (for missing files)
 #[tauri::command(rename_all = "camelCase")]
-fn reset_download_status(app: tauri::AppHandle, drive_file_id: String) -> Result<(), String> {
+fn reset_download_status(app: tauri::AppHandle, drive_file_id: String) -> Result<(), CommandError> {
     bookshelf::reset_download_status(&app, &drive_file_id).map_err(|e| e.into_tauri_error())
 }
 
+/// Check downloaded files against Drive's checksums, returning the number corrupted
+#[tauri::command]
+async fn verify_integrity(app: tauri::AppHandle) -> Result<i32, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || bookshelf::verify_cloud_integrity(&app))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.into_tauri_error())
+}
+
+/// Get the download speed limit in bytes per second (0 = unlimited)
+#[tauri::command]
+fn get_max_download_speed() -> u64 {
+    bookshelf::get_max_download_speed()
+}
+
+/// Set the download speed limit shared by all downloads (0 = unlimited)
+#[tauri::command(rename_all = "camelCase")]
+fn set_max_download_speed(app: tauri::AppHandle, bytes_per_sec: u64) -> Result<(), CommandError> {
+    bookshelf::set_max_download_speed(&app, bytes_per_sec).map_err(|e| e.into_tauri_error())
+}
+
+/// Get the template download file names are built from
+#[tauri::command]
+fn get_download_name_template(app: tauri::AppHandle) -> Result<String, CommandError> {
+    bookshelf::get_download_name_template(&app).map_err(|e| e.into_tauri_error())
+}
+
+/// Set the template download file names are built from (blank restores the default)
+#[tauri::command]
+fn set_download_name_template(
+    app: tauri::AppHandle,
+    template: String,
+) -> Result<String, CommandError> {
+    bookshelf::set_download_name_template(&app, &template).map_err(|e| e.into_tauri_error())
+}
+
+/// Get the minimum time between download progress events in milliseconds
+#[tauri::command]
+fn get_download_progress_interval() -> u64 {
+    bookshelf::get_download_progress_interval()
+}
+
+/// Set the minimum time between download progress events in milliseconds
+#[tauri::command]
+fn set_download_progress_interval(app: tauri::AppHandle, ms: u64) -> Result<(), CommandError> {
+    bookshelf::set_download_progress_interval(&app, ms).map_err(|e| e.into_tauri_error())
+}
+
+/// Get the log level ("off", "error", "warn", "info", "debug" or "trace")
+#[tauri::command]
+fn get_log_level() -> String {
+    logging::get_log_level()
+}
+
+/// Set the log level (None restores the default)
+#[tauri::command]
+fn set_log_level(app: tauri::AppHandle, level: Option<String>) -> Result<(), CommandError> {
+    logging::set_log_level(&app, level.as_deref()).map_err(|e| e.into_tauri_error())
+}
+
+/// Get the proxy URL for outbound requests (None = environment variables)
+#[tauri::command]
+fn get_proxy() -> Option<String> {
+    network::get_proxy()
+}
+
+/// Set the proxy URL for outbound requests (None or empty clears it)
+#[tauri::command]
+fn set_proxy(app: tauri::AppHandle, url: Option<String>) -> Result<(), CommandError> {
+    network::set_proxy(&app, url.as_deref()).map_err(|e| e.into_tauri_error())
+}
+
+/// Get the connect and request timeouts for outbound requests
+#[tauri::command]
+fn get_http_timeouts() -> network::HttpTimeouts {
+    network::http_timeouts()
+}
+
+/// Set the connect and request timeouts (clamped), returning those applied
+#[tauri::command]
+fn set_http_timeouts(
+    app: tauri::AppHandle,
+    timeouts: network::HttpTimeouts,
+) -> Result<network::HttpTimeouts, CommandError> {
+    network::set_http_timeouts(&app, timeouts).map_err(|e| e.into_tauri_error())
+}
+
+/// Get the free disk space available for downloads, in bytes
+#[tauri::command]
+fn get_free_space_bytes(app: tauri::AppHandle) -> Result<u64, CommandError> {
+    bookshelf::free_space_bytes(&app).map_err(|e| e.into_tauri_error())
+}
+
+/// Get the storage cap for downloaded PDFs in bytes (None = unlimited)
+#[tauri::command]
+fn get_storage_cap(app: tauri::AppHandle) -> Result<Option<u64>, CommandError> {
+    bookshelf::get_storage_cap(&app).map_err(|e| e.into_tauri_error())
+}
+
+/// Set the storage cap for downloaded PDFs and evict files if now over it
+#[tauri::command(rename_all = "camelCase")]
+fn set_storage_cap(
+    app: tauri::AppHandle,
+    cap_bytes: Option<u64>,
+) -> Result<bookshelf::CleanupResult, CommandError> {
+    bookshelf::set_storage_cap(&app, cap_bytes).map_err(|e| e.into_tauri_error())?;
+    bookshelf::enforce_storage_cap(&app, None).map_err(|e| e.into_tauri_error())
+}
+
+/// Evict least-recently-read downloads until under the storage cap
+#[tauri::command]
+fn cleanup_storage(app: tauri::AppHandle) -> Result<bookshelf::CleanupResult, CommandError> {
+    bookshelf::enforce_storage_cap(&app, None).map_err(|e| e.into_tauri_error())
+}
+
+/// Disk space used by downloads, in total and per watched folder
+#[tauri::command]
+fn get_storage_usage(app: tauri::AppHandle) -> Result<bookshelf::StorageUsage, CommandError> {
+    bookshelf::storage_usage(&app).map_err(|e| e.into_tauri_error())
+}
+
+/// Re-render the thumbnail of a downloaded cloud item from its first page
+#[tauri::command(rename_all = "camelCase")]
+async fn regenerate_thumbnail(
+    app: tauri::AppHandle,
+    drive_file_id: String,
+) -> Result<String, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        thumbnail::generate_cloud_thumbnail(&app, &drive_file_id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.into_tauri_error())
+}
+
 /// Update bookshelf item thumbnail (cloud items)
 #[tauri::command(rename_all = "camelCase")]
 fn update_bookshelf_thumbnail(
     app: tauri::AppHandle,
     drive_file_id: String,
-    thumbnail_data: String,
-) -> Result<(), String> {
-    bookshelf::update_thumbnail(&app, &drive_file_id, &thumbnail_data)
+    thumbnail_data: bookshelf::ThumbnailInput,
+) -> Result<(), CommandError> {
+    bookshelf::update_thumbnail(&app, &drive_file_id, thumbnail_data)
         .map_err(|e| e.into_tauri_error())
 }
 
@@ -400,23 +841,62 @@ fn update_local_thumbnail(
     app: tauri::AppHandle,
     item_id: i64,
     thumbnail_data: String,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     bookshelf::update_local_thumbnail(&app, item_id, &thumbnail_data)
         .map_err(|e| e.into_tauri_error())
 }
 
 /// Cancel an in-progress download
 #[tauri::command(rename_all = "camelCase")]
-fn cancel_bookshelf_download(drive_file_id: String) -> Result<bool, String> {
+fn cancel_bookshelf_download(
+    app: tauri::AppHandle,
+    drive_file_id: String,
+) -> Result<bool, CommandError> {
+    bookshelf::set_download_requested(&app, &drive_file_id, false)
+        .map_err(|e| e.into_tauri_error())?;
     Ok(bookshelf::cancel_download(&drive_file_id))
 }
 
+/// Cancel every running and queued download, returning how many were cancelled
+#[tauri::command]
+fn cancel_all_bookshelf_downloads(app: tauri::AppHandle) -> Result<usize, CommandError> {
+    bookshelf::clear_download_requests(&app).map_err(|e| e.into_tauri_error())?;
+    Ok(bookshelf::cancel_all_downloads())
+}
+
+/// Get the number of downloads waiting in the queue
+#[tauri::command]
+fn get_download_queue_depth() -> usize {
+    bookshelf::get_download_queue_depth()
+}
+
+/// Set how many downloads may run at once
+#[tauri::command(rename_all = "camelCase")]
+fn set_max_concurrent_downloads(
+    app: tauri::AppHandle,
+    max_concurrent: usize,
+) -> Result<(), CommandError> {
+    bookshelf::set_max_concurrent_downloads(&app, max_concurrent).map_err(|e| e.into_tauri_error())
+}
+
+/// Whether the next item in a folder is downloaded ahead of time
+#[tauri::command]
+fn get_prefetch_enabled() -> bool {
+    bookshelf::is_prefetch_enabled()
+}
+
+/// Turn prefetching on or off, returning how many prefetches were cancelled
+#[tauri::command]
+fn set_prefetch_enabled(app: tauri::AppHandle, enabled: bool) -> Result<usize, CommandError> {
+    bookshelf::set_prefetch_enabled(&app, enabled).map_err(|e| e.into_tauri_error())
+}
+
 /// Import local PDF files to bookshelf
 #[tauri::command]
 fn import_local_files(
     app: tauri::AppHandle,
     paths: Vec<String>,
-) -> Result<bookshelf::ImportResult, String> {
+) -> Result<bookshelf::ImportResult, CommandError> {
     let mut imported_count = 0;
     let mut skipped_count = 0;
     let mut error_count = 0;
@@ -433,7 +913,7 @@ fn import_local_files(
                 if error_str.contains("already imported") {
                     skipped_count += 1;
                 } else {
-                    eprintln!("[Pedaru] Failed to import {}: {:?}", path, e);
+                    log::warn!("Failed to import {}: {:?}", path, e);
                     error_count += 1;
                 }
             }
@@ -452,7 +932,7 @@ fn import_local_files(
 fn import_local_directory(
     app: tauri::AppHandle,
     dir_path: String,
-) -> Result<bookshelf::ImportResult, String> {
+) -> Result<bookshelf::ImportResult, CommandError> {
     let result =
         bookshelf::import_local_directory(&app, &dir_path).map_err(|e| e.into_tauri_error())?;
 
@@ -471,7 +951,7 @@ fn import_local_directory(
 
 /// Delete a local item from bookshelf (removes both database entry and copied file)
 #[tauri::command(rename_all = "camelCase")]
-fn delete_bookshelf_item(app: tauri::AppHandle, item_id: i64) -> Result<(), String> {
+fn delete_bookshelf_item(app: tauri::AppHandle, item_id: i64) -> Result<(), CommandError> {
     bookshelf::delete_local_item(&app, item_id).map_err(|e| e.into_tauri_error())
 }
 
@@ -481,23 +961,248 @@ fn toggle_bookshelf_favorite(
     app: tauri::AppHandle,
     item_id: i64,
     is_cloud: bool,
-) -> Result<bool, String> {
+) -> Result<bool, CommandError> {
     bookshelf::toggle_favorite(&app, item_id, is_cloud).map_err(|e| e.into_tauri_error())
 }
 
+/// Set favorite status for a cloud bookshelf item
+#[tauri::command(rename_all = "camelCase")]
+fn set_bookshelf_favorite(
+    app: tauri::AppHandle,
+    drive_file_id: String,
+    is_favorite: bool,
+) -> Result<(), CommandError> {
+    bookshelf::set_cloud_favorite(&app, &drive_file_id, is_favorite)
+        .map_err(|e| e.into_tauri_error())
+}
+
+/// Set or clear (null) the custom display name of a cloud bookshelf item
+#[tauri::command(rename_all = "camelCase")]
+fn set_bookshelf_custom_name(
+    app: tauri::AppHandle,
+    drive_file_id: String,
+    custom_name: Option<String>,
+) -> Result<(), CommandError> {
+    bookshelf::set_custom_name(&app, &drive_file_id, custom_name.as_deref())
+        .map_err(|e| e.into_tauri_error())
+}
+
+/// Add a tag to a cloud bookshelf item
+#[tauri::command(rename_all = "camelCase")]
+fn add_bookshelf_tag(
+    app: tauri::AppHandle,
+    drive_file_id: String,
+    tag: String,
+) -> Result<(), CommandError> {
+    bookshelf::add_tag(&app, &drive_file_id, &tag).map_err(|e| e.into_tauri_error())
+}
+
+/// Remove a tag from a cloud bookshelf item
+#[tauri::command(rename_all = "camelCase")]
+fn remove_bookshelf_tag(
+    app: tauri::AppHandle,
+    drive_file_id: String,
+    tag: String,
+) -> Result<(), CommandError> {
+    bookshelf::remove_tag(&app, &drive_file_id, &tag).map_err(|e| e.into_tauri_error())
+}
+
+/// Get all tags in use on the bookshelf
+#[tauri::command]
+fn get_bookshelf_tags(app: tauri::AppHandle) -> Result<Vec<String>, CommandError> {
+    bookshelf::get_tags(&app).map_err(|e| e.into_tauri_error())
+}
+
 /// Update last_opened timestamp when a PDF is opened from bookshelf
 #[tauri::command(rename_all = "camelCase")]
-fn update_bookshelf_last_opened(app: tauri::AppHandle, local_path: String) -> Result<(), String> {
-    bookshelf::update_last_opened(&app, &local_path).map_err(|e| e.into_tauri_error())
+fn update_bookshelf_last_opened(
+    app: tauri::AppHandle,
+    local_path: String,
+) -> Result<(), CommandError> {
+    bookshelf::update_last_opened(&app, &local_path).map_err(|e| e.into_tauri_error())?;
+    if let Ok(Some(drive_file_id)) = bookshelf::get_cloud_file_id_by_path(&app, &local_path) {
+        schedule_prefetch(&app, &drive_file_id);
+    }
+    Ok(())
+}
+
+/// Search downloaded PDFs for a phrase
+#[tauri::command]
+fn search_pdfs(
+    app: tauri::AppHandle,
+    query: String,
+) -> Result<Vec<search::SearchHit>, CommandError> {
+    search::search_pdfs(&app, &query).map_err(|e| e.into_tauri_error())
+}
+
+/// Export folders, non-secret settings, tags and favorites as a JSON bundle
+#[tauri::command]
+fn export_config(app: tauri::AppHandle) -> Result<String, CommandError> {
+    config_bundle::export_config(&app).map_err(|e| e.into_tauri_error())
+}
+
+/// Import a JSON bundle from `export_config` (existing entries are skipped by default)
+#[tauri::command]
+fn import_config(
+    app: tauri::AppHandle,
+    json: String,
+    mode: Option<config_bundle::ImportMode>,
+) -> Result<config_bundle::ImportSummary, CommandError> {
+    config_bundle::import_config(&app, &json, mode.unwrap_or_default())
+        .map_err(|e| e.into_tauri_error())
+}
+
+/// Probe whether Google's servers are reachable (updates offline mode)
+#[tauri::command]
+async fn check_connectivity() -> bool {
+    network::check_connectivity().await
+}
+
+/// Compact the database (VACUUM), returning the bytes reclaimed
+#[tauri::command]
+async fn compact_database(app: tauri::AppHandle) -> Result<db::CompactResult, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || db::compact_database(&app))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.into_tauri_error())
+}
+
+/// Index all downloaded PDFs for search (unchanged files are skipped)
+#[tauri::command]
+async fn rebuild_search_index(app: tauri::AppHandle) -> Result<search::IndexSummary, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || search::index_all_documents(&app))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.into_tauri_error())
+}
+
+/// Save the reading position for a cloud document
+#[tauri::command(rename_all = "camelCase")]
+fn save_reading_position(
+    app: tauri::AppHandle,
+    drive_file_id: String,
+    page: i64,
+    scroll_offset: f64,
+) -> Result<(), CommandError> {
+    bookshelf::save_reading_position(&app, &drive_file_id, page, scroll_offset)
+        .map_err(|e| e.into_tauri_error())
+}
+
+/// Get the saved reading position for a cloud document
+#[tauri::command(rename_all = "camelCase")]
+fn get_reading_position(
+    app: tauri::AppHandle,
+    drive_file_id: String,
+) -> Result<Option<bookshelf::ReadingPosition>, CommandError> {
+    bookshelf::get_reading_position(&app, &drive_file_id).map_err(|e| e.into_tauri_error())
+}
+
+/// Start recording a reading session for the PDF open at `local_path`,
+/// returning its id (`None` when the file isn't a downloaded cloud item)
+#[tauri::command(rename_all = "camelCase")]
+fn start_reading_session(
+    app: tauri::AppHandle,
+    local_path: String,
+    page: i64,
+) -> Result<Option<i64>, CommandError> {
+    let Some(drive_file_id) = bookshelf::get_cloud_file_id_by_path(&app, &local_path)
+        .map_err(|e| e.into_tauri_error())?
+    else {
+        return Ok(None);
+    };
+    bookshelf::start_reading_session(&app, &drive_file_id, page)
+        .map(Some)
+        .map_err(|e| e.into_tauri_error())
+}
+
+/// Extend a reading session to now (on page changes and when closing)
+#[tauri::command(rename_all = "camelCase")]
+fn update_reading_session(
+    app: tauri::AppHandle,
+    session_id: i64,
+    page: i64,
+) -> Result<(), CommandError> {
+    bookshelf::update_reading_session(&app, session_id, page).map_err(|e| e.into_tauri_error())
+}
+
+/// Get reading statistics across the bookshelf
+#[tauri::command]
+fn get_reading_stats(app: tauri::AppHandle) -> Result<bookshelf::ReadingStats, CommandError> {
+    bookshelf::reading_stats(&app).map_err(|e| e.into_tauri_error())
+}
+
+// ============================================================================
+// Annotation Commands
+// ============================================================================
+
+/// Add a highlight or note to a cloud document
+#[tauri::command(rename_all = "camelCase")]
+fn add_annotation(
+    app: tauri::AppHandle,
+    drive_file_id: String,
+    annotation: annotations::NewAnnotation,
+) -> Result<annotations::Annotation, CommandError> {
+    annotations::add_annotation(&app, &drive_file_id, &annotation).map_err(|e| e.into_tauri_error())
+}
+
+/// Get all annotations on a cloud document
+#[tauri::command(rename_all = "camelCase")]
+fn get_annotations(
+    app: tauri::AppHandle,
+    drive_file_id: String,
+) -> Result<Vec<annotations::Annotation>, CommandError> {
+    annotations::get_annotations(&app, &drive_file_id).map_err(|e| e.into_tauri_error())
+}
+
+/// Change an annotation's area, color and note
+#[tauri::command]
+fn update_annotation(
+    app: tauri::AppHandle,
+    id: i64,
+    rects: Vec<annotations::AnnotationRect>,
+    color: String,
+    note: Option<String>,
+) -> Result<annotations::Annotation, CommandError> {
+    annotations::update_annotation(&app, id, &rects, &color, note.as_deref())
+        .map_err(|e| e.into_tauri_error())
+}
+
+/// Delete an annotation
+#[tauri::command]
+fn delete_annotation(app: tauri::AppHandle, id: i64) -> Result<bool, CommandError> {
+    annotations::delete_annotation(&app, id).map_err(|e| e.into_tauri_error())
+}
+
+/// Export a cloud document's annotations as JSON
+#[tauri::command(rename_all = "camelCase")]
+fn export_annotations(
+    app: tauri::AppHandle,
+    drive_file_id: String,
+) -> Result<String, CommandError> {
+    annotations::export_annotations(&app, &drive_file_id).map_err(|e| e.into_tauri_error())
+}
+
+/// Bake a cloud document's annotations into a copy of its PDF, returning its path
+#[tauri::command(rename_all = "camelCase")]
+async fn export_annotated_pdf(
+    app: tauri::AppHandle,
+    drive_file_id: String,
+) -> Result<String, CommandError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        annotations::export_annotated_pdf(&app, &drive_file_id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.into_tauri_error())
 }
 
 // ============================================================================
-// Gemini Translation Commands
+// Translation Commands
 // ============================================================================
 
 /// Get Gemini settings
 #[tauri::command]
-fn get_gemini_settings(app: tauri::AppHandle) -> Result<settings::GeminiSettings, String> {
+fn get_gemini_settings(app: tauri::AppHandle) -> Result<settings::GeminiSettings, CommandError> {
     settings::get_gemini_settings(&app).map_err(|e| e.into_tauri_error())
 }
 
@@ -506,34 +1211,141 @@ fn get_gemini_settings(app: tauri::AppHandle) -> Result<settings::GeminiSettings
 fn save_gemini_settings(
     app: tauri::AppHandle,
     settings_data: settings::GeminiSettings,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     settings::save_gemini_settings(&app, &settings_data).map_err(|e| e.into_tauri_error())
 }
 
-/// Translate text using Gemini API
+/// Check a Gemini API key and model with a minimal request
+///
+/// Falls back to the saved key/model when not given, so the UI can test
+/// either unsaved input or the current settings.
+#[tauri::command(rename_all = "camelCase")]
+async fn test_gemini_key(
+    app: tauri::AppHandle,
+    api_key: Option<String>,
+    model: Option<String>,
+) -> Result<(), CommandError> {
+    let saved = settings::get_gemini_settings(&app).map_err(|e| e.into_tauri_error())?;
+    let api_key = api_key.unwrap_or(saved.api_key);
+    let model = model.unwrap_or(saved.model);
+
+    gemini::test_gemini_key(&api_key, &model)
+        .await
+        .map_err(|e| e.into_tauri_error())
+}
+
+/// Replace the Gemini API key after checking that the new one works
+#[tauri::command(rename_all = "camelCase")]
+async fn rotate_gemini_key(
+    app: tauri::AppHandle,
+    new_key: String,
+) -> Result<gemini::KeyRotation, CommandError> {
+    gemini::rotate_gemini_key(&app, &new_key)
+        .await
+        .map_err(|e| e.into_tauri_error())
+}
+
+/// List Gemini models usable for translation, for the settings dropdown
+#[tauri::command]
+async fn list_gemini_models(
+    app: tauri::AppHandle,
+) -> Result<Vec<gemini::GeminiModelInfo>, CommandError> {
+    let gemini_settings = settings::get_gemini_settings(&app).map_err(|e| e.into_tauri_error())?;
+    Ok(gemini::list_models(&gemini_settings.api_key).await)
+}
+
+/// Get settings for all translation providers
+#[tauri::command]
+fn get_translation_settings(
+    app: tauri::AppHandle,
+) -> Result<settings::TranslationSettings, CommandError> {
+    settings::get_translation_settings(&app).map_err(|e| e.into_tauri_error())
+}
+
+/// Save settings for all translation providers
+#[tauri::command(rename_all = "camelCase")]
+fn save_translation_settings(
+    app: tauri::AppHandle,
+    settings_data: settings::TranslationSettings,
+) -> Result<(), CommandError> {
+    settings::save_translation_settings(&app, &settings_data).map_err(|e| e.into_tauri_error())
+}
+
+/// Delete all cached translation and explanation results
+#[tauri::command]
+fn clear_translation_cache(app: tauri::AppHandle) -> Result<usize, CommandError> {
+    translation_cache::clear_translation_cache(&app).map_err(|e| e.into_tauri_error())
+}
+
+/// Get the max age of cached translations in seconds (0 = caching disabled)
+#[tauri::command]
+fn get_translation_cache_max_age(app: tauri::AppHandle) -> Result<u64, CommandError> {
+    translation_cache::get_max_age(&app).map_err(|e| e.into_tauri_error())
+}
+
+/// Set the max age of cached translations in seconds (0 disables caching)
+#[tauri::command(rename_all = "camelCase")]
+fn set_translation_cache_max_age(
+    app: tauri::AppHandle,
+    max_age_secs: u64,
+) -> Result<(), CommandError> {
+    translation_cache::set_max_age(&app, max_age_secs).map_err(|e| e.into_tauri_error())
+}
+
+/// Get the Gemini rate limit in requests per minute (0 = unlimited)
+#[tauri::command]
+fn get_gemini_rate_limit() -> u32 {
+    translation::get_gemini_rate_limit()
+}
+
+/// Set the Gemini rate limit in requests per minute (0 = unlimited)
+#[tauri::command(rename_all = "camelCase")]
+fn set_gemini_rate_limit(
+    app: tauri::AppHandle,
+    requests_per_minute: u32,
+) -> Result<(), CommandError> {
+    translation::set_gemini_rate_limit(&app, requests_per_minute).map_err(|e| e.into_tauri_error())
+}
+
+/// Get the target languages offered for translation
+#[tauri::command]
+fn get_supported_languages() -> Vec<translation::SupportedLanguage> {
+    translation::supported_languages().to_vec()
+}
+
+/// Translate text using the active translation provider
+///
+/// A newer request with the same `context` supersedes this one, which then
+/// returns None.
 #[tauri::command(rename_all = "camelCase")]
-async fn translate_with_gemini(
+async fn translate_text(
     app: tauri::AppHandle,
     text: String,
     context_before: String,
     context_after: String,
     model_override: Option<String>,
-) -> Result<gemini::TranslationResponse, String> {
-    let gemini_settings = settings::get_gemini_settings(&app).map_err(|e| e.into_tauri_error())?;
-    let model = model_override.as_deref().unwrap_or(&gemini_settings.model);
+    context: Option<String>,
+) -> Result<Option<translation::TranslationResponse>, CommandError> {
+    let config = settings::get_active_translation_config(&app).map_err(|e| e.into_tauri_error())?;
+    let model = model_override.as_deref().unwrap_or(&config.provider.model);
 
-    gemini::translate_text(
-        &gemini_settings.api_key,
-        model,
+    translation::translate_text(
+        &app,
+        &config.provider_config(model),
+        &config.prompts,
         &text,
         &context_before,
         &context_after,
+        context.as_deref(),
     )
     .await
     .map_err(|e| e.into_tauri_error())
 }
 
 /// Get explanation of text (returns summary + explanation points)
+///
+/// A newer request with the same `context` supersedes this one, which then
+/// returns None.
 #[tauri::command(rename_all = "camelCase")]
 async fn explain_directly(
     app: tauri::AppHandle,
@@ -541,23 +1353,96 @@ async fn explain_directly(
     context_before: String,
     context_after: String,
     model_override: Option<String>,
-) -> Result<gemini::ExplanationResponse, String> {
-    let gemini_settings = settings::get_gemini_settings(&app).map_err(|e| e.into_tauri_error())?;
+    context: Option<String>,
+) -> Result<Option<translation::ExplanationResponse>, CommandError> {
+    let config = settings::get_active_translation_config(&app).map_err(|e| e.into_tauri_error())?;
+    let model = model_override
+        .as_deref()
+        .unwrap_or(&config.provider.explanation_model);
+
+    translation::explain_text(
+        &app,
+        &config.provider_config(model),
+        &config.prompts,
+        &text,
+        &context_before,
+        &context_after,
+        context.as_deref(),
+    )
+    .await
+    .map_err(|e| e.into_tauri_error())
+}
+
+/// Translate text, emitting `translation-chunk` events as output arrives
+///
+/// Returns None if the stream was cancelled or superseded by a newer request
+/// with the same `context`.
+#[tauri::command(rename_all = "camelCase")]
+async fn translate_text_stream(
+    app: tauri::AppHandle,
+    request_id: String,
+    text: String,
+    context_before: String,
+    context_after: String,
+    model_override: Option<String>,
+    context: Option<String>,
+) -> Result<Option<translation::TranslationResponse>, CommandError> {
+    let config = settings::get_active_translation_config(&app).map_err(|e| e.into_tauri_error())?;
+    let model = model_override.as_deref().unwrap_or(&config.provider.model);
+
+    translation::translate_text_stream(
+        &app,
+        &config.provider_config(model),
+        &config.prompts,
+        &text,
+        &context_before,
+        &context_after,
+        &request_id,
+        context.as_deref(),
+    )
+    .await
+    .map_err(|e| e.into_tauri_error())
+}
+
+/// Explain text, emitting `translation-chunk` events as output arrives
+///
+/// Returns None if the stream was cancelled or superseded by a newer request
+/// with the same `context`.
+#[tauri::command(rename_all = "camelCase")]
+async fn explain_text_stream(
+    app: tauri::AppHandle,
+    request_id: String,
+    text: String,
+    context_before: String,
+    context_after: String,
+    model_override: Option<String>,
+    context: Option<String>,
+) -> Result<Option<translation::ExplanationResponse>, CommandError> {
+    let config = settings::get_active_translation_config(&app).map_err(|e| e.into_tauri_error())?;
     let model = model_override
         .as_deref()
-        .unwrap_or(&gemini_settings.explanation_model);
+        .unwrap_or(&config.provider.explanation_model);
 
-    gemini::explain_text(
-        &gemini_settings.api_key,
-        model,
+    translation::explain_text_stream(
+        &app,
+        &config.provider_config(model),
+        &config.prompts,
         &text,
         &context_before,
         &context_after,
+        &request_id,
+        context.as_deref(),
     )
     .await
     .map_err(|e| e.into_tauri_error())
 }
 
+/// Cancel a streaming translation or explanation
+#[tauri::command(rename_all = "camelCase")]
+fn cancel_translation_stream(request_id: String) -> bool {
+    translation::cancel_request(&request_id)
+}
+
 // ============================================================================
 // Session Commands
 // ============================================================================
@@ -568,7 +1453,7 @@ fn save_session(
     app: tauri::AppHandle,
     file_path: String,
     state: types::PdfSessionState,
-) -> Result<(), String> {
+) -> Result<(), CommandError> {
     session::save_session(&app, &file_path, state).map_err(|e| e.into_tauri_error())
 }
 
@@ -577,13 +1462,13 @@ fn save_session(
 fn load_session(
     app: tauri::AppHandle,
     file_path: String,
-) -> Result<Option<types::PdfSessionState>, String> {
+) -> Result<Option<types::PdfSessionState>, CommandError> {
     session::load_session(&app, &file_path).map_err(|e| e.into_tauri_error())
 }
 
 /// Delete a session by file path
 #[tauri::command(rename_all = "camelCase")]
-fn delete_session(app: tauri::AppHandle, file_path: String) -> Result<(), String> {
+fn delete_session(app: tauri::AppHandle, file_path: String) -> Result<(), CommandError> {
     session::delete_session(&app, &file_path).map_err(|e| e.into_tauri_error())
 }
 
@@ -592,7 +1477,7 @@ fn delete_session(app: tauri::AppHandle, file_path: String) -> Result<(), String
 fn get_recent_files(
     app: tauri::AppHandle,
     limit: Option<i32>,
-) -> Result<Vec<types::RecentFileInfo>, String> {
+) -> Result<Vec<types::RecentFileInfo>, CommandError> {
     session::get_recent_files(&app, limit.unwrap_or(10)).map_err(|e| e.into_tauri_error())
 }
 
@@ -691,13 +1576,13 @@ fn handle_menu_event(app: &tauri::AppHandle, event_id: &str) {
 /// Handle macOS file open events (when a PDF is opened while app is running)
 #[cfg(target_os = "macos")]
 fn handle_opened_event(app: &tauri::AppHandle, urls: &[tauri::Url]) {
-    eprintln!("[Pedaru] Received Opened event with {} urls", urls.len());
+    log::debug!("Received Opened event with {} urls", urls.len());
 
     for url in urls {
-        eprintln!("[Pedaru] URL: {:?}", url);
+        log::debug!("URL: {:?}", url);
         if let Ok(path) = url.to_file_path() {
             let path_str: String = path.to_string_lossy().to_string();
-            eprintln!("[Pedaru] File path: {}", path_str);
+            log::debug!("File path: {}", path_str);
             if path_str.to_lowercase().ends_with(".pdf") {
                 // Check if this is the initial startup (OPENED_VIA_EVENT is false)
                 // If so, store in PENDING_FILE for main window to load
@@ -706,10 +1591,7 @@ fn handle_opened_event(app: &tauri::AppHandle, urls: &[tauri::Url]) {
 
                 if !was_already_opened {
                     // First file open during startup - let main window handle it
-                    eprintln!(
-                        "[Pedaru] Initial startup, storing in PENDING_FILE: {}",
-                        path_str
-                    );
+                    log::debug!("Initial startup, storing in PENDING_FILE: {}", path_str);
                     let pending = get_pending_file();
                     *pending
                         .lock()
@@ -727,9 +1609,10 @@ fn handle_opened_event(app: &tauri::AppHandle, urls: &[tauri::Url]) {
                             .as_millis()
                     );
 
-                    eprintln!(
-                        "[Pedaru] Creating new window: {} with URL: {}",
-                        window_label, window_url
+                    log::debug!(
+                        "Creating new window: {} with URL: {}",
+                        window_label,
+                        window_url
                     );
 
                     let file_name = path
@@ -747,7 +1630,7 @@ fn handle_opened_event(app: &tauri::AppHandle, urls: &[tauri::Url]) {
                     .min_inner_size(800.0, 600.0)
                     .build()
                     {
-                        eprintln!("[Pedaru] Failed to create window: {:?}", e);
+                        log::warn!("Failed to create window: {:?}", e);
                     }
                 }
             }
@@ -757,13 +1640,13 @@ fn handle_opened_event(app: &tauri::AppHandle, urls: &[tauri::Url]) {
 
 /// Handle window close events - close all child windows when main window is closed
 fn handle_window_close(app: &tauri::AppHandle, label: &str) {
-    eprintln!("[Pedaru] CloseRequested event for window: {}", label);
+    log::debug!("CloseRequested event for window: {}", label);
     if label == "main" {
-        eprintln!("[Pedaru] Main window closing, closing all child windows");
+        log::debug!("Main window closing, closing all child windows");
         for (win_label, window) in app.webview_windows() {
-            eprintln!("[Pedaru] Found window: {}", win_label);
+            log::debug!("Found window: {}", win_label);
             if win_label != "main" {
-                eprintln!("[Pedaru] Closing window: {}", win_label);
+                log::debug!("Closing window: {}", win_label);
                 let _ = window.close();
             }
         }
@@ -777,18 +1660,20 @@ fn handle_window_close(app: &tauri::AppHandle, label: &str) {
 /// Main application entry point
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    logging::init();
+
     // Check for CLI arguments first (before building the app)
     // Use args_os() to handle non-UTF-8 paths correctly on macOS
     let args: Vec<String> = std::env::args_os()
         .map(|arg| arg.to_string_lossy().to_string())
         .collect();
-    eprintln!("[Pedaru] CLI args: {:?}", args);
+    log::debug!("CLI args: {:?}", args);
 
     if args.len() > 1 {
         let file_path = &args[1];
-        eprintln!("[Pedaru] Checking file path: {}", file_path);
+        log::debug!("Checking file path: {}", file_path);
         if file_path.to_lowercase().ends_with(".pdf") {
-            eprintln!("[Pedaru] Setting pending file: {}", file_path);
+            log::debug!("Setting pending file: {}", file_path);
             let pending = get_pending_file();
             *pending
                 .lock()
@@ -797,20 +1682,12 @@ pub fn run() {
         }
     }
 
-    // Initialize SQLite database with migrations
-    let migrations = db_schema::get_migrations();
-
     tauri::Builder::default()
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_shell::init())
-        .plugin(
-            SqlBuilder::default()
-                .add_migrations("sqlite:pedaru.db", migrations)
-                .build(),
-        )
         .invoke_handler(tauri::generate_handler![
             get_pdf_info,
             read_pdf_file,
@@ -821,8 +1698,14 @@ pub fn run() {
             save_oauth_credentials,
             get_oauth_credentials,
             start_google_auth,
+            reauthenticate_google,
             get_google_auth_status,
             logout_google,
+            logout,
+            get_token_refresh_margin,
+            set_token_refresh_margin,
+            get_oauth_callback_pages,
+            save_oauth_callback_pages,
             list_drive_folders,
             list_drive_items,
             import_drive_files,
@@ -830,24 +1713,93 @@ pub fn run() {
             remove_drive_folder,
             get_drive_folders,
             sync_bookshelf,
+            set_drive_folder_auto_sync,
+            get_auto_sync_interval,
+            set_auto_sync_interval,
+            get_sync_mime_types,
+            set_sync_mime_types,
+            get_sync_schedule,
             get_bookshelf_items,
+            get_bookshelf_page,
             download_bookshelf_item,
             delete_local_copy,
+            delete_local_copies,
             reset_download_status,
             update_bookshelf_thumbnail,
             update_local_thumbnail,
             cancel_bookshelf_download,
+            cancel_all_bookshelf_downloads,
+            get_download_queue_depth,
+            set_max_concurrent_downloads,
+            get_prefetch_enabled,
+            set_prefetch_enabled,
             // Local import commands
             import_local_files,
             import_local_directory,
             delete_bookshelf_item,
             toggle_bookshelf_favorite,
+            set_bookshelf_favorite,
+            set_bookshelf_custom_name,
+            add_bookshelf_tag,
+            remove_bookshelf_tag,
+            get_bookshelf_tags,
             update_bookshelf_last_opened,
-            // Gemini translation commands
+            save_reading_position,
+            get_reading_position,
+            start_reading_session,
+            update_reading_session,
+            get_reading_stats,
+            add_annotation,
+            get_annotations,
+            update_annotation,
+            delete_annotation,
+            export_annotations,
+            export_annotated_pdf,
+            search_pdfs,
+            regenerate_thumbnail,
+            get_free_space_bytes,
+            get_max_download_speed,
+            set_max_download_speed,
+            get_download_name_template,
+            set_download_name_template,
+            get_download_progress_interval,
+            set_download_progress_interval,
+            get_log_level,
+            set_log_level,
+            get_proxy,
+            set_proxy,
+            get_http_timeouts,
+            set_http_timeouts,
+            verify_integrity,
+            get_storage_cap,
+            set_storage_cap,
+            cleanup_storage,
+            get_storage_usage,
+            rebuild_search_index,
+            compact_database,
+            check_connectivity,
+            run_diagnostics,
+            export_config,
+            import_config,
+            // Translation commands
             get_gemini_settings,
             save_gemini_settings,
-            translate_with_gemini,
+            test_gemini_key,
+            rotate_gemini_key,
+            list_gemini_models,
+            get_translation_settings,
+            save_translation_settings,
+            get_supported_languages,
+            clear_translation_cache,
+            get_translation_cache_max_age,
+            set_translation_cache_max_age,
+            get_gemini_rate_limit,
+            set_gemini_rate_limit,
+            translate_text,
             explain_directly,
+            translate_text_stream,
+            explain_text_stream,
+            cancel_translation_stream,
             // Session commands
             save_session,
             load_session,
@@ -875,6 +1827,24 @@ pub fn run() {
                 }
             }
 
+            // Pick the keychain or the encrypted-file fallback before any secret is read
+            secrets::init_secrets_backend(app.handle());
+
+            // Encrypt a database left by a build without encryption
+            #[cfg(feature = "db-encryption")]
+            if let Err(e) = db::encrypt_plaintext_database(app.handle()) {
+                log::error!("Failed to encrypt the database: {}", e);
+            }
+
+            // Bring the schema up to date before anything reads the database
+            match migrations::run_migrations(app.handle()) {
+                Ok(version) => log::info!("Database schema at version {}", version),
+                Err(e) => log::error!("Database migration failed: {}", e),
+            }
+            logging::init_log_level(app.handle());
+            network::init_proxy(app.handle());
+            network::init_http_timeouts(app.handle());
+
             // Build and set the native menu only on macOS
             // Windows and Linux use custom TitleBar component with integrated menu
             #[cfg(target_os = "macos")]
@@ -899,9 +1869,83 @@ pub fn run() {
                 }
             }
 
+            // Move any plaintext tokens from older versions into the keychain
+            if let Err(e) = oauth::migrate_legacy_db_tokens(app.handle()) {
+                log::warn!("Failed to migrate legacy OAuth tokens: {}", e);
+            }
+
+            // Refresh Google tokens shortly before they expire
+            oauth::init_token_refresh_margin(app.handle());
+            tauri::async_runtime::spawn(oauth::run_background_refresh(app.handle().clone()));
+
+            // Sync folders in the background once auto-sync is turned on
+            tauri::async_runtime::spawn(sync::run_auto_sync(app.handle().clone()));
+
+            // Name folders that were added by id once their account signs in
+            let auth_app = app.handle().clone();
+            app.listen(oauth::AUTH_STATUS_CHANGED_EVENT, move |_| {
+                let app = auth_app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = sync::resolve_folder_names(&app).await {
+                        log::warn!("Failed to resolve folder names: {}", e);
+                    }
+                });
+            });
+
             // Reset any stale "downloading" statuses from previous sessions
             if let Err(e) = bookshelf::reset_stale_downloads(app.handle()) {
-                eprintln!("[Pedaru] Failed to reset stale downloads: {}", e);
+                log::warn!("Failed to reset stale downloads: {}", e);
+            }
+
+            // Move inline thumbnails out to files, then compact the database
+            // in the background (right away if thumbnails were moved, otherwise
+            // only if it has grown a lot)
+            let compact_app = app.handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                let moved =
+                    bookshelf::migrate_thumbnails_to_files(&compact_app).unwrap_or_else(|e| {
+                        log::warn!("Failed to move thumbnails to files: {}", e);
+                        0
+                    });
+                let compacted = if moved > 0 {
+                    db::compact_database(&compact_app).map(|_| ())
+                } else {
+                    db::compact_database_if_grown(&compact_app)
+                };
+                if let Err(e) = compacted {
+                    log::warn!("Failed to compact database: {}", e);
+                }
+            });
+
+            // Apply the configured download concurrency limit
+            bookshelf::init_download_queue(app.handle());
+            bookshelf::init_bandwidth_limit(app.handle());
+            bookshelf::init_prefetch(app.handle());
+            bookshelf::init_download_progress_interval(app.handle());
+            translation::init_gemini_rate_limit(app.handle());
+
+            // Resume downloads the user started before the app last closed
+            match bookshelf::get_requested_downloads(app.handle()) {
+                Ok(downloads) => {
+                    if !downloads.is_empty() {
+                        log::info!("Resuming {} downloads", downloads.len());
+                    }
+                    for (drive_file_id, file_name) in downloads {
+                        let download_app = app.handle().clone();
+                        tauri::async_runtime::spawn(async move {
+                            if let Err(e) =
+                                download_item(download_app, drive_file_id.clone(), file_name).await
+                            {
+                                log::warn!(
+                                    "Resumed download of {} failed: {}",
+                                    drive_file_id,
+                                    e.message
+                                );
+                            }
+                        });
+                    }
+                }
+                Err(e) => log::warn!("Failed to load requested downloads: {}", e),
             }
 
             Ok(())
@@ -923,6 +1967,14 @@ pub fn run() {
             } => {
                 handle_window_close(app, label);
             }
+            tauri::RunEvent::Exit => {
+                // Stop download tasks so they don't keep writing while the app exits
+                // (download requests are kept so they resume on the next launch)
+                let cancelled = bookshelf::cancel_all_downloads();
+                if cancelled > 0 {
+                    log::info!("Cancelled {} downloads on exit", cancelled);
+                }
+            }
             _ => {}
         });
 }
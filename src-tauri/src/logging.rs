@@ -0,0 +1,250 @@
+//! Leveled logging with secret redaction
+//!
+//! Modules log through the `log` macros. Every message goes through `redact`
+//! before it is written to stderr, so an OAuth code, token or API key that
+//! ends up in a message (e.g. inside an error response body) isn't leaked.
+//! The level comes from a setting and can be changed while the app runs.
+
+use log::{LevelFilter, Log, Metadata, Record};
+use std::borrow::Cow;
+use tauri::AppHandle;
+
+use crate::error::{ConfigError, PedaruError};
+use crate::settings;
+
+/// Level used until the setting is read, and when it isn't set
+pub const DEFAULT_LOG_LEVEL: LevelFilter = LevelFilter::Info;
+
+/// Dependencies only log at this level or above, whatever the setting
+const DEPENDENCY_LOG_LEVEL: LevelFilter = LevelFilter::Warn;
+
+/// Written in place of a redacted value
+const REDACTED: &str = "[REDACTED]";
+
+/// Query parameters and JSON fields whose values are secrets
+const SECRET_KEYS: &[&str] = &[
+    "code",
+    "code_verifier",
+    "access_token",
+    "refresh_token",
+    "id_token",
+    "client_secret",
+    "api_key",
+    "key",
+];
+
+/// Prefixes of secrets found on their own: bearer tokens, Google OAuth
+/// access and refresh tokens, and Google API keys
+const SECRET_PREFIXES: &[&str] = &["Bearer ", "ya29.", "1//", "AIza"];
+
+// ============================================================================
+// Redaction
+// ============================================================================
+
+/// Whether a secret value ends at `c`
+fn is_value_end(c: char) -> bool {
+    c.is_whitespace()
+        || matches!(
+            c,
+            '&' | '"' | '\'' | ',' | ';' | ')' | ']' | '}' | '<' | '>'
+        )
+}
+
+/// Offset of the secret value in `rest`, if `rest` starts with a secret
+///
+/// Keys only count as `key=value` or `"key": value`, so prose such as
+/// "status code: 404" is left alone.
+fn secret_value_start(rest: &str) -> Option<usize> {
+    for key in SECRET_KEYS {
+        let Some(after_key) = rest.strip_prefix(key) else {
+            continue;
+        };
+        let value = match after_key.strip_prefix('=') {
+            Some(value) => value,
+            None => match after_key.strip_prefix("\":") {
+                Some(value) => {
+                    let value = value.trim_start();
+                    value.strip_prefix('"').unwrap_or(value)
+                }
+                None => continue,
+            },
+        };
+        return Some(rest.len() - value.len());
+    }
+
+    SECRET_PREFIXES
+        .iter()
+        .find(|prefix| rest.starts_with(**prefix))
+        .map(|prefix| prefix.len())
+}
+
+/// Replace OAuth codes, tokens and API keys in `message` with a placeholder
+pub fn redact(message: &str) -> Cow<'_, str> {
+    let bytes = message.as_bytes();
+    let mut redacted = String::new();
+    let mut copied = 0;
+    let mut i = 0;
+
+    while i < message.len() {
+        // Secrets only start at a word boundary ("pagecode=" isn't "code=")
+        let at_word_start =
+            i == 0 || !(bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_');
+        if at_word_start
+            && message.is_char_boundary(i)
+            && let Some(offset) = secret_value_start(&message[i..])
+        {
+            let start = i + offset;
+            let end = message[start..]
+                .find(is_value_end)
+                .map_or(message.len(), |len| start + len);
+            if end > start {
+                redacted.push_str(&message[copied..start]);
+                redacted.push_str(REDACTED);
+                copied = end;
+                i = end;
+                continue;
+            }
+        }
+        i += 1;
+    }
+
+    if copied == 0 {
+        return Cow::Borrowed(message);
+    }
+    redacted.push_str(&message[copied..]);
+    Cow::Owned(redacted)
+}
+
+// ============================================================================
+// Logger
+// ============================================================================
+
+/// Writes redacted records to stderr
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let own = metadata.target().starts_with(env!("CARGO_CRATE_NAME"));
+        metadata.level() <= log::max_level() && (own || metadata.level() <= DEPENDENCY_LOG_LEVEL)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message = record.args().to_string();
+        eprintln!(
+            "[Pedaru] {:<5} {}: {}",
+            record.level(),
+            record.target(),
+            redact(&message)
+        );
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+/// Install the logger at the default level (call once, before logging)
+pub fn init() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(DEFAULT_LOG_LEVEL);
+    }
+}
+
+// ============================================================================
+// Log Level Setting
+// ============================================================================
+
+fn parse_log_level(level: &str) -> Result<LevelFilter, PedaruError> {
+    level
+        .trim()
+        .parse()
+        .map_err(|_| ConfigError::InvalidLogLevel(level.to_string()).into())
+}
+
+/// Apply the saved log level (called once at startup)
+pub fn init_log_level(app: &AppHandle) {
+    let level = settings::get_setting(app, settings::KEY_LOG_LEVEL)
+        .ok()
+        .flatten()
+        .and_then(|v| parse_log_level(&v).ok())
+        .unwrap_or(DEFAULT_LOG_LEVEL);
+    log::set_max_level(level);
+}
+
+/// Get the current log level ("off", "error", "warn", "info", "debug" or "trace")
+pub fn get_log_level() -> String {
+    log::max_level().as_str().to_lowercase()
+}
+
+/// Change the log level (None restores the default)
+pub fn set_log_level(app: &AppHandle, level: Option<&str>) -> Result<(), PedaruError> {
+    let level = match level {
+        Some(level) => {
+            let level = parse_log_level(level)?;
+            settings::set_setting(app, settings::KEY_LOG_LEVEL, &level.as_str().to_lowercase())?;
+            level
+        }
+        None => {
+            settings::delete_setting(app, settings::KEY_LOG_LEVEL)?;
+            DEFAULT_LOG_LEVEL
+        }
+    };
+    log::set_max_level(level);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_secrets() {
+        assert_eq!(
+            redact("GET /callback?state=abc&code=4/0AbCd-123&scope=drive"),
+            "GET /callback?state=abc&code=[REDACTED]&scope=drive"
+        );
+        assert_eq!(
+            redact(
+                r#"{"access_token": "secret-1", "refresh_token":"secret-2", "expires_in": 3599}"#
+            ),
+            r#"{"access_token": "[REDACTED]", "refresh_token":"[REDACTED]", "expires_in": 3599}"#
+        );
+        assert_eq!(
+            redact("Authorization: Bearer abc.def"),
+            "Authorization: Bearer [REDACTED]"
+        );
+        assert_eq!(
+            redact("request to /models?key=AIzaSyExample failed"),
+            "request to /models?key=[REDACTED] failed"
+        );
+        assert_eq!(
+            redact("token ya29.a0Af-xyz expired"),
+            "token ya29.[REDACTED] expired"
+        );
+    }
+
+    #[test]
+    fn test_redact_leaves_ordinary_messages_alone() {
+        for message in [
+            "Download failed (status code: 404)",
+            "Saved OAuth tokens for account 3 to the OS keychain",
+            "barcode=123 and encode=utf8",
+            "日本語のファイル名.pdf",
+        ] {
+            assert!(matches!(redact(message), Cow::Borrowed(_)), "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_parse_log_level() {
+        assert_eq!(parse_log_level("debug").unwrap(), LevelFilter::Debug);
+        assert_eq!(parse_log_level(" WARN ").unwrap(), LevelFilter::Warn);
+        assert_eq!(
+            parse_log_level("verbose").unwrap_err().code(),
+            "config.invalid_log_level"
+        );
+    }
+}
@@ -0,0 +1,326 @@
+//! Versioned schema migrations
+//!
+//! Migrations are applied in order on startup, before anything else touches
+//! the database. Each one runs in its own transaction together with the row
+//! recording it in `schema_version`, so a failing migration leaves the
+//! database at the last version that applied cleanly.
+//!
+//! Older versions let tauri-plugin-sql apply these same migrations; its
+//! `_sqlx_migrations` bookkeeping is imported the first time the runner sees
+//! such a database.
+
+use rusqlite::{Connection, OptionalExtension};
+
+use crate::db::{ToDbError, now_timestamp, open_db};
+use crate::error::{DatabaseError, PedaruError};
+
+/// A single schema migration
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+/// All migrations, in ascending version order
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial_schema",
+        sql: include_str!("migrations/001_initial_schema.sql"),
+    },
+    Migration {
+        version: 2,
+        description: "resumable_downloads",
+        sql: include_str!("migrations/002_resumable_downloads.sql"),
+    },
+    Migration {
+        version: 3,
+        description: "recursive_folder_sync",
+        sql: include_str!("migrations/003_recursive_folder_sync.sql"),
+    },
+    Migration {
+        version: 4,
+        description: "reading_progress",
+        sql: include_str!("migrations/004_reading_progress.sql"),
+    },
+    Migration {
+        version: 5,
+        description: "pdf_text_search",
+        sql: include_str!("migrations/005_pdf_text_search.sql"),
+    },
+    Migration {
+        version: 6,
+        description: "pdf_page_count",
+        sql: include_str!("migrations/006_pdf_page_count.sql"),
+    },
+    Migration {
+        version: 7,
+        description: "download_error",
+        sql: include_str!("migrations/007_download_error.sql"),
+    },
+    Migration {
+        version: 8,
+        description: "download_retries",
+        sql: include_str!("migrations/008_download_retries.sql"),
+    },
+    Migration {
+        version: 9,
+        description: "keep_progress_for_removed_files",
+        sql: include_str!("migrations/009_keep_progress_for_removed_files.sql"),
+    },
+    Migration {
+        version: 10,
+        description: "md5_checksum",
+        sql: include_str!("migrations/010_md5_checksum.sql"),
+    },
+    Migration {
+        version: 11,
+        description: "tags",
+        sql: include_str!("migrations/011_tags.sql"),
+    },
+    Migration {
+        version: 12,
+        description: "translation_cache",
+        sql: include_str!("migrations/012_translation_cache.sql"),
+    },
+    Migration {
+        version: 13,
+        description: "google_accounts",
+        sql: include_str!("migrations/013_google_accounts.sql"),
+    },
+    Migration {
+        version: 14,
+        description: "drive_change_tokens",
+        sql: include_str!("migrations/014_drive_change_tokens.sql"),
+    },
+    Migration {
+        version: 15,
+        description: "folder_auto_sync",
+        sql: include_str!("migrations/015_folder_auto_sync.sql"),
+    },
+    Migration {
+        version: 16,
+        description: "download_intent",
+        sql: include_str!("migrations/016_download_intent.sql"),
+    },
+    Migration {
+        version: 17,
+        description: "thumbnail_files",
+        sql: include_str!("migrations/017_thumbnail_files.sql"),
+    },
+    Migration {
+        version: 18,
+        description: "custom_name",
+        sql: include_str!("migrations/018_custom_name.sql"),
+    },
+    Migration {
+        version: 19,
+        description: "reading_sessions",
+        sql: include_str!("migrations/019_reading_sessions.sql"),
+    },
+    Migration {
+        version: 20,
+        description: "annotations",
+        sql: include_str!("migrations/020_annotations.sql"),
+    },
+    Migration {
+        version: 21,
+        description: "folder_name_pending",
+        sql: include_str!("migrations/021_folder_name_pending.sql"),
+    },
+    Migration {
+        version: 22,
+        description: "shared_drive_folders",
+        sql: include_str!("migrations/022_shared_drive_folders.sql"),
+    },
+    Migration {
+        version: 23,
+        description: "download_failure",
+        sql: include_str!("migrations/023_download_failure.sql"),
+    },
+    Migration {
+        version: 24,
+        description: "download_reserved_path",
+        sql: include_str!("migrations/024_download_reserved_path.sql"),
+    },
+];
+
+/// Create the version table and import tauri-plugin-sql's history if needed
+fn ensure_version_table(conn: &Connection) -> Result<(), PedaruError> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+             version INTEGER PRIMARY KEY,
+             description TEXT NOT NULL,
+             applied_at INTEGER NOT NULL
+         )",
+    )
+    .db_err()?;
+
+    let has_versions: bool = conn
+        .query_row("SELECT EXISTS(SELECT 1 FROM schema_version)", [], |row| {
+            row.get(0)
+        })
+        .db_err()?;
+    let has_plugin_history: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+            [],
+            |row| row.get(0),
+        )
+        .db_err()?;
+
+    if !has_versions && has_plugin_history {
+        let imported = conn
+            .execute(
+                "INSERT INTO schema_version (version, description, applied_at)
+                 SELECT version, description, ?1 FROM _sqlx_migrations WHERE success = 1",
+                [now_timestamp()],
+            )
+            .db_err()?;
+        log::info!(
+            "Imported {} applied migrations from tauri-plugin-sql",
+            imported
+        );
+    }
+
+    Ok(())
+}
+
+/// Highest applied migration version (0 for an empty database)
+pub fn current_version(conn: &Connection) -> Result<i64, PedaruError> {
+    let version: Option<i64> = conn
+        .query_row("SELECT MAX(version) FROM schema_version", [], |row| {
+            row.get(0)
+        })
+        .optional()
+        .db_err()?
+        .flatten();
+    Ok(version.unwrap_or(0))
+}
+
+/// Apply every migration newer than the current version
+///
+/// Returns the resulting schema version.
+pub(crate) fn apply_migrations(
+    conn: &mut Connection,
+    migrations: &[Migration],
+) -> Result<i64, PedaruError> {
+    ensure_version_table(conn)?;
+    let start_version = current_version(conn)?;
+    let mut version = start_version;
+
+    for migration in migrations.iter().filter(|m| m.version > start_version) {
+        let failed = |e: rusqlite::Error| DatabaseError::MigrationFailed {
+            version: migration.version,
+            description: migration.description,
+            message: e.to_string(),
+        };
+
+        let tx = conn.transaction().map_err(failed)?;
+        tx.execute_batch(migration.sql).map_err(failed)?;
+        tx.execute(
+            "INSERT INTO schema_version (version, description, applied_at) VALUES (?1, ?2, ?3)",
+            rusqlite::params![migration.version, migration.description, now_timestamp()],
+        )
+        .map_err(failed)?;
+        tx.commit().map_err(failed)?;
+
+        log::info!(
+            "Applied migration {} ({})",
+            migration.version,
+            migration.description
+        );
+        version = migration.version;
+    }
+
+    Ok(version)
+}
+
+/// Bring the app database up to the latest schema version
+pub fn run_migrations(app: &tauri::AppHandle) -> Result<i64, PedaruError> {
+    let mut conn = open_db(app)?;
+    apply_migrations(&mut conn, MIGRATIONS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_MIGRATIONS: &[Migration] = &[
+        Migration {
+            version: 1,
+            description: "items",
+            sql: "CREATE TABLE items (id INTEGER PRIMARY KEY);",
+        },
+        Migration {
+            version: 2,
+            description: "item_name",
+            sql: "ALTER TABLE items ADD COLUMN name TEXT;",
+        },
+    ];
+
+    #[test]
+    fn test_migration_versions_are_ascending() {
+        assert!(MIGRATIONS.windows(2).all(|w| w[0].version < w[1].version));
+        assert_eq!(MIGRATIONS[0].version, 1);
+    }
+
+    #[test]
+    fn test_apply_migrations_is_idempotent() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(apply_migrations(&mut conn, TEST_MIGRATIONS).unwrap(), 2);
+        assert_eq!(apply_migrations(&mut conn, TEST_MIGRATIONS).unwrap(), 2);
+        conn.execute("INSERT INTO items (name) VALUES ('a')", [])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_failed_migration_keeps_last_good_version() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let migrations = [
+            Migration {
+                version: 1,
+                description: "items",
+                sql: "CREATE TABLE items (id INTEGER PRIMARY KEY);",
+            },
+            Migration {
+                version: 2,
+                description: "broken",
+                sql: "CREATE TABLE other (id INTEGER); ALTER TABLE missing ADD COLUMN x;",
+            },
+        ];
+
+        let err = apply_migrations(&mut conn, &migrations).unwrap_err();
+        assert!(matches!(
+            err,
+            PedaruError::Database(DatabaseError::MigrationFailed { version: 2, .. })
+        ));
+        assert_eq!(current_version(&conn).unwrap(), 1);
+
+        // The partial migration was rolled back
+        let other_exists: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE name = 'other')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(!other_exists);
+    }
+
+    #[test]
+    fn test_imports_plugin_migration_history() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE items (id INTEGER PRIMARY KEY);
+             CREATE TABLE _sqlx_migrations (
+                 version BIGINT PRIMARY KEY, description TEXT NOT NULL, success BOOLEAN NOT NULL
+             );
+             INSERT INTO _sqlx_migrations VALUES (1, 'items', 1);",
+        )
+        .unwrap();
+
+        // Version 1 must not be re-run (it would fail on the existing table)
+        assert_eq!(apply_migrations(&mut conn, TEST_MIGRATIONS).unwrap(), 2);
+    }
+}
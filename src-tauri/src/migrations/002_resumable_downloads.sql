@@ -0,0 +1,4 @@
+-- Pedaru Database Schema V2
+-- Track bytes written for partially downloaded cloud files so downloads can resume
+
+ALTER TABLE bookshelf_cloud ADD COLUMN downloaded_bytes INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,12 @@
+-- Pedaru Database Schema V3
+-- Recursive folder sync: watched folders may include their subfolders
+
+ALTER TABLE drive_folders ADD COLUMN recursive INTEGER NOT NULL DEFAULT 0;
+
+-- drive_folder_id holds the immediate parent; root_folder_id is the watched folder
+ALTER TABLE bookshelf_cloud ADD COLUMN root_folder_id TEXT;
+ALTER TABLE bookshelf_cloud ADD COLUMN folder_path TEXT;
+
+UPDATE bookshelf_cloud SET root_folder_id = drive_folder_id WHERE root_folder_id IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_cloud_root_folder_id ON bookshelf_cloud(root_folder_id);
@@ -0,0 +1,17 @@
+-- Pedaru Database Schema V4
+-- Reading progress: where the user left off in each cloud document
+
+CREATE TABLE IF NOT EXISTS reading_progress (
+    drive_file_id TEXT PRIMARY KEY,
+    page INTEGER NOT NULL,
+    scroll_offset REAL NOT NULL DEFAULT 0,
+    updated_at INTEGER NOT NULL
+);
+
+-- Progress is kept across delete_local_copy (the row stays in bookshelf_cloud)
+-- but goes away once the item itself is removed from the shelf
+CREATE TRIGGER IF NOT EXISTS trg_cloud_delete_reading_progress
+AFTER DELETE ON bookshelf_cloud
+BEGIN
+    DELETE FROM reading_progress WHERE drive_file_id = OLD.drive_file_id;
+END;
@@ -0,0 +1,26 @@
+-- Pedaru Database Schema V5
+-- Full-text search over the text of downloaded cloud PDFs
+
+CREATE VIRTUAL TABLE IF NOT EXISTS pdf_text_index USING fts5(
+    drive_file_id UNINDEXED,
+    page UNINDEXED,
+    content,
+    tokenize = 'unicode61'
+);
+
+-- One row per indexed (or deliberately skipped) document, used to avoid
+-- re-indexing files whose Drive modification time hasn't changed
+CREATE TABLE IF NOT EXISTS pdf_text_index_state (
+    drive_file_id TEXT PRIMARY KEY,
+    drive_modified_time TEXT,
+    status TEXT NOT NULL,  -- 'indexed' or 'skipped'
+    page_count INTEGER NOT NULL DEFAULT 0,
+    indexed_at INTEGER NOT NULL
+);
+
+CREATE TRIGGER IF NOT EXISTS trg_cloud_delete_pdf_text_index
+AFTER DELETE ON bookshelf_cloud
+BEGIN
+    DELETE FROM pdf_text_index WHERE drive_file_id = OLD.drive_file_id;
+    DELETE FROM pdf_text_index_state WHERE drive_file_id = OLD.drive_file_id;
+END;
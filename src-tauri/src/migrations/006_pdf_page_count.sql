@@ -0,0 +1,4 @@
+-- Pedaru Database Schema V6
+-- Page count read from downloaded PDFs
+
+ALTER TABLE bookshelf_cloud ADD COLUMN page_count INTEGER;
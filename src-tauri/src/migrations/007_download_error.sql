@@ -0,0 +1,4 @@
+-- Pedaru Database Schema V7
+-- Reason the last download attempt failed (NULL unless download_status = 'error')
+
+ALTER TABLE bookshelf_cloud ADD COLUMN download_error TEXT;
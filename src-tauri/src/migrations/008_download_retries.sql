@@ -0,0 +1,4 @@
+-- Pedaru Database Schema V8
+-- Automatic download retries: attempts made since the download was started
+
+ALTER TABLE bookshelf_cloud ADD COLUMN download_retry_count INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,6 @@
+-- Pedaru Database Schema V9
+-- Keep reading progress when a file disappears from Drive during sync, so it
+-- is restored if the file comes back. Progress is now deleted explicitly when
+-- the user removes an item (e.g. by removing its folder).
+
+DROP TRIGGER IF EXISTS trg_cloud_delete_reading_progress;
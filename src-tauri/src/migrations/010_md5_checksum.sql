@@ -0,0 +1,4 @@
+-- Pedaru Database Schema V10
+-- Drive's MD5 checksum of the downloaded file, for integrity verification
+
+ALTER TABLE bookshelf_cloud ADD COLUMN md5_checksum TEXT;
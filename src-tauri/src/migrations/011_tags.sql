@@ -0,0 +1,26 @@
+-- Pedaru Database Schema V11
+-- Tags for organizing cloud bookshelf items
+
+CREATE TABLE IF NOT EXISTS tags (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    created_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS bookshelf_tags (
+    drive_file_id TEXT NOT NULL,
+    tag_id INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (drive_file_id, tag_id),
+    FOREIGN KEY (tag_id) REFERENCES tags(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_bookshelf_tags_tag ON bookshelf_tags(tag_id);
+
+-- Tags stay when only the local copy is deleted (the row remains), but go
+-- away once the item itself is removed from the shelf
+CREATE TRIGGER IF NOT EXISTS trg_cloud_delete_bookshelf_tags
+AFTER DELETE ON bookshelf_cloud
+BEGIN
+    DELETE FROM bookshelf_tags WHERE drive_file_id = OLD.drive_file_id;
+END;
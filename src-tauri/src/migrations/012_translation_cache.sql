@@ -0,0 +1,10 @@
+-- Pedaru Database Schema V12
+-- Cache of translation/explanation results to avoid repeat API calls
+
+CREATE TABLE IF NOT EXISTS translation_cache (
+    cache_key TEXT PRIMARY KEY,  -- SHA-256 of provider, model, language, text and prompts
+    response TEXT NOT NULL,      -- JSON-encoded TranslationResponse/ExplanationResponse
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_translation_cache_created_at ON translation_cache(created_at);
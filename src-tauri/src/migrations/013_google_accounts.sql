@@ -0,0 +1,19 @@
+-- Pedaru Database Schema V13
+-- Multiple Google accounts: tokens live in the keychain per account, and
+-- synced folders and cloud items record which account they belong to
+
+CREATE TABLE IF NOT EXISTS google_accounts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    label TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    created_at INTEGER NOT NULL
+);
+
+-- Account 1 owns the tokens and folders from before accounts existed
+INSERT OR IGNORE INTO google_accounts (id, label, created_at)
+VALUES (1, 'Default', CAST(strftime('%s', 'now') AS INTEGER));
+
+ALTER TABLE drive_folders ADD COLUMN account_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE bookshelf_cloud ADD COLUMN account_id INTEGER NOT NULL DEFAULT 1;
+
+CREATE INDEX IF NOT EXISTS idx_drive_folders_account_id ON drive_folders(account_id);
+CREATE INDEX IF NOT EXISTS idx_cloud_account_id ON bookshelf_cloud(account_id);
@@ -0,0 +1,13 @@
+-- Pedaru Database Schema V14
+-- Incremental sync: each synced folder remembers the Drive changes page token
+-- from its last sync, and recursive folders record their subfolder tree so
+-- changes can be matched to it without listing the whole tree again
+
+ALTER TABLE drive_folders ADD COLUMN changes_page_token TEXT;
+
+CREATE TABLE IF NOT EXISTS drive_subfolders (
+    root_folder_id TEXT NOT NULL,
+    folder_id TEXT NOT NULL,
+    folder_path TEXT NOT NULL,
+    PRIMARY KEY (root_folder_id, folder_id)
+);
@@ -0,0 +1,4 @@
+-- Pedaru Database Schema V15
+-- Per-folder opt-out of the background auto-sync scheduler
+
+ALTER TABLE drive_folders ADD COLUMN auto_sync INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,4 @@
+-- Pedaru Database Schema V16
+-- Remember downloads the user started so they resume after a restart
+
+ALTER TABLE bookshelf_cloud ADD COLUMN download_requested INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,6 @@
+-- Pedaru Database Schema V17
+-- Cloud item thumbnails are stored as files in the thumbnails directory;
+-- thumbnail_path is relative to it. Existing thumbnail_data is moved out by
+-- bookshelf::migrate_thumbnails_to_files on startup.
+
+ALTER TABLE bookshelf_cloud ADD COLUMN thumbnail_path TEXT;
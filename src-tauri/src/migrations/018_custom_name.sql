@@ -0,0 +1,4 @@
+-- Pedaru Database Schema V18
+-- User-chosen display name for cloud items (NULL uses pdf_title or file_name)
+
+ALTER TABLE bookshelf_cloud ADD COLUMN custom_name TEXT;
@@ -0,0 +1,16 @@
+-- Pedaru Database Schema V19
+-- Reading sessions for statistics. ended_at is moved forward while the
+-- session is active, so a session cut short by a crash still counts up to
+-- its last activity. max_page is the furthest page reached in the session.
+
+CREATE TABLE IF NOT EXISTS reading_sessions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    drive_file_id TEXT NOT NULL,
+    started_at INTEGER NOT NULL,
+    ended_at INTEGER NOT NULL,
+    max_page INTEGER NOT NULL
+);
+
+-- Covers the per-document aggregation in reading_stats
+CREATE INDEX IF NOT EXISTS idx_reading_sessions_file
+    ON reading_sessions(drive_file_id, started_at, ended_at, max_page);
@@ -0,0 +1,17 @@
+-- Pedaru Database Schema V20
+-- Highlights and notes, keyed by drive_file_id so they survive re-downloads.
+-- rects is a JSON array of {x, y, width, height} as fractions of the page size.
+
+CREATE TABLE IF NOT EXISTS annotations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    drive_file_id TEXT NOT NULL,
+    page INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    rects TEXT NOT NULL,
+    color TEXT NOT NULL,
+    note TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_annotations_file_page ON annotations(drive_file_id, page);
@@ -0,0 +1,5 @@
+-- Pedaru Database Schema V21
+-- Folders added by id or URL start out named after their id; name_pending
+-- marks them until their Drive name has been fetched (after sign-in).
+
+ALTER TABLE drive_folders ADD COLUMN name_pending INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,5 @@
+-- Pedaru Database Schema V22
+-- Folders on a shared drive remember the drive's id, which Drive requires
+-- when reading that drive's change log
+
+ALTER TABLE drive_folders ADD COLUMN drive_id TEXT;
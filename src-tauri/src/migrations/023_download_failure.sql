@@ -0,0 +1,5 @@
+-- Pedaru Database Schema V23
+-- Kind of the last download failure (network, disk_full, ...), next to the
+-- human-readable download_error, so failures can be told apart
+
+ALTER TABLE bookshelf_cloud ADD COLUMN download_failure TEXT;
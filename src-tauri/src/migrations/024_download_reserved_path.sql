@@ -0,0 +1,5 @@
+-- Pedaru Database Schema V24
+-- Path an unfinished download writes to, reserved when it starts so two
+-- downloads whose names render the same pick different files
+
+ALTER TABLE bookshelf_cloud ADD COLUMN reserved_path TEXT;
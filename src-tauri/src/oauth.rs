@@ -3,26 +3,101 @@
 //! This module handles the OAuth authorization flow for desktop applications
 //! using the PKCE (Proof Key for Code Exchange) extension.
 //!
-//! All OAuth credentials and tokens are stored in Stronghold (encrypted).
+//! All OAuth credentials and tokens are stored in the OS keychain (see `secrets`).
+//!
+//! Several Google accounts can be connected at once. They share the OAuth
+//! client credentials, while tokens and granted scopes are stored per account
+//! (see `account_key`). Accounts are listed in the `google_accounts` table.
 
 use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
 use rand::Rng;
+use rusqlite::OptionalExtension;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::sync::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::thread;
-use tauri::AppHandle;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
 use tiny_http::{Response, Server};
 
-use crate::error::{OAuthError, PedaruError};
+use crate::bookshelf;
+use crate::db::{ToDbError, now_timestamp, open_db};
+use crate::error::{IntoTauriError, OAuthError, PedaruError, is_retryable_status};
+use crate::network;
 use crate::secrets;
+use crate::settings;
 
 /// Google OAuth endpoints
 const GOOGLE_AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
 const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const GOOGLE_REVOKE_URL: &str = "https://oauth2.googleapis.com/revoke";
+
+/// Service name used in offline errors
+const OAUTH_SERVICE: &str = "Google sign-in";
+
+/// Default OAuth scopes for Google Drive access (space-separated)
+pub const DEFAULT_SCOPES: &str = "https://www.googleapis.com/auth/drive.readonly";
+
+/// Account that owns tokens and folders from before multiple accounts were
+/// supported (created by the `google_accounts` migration)
+pub const DEFAULT_ACCOUNT_ID: i64 = 1;
+
+/// Event emitted with the current `AuthStatus` whenever it changes
+pub const AUTH_STATUS_CHANGED_EVENT: &str = "auth-status-changed";
+
+/// Event emitted with an account id when Google rejects the account's
+/// refresh token and it has to reconnect
+pub const AUTH_REAUTH_REQUIRED_EVENT: &str = "auth-reauth-required";
+
+/// Event emitted with a `CommandError` when the OAuth flow fails in the background
+pub const AUTH_ERROR_EVENT: &str = "auth-error";
+
+/// How long the callback server waits for the browser redirect
+const CALLBACK_TIMEOUT_SECS: u64 = 300;
+
+/// How often the callback server checks whether it has been told to stop
+const CALLBACK_SHUTDOWN_POLL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Loopback address for the callback server (Google prefers the IP over `localhost`)
+const CALLBACK_HOST: &str = "127.0.0.1";
+
+/// Random bytes in a PKCE code verifier (32 gives 43 characters, 96 the
+/// maximum of 128)
+const CODE_VERIFIER_ENTROPY_BYTES: usize = 32;
+
+/// Code verifier length limits from RFC 7636 section 4.1
+const CODE_VERIFIER_MIN_LEN: usize = 43;
+const CODE_VERIFIER_MAX_LEN: usize = 128;
+
+/// Tokens are refreshed this long before they expire, unless configured
+pub const DEFAULT_TOKEN_REFRESH_MARGIN_SECS: i64 = 300;
 
-/// Required OAuth scopes for Google Drive access
-const SCOPES: &str = "https://www.googleapis.com/auth/drive.readonly";
+/// Largest configurable refresh margin (half of Google's one-hour token lifetime)
+pub const MAX_TOKEN_REFRESH_MARGIN_SECS: i64 = 1800;
+
+/// Never use more than this fraction of a token's lifetime as the margin, so
+/// short-lived tokens aren't considered expiring as soon as they arrive
+const TOKEN_REFRESH_MARGIN_MAX_LIFETIME_DIVISOR: i64 = 2;
+
+/// How often the background task checks for expiring tokens
+const BACKGROUND_REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How long the background task leaves an account alone after a failed refresh
+const BACKGROUND_REFRESH_RETRY_SECS: i64 = 600;
+
+/// Maximum number of token refresh attempts on transient failures
+const REFRESH_MAX_ATTEMPTS: u32 = 3;
+
+/// Base delay before the first token refresh retry (doubled on each retry)
+const REFRESH_BASE_DELAY_MS: u64 = 500;
+
+/// Ports tried (in order) for the local OAuth callback server
+const CALLBACK_PORT_RANGE: std::ops::RangeInclusive<u16> = 8585..=8600;
+
+/// Suffix of every Google OAuth client id
+const CLIENT_ID_SUFFIX: &str = ".apps.googleusercontent.com";
 
 /// OAuth credentials stored in database
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,28 +126,130 @@ pub struct AuthState {
     pub token_expiry: Option<i64>,
 }
 
+/// A Google account known to the app
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoogleAccount {
+    pub id: i64,
+    pub label: String,
+}
+
+/// Whether an account can talk to Google
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountConnection {
+    /// No tokens: never signed in, or signed out
+    SignedOut,
+    /// Google rejected the account's tokens; reconnecting keeps its folders
+    /// and downloads
+    NeedsReauth,
+    Connected,
+}
+
+impl AccountConnection {
+    fn new(has_tokens: bool, needs_reauth: bool) -> Self {
+        match (has_tokens, needs_reauth) {
+            (_, true) => Self::NeedsReauth,
+            (true, false) => Self::Connected,
+            (false, false) => Self::SignedOut,
+        }
+    }
+}
+
+/// Authentication status of a single account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountStatus {
+    pub id: i64,
+    pub label: String,
+    /// Whether the account is `Connected`
+    pub authenticated: bool,
+    pub connection: AccountConnection,
+    /// Scopes Google actually granted (may be narrower than requested)
+    pub granted_scopes: Vec<String>,
+}
+
 /// Authentication status for frontend
+///
+/// `authenticated` and `granted_scopes` describe the default account, so
+/// callers that predate multiple accounts keep working.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthStatus {
     pub authenticated: bool,
+    /// Whether OAuth client credentials are set (tokens may still be missing
+    /// or rejected, see `connection`)
     pub configured: bool,
+    pub connection: AccountConnection,
+    /// Scopes Google actually granted (may be narrower than requested)
+    pub granted_scopes: Vec<String>,
+    /// Every known account, connected or not
+    pub accounts: Vec<AccountStatus>,
 }
 
 /// State during OAuth flow
 struct OAuthFlowState {
     code_verifier: String,
     state: String,
+    /// Account the tokens will be stored for
+    account_id: i64,
 }
 
 /// Global state for OAuth callback handling
 static OAUTH_FLOW_STATE: Mutex<Option<OAuthFlowState>> = Mutex::new(None);
 static OAUTH_CALLBACK_CODE: Mutex<Option<String>> = Mutex::new(None);
+/// Port the callback server is bound to (shared with the token exchange)
+static OAUTH_CALLBACK_PORT: Mutex<Option<u16>> = Mutex::new(None);
+/// Callback server of the flow in progress, if any
+static OAUTH_CALLBACK_SERVER: Mutex<Option<CallbackServerHandle>> = Mutex::new(None);
+
+/// Running callback server thread and the flag that tells it to stop
+struct CallbackServerHandle {
+    shutdown: Arc<AtomicBool>,
+    thread: thread::JoinHandle<()>,
+}
+
+/// Configured refresh margin in seconds (loaded from settings on startup)
+static TOKEN_REFRESH_MARGIN_SECS: AtomicI64 = AtomicI64::new(DEFAULT_TOKEN_REFRESH_MARGIN_SECS);
+
+/// Monotonic expiry of tokens obtained by this process, keyed by account id
+///
+/// Unlike the stored wall-clock expiry, these survive the system clock being
+/// changed. Tokens loaded from an earlier run have no entry.
+static TOKEN_DEADLINES: Mutex<Option<HashMap<i64, TokenDeadline>>> = Mutex::new(None);
+
+/// Serializes token refreshes so rotated refresh tokens are never raced
+static TOKEN_REFRESH_LOCK: OnceLock<tokio::sync::Mutex<()>> = OnceLock::new();
+
+fn get_refresh_lock() -> &'static tokio::sync::Mutex<()> {
+    TOKEN_REFRESH_LOCK.get_or_init(|| tokio::sync::Mutex::new(()))
+}
 
-/// Generate a random code verifier for PKCE (43-128 chars, URL-safe)
-fn generate_code_verifier() -> String {
+/// Length of a code verifier built from `entropy_bytes` random bytes
+/// (unpadded base64url: 4 characters per 3 bytes, rounded up)
+const fn code_verifier_len(entropy_bytes: usize) -> usize {
+    (entropy_bytes * 4).div_ceil(3)
+}
+
+/// Whether `entropy_bytes` gives a verifier RFC 7636 accepts (32..=96 bytes)
+const fn is_valid_verifier_entropy(entropy_bytes: usize) -> bool {
+    let len = code_verifier_len(entropy_bytes);
+    len >= CODE_VERIFIER_MIN_LEN && len <= CODE_VERIFIER_MAX_LEN
+}
+
+const _: () = assert!(
+    is_valid_verifier_entropy(CODE_VERIFIER_ENTROPY_BYTES),
+    "CODE_VERIFIER_ENTROPY_BYTES must give a 43-128 character verifier"
+);
+
+/// Generate a random code verifier for PKCE (URL-safe)
+///
+/// Returns `None` if `entropy_bytes` would give a verifier outside RFC 7636's
+/// 43-128 character range.
+fn generate_code_verifier(entropy_bytes: usize) -> Option<String> {
+    if !is_valid_verifier_entropy(entropy_bytes) {
+        return None;
+    }
     let mut rng = rand::thread_rng();
-    let bytes: Vec<u8> = (0..32).map(|_| rng.r#gen()).collect();
-    URL_SAFE_NO_PAD.encode(&bytes)
+    let bytes: Vec<u8> = (0..entropy_bytes).map(|_| rng.r#gen()).collect();
+    Some(URL_SAFE_NO_PAD.encode(&bytes))
 }
 
 /// Generate code challenge from verifier (SHA256 + Base64URL)
@@ -90,25 +267,207 @@ fn generate_state() -> String {
     URL_SAFE_NO_PAD.encode(&bytes)
 }
 
-/// Save OAuth credentials to Stronghold (encrypted)
+/// Build the redirect URI for the callback server listening on `port`
+fn redirect_uri(port: u16) -> String {
+    format!("http://{}:{}/callback", CALLBACK_HOST, port)
+}
+
+/// Escape text for safe inclusion in the callback HTML pages
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Build the Google authorization URL
+fn build_auth_url(
+    client_id: &str,
+    redirect_uri: &str,
+    scopes: &str,
+    state: &str,
+    code_challenge: &str,
+) -> String {
+    format!(
+        "{}?client_id={}&redirect_uri={}&response_type=code&scope={}&state={}&code_challenge={}&code_challenge_method=S256&access_type=offline&prompt=consent",
+        GOOGLE_AUTH_URL,
+        urlencoding::encode(client_id),
+        urlencoding::encode(redirect_uri),
+        urlencoding::encode(scopes),
+        urlencoding::encode(state),
+        urlencoding::encode(code_challenge),
+    )
+}
+
+/// Form parameters for exchanging an authorization code for tokens
+fn token_exchange_params<'a>(
+    credentials: &'a OAuthCredentials,
+    code: &'a str,
+    code_verifier: &'a str,
+    redirect_uri: &'a str,
+) -> [(&'static str, &'a str); 6] {
+    [
+        ("client_id", credentials.client_id.as_str()),
+        ("client_secret", credentials.client_secret.as_str()),
+        ("code", code),
+        ("code_verifier", code_verifier),
+        ("grant_type", "authorization_code"),
+        ("redirect_uri", redirect_uri),
+    ]
+}
+
+/// Bind the callback server to the first free port in `CALLBACK_PORT_RANGE`
+fn bind_callback_server() -> Result<(Server, u16), PedaruError> {
+    let mut last_error = String::from("no ports available");
+    for port in CALLBACK_PORT_RANGE {
+        match Server::http((CALLBACK_HOST, port)) {
+            Ok(server) => return Ok((server, port)),
+            Err(e) => last_error = format!("port {}: {}", port, e),
+        }
+    }
+    Err(PedaruError::OAuth(OAuthError::CallbackServerFailed(
+        last_error,
+    )))
+}
+
+/// Bind a callback server and run `serve` on it in a background thread
+///
+/// Returns the bound port. The thread is recorded so `stop_callback_server`
+/// can shut it down; `serve` should stop once its shutdown flag is set.
+fn spawn_callback_server<F>(serve: F) -> Result<u16, PedaruError>
+where
+    F: FnOnce(Server, u16, &AtomicBool) + Send + 'static,
+{
+    let (server, port) = bind_callback_server()?;
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let thread_shutdown = Arc::clone(&shutdown);
+    let thread = thread::spawn(move || serve(server, port, &thread_shutdown));
+
+    *OAUTH_CALLBACK_SERVER.lock().unwrap() = Some(CallbackServerHandle { shutdown, thread });
+    Ok(port)
+}
+
+/// Stop the callback server of an earlier flow and wait for it to exit
+///
+/// Joining frees the port before a new server binds. A no-op if no server is
+/// running or it has already finished.
+fn stop_callback_server() {
+    let handle = OAUTH_CALLBACK_SERVER.lock().unwrap().take();
+    if let Some(handle) = handle {
+        handle.shutdown.store(true, Ordering::SeqCst);
+        if handle.thread.join().is_err() {
+            log::error!("OAuth callback server thread panicked");
+        }
+    }
+}
+
+/// Wait for the next request until `deadline`
+///
+/// Returns `None` on timeout or once `shutdown` is set.
+fn next_callback_request(
+    server: &Server,
+    deadline: std::time::Instant,
+    shutdown: &AtomicBool,
+) -> Result<Option<tiny_http::Request>, PedaruError> {
+    loop {
+        if shutdown.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+        if remaining.is_zero() {
+            return Ok(None);
+        }
+        match server.recv_timeout(remaining.min(CALLBACK_SHUTDOWN_POLL)) {
+            Ok(Some(request)) => return Ok(Some(request)),
+            Ok(None) => continue,
+            Err(e) => {
+                return Err(PedaruError::OAuth(OAuthError::CallbackServerFailed(
+                    e.to_string(),
+                )));
+            }
+        }
+    }
+}
+
+/// Check pasted OAuth credentials, returning them trimmed
+///
+/// Both values must be non-empty and free of whitespace. `strict` also
+/// requires the client id to look like a Google one
+/// (`<project>-<id>.apps.googleusercontent.com`); lax mode accepts any id
+/// for setups that differ (e.g. a test server standing in for Google).
+fn validate_credentials(
+    credentials: &OAuthCredentials,
+    strict: bool,
+) -> Result<OAuthCredentials, OAuthError> {
+    let invalid = |reason: &str| Err(OAuthError::InvalidCredentials(reason.to_string()));
+    let client_id = credentials.client_id.trim();
+    let client_secret = credentials.client_secret.trim();
+
+    if client_id.is_empty() {
+        return invalid("the client ID is empty");
+    }
+    if client_id.contains(char::is_whitespace) {
+        return invalid("the client ID contains spaces");
+    }
+    if strict {
+        let well_formed = client_id
+            .strip_suffix(CLIENT_ID_SUFFIX)
+            .is_some_and(|prefix| {
+                !prefix.is_empty()
+                    && prefix
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-')
+            });
+        if !well_formed {
+            return invalid(
+                "the client ID should end with .apps.googleusercontent.com (was it copied in full?)",
+            );
+        }
+    }
+    if client_secret.is_empty() {
+        return invalid("the client secret is empty");
+    }
+    if client_secret.contains(char::is_whitespace) {
+        return invalid("the client secret contains spaces");
+    }
+
+    Ok(OAuthCredentials {
+        client_id: client_id.to_string(),
+        client_secret: client_secret.to_string(),
+    })
+}
+
+/// Save OAuth credentials to the OS keychain
+///
+/// The credentials are checked first (see `validate_credentials`).
 pub fn save_credentials(
     app: &AppHandle,
     credentials: &OAuthCredentials,
+    strict: bool,
 ) -> Result<(), PedaruError> {
+    let credentials = validate_credentials(credentials, strict)?;
     secrets::store_secret(app, secrets::keys::GOOGLE_CLIENT_ID, &credentials.client_id)?;
     secrets::store_secret(
         app,
         secrets::keys::GOOGLE_CLIENT_SECRET,
         &credentials.client_secret,
     )?;
-    eprintln!("[Pedaru] Saved OAuth credentials to Stronghold");
+    log::info!("Saved OAuth credentials to the OS keychain");
     Ok(())
 }
 
-/// Load OAuth credentials from Stronghold
+/// Load OAuth credentials from the OS keychain
 pub fn load_credentials(app: &AppHandle) -> Result<Option<OAuthCredentials>, PedaruError> {
-    let client_id = secrets::get_secret(app, secrets::keys::GOOGLE_CLIENT_ID)?;
-    let client_secret = secrets::get_secret(app, secrets::keys::GOOGLE_CLIENT_SECRET)?;
+    let mut values = secrets::get_secrets(
+        app,
+        &[
+            secrets::keys::GOOGLE_CLIENT_ID,
+            secrets::keys::GOOGLE_CLIENT_SECRET,
+        ],
+    )?;
+    let mut take = |key: &str| values.remove(key).flatten();
+    let client_id = take(secrets::keys::GOOGLE_CLIENT_ID);
+    let client_secret = take(secrets::keys::GOOGLE_CLIENT_SECRET);
 
     match (client_id, client_secret) {
         (Some(id), Some(secret)) => Ok(Some(OAuthCredentials {
@@ -119,17 +478,42 @@ pub fn load_credentials(app: &AppHandle) -> Result<Option<OAuthCredentials>, Ped
     }
 }
 
-/// Load complete auth state from Stronghold
-pub fn load_auth_state(app: &AppHandle) -> Result<Option<AuthState>, PedaruError> {
-    let client_id = secrets::get_secret(app, secrets::keys::GOOGLE_CLIENT_ID)?;
-    let client_secret = secrets::get_secret(app, secrets::keys::GOOGLE_CLIENT_SECRET)?;
+/// Key of a per-account secret or setting
+///
+/// The default account keeps the unsuffixed key, so tokens stored before
+/// multiple accounts were supported stay valid.
+fn account_key(key: &str, account_id: i64) -> String {
+    if account_id == DEFAULT_ACCOUNT_ID {
+        key.to_string()
+    } else {
+        format!("{}:{}", key, account_id)
+    }
+}
+
+/// Load complete auth state for an account from the OS keychain
+pub fn load_auth_state(app: &AppHandle, account_id: i64) -> Result<Option<AuthState>, PedaruError> {
+    let access_key = account_key(secrets::keys::GOOGLE_ACCESS_TOKEN, account_id);
+    let refresh_key = account_key(secrets::keys::GOOGLE_REFRESH_TOKEN, account_id);
+    let expiry_key = account_key(secrets::keys::GOOGLE_TOKEN_EXPIRY, account_id);
+    let mut values = secrets::get_secrets(
+        app,
+        &[
+            secrets::keys::GOOGLE_CLIENT_ID,
+            secrets::keys::GOOGLE_CLIENT_SECRET,
+            access_key.as_str(),
+            refresh_key.as_str(),
+            expiry_key.as_str(),
+        ],
+    )?;
+    let mut take = |key: &str| values.remove(key).flatten();
+    let client_id = take(secrets::keys::GOOGLE_CLIENT_ID);
+    let client_secret = take(secrets::keys::GOOGLE_CLIENT_SECRET);
 
     match (client_id, client_secret) {
         (Some(id), Some(secret)) => {
-            let access_token = secrets::get_secret(app, secrets::keys::GOOGLE_ACCESS_TOKEN)?;
-            let refresh_token = secrets::get_secret(app, secrets::keys::GOOGLE_REFRESH_TOKEN)?;
-            let token_expiry = secrets::get_secret(app, secrets::keys::GOOGLE_TOKEN_EXPIRY)?
-                .and_then(|s| s.parse::<i64>().ok());
+            let access_token = take(&access_key);
+            let refresh_token = take(&refresh_key);
+            let token_expiry = take(&expiry_key).and_then(|s| s.parse::<i64>().ok());
 
             Ok(Some(AuthState {
                 client_id: id,
@@ -143,50 +527,402 @@ pub fn load_auth_state(app: &AppHandle) -> Result<Option<AuthState>, PedaruError
     }
 }
 
-/// Save tokens to Stronghold (encrypted)
+/// Save an account's tokens to the OS keychain
+///
+/// `requested_at` is when the token request was sent; `expires_in` counts
+/// from then, which also keeps a monotonic deadline for this process.
 pub fn save_tokens(
     app: &AppHandle,
+    account_id: i64,
     access_token: &str,
     refresh_token: Option<&str>,
     expires_in: Option<i64>,
+    requested_at: Instant,
 ) -> Result<(), PedaruError> {
     // Store access token
-    secrets::store_secret(app, secrets::keys::GOOGLE_ACCESS_TOKEN, access_token)?;
+    secrets::store_secret(
+        app,
+        &account_key(secrets::keys::GOOGLE_ACCESS_TOKEN, account_id),
+        access_token,
+    )?;
 
     // Store refresh token if provided (keep existing if not)
     if let Some(token) = refresh_token {
-        secrets::store_secret(app, secrets::keys::GOOGLE_REFRESH_TOKEN, token)?;
+        secrets::store_secret(
+            app,
+            &account_key(secrets::keys::GOOGLE_REFRESH_TOKEN, account_id),
+            token,
+        )?;
     }
 
     // Calculate and store token expiry
     if let Some(expires) = expires_in {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs() as i64;
-        let expiry = now + expires;
+        let expiry = now_timestamp() + expires;
+        secrets::store_secret(
+            app,
+            &account_key(secrets::keys::GOOGLE_TOKEN_EXPIRY, account_id),
+            &expiry.to_string(),
+        )?;
+    }
+    set_token_deadline(
+        account_id,
+        expires_in.map(|expires| TokenDeadline::new(requested_at, expires)),
+    );
+    set_needs_reauth(app, account_id, false)?;
+
+    log::info!(
+        "Saved OAuth tokens for account {} to the OS keychain",
+        account_id
+    );
+    Ok(())
+}
+
+/// Save the scope string granted by Google (non-sensitive, stored in SQLite)
+///
+/// Token responses omit `scope` when it is unchanged, so `None` keeps the
+/// previously stored value.
+fn save_granted_scopes(
+    app: &AppHandle,
+    account_id: i64,
+    scope: Option<&str>,
+) -> Result<(), PedaruError> {
+    if let Some(scope) = scope {
+        settings::set_setting(
+            app,
+            &account_key(settings::KEY_GOOGLE_GRANTED_SCOPES, account_id),
+            scope,
+        )?;
+    }
+    Ok(())
+}
+
+/// Load an account's granted scopes as a list (empty if never recorded)
+pub(crate) fn load_granted_scopes(
+    app: &AppHandle,
+    account_id: i64,
+) -> Result<Vec<String>, PedaruError> {
+    Ok(settings::get_setting(
+        app,
+        &account_key(settings::KEY_GOOGLE_GRANTED_SCOPES, account_id),
+    )?
+    .map(|scope| scope.split_whitespace().map(str::to_string).collect())
+    .unwrap_or_default())
+}
+
+/// Whether Google rejected an account's tokens
+fn needs_reauth(app: &AppHandle, account_id: i64) -> Result<bool, PedaruError> {
+    Ok(settings::get_setting(
+        app,
+        &account_key(settings::KEY_GOOGLE_NEEDS_REAUTH, account_id),
+    )?
+    .is_some())
+}
+
+/// Record whether Google rejects an account's tokens (cleared by new tokens
+/// or by signing out)
+fn set_needs_reauth(app: &AppHandle, account_id: i64, value: bool) -> Result<(), PedaruError> {
+    let key = account_key(settings::KEY_GOOGLE_NEEDS_REAUTH, account_id);
+    if value {
+        settings::set_setting(app, &key, "true")
+    } else {
+        settings::delete_setting(app, &key)
+    }
+}
+
+/// Clear an account's tokens from the OS keychain (logout)
+pub fn clear_tokens(app: &AppHandle, account_id: i64) -> Result<(), PedaruError> {
+    remove_tokens(app, account_id)?;
+    emit_auth_status_changed(app);
+    Ok(())
+}
+
+/// `clear_tokens` without the status event
+fn remove_tokens(app: &AppHandle, account_id: i64) -> Result<(), PedaruError> {
+    remove_token_secrets(app, account_id)?;
+    settings::delete_setting(
+        app,
+        &account_key(settings::KEY_GOOGLE_GRANTED_SCOPES, account_id),
+    )?;
+    set_needs_reauth(app, account_id, false)?;
+    log::info!(
+        "Cleared OAuth tokens for account {} from the OS keychain",
+        account_id
+    );
+    Ok(())
+}
+
+/// Delete an account's tokens from the OS keychain, keeping its granted
+/// scopes and reconnect flag
+fn remove_token_secrets(app: &AppHandle, account_id: i64) -> Result<(), PedaruError> {
+    for key in [
+        secrets::keys::GOOGLE_ACCESS_TOKEN,
+        secrets::keys::GOOGLE_REFRESH_TOKEN,
+        secrets::keys::GOOGLE_TOKEN_EXPIRY,
+    ] {
+        secrets::delete_secret(app, &account_key(key, account_id))?;
+    }
+    set_token_deadline(account_id, None);
+    Ok(())
+}
+
+/// Drop tokens Google rejected and mark the account as needing to reconnect
+///
+/// The OAuth credentials and granted scopes stay, so `reauthenticate` can
+/// sign the account in again with the same access.
+fn drop_rejected_tokens(app: &AppHandle, account_id: i64) -> Result<(), PedaruError> {
+    remove_token_secrets(app, account_id)?;
+    set_needs_reauth(app, account_id, true)?;
+    log::info!(
+        "Cleared the rejected OAuth tokens of account {}",
+        account_id
+    );
+    Ok(())
+}
+
+/// Emit `AUTH_STATUS_CHANGED_EVENT` with the current status
+///
+/// `AppHandle::emit` is thread-safe, so this can be called from the callback
+/// server thread as well as from async commands.
+fn emit_auth_status_changed(app: &AppHandle) {
+    match get_auth_status(app) {
+        Ok(status) => {
+            let _ = app.emit(AUTH_STATUS_CHANGED_EVENT, status);
+        }
+        Err(e) => log::warn!("Failed to read auth status for event: {}", e),
+    }
+}
+
+/// Move tokens left in the legacy `google_auth` table into the OS keychain
+///
+/// Older versions persisted tokens in plaintext in SQLite. This copies any
+/// non-null token columns into the keychain and then nulls them, so it is a
+/// no-op once the migration has run (or if the table never existed).
+pub fn migrate_legacy_db_tokens(app: &AppHandle) -> Result<(), PedaruError> {
+    let conn = open_db(app)?;
+
+    let table_exists: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'google_auth')",
+            [],
+            |row| row.get(0),
+        )
+        .db_err()?;
+    if !table_exists {
+        return Ok(());
+    }
+
+    let legacy: Option<(Option<String>, Option<String>, Option<i64>)> = conn
+        .query_row(
+            "SELECT access_token, refresh_token, token_expiry FROM google_auth
+             WHERE access_token IS NOT NULL OR refresh_token IS NOT NULL
+             LIMIT 1",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .db_err()?;
+
+    let Some((access_token, refresh_token, token_expiry)) = legacy else {
+        return Ok(());
+    };
+
+    if let Some(token) = access_token {
+        secrets::store_secret(app, secrets::keys::GOOGLE_ACCESS_TOKEN, &token)?;
+    }
+    if let Some(token) = refresh_token {
+        secrets::store_secret(app, secrets::keys::GOOGLE_REFRESH_TOKEN, &token)?;
+    }
+    if let Some(expiry) = token_expiry {
         secrets::store_secret(app, secrets::keys::GOOGLE_TOKEN_EXPIRY, &expiry.to_string())?;
     }
 
-    eprintln!("[Pedaru] Saved OAuth tokens to Stronghold");
+    conn.execute(
+        "UPDATE google_auth SET access_token = NULL, refresh_token = NULL, token_expiry = NULL",
+        [],
+    )
+    .db_err()?;
+
+    log::info!("Migrated OAuth tokens from database to the OS keychain");
     Ok(())
 }
 
-/// Clear tokens from Stronghold (logout)
-pub fn clear_tokens(app: &AppHandle) -> Result<(), PedaruError> {
-    secrets::delete_secret(app, secrets::keys::GOOGLE_ACCESS_TOKEN)?;
-    secrets::delete_secret(app, secrets::keys::GOOGLE_REFRESH_TOKEN)?;
-    secrets::delete_secret(app, secrets::keys::GOOGLE_TOKEN_EXPIRY)?;
-    eprintln!("[Pedaru] Cleared OAuth tokens from Stronghold");
+/// Revoke tokens on Google's side, then clear them locally (logout)
+///
+/// The refresh token is preferred since revoking it also invalidates the
+/// access tokens issued from it. A 400 response means the token is already
+/// invalid, which is treated as success. Local tokens are only cleared once
+/// revocation has gone through, so a network failure leaves them intact.
+pub async fn revoke_tokens(app: &AppHandle, account_id: i64) -> Result<(), PedaruError> {
+    // Wait out any in-flight refresh so it can't store tokens after logout
+    let _guard = get_refresh_lock().lock().await;
+
+    revoke_on_google(app, account_id).await?;
+    clear_tokens(app, account_id)
+}
+
+/// Revoke an account's tokens on Google's side, leaving the local copies
+async fn revoke_on_google(app: &AppHandle, account_id: i64) -> Result<(), PedaruError> {
+    let token = match load_auth_state(app, account_id)? {
+        Some(state) => state.refresh_token.or(state.access_token),
+        None => None,
+    };
+
+    if let Some(token) = token {
+        let client = network::http_client()?;
+        let response = client
+            .post(GOOGLE_REVOKE_URL)
+            .form(&[("token", token.as_str())])
+            .send()
+            .await
+            .map_err(request_failed)?;
+
+        let status = response.status();
+        if status.as_u16() == 400 {
+            log::info!("Token already invalid on Google's side, continuing logout");
+        } else if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(PedaruError::OAuth(OAuthError::HttpRequestFailed {
+                status: Some(status.as_u16()),
+                message: format!("Token revocation failed ({}): {}", status, error_text),
+            }));
+        }
+    }
     Ok(())
 }
 
+/// Sign every account out and clear what Pedaru stored for them
+///
+/// Revokes and clears each account's tokens, cancels downloads, deletes all
+/// secrets (OAuth credentials and API keys included) and, with
+/// `wipe_downloads`, every downloaded PDF. The database key is kept, since
+/// the library can't be opened without it. Each step runs even if an earlier
+/// one failed, so a network error or a locked file can't leave tokens
+/// behind; the failures are returned together at the end.
+pub async fn logout(app: &AppHandle, wipe_downloads: bool) -> Result<(), PedaruError> {
+    // Wait out any in-flight refresh so it can't store tokens after logout
+    let _guard = get_refresh_lock().lock().await;
+    let mut failures = Vec::new();
+    let mut record = |step: &str, result: Result<(), PedaruError>| {
+        if let Err(e) = result {
+            log::warn!("Logout step failed ({}): {}", step, e);
+            failures.push(format!("{}: {}", step, e));
+        }
+    };
+
+    match list_accounts(app) {
+        Ok(accounts) => {
+            for account in accounts {
+                record("revoke", revoke_on_google(app, account.id).await);
+                record("tokens", remove_tokens(app, account.id));
+            }
+        }
+        Err(e) => record("accounts", Err(e)),
+    }
+
+    let cancelled = bookshelf::cancel_all_downloads();
+    if cancelled > 0 {
+        log::info!("Cancelled {} downloads for logout", cancelled);
+    }
+    if wipe_downloads {
+        let result = bookshelf::delete_all_cloud_local_copies(app).map(|results| {
+            for failed in results.iter().filter(|result| !result.success) {
+                log::warn!(
+                    "Couldn't delete the download of {}: {}",
+                    failed.drive_file_id,
+                    failed.error.as_deref().unwrap_or_default()
+                );
+            }
+        });
+        record("downloads", result);
+    }
+
+    record(
+        "secrets",
+        secrets::delete_all_secrets_except(app, &[secrets::keys::DATABASE_KEY]),
+    );
+
+    emit_auth_status_changed(app);
+    if failures.is_empty() {
+        log::info!("Logged out");
+        Ok(())
+    } else {
+        Err(OAuthError::LogoutIncomplete(failures.join("; ")).into())
+    }
+}
+
 /// Start OAuth flow and return authorization URL
-pub fn start_auth_flow(app: &AppHandle) -> Result<String, PedaruError> {
+///
+/// `scopes` is a space-separated scope list; `None` requests `DEFAULT_SCOPES`.
+/// The tokens are stored for the account named `account_label` (created if
+/// it doesn't exist yet), or for the default account when it is `None`.
+pub fn start_auth_flow(
+    app: &AppHandle,
+    scopes: Option<&str>,
+    account_label: Option<&str>,
+) -> Result<String, PedaruError> {
+    let credentials =
+        load_credentials(app)?.ok_or(PedaruError::OAuth(OAuthError::NotConfigured))?;
+
+    let account_id = match account_label.map(str::trim) {
+        Some(label) if !label.is_empty() => get_or_create_account(app, label)?.id,
+        _ => DEFAULT_ACCOUNT_ID,
+    };
+    begin_auth_flow(
+        app,
+        &credentials,
+        account_id,
+        scopes.unwrap_or(DEFAULT_SCOPES),
+    )
+}
+
+/// Start a fresh sign-in for an existing account and return the
+/// authorization URL
+///
+/// For accounts whose tokens Google no longer accepts. Nothing is cleared up
+/// front: the new tokens replace the old ones once the sign-in completes, and
+/// the account's synced folders, tags and downloaded files stay as they are.
+/// See `reauth_scopes` for the scopes requested.
+pub fn reauthenticate(
+    app: &AppHandle,
+    account_id: i64,
+    required_scope: Option<&str>,
+) -> Result<String, PedaruError> {
     let credentials =
         load_credentials(app)?.ok_or(PedaruError::OAuth(OAuthError::NotConfigured))?;
+    if !list_accounts(app)?.iter().any(|a| a.id == account_id) {
+        return Err(OAuthError::UnknownAccount(account_id).into());
+    }
+    let scopes = reauth_scopes(&load_granted_scopes(app, account_id)?, required_scope);
+    log::info!("Reconnecting account {}", account_id);
+    begin_auth_flow(app, &credentials, account_id, &scopes)
+}
 
-    let code_verifier = generate_code_verifier();
+/// Scopes to request when reconnecting: the ones the account had, plus
+/// `DEFAULT_SCOPES` and `required_scope` (from `drive.insufficient_scope`),
+/// so a reconnect never narrows access
+fn reauth_scopes(granted: &[String], required_scope: Option<&str>) -> String {
+    let mut scopes: Vec<&str> = granted.iter().map(String::as_str).collect();
+    let extra = DEFAULT_SCOPES.split_whitespace().chain(required_scope);
+    for scope in extra {
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    scopes.join(" ")
+}
+
+/// Start the callback server and build the authorization URL for an account
+fn begin_auth_flow(
+    app: &AppHandle,
+    credentials: &OAuthCredentials,
+    account_id: i64,
+    scopes: &str,
+) -> Result<String, PedaruError> {
+    // Tear down a flow that is still waiting (e.g. "Connect" clicked twice)
+    stop_callback_server();
+
+    let code_verifier = generate_code_verifier(CODE_VERIFIER_ENTROPY_BYTES)
+        .expect("CODE_VERIFIER_ENTROPY_BYTES is checked at compile time");
     let code_challenge = generate_code_challenge(&code_verifier);
     let state = generate_state();
 
@@ -196,6 +932,7 @@ pub fn start_auth_flow(app: &AppHandle) -> Result<String, PedaruError> {
         *flow_state = Some(OAuthFlowState {
             code_verifier: code_verifier.clone(),
             state: state.clone(),
+            account_id,
         });
     }
 
@@ -205,43 +942,61 @@ pub fn start_auth_flow(app: &AppHandle) -> Result<String, PedaruError> {
         *callback_code = None;
     }
 
-    // Start callback server in background
+    // Start the callback server before building the URL so the port is known
     let app_handle = app.clone();
-    thread::spawn(move || {
-        if let Err(e) = run_callback_server(&app_handle) {
-            eprintln!("OAuth callback server error: {}", e);
+    let port = spawn_callback_server(move |server, port, shutdown| {
+        if let Err(e) = run_callback_server(&app_handle, server, port, shutdown) {
+            log::warn!("OAuth callback server error: {}", e);
         }
-    });
+    })?;
+    {
+        let mut callback_port = OAUTH_CALLBACK_PORT.lock().unwrap();
+        *callback_port = Some(port);
+    }
 
     // Build authorization URL
-    let redirect_uri = "http://localhost:8585/callback";
-    let auth_url = format!(
-        "{}?client_id={}&redirect_uri={}&response_type=code&scope={}&state={}&code_challenge={}&code_challenge_method=S256&access_type=offline&prompt=consent",
-        GOOGLE_AUTH_URL,
-        urlencoding::encode(&credentials.client_id),
-        urlencoding::encode(redirect_uri),
-        urlencoding::encode(SCOPES),
-        urlencoding::encode(&state),
-        urlencoding::encode(&code_challenge),
+    let redirect_uri = redirect_uri(port);
+    let auth_url = build_auth_url(
+        &credentials.client_id,
+        &redirect_uri,
+        scopes,
+        &state,
+        &code_challenge,
     );
 
     Ok(auth_url)
 }
 
 /// Run local HTTP server to receive OAuth callback
-fn run_callback_server(app: &AppHandle) -> Result<(), PedaruError> {
-    let server = Server::http("127.0.0.1:8585")
-        .map_err(|e| PedaruError::OAuth(OAuthError::CallbackServerFailed(e.to_string())))?;
-
-    eprintln!("OAuth callback server started on port 8585");
+///
+/// Returns quietly if `shutdown` is set because a newer flow replaced this one.
+fn run_callback_server(
+    app: &AppHandle,
+    server: Server,
+    port: u16,
+    shutdown: &AtomicBool,
+) -> Result<(), PedaruError> {
+    log::debug!("OAuth callback server started on port {}", port);
+    let pages = get_callback_pages(app).unwrap_or_else(|e| {
+        log::warn!(
+            "Failed to load callback pages, using the built-in ones: {}",
+            e
+        );
+        CallbackPages::default()
+    });
 
-    // Wait for callback (with timeout using recv_timeout)
-    let timeout = std::time::Duration::from_secs(300); // 5 minutes
+    // Wait for callback until the deadline (unrelated requests don't extend it)
+    let timeout = std::time::Duration::from_secs(CALLBACK_TIMEOUT_SECS);
+    let deadline = std::time::Instant::now() + timeout;
+    let mut received_callback = false;
 
-    // Use recv_timeout to wait for a single request with timeout
-    while let Ok(Some(request)) = server.recv_timeout(timeout) {
+    while let Some(request) = next_callback_request(&server, deadline, shutdown)? {
         let url = request.url();
-        eprintln!("Received callback: {}", url);
+        // The query holds the authorization code, so only the path is logged
+        log::debug!(
+            "Received callback request for {}",
+            url.split('?').next().unwrap_or_default()
+        );
 
         if url.starts_with("/callback") {
             // Parse query parameters
@@ -270,54 +1025,67 @@ fn run_callback_server(app: &AppHandle) -> Result<(), PedaruError> {
 
                     if expected_state == received_state {
                         // Exchange code for tokens
-                        if let Err(e) = exchange_code_for_tokens(app, &code) {
-                            eprintln!("Token exchange failed: {}", e);
-                            let response = Response::from_string(
-                                "<html><body><h1>Authentication Failed</h1><p>Please try again.</p></body></html>"
-                            ).with_header(
-                                tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..]).unwrap()
-                            );
-                            let _ = request.respond(response);
-                        } else {
-                            let response = Response::from_string(
-                                "<html><body><h1>Authentication Successful!</h1><p>You can close this window and return to Pedaru.</p><script>setTimeout(() => window.close(), 2000);</script></body></html>"
-                            ).with_header(
-                                tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..]).unwrap()
-                            );
-                            let _ = request.respond(response);
-                        }
+                        let page = match exchange_code_for_tokens(app, &code) {
+                            Ok(()) => pages.success.clone(),
+                            Err(e) => {
+                                log::warn!("Token exchange failed: {}", e);
+                                let _ = app.emit(AUTH_ERROR_EVENT, e.into_tauri_error());
+                                pages.failure_page("Please try again.")
+                            }
+                        };
+                        let _ = request.respond(html_response(page));
                     } else {
-                        eprintln!(
-                            "State mismatch! Expected: {:?}, Received: {:?}",
-                            expected_state, received_state
-                        );
-                        let response = Response::from_string(
-                            "<html><body><h1>Authentication Failed</h1><p>State verification failed.</p></body></html>"
-                        ).with_header(
-                            tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..]).unwrap()
-                        );
-                        let _ = request.respond(response);
+                        log::warn!("OAuth state mismatch, ignoring callback");
+                        let _ = request.respond(html_response(pages.state_mismatch.clone()));
                     }
                 } else if let Some(error) = params.get("error") {
-                    eprintln!("OAuth error: {}", error);
-                    let response = Response::from_string(format!(
-                        "<html><body><h1>Authentication Failed</h1><p>Error: {}</p></body></html>",
-                        error
-                    ))
-                    .with_header(
-                        tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..])
-                            .unwrap(),
-                    );
-                    let _ = request.respond(response);
+                    let error = urlencoding::decode(error).unwrap_or_default().to_string();
+                    // Google encodes spaces in error_description as '+'
+                    let description = params.get("error_description").map(|d| {
+                        urlencoding::decode(&d.replace('+', " "))
+                            .unwrap_or_default()
+                            .to_string()
+                    });
+
+                    let message = match &description {
+                        Some(description) => format!("{}: {}", error, description),
+                        None => error.clone(),
+                    };
+                    let auth_error =
+                        PedaruError::OAuth(OAuthError::AuthorizationFailed(message.clone()));
+                    log::warn!("OAuth error: {}", auth_error);
+                    let _ = app.emit(AUTH_ERROR_EVENT, auth_error.into_tauri_error());
+
+                    let page = pages.failure_page(&format!("Error: {}", message));
+                    let _ = request.respond(html_response(page));
                 }
             }
 
             // Only handle one callback
+            received_callback = true;
             break;
         }
     }
 
-    eprintln!("OAuth callback server stopped");
+    log::debug!("OAuth callback server stopped");
+
+    // The newer flow owns the shared state now
+    if shutdown.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    if !received_callback {
+        // Reset so a subsequent start_auth_flow starts clean
+        {
+            let mut flow_state = OAUTH_FLOW_STATE.lock().unwrap();
+            *flow_state = None;
+        }
+
+        let timeout = || PedaruError::OAuth(OAuthError::CallbackTimeout(CALLBACK_TIMEOUT_SECS));
+        let _ = app.emit(AUTH_ERROR_EVENT, timeout().into_tauri_error());
+        return Err(timeout());
+    }
+
     Ok(())
 }
 
@@ -326,30 +1094,37 @@ fn exchange_code_for_tokens(app: &AppHandle, code: &str) -> Result<(), PedaruErr
     let credentials =
         load_credentials(app)?.ok_or(PedaruError::OAuth(OAuthError::NotConfigured))?;
 
-    let code_verifier = {
+    let (code_verifier, account_id) = {
         let flow_state = OAUTH_FLOW_STATE.lock().unwrap();
-        flow_state.as_ref().map(|s| s.code_verifier.clone())
+        flow_state
+            .as_ref()
+            .map(|s| (s.code_verifier.clone(), s.account_id))
     }
     .ok_or(PedaruError::OAuth(OAuthError::AuthorizationFailed(
         "No flow state".to_string(),
     )))?;
 
-    let redirect_uri = "http://localhost:8585/callback";
+    let port = OAUTH_CALLBACK_PORT
+        .lock()
+        .unwrap()
+        .ok_or(PedaruError::OAuth(OAuthError::AuthorizationFailed(
+            "No callback port".to_string(),
+        )))?;
+    let redirect_uri = redirect_uri(port);
 
     // Use blocking reqwest client for sync context
-    let client = reqwest::blocking::Client::new();
+    let client = network::blocking_http_client()?;
+    let requested_at = Instant::now();
     let response = client
         .post(GOOGLE_TOKEN_URL)
-        .form(&[
-            ("client_id", credentials.client_id.as_str()),
-            ("client_secret", credentials.client_secret.as_str()),
-            ("code", code),
-            ("code_verifier", code_verifier.as_str()),
-            ("grant_type", "authorization_code"),
-            ("redirect_uri", redirect_uri),
-        ])
+        .form(&token_exchange_params(
+            &credentials,
+            code,
+            &code_verifier,
+            &redirect_uri,
+        ))
         .send()
-        .map_err(|e| PedaruError::OAuth(OAuthError::HttpRequestFailed(e.to_string())))?;
+        .map_err(request_failed)?;
 
     if !response.status().is_success() {
         let error_text = response.text().unwrap_or_default();
@@ -364,10 +1139,13 @@ fn exchange_code_for_tokens(app: &AppHandle, code: &str) -> Result<(), PedaruErr
 
     save_tokens(
         app,
+        account_id,
         &token_response.access_token,
         token_response.refresh_token.as_deref(),
         token_response.expires_in,
+        requested_at,
     )?;
+    save_granted_scopes(app, account_id, token_response.scope.as_deref())?;
 
     // Clear flow state
     {
@@ -375,93 +1153,655 @@ fn exchange_code_for_tokens(app: &AppHandle, code: &str) -> Result<(), PedaruErr
         *flow_state = None;
     }
 
+    emit_auth_status_changed(app);
     Ok(())
 }
 
-/// Refresh access token using refresh token (async version)
-pub async fn refresh_access_token(app: &AppHandle) -> Result<String, PedaruError> {
-    let auth_state = load_auth_state(app)?.ok_or(PedaruError::OAuth(OAuthError::NotConfigured))?;
+/// Map a request that got no response (network failure) to an OAuth error
+fn request_failed(e: reqwest::Error) -> PedaruError {
+    network::network_error(OAUTH_SERVICE, &e).unwrap_or_else(|| {
+        PedaruError::OAuth(OAuthError::HttpRequestFailed {
+            status: None,
+            message: e.to_string(),
+        })
+    })
+}
 
-    let refresh_token =
-        auth_state
-            .refresh_token
-            .ok_or(PedaruError::OAuth(OAuthError::TokenRefreshFailed(
-                "No refresh token".to_string(),
-            )))?;
+/// Delay before retry number `attempt` (0-based): exponential plus up to 50% jitter
+pub(crate) fn backoff_delay(base: std::time::Duration, attempt: u32) -> std::time::Duration {
+    let delay = base * 2u32.pow(attempt);
+    let max_jitter = delay.as_millis() as u64 / 2;
+    let jitter = rand::thread_rng().gen_range(0..=max_jitter);
+    delay + std::time::Duration::from_millis(jitter)
+}
 
-    let client = reqwest::Client::new();
-    let response = client
-        .post(GOOGLE_TOKEN_URL)
-        .form(&[
-            ("client_id", auth_state.client_id.as_str()),
-            ("client_secret", auth_state.client_secret.as_str()),
-            ("refresh_token", refresh_token.as_str()),
-            ("grant_type", "refresh_token"),
-        ])
-        .send()
-        .await
-        .map_err(|e| PedaruError::OAuth(OAuthError::HttpRequestFailed(e.to_string())))?;
+/// Run `attempt` up to `max_attempts` times, backing off between retryable
+/// failures (see `PedaruError::is_retryable`)
+async fn retry_with_backoff<T, F, Fut>(
+    mut attempt: F,
+    max_attempts: u32,
+    base_delay: std::time::Duration,
+) -> Result<T, PedaruError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, PedaruError>>,
+{
+    let mut last_error = PedaruError::OAuth(OAuthError::TokenRefreshFailed(
+        "No refresh attempts made".to_string(),
+    ));
+
+    for n in 0..max_attempts {
+        match attempt().await {
+            Ok(value) => return Ok(value),
+            Err(e) if !e.is_retryable() => return Err(e),
+            Err(e) => {
+                log::warn!(
+                    "Token refresh attempt {}/{} failed: {}",
+                    n + 1,
+                    max_attempts,
+                    e
+                );
+                last_error = e;
+                if n + 1 < max_attempts {
+                    tokio::time::sleep(backoff_delay(base_delay, n)).await;
+                }
+            }
+        }
+    }
 
-    if !response.status().is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(PedaruError::OAuth(OAuthError::TokenRefreshFailed(
-            error_text,
-        )));
+    Err(last_error)
+}
+
+/// Refresh an account's access token using its refresh token (async version)
+///
+/// When Google rejects the refresh token (`ReauthRequired`), the account's
+/// tokens are cleared, it is marked as needing to reconnect and
+/// `AUTH_REAUTH_REQUIRED_EVENT` is emitted. Other failures leave the tokens
+/// alone, since they may be transient.
+pub async fn refresh_access_token(app: &AppHandle, account_id: i64) -> Result<String, PedaruError> {
+    let result = request_token_refresh(app, account_id).await;
+    if let Err(PedaruError::OAuth(OAuthError::ReauthRequired(_))) = &result {
+        log::warn!(
+            "Google rejected the refresh token of account {}, it needs to reconnect",
+            account_id
+        );
+        if let Err(e) = drop_rejected_tokens(app, account_id) {
+            log::warn!(
+                "Failed to record that account {} needs to reconnect: {}",
+                account_id,
+                e
+            );
+        }
+        emit_auth_status_changed(app);
+        let _ = app.emit(AUTH_REAUTH_REQUIRED_EVENT, account_id);
     }
+    result
+}
 
-    let token_response: TokenResponse = response
-        .json()
-        .await
-        .map_err(|e| PedaruError::OAuth(OAuthError::InvalidResponse(e.to_string())))?;
+/// Whether a token endpoint error body is `invalid_grant` (the refresh token
+/// was revoked or expired)
+fn is_invalid_grant(body: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(body)
+        .is_ok_and(|v| v.get("error").and_then(|e| e.as_str()) == Some("invalid_grant"))
+}
+
+/// Request a new access token from Google and store it
+async fn request_token_refresh(app: &AppHandle, account_id: i64) -> Result<String, PedaruError> {
+    network::ensure_online(OAUTH_SERVICE)?;
+    let auth_state =
+        load_auth_state(app, account_id)?.ok_or(PedaruError::OAuth(OAuthError::NotConfigured))?;
+
+    // Without a refresh token only signing in again gets a new access token
+    let refresh_token = auth_state
+        .refresh_token
+        .ok_or(PedaruError::OAuth(OAuthError::ReauthRequired(account_id)))?;
+
+    let client = network::http_client()?;
+    // Taken before the first attempt, so retries only make the deadline earlier
+    let requested_at = Instant::now();
+    let token_response = retry_with_backoff(
+        || async {
+            let response = client
+                .post(GOOGLE_TOKEN_URL)
+                .form(&[
+                    ("client_id", auth_state.client_id.as_str()),
+                    ("client_secret", auth_state.client_secret.as_str()),
+                    ("refresh_token", refresh_token.as_str()),
+                    ("grant_type", "refresh_token"),
+                ])
+                .send()
+                .await
+                .map_err(request_failed)?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let error_text = response.text().await.unwrap_or_default();
+                // 4xx errors won't fix themselves; invalid_grant needs a new sign-in
+                return Err(PedaruError::OAuth(if is_invalid_grant(&error_text) {
+                    OAuthError::ReauthRequired(account_id)
+                } else if is_retryable_status(status.as_u16()) {
+                    OAuthError::HttpRequestFailed {
+                        status: Some(status.as_u16()),
+                        message: format!("Token refresh failed ({}): {}", status, error_text),
+                    }
+                } else {
+                    OAuthError::TokenRefreshFailed(error_text)
+                }));
+            }
+
+            response
+                .json::<TokenResponse>()
+                .await
+                .map_err(|e| PedaruError::OAuth(OAuthError::InvalidResponse(e.to_string())))
+        },
+        REFRESH_MAX_ATTEMPTS,
+        std::time::Duration::from_millis(REFRESH_BASE_DELAY_MS),
+    )
+    .await?;
 
     save_tokens(
         app,
+        account_id,
         &token_response.access_token,
         token_response.refresh_token.as_deref(),
         token_response.expires_in,
+        requested_at,
     )?;
+    save_granted_scopes(app, account_id, token_response.scope.as_deref())?;
+    emit_auth_status_changed(app);
 
     Ok(token_response.access_token)
 }
 
-/// Get valid access token (refreshing if necessary) - async version
-pub async fn get_valid_access_token(app: &AppHandle) -> Result<String, PedaruError> {
-    let auth_state = load_auth_state(app)?.ok_or(PedaruError::OAuth(OAuthError::NotConfigured))?;
+/// Read the stored access token, or `None` if it is expired or expiring soon
+fn read_fresh_access_token(
+    app: &AppHandle,
+    account_id: i64,
+) -> Result<Option<String>, PedaruError> {
+    let auth_state =
+        load_auth_state(app, account_id)?.ok_or(PedaruError::OAuth(OAuthError::NotConfigured))?;
 
     let access_token = auth_state.access_token.ok_or(PedaruError::GoogleDrive(
         crate::error::GoogleDriveError::NotAuthenticated,
     ))?;
 
-    // Check if token is expired (with 5 minute buffer)
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
+    match auth_state.token_expiry {
+        Some(expiry)
+            if is_expiring(
+                expiry,
+                token_deadline(account_id),
+                ClockReading::now(),
+                get_token_refresh_margin(),
+            ) =>
+        {
+            Ok(None)
+        }
+        _ => Ok(Some(access_token)),
+    }
+}
+
+// ============================================================================
+// Callback Pages
+// ============================================================================
+
+/// Placeholder in the failure page replaced with the (escaped) reason
+const MESSAGE_PLACEHOLDER: &str = "{message}";
+
+/// HTML pages shown in the browser when Google redirects back to Pedaru
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallbackPages {
+    pub success: String,
+    /// `{message}` is replaced with why signing in failed
+    pub failure: String,
+    /// Shown for a redirect that doesn't belong to the current sign-in
+    pub state_mismatch: String,
+}
+
+impl Default for CallbackPages {
+    fn default() -> Self {
+        Self {
+            success: "<html><body><h1>Authentication Successful!</h1><p>You can close this window and return to Pedaru.</p><script>setTimeout(() => window.close(), 2000);</script></body></html>".to_string(),
+            failure: "<html><body><h1>Authentication Failed</h1><p>{message}</p></body></html>".to_string(),
+            state_mismatch: "<html><body><h1>Authentication Failed</h1><p>State verification failed.</p></body></html>".to_string(),
+        }
+    }
+}
+
+impl CallbackPages {
+    /// The failure page explaining `message`
+    fn failure_page(&self, message: &str) -> String {
+        self.failure
+            .replace(MESSAGE_PLACEHOLDER, &escape_html(message))
+    }
+}
+
+/// Get the callback pages, using the built-in page for any not customized
+pub fn get_callback_pages(app: &AppHandle) -> Result<CallbackPages, PedaruError> {
+    let defaults = CallbackPages::default();
+    let page = |key: &str, default: String| -> Result<String, PedaruError> {
+        Ok(settings::get_setting(app, key)?
+            .filter(|page| !page.trim().is_empty())
+            .unwrap_or(default))
+    };
+    Ok(CallbackPages {
+        success: page(settings::KEY_OAUTH_SUCCESS_PAGE, defaults.success)?,
+        failure: page(settings::KEY_OAUTH_FAILURE_PAGE, defaults.failure)?,
+        state_mismatch: page(
+            settings::KEY_OAUTH_STATE_MISMATCH_PAGE,
+            defaults.state_mismatch,
+        )?,
+    })
+}
+
+/// Save the callback pages; blank or built-in pages are stored as unset so
+/// future changes to the built-in pages still apply
+pub fn save_callback_pages(app: &AppHandle, pages: &CallbackPages) -> Result<(), PedaruError> {
+    let defaults = CallbackPages::default();
+    for (key, page, default) in [
+        (
+            settings::KEY_OAUTH_SUCCESS_PAGE,
+            &pages.success,
+            &defaults.success,
+        ),
+        (
+            settings::KEY_OAUTH_FAILURE_PAGE,
+            &pages.failure,
+            &defaults.failure,
+        ),
+        (
+            settings::KEY_OAUTH_STATE_MISMATCH_PAGE,
+            &pages.state_mismatch,
+            &defaults.state_mismatch,
+        ),
+    ] {
+        if page.trim().is_empty() || page == default {
+            settings::delete_setting(app, key)?;
+        } else {
+            settings::set_setting(app, key, page)?;
+        }
+    }
+    Ok(())
+}
+
+/// An HTML response for the browser (UTF-8, so translated pages render)
+fn html_response(html: String) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(html).with_header(
+        tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..])
+            .expect("static header is valid"),
+    )
+}
+
+// ============================================================================
+// Token Expiry
+// ============================================================================
+
+/// The current time on both the wall clock and the monotonic clock
+#[derive(Debug, Clone, Copy)]
+struct ClockReading {
+    /// Unix timestamp in seconds
+    wall: i64,
+    monotonic: Instant,
+}
+
+impl ClockReading {
+    fn now() -> Self {
+        Self {
+            wall: now_timestamp(),
+            monotonic: Instant::now(),
+        }
+    }
+}
+
+/// When a token obtained by this process expires, on the monotonic clock
+#[derive(Debug, Clone, Copy)]
+struct TokenDeadline {
+    expires_at: Instant,
+    lifetime_secs: i64,
+}
+
+impl TokenDeadline {
+    fn new(requested_at: Instant, expires_in: i64) -> Self {
+        Self {
+            expires_at: requested_at + std::time::Duration::from_secs(expires_in.max(0) as u64),
+            lifetime_secs: expires_in,
+        }
+    }
+}
+
+fn token_deadline(account_id: i64) -> Option<TokenDeadline> {
+    TOKEN_DEADLINES
+        .lock()
         .unwrap()
-        .as_secs() as i64;
+        .as_ref()
+        .and_then(|deadlines| deadlines.get(&account_id).copied())
+}
 
-    if let Some(expiry) = auth_state.token_expiry
-        && now >= expiry - 300
-    {
-        // Token expired or expiring soon, refresh it
-        return refresh_access_token(app).await;
+fn set_token_deadline(account_id: i64, deadline: Option<TokenDeadline>) {
+    let mut deadlines = TOKEN_DEADLINES.lock().unwrap();
+    let deadlines = deadlines.get_or_insert_with(HashMap::new);
+    match deadline {
+        Some(deadline) => deadlines.insert(account_id, deadline),
+        None => deadlines.remove(&account_id),
+    };
+}
+
+/// Check a refresh margin is usable: non-negative, and short enough that a
+/// fresh token isn't due for a refresh straight away
+fn validate_refresh_margin(secs: i64) -> Result<i64, PedaruError> {
+    if (0..=MAX_TOKEN_REFRESH_MARGIN_SECS).contains(&secs) {
+        Ok(secs)
+    } else {
+        Err(PedaruError::OAuth(OAuthError::InvalidRefreshMargin {
+            secs,
+            max: MAX_TOKEN_REFRESH_MARGIN_SECS,
+        }))
     }
+}
+
+/// Load the refresh margin from settings (called on app startup)
+pub fn init_token_refresh_margin(app: &AppHandle) {
+    let margin = settings::get_setting(app, settings::KEY_TOKEN_REFRESH_MARGIN_SECS)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<i64>().ok())
+        .and_then(|secs| validate_refresh_margin(secs).ok())
+        .unwrap_or(DEFAULT_TOKEN_REFRESH_MARGIN_SECS);
+    TOKEN_REFRESH_MARGIN_SECS.store(margin, Ordering::SeqCst);
+}
 
-    Ok(access_token)
+/// Get how many seconds before expiry tokens are refreshed
+pub fn get_token_refresh_margin() -> i64 {
+    TOKEN_REFRESH_MARGIN_SECS.load(Ordering::SeqCst)
 }
 
-/// Get current authentication status
+/// Change the refresh margin (None restores the default)
+pub fn set_token_refresh_margin(app: &AppHandle, secs: Option<i64>) -> Result<(), PedaruError> {
+    let margin = match secs {
+        Some(secs) => {
+            let secs = validate_refresh_margin(secs)?;
+            settings::set_setting(
+                app,
+                settings::KEY_TOKEN_REFRESH_MARGIN_SECS,
+                &secs.to_string(),
+            )?;
+            secs
+        }
+        None => {
+            settings::delete_setting(app, settings::KEY_TOKEN_REFRESH_MARGIN_SECS)?;
+            DEFAULT_TOKEN_REFRESH_MARGIN_SECS
+        }
+    };
+    TOKEN_REFRESH_MARGIN_SECS.store(margin, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Refresh margin for a token valid for `lifetime_secs`
+fn refresh_margin_secs(margin_secs: i64, lifetime_secs: i64) -> i64 {
+    margin_secs.min(lifetime_secs / TOKEN_REFRESH_MARGIN_MAX_LIFETIME_DIVISOR)
+}
+
+/// Whether a token with wall-clock `expiry` is due for a refresh, i.e. within
+/// `margin_secs` of expiring
+///
+/// A monotonic `deadline` wins when this process obtained the token, so a
+/// wrong or jumping system clock neither discards a fresh token nor keeps an
+/// expired one. Tokens from an earlier run fall back to the wall clock.
+fn is_expiring(
+    expiry: i64,
+    deadline: Option<TokenDeadline>,
+    now: ClockReading,
+    margin_secs: i64,
+) -> bool {
+    match deadline {
+        Some(deadline) => {
+            let margin = std::time::Duration::from_secs(
+                refresh_margin_secs(margin_secs, deadline.lifetime_secs).max(0) as u64,
+            );
+            now.monotonic + margin >= deadline.expires_at
+        }
+        None => now.wall >= expiry - margin_secs,
+    }
+}
+
+/// Run `refresh` while holding the global refresh lock (single-flight)
+///
+/// Callers that were waiting on the lock re-check `read_fresh_token` first, so
+/// only the first caller hits the network and the rest reuse its saved token.
+/// The guard is dropped on every return path, including refresh errors.
+async fn single_flight_refresh<C, R, Fut>(
+    read_fresh_token: C,
+    refresh: R,
+) -> Result<String, PedaruError>
+where
+    C: Fn() -> Result<Option<String>, PedaruError>,
+    R: FnOnce() -> Fut,
+    Fut: Future<Output = Result<String, PedaruError>>,
+{
+    let _guard = get_refresh_lock().lock().await;
+
+    if let Some(token) = read_fresh_token()? {
+        return Ok(token);
+    }
+
+    refresh().await
+}
+
+/// Get a valid access token for an account (refreshing if necessary) - async version
+pub async fn get_valid_access_token(
+    app: &AppHandle,
+    account_id: i64,
+) -> Result<String, PedaruError> {
+    if let Some(token) = read_fresh_access_token(app, account_id)? {
+        return Ok(token);
+    }
+
+    // Token expired or expiring soon, refresh it (one network refresh at a time)
+    single_flight_refresh(
+        || read_fresh_access_token(app, account_id),
+        || refresh_access_token(app, account_id),
+    )
+    .await
+}
+
+// ============================================================================
+// Background Refresh
+// ============================================================================
+
+/// Whether the background task should refresh this account's token now
+///
+/// Accounts without tokens (logged out or never connected) are skipped.
+fn needs_background_refresh(
+    state: &AuthState,
+    deadline: Option<TokenDeadline>,
+    now: ClockReading,
+    margin_secs: i64,
+) -> bool {
+    state.access_token.is_some()
+        && state.refresh_token.is_some()
+        && state
+            .token_expiry
+            .is_some_and(|expiry| is_expiring(expiry, deadline, now, margin_secs))
+}
+
+/// Refresh every connected account whose token is about to expire
+///
+/// `failed_at` records when an account's last refresh failed, so a revoked
+/// refresh token isn't retried every interval.
+async fn refresh_expiring_tokens(app: &AppHandle, failed_at: &mut HashMap<i64, i64>) {
+    if !matches!(load_credentials(app), Ok(Some(_))) {
+        return;
+    }
+    let accounts = match list_accounts(app) {
+        Ok(accounts) => accounts,
+        Err(e) => {
+            log::warn!("Background token refresh: failed to list accounts: {}", e);
+            return;
+        }
+    };
+
+    let now = now_timestamp();
+    for account in accounts {
+        if failed_at
+            .get(&account.id)
+            .is_some_and(|failed| now < failed + BACKGROUND_REFRESH_RETRY_SECS)
+        {
+            continue;
+        }
+        match load_auth_state(app, account.id) {
+            Ok(Some(state))
+                if needs_background_refresh(
+                    &state,
+                    token_deadline(account.id),
+                    ClockReading::now(),
+                    get_token_refresh_margin(),
+                ) => {}
+            Ok(_) => continue,
+            Err(e) => {
+                log::warn!(
+                    "Background token refresh: failed to load account {}: {}",
+                    account.id,
+                    e
+                );
+                continue;
+            }
+        }
+
+        // Shares the on-demand guard: if a request refreshed first, the
+        // re-check finds its token, and a logout in between ends in an error
+        match single_flight_refresh(
+            || read_fresh_access_token(app, account.id),
+            || refresh_access_token(app, account.id),
+        )
+        .await
+        {
+            Ok(_) => {
+                failed_at.remove(&account.id);
+            }
+            Err(e) => {
+                log::warn!(
+                    "Background token refresh failed for account {}: {}",
+                    account.id,
+                    e
+                );
+                failed_at.insert(account.id, now);
+            }
+        }
+    }
+}
+
+/// Refresh access tokens shortly before they expire, for the app's lifetime
+///
+/// Spawned from setup so the first Drive request after expiry doesn't stall on
+/// a refresh. Successful refreshes emit `AUTH_STATUS_CHANGED_EVENT`.
+pub async fn run_background_refresh(app: AppHandle) {
+    let mut failed_at = HashMap::new();
+    loop {
+        refresh_expiring_tokens(&app, &mut failed_at).await;
+        tokio::time::sleep(BACKGROUND_REFRESH_INTERVAL).await;
+    }
+}
+
+/// List all known accounts, default account first
+pub fn list_accounts(app: &AppHandle) -> Result<Vec<GoogleAccount>, PedaruError> {
+    let conn = open_db(app)?;
+    let mut stmt = conn
+        .prepare("SELECT id, label FROM google_accounts ORDER BY id")
+        .db_err()?;
+    let accounts = stmt
+        .query_map([], |row| {
+            Ok(GoogleAccount {
+                id: row.get(0)?,
+                label: row.get(1)?,
+            })
+        })
+        .db_err()?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(accounts)
+}
+
+/// Find the account with `label` (case-insensitive), creating it if needed
+pub fn get_or_create_account(app: &AppHandle, label: &str) -> Result<GoogleAccount, PedaruError> {
+    get_or_create_account_on(&open_db(app)?, label)
+}
+
+/// `get_or_create_account` on an existing connection (or transaction)
+pub(crate) fn get_or_create_account_on(
+    conn: &rusqlite::Connection,
+    label: &str,
+) -> Result<GoogleAccount, PedaruError> {
+    conn.execute(
+        "INSERT INTO google_accounts (label, created_at) VALUES (?1, ?2)
+         ON CONFLICT(label) DO NOTHING",
+        rusqlite::params![label, now_timestamp()],
+    )
+    .db_err()?;
+    conn.query_row(
+        "SELECT id, label FROM google_accounts WHERE label = ?1",
+        [label],
+        |row| {
+            Ok(GoogleAccount {
+                id: row.get(0)?,
+                label: row.get(1)?,
+            })
+        },
+    )
+    .db_err()
+}
+
+/// Whether an account is signed in with tokens Google accepts
+pub fn is_authenticated(app: &AppHandle, account_id: i64) -> Result<bool, PedaruError> {
+    Ok(account_connection(app, account_id)? == AccountConnection::Connected)
+}
+
+/// Whether an account is connected, needs to reconnect, or is signed out
+pub fn account_connection(
+    app: &AppHandle,
+    account_id: i64,
+) -> Result<AccountConnection, PedaruError> {
+    let has_tokens =
+        load_auth_state(app, account_id)?.is_some_and(|state| state.access_token.is_some());
+    Ok(AccountConnection::new(
+        has_tokens,
+        needs_reauth(app, account_id)?,
+    ))
+}
+
+/// Get current authentication status for every account
 pub fn get_auth_status(app: &AppHandle) -> Result<AuthStatus, PedaruError> {
-    let auth_state = load_auth_state(app)?;
-
-    match auth_state {
-        Some(state) => Ok(AuthStatus {
-            configured: true,
-            authenticated: state.access_token.is_some(),
-        }),
-        None => Ok(AuthStatus {
+    if load_credentials(app)?.is_none() {
+        return Ok(AuthStatus {
             configured: false,
             authenticated: false,
-        }),
+            connection: AccountConnection::SignedOut,
+            granted_scopes: Vec::new(),
+            accounts: Vec::new(),
+        });
     }
+
+    let mut accounts = Vec::new();
+    for account in list_accounts(app)? {
+        let connection = account_connection(app, account.id)?;
+        accounts.push(AccountStatus {
+            id: account.id,
+            authenticated: connection == AccountConnection::Connected,
+            connection,
+            label: account.label,
+            granted_scopes: load_granted_scopes(app, account.id)?,
+        });
+    }
+
+    let default_account = accounts.iter().find(|a| a.id == DEFAULT_ACCOUNT_ID);
+    Ok(AuthStatus {
+        configured: true,
+        authenticated: default_account.is_some_and(|a| a.authenticated),
+        connection: default_account.map_or(AccountConnection::SignedOut, |a| a.connection),
+        granted_scopes: default_account
+            .map(|a| a.granted_scopes.clone())
+            .unwrap_or_default(),
+        accounts,
+    })
 }
 
 #[cfg(test)]
@@ -470,11 +1810,37 @@ mod tests {
 
     #[test]
     fn test_code_verifier_length() {
-        let verifier = generate_code_verifier();
+        let verifier = generate_code_verifier(CODE_VERIFIER_ENTROPY_BYTES).unwrap();
         assert!(verifier.len() >= 43);
         assert!(verifier.len() <= 128);
     }
 
+    #[test]
+    fn test_code_verifier_entropy_boundaries() {
+        // 32 bytes is the fewest that reach 43 characters
+        assert_eq!(generate_code_verifier(32).unwrap().len(), 43);
+        assert!(generate_code_verifier(31).is_none());
+
+        // 96 bytes encodes to exactly 128 characters; one more overflows
+        let verifier = generate_code_verifier(96).unwrap();
+        assert_eq!(verifier.len(), 128);
+        assert!(
+            verifier
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        );
+        assert!(generate_code_verifier(97).is_none());
+
+        for bytes in 0..200 {
+            assert_eq!(
+                is_valid_verifier_entropy(bytes),
+                (32..=96).contains(&bytes),
+                "{} bytes",
+                bytes
+            );
+        }
+    }
+
     #[test]
     fn test_code_challenge_generation() {
         let verifier = "test_verifier_12345";
@@ -485,6 +1851,514 @@ mod tests {
         assert!(!challenge.contains('/'));
     }
 
+    #[test]
+    fn test_reauth_scopes_keep_granted_and_add_defaults() {
+        assert_eq!(reauth_scopes(&[], None), DEFAULT_SCOPES);
+
+        let file = "https://www.googleapis.com/auth/drive.file".to_string();
+        assert_eq!(
+            reauth_scopes(std::slice::from_ref(&file), None),
+            format!("{} {}", file, DEFAULT_SCOPES)
+        );
+
+        let granted = vec![DEFAULT_SCOPES.to_string(), file.clone()];
+        assert_eq!(
+            reauth_scopes(&granted, Some(&file)),
+            format!("{} {}", DEFAULT_SCOPES, file)
+        );
+
+        let full = "https://www.googleapis.com/auth/drive";
+        assert_eq!(
+            reauth_scopes(&[], Some(full)),
+            format!("{} {}", DEFAULT_SCOPES, full)
+        );
+    }
+
+    #[test]
+    fn test_account_key_keeps_default_account_unsuffixed() {
+        assert_eq!(
+            account_key(secrets::keys::GOOGLE_ACCESS_TOKEN, DEFAULT_ACCOUNT_ID),
+            "google_access_token"
+        );
+        assert_eq!(
+            account_key(secrets::keys::GOOGLE_ACCESS_TOKEN, 2),
+            "google_access_token:2"
+        );
+    }
+
+    #[test]
+    fn test_background_refresh_only_for_expiring_connected_accounts() {
+        let now = ClockReading::now();
+        let state = |access: Option<&str>, refresh: Option<&str>, expiry: Option<i64>| AuthState {
+            client_id: "id".to_string(),
+            client_secret: "secret".to_string(),
+            access_token: access.map(String::from),
+            refresh_token: refresh.map(String::from),
+            token_expiry: expiry,
+        };
+
+        let margin = DEFAULT_TOKEN_REFRESH_MARGIN_SECS;
+        let expiring = now.wall + margin;
+        assert!(needs_background_refresh(
+            &state(Some("a"), Some("r"), Some(expiring)),
+            None,
+            now,
+            margin
+        ));
+        assert!(!needs_background_refresh(
+            &state(Some("a"), Some("r"), Some(expiring + 1)),
+            None,
+            now,
+            margin
+        ));
+        // Logged out, or no way to refresh
+        assert!(!needs_background_refresh(
+            &state(None, None, Some(expiring)),
+            None,
+            now,
+            margin
+        ));
+        assert!(!needs_background_refresh(
+            &state(Some("a"), None, Some(expiring)),
+            None,
+            now,
+            margin
+        ));
+        assert!(!needs_background_refresh(
+            &state(Some("a"), Some("r"), None),
+            None,
+            now,
+            margin
+        ));
+    }
+
+    #[test]
+    fn test_token_expiry_tolerates_clock_skew() {
+        let requested_at = Instant::now();
+        let deadline = Some(TokenDeadline::new(requested_at, 3600));
+        let at = |wall: i64, elapsed_secs: u64| ClockReading {
+            wall,
+            monotonic: requested_at + std::time::Duration::from_secs(elapsed_secs),
+        };
+        let expiry = 1_000_000 + 3600;
+
+        // Clock jumped forward a day after the refresh: the token is still fresh
+        assert!(!is_expiring(
+            expiry,
+            deadline,
+            at(1_000_000 + 86_400, 60),
+            300
+        ));
+        // Clock jumped back a day: the token still expires on schedule
+        assert!(is_expiring(
+            expiry,
+            deadline,
+            at(1_000_000 - 86_400, 3600 - 300),
+            300
+        ));
+        assert!(!is_expiring(
+            expiry,
+            deadline,
+            at(1_000_000 - 86_400, 3600 - 301),
+            300
+        ));
+        // Tokens from an earlier run can only go by the wall clock
+        assert!(is_expiring(expiry, None, at(expiry - 300, 0), 300));
+        assert!(!is_expiring(expiry, None, at(expiry - 301, 0), 300));
+    }
+
+    #[test]
+    fn test_short_lived_token_not_expiring_on_arrival() {
+        let requested_at = Instant::now();
+        let deadline = Some(TokenDeadline::new(requested_at, 120));
+        let at = |elapsed_secs: u64| ClockReading {
+            wall: 0,
+            monotonic: requested_at + std::time::Duration::from_secs(elapsed_secs),
+        };
+
+        assert!(!is_expiring(0, deadline, at(0), 300));
+        assert!(!is_expiring(0, deadline, at(59), 300));
+        assert!(is_expiring(0, deadline, at(60), 300));
+    }
+
+    #[test]
+    fn test_refresh_margin_is_configurable() {
+        let expiry = 1_000_000;
+        let at = |wall: i64| ClockReading {
+            wall,
+            monotonic: Instant::now(),
+        };
+
+        // A larger margin refreshes earlier
+        assert!(is_expiring(expiry, None, at(expiry - 900), 900));
+        assert!(!is_expiring(expiry, None, at(expiry - 901), 900));
+        assert!(!is_expiring(expiry, None, at(expiry - 900), 300));
+        // A zero margin waits for the expiry itself
+        assert!(!is_expiring(expiry, None, at(expiry - 1), 0));
+        assert!(is_expiring(expiry, None, at(expiry), 0));
+
+        assert_eq!(validate_refresh_margin(0).unwrap(), 0);
+        assert_eq!(
+            validate_refresh_margin(MAX_TOKEN_REFRESH_MARGIN_SECS).unwrap(),
+            MAX_TOKEN_REFRESH_MARGIN_SECS
+        );
+        for invalid in [-1, MAX_TOKEN_REFRESH_MARGIN_SECS + 1] {
+            assert_eq!(
+                validate_refresh_margin(invalid).unwrap_err().code(),
+                "oauth.invalid_refresh_margin"
+            );
+        }
+    }
+
+    #[test]
+    fn test_second_flow_stops_first_callback_server() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let serve = |tx: std::sync::mpsc::Sender<u16>| {
+            move |server: Server, port: u16, shutdown: &AtomicBool| {
+                let deadline = std::time::Instant::now()
+                    + std::time::Duration::from_secs(CALLBACK_TIMEOUT_SECS);
+                while let Ok(Some(_)) = next_callback_request(&server, deadline, shutdown) {}
+                drop(server);
+                tx.send(port).unwrap();
+            }
+        };
+
+        // Same sequence as calling start_auth_flow twice
+        stop_callback_server();
+        let first = spawn_callback_server(serve(tx.clone())).unwrap();
+        stop_callback_server();
+        assert_eq!(rx.try_recv(), Ok(first), "first server should have exited");
+
+        let second = spawn_callback_server(serve(tx)).unwrap();
+        stop_callback_server();
+        assert_eq!(rx.try_recv(), Ok(second));
+        assert!(OAUTH_CALLBACK_SERVER.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_redirect_uri_uses_port() {
+        assert_eq!(redirect_uri(8590), "http://127.0.0.1:8590/callback");
+    }
+
+    #[test]
+    fn test_auth_url_and_token_exchange_redirect_uri_match() {
+        let credentials = OAuthCredentials {
+            client_id: "client".to_string(),
+            client_secret: "secret".to_string(),
+        };
+        let uri = redirect_uri(8585);
+
+        let auth_url = build_auth_url(
+            &credentials.client_id,
+            &uri,
+            DEFAULT_SCOPES,
+            "state",
+            "challenge",
+        );
+        let encoded = auth_url
+            .split('&')
+            .find_map(|p| p.strip_prefix("redirect_uri="))
+            .unwrap();
+        let from_auth_url = urlencoding::decode(encoded).unwrap();
+
+        let params = token_exchange_params(&credentials, "code", "verifier", &uri);
+        let from_exchange = params
+            .iter()
+            .find(|(key, _)| *key == "redirect_uri")
+            .map(|(_, value)| *value)
+            .unwrap();
+
+        assert_eq!(from_auth_url.as_bytes(), from_exchange.as_bytes());
+    }
+
+    #[test]
+    fn test_concurrent_refresh_hits_token_endpoint_once() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // Mock token endpoint that counts refresh requests
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        let hits = Arc::new(AtomicUsize::new(0));
+        let server_hits = hits.clone();
+        thread::spawn(move || {
+            while let Ok(Some(request)) = server.recv_timeout(std::time::Duration::from_secs(5)) {
+                let n = server_hits.fetch_add(1, Ordering::SeqCst) + 1;
+                let body = format!(
+                    r#"{{"access_token":"token-{}","expires_in":3600,"token_type":"Bearer"}}"#,
+                    n
+                );
+                let _ = request.respond(Response::from_string(body));
+            }
+        });
+
+        // In-memory token store: (access_token, expiry), starting out expired
+        let store: Arc<Mutex<(String, i64)>> = Arc::new(Mutex::new(("stale".to_string(), 0)));
+        let token_url = format!("http://127.0.0.1:{}/token", port);
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let store = store.clone();
+                let token_url = token_url.clone();
+                thread::spawn(move || {
+                    let runtime = tokio::runtime::Builder::new_current_thread()
+                        .enable_all()
+                        .build()
+                        .unwrap();
+                    runtime.block_on(single_flight_refresh(
+                        || {
+                            let (token, expiry) = store.lock().unwrap().clone();
+                            let now = crate::db::now_timestamp();
+                            Ok((now < expiry - 300).then_some(token))
+                        },
+                        || async {
+                            // Simulate a slow network round-trip so callers pile up
+                            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                            let response: TokenResponse = reqwest::Client::new()
+                                .post(&token_url)
+                                .send()
+                                .await
+                                .unwrap()
+                                .json()
+                                .await
+                                .unwrap();
+                            let expiry = crate::db::now_timestamp() + 3600;
+                            *store.lock().unwrap() = (response.access_token.clone(), expiry);
+                            Ok(response.access_token)
+                        },
+                    ))
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap().unwrap(), "token-1");
+        }
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_refresh_lock_released_after_error() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+
+        let failed = runtime.block_on(single_flight_refresh(
+            || Ok(None),
+            || async {
+                Err(PedaruError::OAuth(OAuthError::TokenRefreshFailed(
+                    "boom".to_string(),
+                )))
+            },
+        ));
+        assert!(failed.is_err());
+
+        // A poisoned or leaked guard would make this hang
+        let recovered = runtime.block_on(single_flight_refresh(
+            || Ok(None),
+            || async { Ok("fresh".to_string()) },
+        ));
+        assert_eq!(recovered.unwrap(), "fresh");
+    }
+
+    fn token(access_token: &str) -> TokenResponse {
+        TokenResponse {
+            access_token: access_token.to_string(),
+            refresh_token: None,
+            expires_in: Some(3600),
+            token_type: "Bearer".to_string(),
+            scope: None,
+        }
+    }
+
+    /// Run `retry_with_backoff` over a scripted sequence of attempt outcomes
+    fn run_retry(
+        outcomes: Vec<Result<TokenResponse, PedaruError>>,
+    ) -> (Result<TokenResponse, PedaruError>, usize) {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let mut outcomes = std::collections::VecDeque::from(outcomes);
+        let mut calls = 0;
+        let result = runtime.block_on(retry_with_backoff(
+            || {
+                calls += 1;
+                let outcome = outcomes.pop_front().unwrap();
+                async move { outcome }
+            },
+            REFRESH_MAX_ATTEMPTS,
+            std::time::Duration::ZERO,
+        ));
+        (result, calls)
+    }
+
+    #[test]
+    fn test_retry_recovers_after_transient_failure() {
+        let (result, calls) = run_retry(vec![
+            Err(PedaruError::OAuth(OAuthError::HttpRequestFailed {
+                status: None,
+                message: "connection reset".to_string(),
+            })),
+            Ok(token("fresh")),
+        ]);
+        assert_eq!(result.unwrap().access_token, "fresh");
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn test_retry_stops_on_invalid_grant() {
+        let (result, calls) = run_retry(vec![
+            Err(PedaruError::OAuth(OAuthError::ReauthRequired(
+                DEFAULT_ACCOUNT_ID,
+            ))),
+            Ok(token("unreachable")),
+        ]);
+        assert!(matches!(
+            result,
+            Err(PedaruError::OAuth(OAuthError::ReauthRequired(_)))
+        ));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_is_invalid_grant() {
+        assert!(is_invalid_grant(
+            r#"{"error": "invalid_grant", "error_description": "Token has been expired or revoked."}"#
+        ));
+        assert!(!is_invalid_grant(
+            r#"{"error": "invalid_client", "error_description": "Unauthorized"}"#
+        ));
+        assert!(!is_invalid_grant("invalid_grant"));
+        assert!(!is_invalid_grant(""));
+    }
+
+    #[test]
+    fn test_retry_gives_up_after_max_attempts() {
+        let (result, calls) = run_retry(
+            (0..REFRESH_MAX_ATTEMPTS)
+                .map(|_| {
+                    Err(PedaruError::OAuth(OAuthError::HttpRequestFailed {
+                        status: Some(503),
+                        message: "Service Unavailable".to_string(),
+                    }))
+                })
+                .collect(),
+        );
+        assert!(result.is_err());
+        assert_eq!(calls, REFRESH_MAX_ATTEMPTS as usize);
+    }
+
+    #[test]
+    fn test_backoff_delay_grows_exponentially() {
+        let base = std::time::Duration::from_millis(100);
+        let first = backoff_delay(base, 0);
+        let third = backoff_delay(base, 2);
+        assert!(first >= base && first <= base * 3 / 2);
+        assert!(third >= base * 4 && third <= base * 6);
+    }
+
+    fn credentials(client_id: &str, client_secret: &str) -> OAuthCredentials {
+        OAuthCredentials {
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_validate_credentials() {
+        let valid = credentials(
+            " 1234-abc123.apps.googleusercontent.com\n",
+            " GOCSPX-secret ",
+        );
+        let trimmed = validate_credentials(&valid, true).unwrap();
+        assert_eq!(trimmed.client_id, "1234-abc123.apps.googleusercontent.com");
+        assert_eq!(trimmed.client_secret, "GOCSPX-secret");
+
+        for (client_id, client_secret) in [
+            ("", "secret"),
+            ("1234-abc123.apps.googleusercontent.com", "  "),
+            ("1234-abc123.apps.googleusercontent.com", "GOCSPX secret"),
+            ("1234-abc 123.apps.googleusercontent.com", "secret"),
+            // Truncated while copying
+            ("1234-abc123.apps.googleuser", "secret"),
+            (".apps.googleusercontent.com", "secret"),
+            ("1234/abc.apps.googleusercontent.com", "secret"),
+        ] {
+            assert!(
+                matches!(
+                    validate_credentials(&credentials(client_id, client_secret), true),
+                    Err(OAuthError::InvalidCredentials(_))
+                ),
+                "{client_id:?} / {client_secret:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_credentials_lax_mode() {
+        let custom = credentials("test-client", "secret");
+        assert!(validate_credentials(&custom, true).is_err());
+        assert_eq!(
+            validate_credentials(&custom, false).unwrap().client_id,
+            "test-client"
+        );
+        // Lax mode still rejects missing values
+        assert!(validate_credentials(&credentials("test-client", ""), false).is_err());
+        assert!(validate_credentials(&credentials("", "secret"), false).is_err());
+    }
+
+    #[test]
+    fn test_account_connection() {
+        assert_eq!(
+            AccountConnection::new(true, false),
+            AccountConnection::Connected
+        );
+        assert_eq!(
+            AccountConnection::new(false, false),
+            AccountConnection::SignedOut
+        );
+        // Rejected tokens need a reconnect whether or not they are still stored
+        assert_eq!(
+            AccountConnection::new(true, true),
+            AccountConnection::NeedsReauth
+        );
+        assert_eq!(
+            AccountConnection::new(false, true),
+            AccountConnection::NeedsReauth
+        );
+        assert_eq!(
+            serde_json::to_string(&AccountConnection::NeedsReauth).unwrap(),
+            "\"needs_reauth\""
+        );
+    }
+
+    #[test]
+    fn test_failure_page_escapes_message() {
+        let pages = CallbackPages {
+            failure: "<p>Échec : {message}</p>".to_string(),
+            ..CallbackPages::default()
+        };
+        assert_eq!(
+            pages.failure_page("access_denied: <b>no</b>"),
+            "<p>Échec : access_denied: &lt;b&gt;no&lt;/b&gt;</p>"
+        );
+        assert!(
+            CallbackPages::default()
+                .failure_page("Please try again.")
+                .contains("<p>Please try again.</p>")
+        );
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("<script>\"a\" & b</script>"),
+            "&lt;script&gt;&quot;a&quot; &amp; b&lt;/script&gt;"
+        );
+    }
+
     #[test]
     fn test_state_generation() {
         let state1 = generate_state();
@@ -0,0 +1,181 @@
+//! OpenAI API client for translation
+//!
+//! This module provides the OpenAI (Chat Completions) backend for `translation`.
+
+use reqwest::{RequestBuilder, Response};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::{PedaruError, TranslationError};
+use crate::translation::{
+    GenerationParams, TranslationProvider, http_client, send_json_request, send_request,
+};
+
+/// OpenAI Chat Completions endpoint
+const OPENAI_CHAT_COMPLETIONS_URL: &str = "https://api.openai.com/v1/chat/completions";
+
+// ============================================================================
+// Request Types
+// ============================================================================
+
+#[derive(Debug, Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage<'a>>,
+    response_format: ResponseFormat,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_completion_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatMessage<'a> {
+    role: &'static str,
+    content: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct ResponseFormat {
+    #[serde(rename = "type")]
+    format_type: &'static str,
+}
+
+// ============================================================================
+// API Functions
+// ============================================================================
+
+/// OpenAI backend for translation and explanation
+pub struct OpenAiProvider {
+    api_key: String,
+    model: String,
+    params: GenerationParams,
+}
+
+impl OpenAiProvider {
+    pub fn new(api_key: &str, model: &str, params: GenerationParams) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            params,
+        }
+    }
+}
+
+impl OpenAiProvider {
+    /// Build a Chat Completions request, optionally streamed
+    fn build_request(
+        &self,
+        prompt: &str,
+        system_instruction: &str,
+        stream: bool,
+    ) -> Result<RequestBuilder, PedaruError> {
+        let provider = self.name();
+        if self.api_key.is_empty() {
+            return Err(TranslationError::ApiKeyMissing { provider }.into());
+        }
+
+        let request = ChatRequest {
+            model: &self.model,
+            messages: vec![
+                ChatMessage {
+                    role: "system",
+                    content: system_instruction,
+                },
+                ChatMessage {
+                    role: "user",
+                    content: prompt,
+                },
+            ],
+            response_format: ResponseFormat {
+                format_type: "json_object",
+            },
+            temperature: self.params.temperature,
+            max_completion_tokens: self.params.max_output_tokens,
+            stream,
+        };
+
+        Ok(http_client(provider, stream)?
+            .post(OPENAI_CHAT_COMPLETIONS_URL)
+            .bearer_auth(&self.api_key)
+            .json(&request))
+    }
+}
+
+impl TranslationProvider for OpenAiProvider {
+    fn name(&self) -> &'static str {
+        "OpenAI"
+    }
+
+    async fn generate(
+        &self,
+        prompt: &str,
+        system_instruction: &str,
+    ) -> Result<String, PedaruError> {
+        let provider = self.name();
+        let request = self.build_request(prompt, system_instruction, false)?;
+        let body = send_json_request(provider, request).await?;
+
+        extract_text(&body).ok_or_else(|| {
+            TranslationError::InvalidResponse {
+                provider,
+                message: "No text in response".to_string(),
+            }
+            .into()
+        })
+    }
+
+    async fn start_stream(
+        &self,
+        prompt: &str,
+        system_instruction: &str,
+    ) -> Result<Response, PedaruError> {
+        let request = self.build_request(prompt, system_instruction, true)?;
+        send_request(self.name(), request).await
+    }
+
+    fn stream_delta(&self, event: &Value) -> Option<String> {
+        event
+            .get("choices")?
+            .get(0)?
+            .get("delta")?
+            .get("content")?
+            .as_str()
+            .map(str::to_string)
+    }
+}
+
+/// Extract the first choice's message content from a Chat Completions response
+fn extract_text(body: &Value) -> Option<String> {
+    body.get("choices")?
+        .get(0)?
+        .get("message")?
+        .get("content")?
+        .as_str()
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_text() {
+        let body = serde_json::json!({
+            "choices": [{ "message": { "role": "assistant", "content": "{\"summary\": \"x\"}" } }]
+        });
+        assert_eq!(extract_text(&body).as_deref(), Some("{\"summary\": \"x\"}"));
+        assert_eq!(extract_text(&serde_json::json!({ "choices": [] })), None);
+    }
+
+    #[test]
+    fn test_stream_delta() {
+        let provider = OpenAiProvider::new("key", "m", GenerationParams::default());
+        let event = serde_json::json!({ "choices": [{ "delta": { "content": "Hel" } }] });
+        assert_eq!(provider.stream_delta(&event).as_deref(), Some("Hel"));
+        let done = serde_json::json!({ "choices": [{ "delta": {}, "finish_reason": "stop" }] });
+        assert_eq!(provider.stream_delta(&done), None);
+    }
+}
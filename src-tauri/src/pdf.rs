@@ -4,9 +4,10 @@
 //! - Table of Contents (TOC) extraction
 //! - Named destination resolution
 //! - Page number resolution from PDF destinations
+//! - Document metadata (title, author, page count)
 
 use crate::encoding::{decode_name_string, decode_pdf_string, decode_utf16be_or_utf8};
-use crate::types::TocEntry;
+use crate::types::{DocumentMetadata, TocEntry};
 use lopdf::Document;
 use std::collections::HashMap;
 
@@ -238,60 +239,60 @@ pub fn parse_outline_item(
 /// This function parses the PDF outline structure and returns a vector
 /// of top-level TOC entries, each potentially containing nested children.
 pub fn extract_toc(doc: &Document) -> Vec<TocEntry> {
-    eprintln!("[Pedaru] extract_toc called");
+    log::debug!("extract_toc called");
     let mut toc = Vec::new();
 
     let named_dests = build_named_destinations(doc);
-    eprintln!("[Pedaru] Named destinations count: {}", named_dests.len());
+    log::debug!("Named destinations count: {}", named_dests.len());
 
     let catalog = match doc.catalog() {
         Ok(c) => {
-            eprintln!("[Pedaru] Got catalog successfully");
+            log::debug!("Got catalog successfully");
             c
         }
         Err(e) => {
-            eprintln!("[Pedaru] Failed to get catalog: {:?}", e);
+            log::debug!("Failed to get catalog: {:?}", e);
             return toc;
         }
     };
 
     let outlines_ref = match catalog.get(b"Outlines") {
         Ok(lopdf::Object::Reference(r)) => {
-            eprintln!("[Pedaru] Got Outlines reference: {:?}", r);
+            log::debug!("Got Outlines reference: {:?}", r);
             *r
         }
         Ok(other) => {
-            eprintln!("[Pedaru] Outlines is not a reference: {:?}", other);
+            log::debug!("Outlines is not a reference: {:?}", other);
             return toc;
         }
         Err(e) => {
-            eprintln!("[Pedaru] No Outlines in catalog: {:?}", e);
+            log::debug!("No Outlines in catalog: {:?}", e);
             return toc;
         }
     };
 
     let outlines = match doc.get_dictionary(outlines_ref) {
         Ok(o) => {
-            eprintln!("[Pedaru] Got Outlines dictionary");
+            log::debug!("Got Outlines dictionary");
             o
         }
         Err(e) => {
-            eprintln!("[Pedaru] Failed to get Outlines dictionary: {:?}", e);
+            log::debug!("Failed to get Outlines dictionary: {:?}", e);
             return toc;
         }
     };
 
     let first_ref = match outlines.get(b"First") {
         Ok(lopdf::Object::Reference(r)) => {
-            eprintln!("[Pedaru] Got First reference: {:?}", r);
+            log::debug!("Got First reference: {:?}", r);
             *r
         }
         Ok(other) => {
-            eprintln!("[Pedaru] First is not a reference: {:?}", other);
+            log::debug!("First is not a reference: {:?}", other);
             return toc;
         }
         Err(e) => {
-            eprintln!("[Pedaru] No First in Outlines: {:?}", e);
+            log::debug!("No First in Outlines: {:?}", e);
             return toc;
         }
     };
@@ -314,13 +315,46 @@ pub fn extract_toc(doc: &Document) -> Vec<TocEntry> {
             });
     }
 
-    eprintln!(
-        "[Pedaru] extract_toc finished, found {} top-level entries",
+    log::debug!(
+        "extract_toc finished, found {} top-level entries",
         toc.len()
     );
     toc
 }
 
+/// Extract title, author and page count from a PDF document
+///
+/// Blank Title/Author entries are treated as missing.
+pub fn extract_metadata(doc: &Document) -> DocumentMetadata {
+    let mut title = None;
+    let mut author = None;
+
+    if let Ok(lopdf::Object::Reference(ref_id)) = doc.trailer.get(b"Info")
+        && let Ok(info_dict) = doc.get_dictionary(*ref_id)
+    {
+        let non_empty = |s: String| {
+            let trimmed = s.trim();
+            (!trimmed.is_empty()).then(|| trimmed.to_string())
+        };
+        title = info_dict
+            .get(b"Title")
+            .ok()
+            .and_then(decode_pdf_string)
+            .and_then(non_empty);
+        author = info_dict
+            .get(b"Author")
+            .ok()
+            .and_then(decode_pdf_string)
+            .and_then(non_empty);
+    }
+
+    DocumentMetadata {
+        title,
+        author,
+        page_count: doc.get_pages().len() as u32,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -411,4 +445,27 @@ mod tests {
         let result = extract_toc(&doc);
         assert!(result.is_empty());
     }
+
+    #[test]
+    fn test_extract_metadata_reads_info_dictionary() {
+        let mut doc = Document::new();
+        let mut info = lopdf::Dictionary::new();
+        info.set("Title", lopdf::Object::string_literal("  A Title "));
+        info.set("Author", lopdf::Object::string_literal(""));
+        let info_id = doc.add_object(info);
+        doc.trailer.set("Info", info_id);
+
+        let metadata = extract_metadata(&doc);
+        assert_eq!(metadata.title.as_deref(), Some("A Title"));
+        assert_eq!(metadata.author, None);
+        assert_eq!(metadata.page_count, 0);
+    }
+
+    #[test]
+    fn test_extract_metadata_without_info() {
+        let doc = Document::new();
+        let metadata = extract_metadata(&doc);
+        assert_eq!(metadata.title, None);
+        assert_eq!(metadata.author, None);
+    }
 }
@@ -0,0 +1,293 @@
+//! Full-text search across downloaded PDFs
+//!
+//! Text is extracted per page from completed cloud downloads with `lopdf` and
+//! stored in the `pdf_text_index` FTS5 table. Indexing is incremental: a file
+//! is only re-indexed when its Drive modification time changes.
+
+use lopdf::Document;
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::db::{ToDbError, now_timestamp, open_db};
+use crate::error::{PdfError, PedaruError};
+
+/// Maximum number of hits returned by `search_pdfs`
+const MAX_SEARCH_RESULTS: i64 = 100;
+
+/// Number of tokens around a match included in a snippet
+const SNIPPET_TOKENS: i64 = 12;
+
+/// Highlight markers inserted by FTS5, replaced after HTML escaping
+const MATCH_START: &str = "\u{2}";
+const MATCH_END: &str = "\u{3}";
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// A page matching a search query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub drive_file_id: String,
+    pub page: i64,
+    /// HTML-escaped excerpt with matches wrapped in `<mark>`
+    pub snippet: String,
+}
+
+/// Outcome of indexing a single document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexOutcome {
+    Indexed,
+    /// Unchanged since the last run
+    UpToDate,
+    /// Encrypted or has no extractable text
+    Skipped,
+}
+
+/// Summary of an indexing run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexSummary {
+    pub indexed: i32,
+    pub up_to_date: i32,
+    pub skipped: i32,
+    pub failed: i32,
+}
+
+// ============================================================================
+// Indexing
+// ============================================================================
+
+/// Index a downloaded cloud document if it changed since it was last indexed
+pub fn index_document(app: &AppHandle, drive_file_id: &str) -> Result<IndexOutcome, PedaruError> {
+    let mut conn = open_db(app)?;
+
+    let Some((local_path, modified_time)) = conn
+        .query_row(
+            "SELECT local_path, drive_modified_time FROM bookshelf_cloud
+             WHERE drive_file_id = ?1 AND download_status = 'completed'
+               AND local_path IS NOT NULL",
+            [drive_file_id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)),
+        )
+        .optional()
+        .db_err()?
+    else {
+        return Ok(IndexOutcome::Skipped);
+    };
+
+    let indexed_time: Option<Option<String>> = conn
+        .query_row(
+            "SELECT drive_modified_time FROM pdf_text_index_state WHERE drive_file_id = ?1",
+            [drive_file_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .db_err()?;
+    if indexed_time.is_some_and(|t| t.is_some() && t == modified_time) {
+        return Ok(IndexOutcome::UpToDate);
+    }
+
+    let doc = Document::load(&local_path).map_err(|source| PdfError::LoadFailed {
+        path: local_path.clone(),
+        source,
+    })?;
+
+    let pages: Vec<(u32, String)> = if doc.is_encrypted() {
+        log::info!("Skipping encrypted PDF for search: {}", local_path);
+        Vec::new()
+    } else {
+        doc.get_pages()
+            .keys()
+            .filter_map(|&page| {
+                let text = doc.extract_text(&[page]).ok()?;
+                let text = text.trim();
+                (!text.is_empty()).then(|| (page, text.to_string()))
+            })
+            .collect()
+    };
+
+    let status = if pages.is_empty() {
+        "skipped"
+    } else {
+        "indexed"
+    };
+
+    let tx = conn.transaction().db_err()?;
+    tx.execute(
+        "DELETE FROM pdf_text_index WHERE drive_file_id = ?1",
+        [drive_file_id],
+    )
+    .db_err()?;
+    for (page, text) in &pages {
+        tx.execute(
+            "INSERT INTO pdf_text_index (drive_file_id, page, content) VALUES (?1, ?2, ?3)",
+            rusqlite::params![drive_file_id, page, text],
+        )
+        .db_err()?;
+    }
+    tx.execute(
+        "INSERT INTO pdf_text_index_state (drive_file_id, drive_modified_time, status, page_count, indexed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(drive_file_id) DO UPDATE SET
+           drive_modified_time = excluded.drive_modified_time,
+           status = excluded.status,
+           page_count = excluded.page_count,
+           indexed_at = excluded.indexed_at",
+        rusqlite::params![
+            drive_file_id,
+            modified_time,
+            status,
+            pages.len() as i64,
+            now_timestamp()
+        ],
+    )
+    .db_err()?;
+    tx.commit().db_err()?;
+
+    Ok(if pages.is_empty() {
+        IndexOutcome::Skipped
+    } else {
+        IndexOutcome::Indexed
+    })
+}
+
+/// Index every completed cloud download, skipping unchanged files
+///
+/// Failures on individual documents are logged and counted rather than
+/// aborting the run.
+pub fn index_all_documents(app: &AppHandle) -> Result<IndexSummary, PedaruError> {
+    let conn = open_db(app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT drive_file_id FROM bookshelf_cloud
+             WHERE download_status = 'completed' AND local_path IS NOT NULL",
+        )
+        .db_err()?;
+    let file_ids: Vec<String> = stmt
+        .query_map([], |row| row.get(0))
+        .db_err()?
+        .filter_map(|r| r.ok())
+        .collect();
+    drop(stmt);
+    drop(conn);
+
+    let mut summary = IndexSummary::default();
+    for file_id in file_ids {
+        match index_document(app, &file_id) {
+            Ok(IndexOutcome::Indexed) => summary.indexed += 1,
+            Ok(IndexOutcome::UpToDate) => summary.up_to_date += 1,
+            Ok(IndexOutcome::Skipped) => summary.skipped += 1,
+            Err(e) => {
+                log::warn!("Failed to index {} for search: {}", file_id, e);
+                summary.failed += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+// ============================================================================
+// Searching
+// ============================================================================
+
+/// Search downloaded PDFs for pages containing every term in `query`
+pub fn search_pdfs(app: &AppHandle, query: &str) -> Result<Vec<SearchHit>, PedaruError> {
+    let Some(fts_query) = build_fts_query(query) else {
+        return Ok(Vec::new());
+    };
+
+    let conn = open_db(app)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT i.drive_file_id, i.page,
+                    snippet(pdf_text_index, 2, ?2, ?3, '…', ?4)
+             FROM pdf_text_index i
+             JOIN bookshelf_cloud c ON c.drive_file_id = i.drive_file_id
+             WHERE pdf_text_index MATCH ?1 AND c.download_status = 'completed'
+             ORDER BY rank
+             LIMIT ?5",
+        )
+        .db_err()?;
+
+    let hits = stmt
+        .query_map(
+            rusqlite::params![
+                fts_query,
+                MATCH_START,
+                MATCH_END,
+                SNIPPET_TOKENS,
+                MAX_SEARCH_RESULTS
+            ],
+            |row| {
+                let snippet: String = row.get(2)?;
+                Ok(SearchHit {
+                    drive_file_id: row.get(0)?,
+                    page: row.get(1)?,
+                    snippet: highlight_snippet(&snippet),
+                })
+            },
+        )
+        .db_err()?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    Ok(hits)
+}
+
+/// Turn free-form user input into an FTS5 query
+///
+/// Each whitespace-separated term is quoted so FTS5 operators and syntax
+/// characters in the input are matched literally.
+fn build_fts_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Escape snippet text for HTML and turn match markers into `<mark>` tags
+fn highlight_snippet(snippet: &str) -> String {
+    snippet
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace(MATCH_START, "<mark>")
+        .replace(MATCH_END, "</mark>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_fts_query_quotes_terms() {
+        assert_eq!(
+            build_fts_query("neural  networks").as_deref(),
+            Some("\"neural\" \"networks\"")
+        );
+        assert_eq!(
+            build_fts_query("say \"hi\" OR x*").as_deref(),
+            Some("\"say\" \"\"\"hi\"\"\" \"OR\" \"x*\"")
+        );
+    }
+
+    #[test]
+    fn test_build_fts_query_empty() {
+        assert_eq!(build_fts_query("   "), None);
+    }
+
+    #[test]
+    fn test_highlight_snippet_escapes_html() {
+        let snippet = format!("a <b> {}match{} & more", MATCH_START, MATCH_END);
+        assert_eq!(
+            highlight_snippet(&snippet),
+            "a &lt;b&gt; <mark>match</mark> &amp; more"
+        );
+    }
+}
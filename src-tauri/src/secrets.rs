@@ -4,31 +4,83 @@
 //! It uses the OS keychain (via keyring-rs) for cross-platform secure storage:
 //! - macOS: Keychain
 //! - Windows: Credential Manager
-//! - Linux: Secret Service (gnome-keyring, KWallet, etc.)
+//! - Linux: kernel keyutils
 //!
 //! All secrets are stored in a single JSON entry to minimize keychain access prompts.
+//!
+//! When the keychain is unavailable (e.g. no Secret Service on headless Linux),
+//! secrets fall back to an encrypted file in the app config dir (see
+//! `secrets_file`). Once the keychain works it is used for the rest of the
+//! session; while it doesn't, every access checks it again, and secrets saved
+//! to the file meanwhile move into the keychain once it is back.
 
-use crate::error::PedaruError;
+use crate::error::{ConfigError, PedaruError, SecretsError};
+use crate::secrets_file;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::Manager;
 
 /// Service name for keyring storage
 const KEYRING_SERVICE: &str = "pedaru";
 /// Single key for all secrets (stored as JSON)
 const KEYRING_KEY: &str = "secrets";
 
+/// Human-readable name of the keyring backend selected in Cargo.toml,
+/// included in error messages
+#[cfg(target_os = "linux")]
+const KEYRING_BACKEND: &str = "Linux kernel keyring (keyutils)";
+#[cfg(target_os = "macos")]
+const KEYRING_BACKEND: &str = "macOS Keychain";
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+const KEYRING_BACKEND: &str = "default keyring store";
+
+/// Name of the encrypted fallback file in the app config dir
+const SECRETS_FILE_NAME: &str = "secrets.enc.json";
+
+/// Backend name used in error messages for the encrypted file
+const SECRETS_FILE_BACKEND: &str = "encrypted secrets file";
+
+/// Where secrets are persisted
+#[derive(Debug, Clone)]
+enum Backend {
+    Keychain,
+    /// Encrypted file, used when the keychain is unavailable
+    EncryptedFile(PathBuf),
+}
+
+impl Backend {
+    fn name(&self) -> &'static str {
+        match self {
+            Backend::Keychain => KEYRING_BACKEND,
+            Backend::EncryptedFile(_) => SECRETS_FILE_BACKEND,
+        }
+    }
+}
+
+/// Set once a keychain probe succeeds (a failed probe isn't remembered, so a
+/// keychain that was locked or not yet running is picked up later)
+static KEYCHAIN_AVAILABLE: AtomicBool = AtomicBool::new(false);
+
+/// Whether the last probe fell back to the encrypted file
+static USING_SECRETS_FILE: AtomicBool = AtomicBool::new(false);
+
 /// In-memory cache of all secrets to avoid repeated keychain access
 static SECRETS_CACHE: RwLock<Option<HashMap<String, String>>> = RwLock::new(None);
 
 /// Keys for secrets stored in keyring
 pub mod keys {
     pub const GEMINI_API_KEY: &str = "gemini_api_key";
+    pub const OPENAI_API_KEY: &str = "openai_api_key";
+    pub const ANTHROPIC_API_KEY: &str = "anthropic_api_key";
     pub const GOOGLE_CLIENT_ID: &str = "google_client_id";
     pub const GOOGLE_CLIENT_SECRET: &str = "google_client_secret";
     pub const GOOGLE_ACCESS_TOKEN: &str = "google_access_token";
     pub const GOOGLE_REFRESH_TOKEN: &str = "google_refresh_token";
     pub const GOOGLE_TOKEN_EXPIRY: &str = "google_token_expiry";
+    pub const DATABASE_KEY: &str = "database_key";
 }
 
 /// All secrets stored as a single JSON object
@@ -38,10 +90,100 @@ struct AllSecrets {
     secrets: HashMap<String, String>,
 }
 
+/// Map a keyring error, reporting a missing or locked backend separately
+fn keyring_error(
+    error: keyring::Error,
+    make: fn(&'static str, String) -> SecretsError,
+) -> SecretsError {
+    match error {
+        keyring::Error::NoStorageAccess(_) | keyring::Error::PlatformFailure(_) => {
+            SecretsError::BackendUnavailable {
+                backend: KEYRING_BACKEND,
+                message: error.to_string(),
+            }
+        }
+        _ => make(KEYRING_BACKEND, error.to_string()),
+    }
+}
+
+/// Open the keyring entry holding all secrets
+fn open_entry() -> Result<keyring::Entry, SecretsError> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_KEY).map_err(|e| {
+        keyring_error(e, |backend, message| SecretsError::EntryFailed {
+            backend,
+            message,
+        })
+    })
+}
+
+/// Check that the keychain can be read
+fn probe_keychain() -> Result<(), SecretsError> {
+    match open_entry()?.get_password() {
+        Ok(_) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(keyring_error(e, |backend, message| {
+            SecretsError::LoadFailed { backend, message }
+        })),
+    }
+}
+
+/// Path of the encrypted fallback file
+fn secrets_file_path(app: &tauri::AppHandle) -> Result<PathBuf, PedaruError> {
+    let config_dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| ConfigError::ConfigDirResolutionFailed(e.to_string()))?;
+    Ok(config_dir.join(SECRETS_FILE_NAME))
+}
+
+/// Pick the keychain if it works, otherwise the encrypted file
+///
+/// Only an unavailable keychain triggers the fallback; other keychain errors
+/// are returned so secrets aren't silently split across two stores.
+fn get_backend(app: &tauri::AppHandle) -> Result<Backend, PedaruError> {
+    if KEYCHAIN_AVAILABLE.load(Ordering::SeqCst) {
+        return Ok(Backend::Keychain);
+    }
+
+    match probe_keychain() {
+        Ok(()) => {
+            if !KEYCHAIN_AVAILABLE.swap(true, Ordering::SeqCst) {
+                log::info!("Secrets backend: {}", KEYRING_BACKEND);
+            }
+            USING_SECRETS_FILE.store(false, Ordering::SeqCst);
+            Ok(Backend::Keychain)
+        }
+        Err(SecretsError::BackendUnavailable { message, .. }) => {
+            if !USING_SECRETS_FILE.swap(true, Ordering::SeqCst) {
+                log::info!(
+                    "{} unavailable ({}), falling back to {}",
+                    KEYRING_BACKEND,
+                    message,
+                    SECRETS_FILE_BACKEND
+                );
+            }
+            Ok(Backend::EncryptedFile(secrets_file_path(app)?))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Select the secrets backend and log which one is active (called on app startup)
+pub fn init_secrets_backend(app: &tauri::AppHandle) {
+    if let Err(e) = get_backend(app) {
+        log::error!("Failed to initialize secrets storage: {}", e);
+    }
+}
+
+/// Read the active backend, bypassing the cache, and return its name
+pub fn check_backend(app: &tauri::AppHandle) -> Result<&'static str, PedaruError> {
+    let backend = get_backend(app)?;
+    load_secrets(app)?;
+    Ok(backend.name())
+}
+
 /// Load all secrets from keychain into cache (called once on first access)
 fn load_secrets_from_keychain() -> Result<HashMap<String, String>, PedaruError> {
-    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_KEY)
-        .map_err(|e| PedaruError::Secrets(format!("Failed to create keyring entry: {}", e)))?;
+    let entry = open_entry()?;
 
     match entry.get_password() {
         Ok(json) => {
@@ -49,44 +191,125 @@ fn load_secrets_from_keychain() -> Result<HashMap<String, String>, PedaruError>
             Ok(all_secrets.secrets)
         }
         Err(keyring::Error::NoEntry) => Ok(HashMap::new()),
-        Err(e) => Err(PedaruError::Secrets(format!(
-            "Failed to load secrets from keychain: {}",
-            e
-        ))),
+        Err(e) => Err(
+            keyring_error(e, |backend, message| SecretsError::LoadFailed {
+                backend,
+                message,
+            })
+            .into(),
+        ),
     }
 }
 
 /// Save all secrets from cache to keychain
 fn save_secrets_to_keychain(secrets: &HashMap<String, String>) -> Result<(), PedaruError> {
-    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_KEY)
-        .map_err(|e| PedaruError::Secrets(format!("Failed to create keyring entry: {}", e)))?;
+    let entry = open_entry()?;
 
     if secrets.is_empty() {
         // Delete the entry if no secrets remain
         match entry.delete_credential() {
             Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
-            Err(e) => Err(PedaruError::Secrets(format!(
-                "Failed to delete keyring entry: {}",
-                e
-            ))),
+            Err(e) => Err(
+                keyring_error(e, |backend, message| SecretsError::DeleteFailed {
+                    backend,
+                    message,
+                })
+                .into(),
+            ),
         }
     } else {
         let all_secrets = AllSecrets {
             secrets: secrets.clone(),
         };
         let json = serde_json::to_string(&all_secrets)
-            .map_err(|e| PedaruError::Secrets(format!("Failed to serialize secrets: {}", e)))?;
+            .map_err(|e| SecretsError::SerializeFailed(e.to_string()))?;
 
         entry.set_password(&json).map_err(|e| {
-            PedaruError::Secrets(format!("Failed to save secrets to keychain: {}", e))
+            keyring_error(e, |backend, message| SecretsError::SaveFailed {
+                backend,
+                message,
+            })
         })?;
 
         Ok(())
     }
 }
 
+/// Move secrets saved to the encrypted file while the keychain was
+/// unavailable into the (empty) keychain
+fn move_file_secrets_to_keychain(
+    app: &tauri::AppHandle,
+) -> Result<HashMap<String, String>, PedaruError> {
+    let path = secrets_file_path(app)?;
+    let secrets = match secrets_file::load(&path) {
+        Ok(secrets) => secrets,
+        Err(e) => {
+            log::warn!("Could not read the {}: {}", SECRETS_FILE_BACKEND, e);
+            return Ok(HashMap::new());
+        }
+    };
+    if secrets.is_empty() {
+        return Ok(secrets);
+    }
+
+    save_secrets_to_keychain(&secrets)?;
+    if let Err(e) = secrets_file::delete(&path) {
+        log::warn!("Could not delete the {}: {}", SECRETS_FILE_BACKEND, e);
+    }
+    log::info!(
+        "Moved secrets from the {} to the {}",
+        SECRETS_FILE_BACKEND,
+        KEYRING_BACKEND
+    );
+    Ok(secrets)
+}
+
+/// Load all secrets from the active backend
+fn load_secrets(app: &tauri::AppHandle) -> Result<HashMap<String, String>, PedaruError> {
+    match get_backend(app)? {
+        Backend::Keychain => {
+            let secrets = load_secrets_from_keychain()?;
+            if secrets.is_empty() {
+                return move_file_secrets_to_keychain(app);
+            }
+            Ok(secrets)
+        }
+        Backend::EncryptedFile(path) => secrets_file::load(&path).map_err(|message| {
+            SecretsError::LoadFailed {
+                backend: SECRETS_FILE_BACKEND,
+                message,
+            }
+            .into()
+        }),
+    }
+}
+
+/// Save all secrets to the active backend
+fn save_secrets(
+    app: &tauri::AppHandle,
+    secrets: &HashMap<String, String>,
+) -> Result<(), PedaruError> {
+    match get_backend(app)? {
+        Backend::Keychain => save_secrets_to_keychain(secrets),
+        Backend::EncryptedFile(path) => {
+            let result = if secrets.is_empty() {
+                secrets_file::delete(&path)
+            } else {
+                secrets_file::save(&path, secrets)
+            };
+            result.map_err(|message| {
+                SecretsError::SaveFailed {
+                    backend: SECRETS_FILE_BACKEND,
+                    message,
+                }
+                .into()
+            })
+        }
+    }
+}
+
 /// Get or initialize the secrets cache
-fn get_secrets_cache() -> Result<HashMap<String, String>, PedaruError> {
+fn get_secrets_cache(app: &tauri::AppHandle) -> Result<HashMap<String, String>, PedaruError> {
     // First try to read from cache
     {
         let cache = SECRETS_CACHE.read().unwrap();
@@ -95,8 +318,8 @@ fn get_secrets_cache() -> Result<HashMap<String, String>, PedaruError> {
         }
     }
 
-    // Cache is empty, load from keychain
-    let secrets = load_secrets_from_keychain()?;
+    // Cache is empty, load from the backend
+    let secrets = load_secrets(app)?;
 
     // Store in cache
     {
@@ -108,12 +331,12 @@ fn get_secrets_cache() -> Result<HashMap<String, String>, PedaruError> {
 }
 
 /// Store a secret in the OS keychain
-pub fn store_secret(_app: &tauri::AppHandle, key: &str, value: &str) -> Result<(), PedaruError> {
-    let mut secrets = get_secrets_cache()?;
+pub fn store_secret(app: &tauri::AppHandle, key: &str, value: &str) -> Result<(), PedaruError> {
+    let mut secrets = get_secrets_cache(app)?;
     secrets.insert(key.to_string(), value.to_string());
 
-    // Save to keychain
-    save_secrets_to_keychain(&secrets)?;
+    // Save to the backend
+    save_secrets(app, &secrets)?;
 
     // Update cache
     {
@@ -125,20 +348,40 @@ pub fn store_secret(_app: &tauri::AppHandle, key: &str, value: &str) -> Result<(
 }
 
 /// Retrieve a secret from the OS keychain
-pub fn get_secret(_app: &tauri::AppHandle, key: &str) -> Result<Option<String>, PedaruError> {
-    let secrets = get_secrets_cache()?;
+pub fn get_secret(app: &tauri::AppHandle, key: &str) -> Result<Option<String>, PedaruError> {
+    let secrets = get_secrets_cache(app)?;
     Ok(secrets.get(key).cloned())
 }
 
+/// Retrieve several secrets in one pass
+///
+/// Every requested key is present in the result, with `None` for unset secrets.
+pub fn get_secrets(
+    app: &tauri::AppHandle,
+    keys: &[&str],
+) -> Result<HashMap<String, Option<String>>, PedaruError> {
+    let secrets = get_secrets_cache(app)?;
+    Ok(pick_secrets(&secrets, keys))
+}
+
+fn pick_secrets(
+    secrets: &HashMap<String, String>,
+    keys: &[&str],
+) -> HashMap<String, Option<String>> {
+    keys.iter()
+        .map(|&key| (key.to_string(), secrets.get(key).cloned()))
+        .collect()
+}
+
 /// Delete a secret from the OS keychain
-pub fn delete_secret(_app: &tauri::AppHandle, key: &str) -> Result<(), PedaruError> {
-    let mut secrets = get_secrets_cache()?;
+pub fn delete_secret(app: &tauri::AppHandle, key: &str) -> Result<(), PedaruError> {
+    let mut secrets = get_secrets_cache(app)?;
 
     if secrets.remove(key).is_some() {
-        eprintln!("[Pedaru] Deleted secret: {}", key);
+        log::info!("Deleted secret: {}", key);
 
-        // Save to keychain
-        save_secrets_to_keychain(&secrets)?;
+        // Save to the backend
+        save_secrets(app, &secrets)?;
 
         // Update cache
         {
@@ -150,19 +393,66 @@ pub fn delete_secret(_app: &tauri::AppHandle, key: &str) -> Result<(), PedaruErr
     Ok(())
 }
 
-/// Delete all secrets from the OS keychain
-pub fn delete_all_secrets(_app: &tauri::AppHandle) -> Result<(), PedaruError> {
-    // Clear from keychain
-    let entry = keyring::Entry::new(KEYRING_SERVICE, KEYRING_KEY)
-        .map_err(|e| PedaruError::Secrets(format!("Failed to create keyring entry: {}", e)))?;
+/// Delete the keyring entry holding all secrets, if there is one
+fn delete_keychain_entry() -> Result<(), SecretsError> {
+    match open_entry()?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(keyring_error(e, |backend, message| {
+            SecretsError::DeleteFailed { backend, message }
+        })),
+    }
+}
 
-    match entry.delete_credential() {
-        Ok(()) => eprintln!("[Pedaru] Deleted all secrets"),
-        Err(keyring::Error::NoEntry) => {}
-        Err(e) => {
-            eprintln!("[Pedaru] Failed to delete secrets: {}", e);
-        }
+/// A store that may hold secrets, with how to delete them from it
+type SecretStore<'a> = (&'static str, &'a dyn Fn() -> Result<(), String>);
+
+/// Delete secrets from every store, carrying on past failures
+///
+/// Failures are logged and returned together at the end, so one store that
+/// can't be cleared doesn't leave secrets behind in the others.
+fn delete_from_stores(stores: &[SecretStore]) -> Result<(), SecretsError> {
+    let failures: Vec<String> = stores
+        .iter()
+        .filter_map(|(backend, delete)| {
+            let message = delete().err()?;
+            log::warn!("Failed to delete secrets from {}: {}", backend, message);
+            Some(format!("{}: {}", backend, message))
+        })
+        .collect();
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(SecretsError::DeleteIncomplete(failures.join("; ")))
     }
+}
+
+/// Delete all secrets from the OS keychain and the encrypted file
+///
+/// Both stores are cleared whichever backend is active, since secrets may
+/// have been saved to the other one in an earlier session. The cache is
+/// cleared even if a store fails; the error then names each store that did.
+pub fn delete_all_secrets(app: &tauri::AppHandle) -> Result<(), PedaruError> {
+    let delete_keychain = || match delete_keychain_entry() {
+        Ok(()) => Ok(()),
+        // An unavailable keychain holds nothing (its secrets are in the file)
+        Err(SecretsError::BackendUnavailable { .. })
+            if USING_SECRETS_FILE.load(Ordering::SeqCst) =>
+        {
+            Ok(())
+        }
+        Err(e) => Err(e.to_string()),
+    };
+    let file_path = secrets_file_path(app);
+    let delete_file = || match &file_path {
+        Ok(path) => secrets_file::delete(path),
+        Err(e) => Err(e.to_string()),
+    };
+
+    let result = delete_from_stores(&[
+        (KEYRING_BACKEND, &delete_keychain),
+        (SECRETS_FILE_BACKEND, &delete_file),
+    ]);
 
     // Clear cache
     {
@@ -170,10 +460,95 @@ pub fn delete_all_secrets(_app: &tauri::AppHandle) -> Result<(), PedaruError> {
         *cache = Some(HashMap::new());
     }
 
+    result?;
+    log::info!("Deleted all secrets");
+    Ok(())
+}
+
+/// Delete every secret except those under `keep`
+///
+/// The kept secrets are written back in place of the full set rather than
+/// deleted and stored again, so they are never missing from the backend.
+pub fn delete_all_secrets_except(app: &tauri::AppHandle, keep: &[&str]) -> Result<(), PedaruError> {
+    let mut kept = get_secrets_cache(app)?;
+    kept.retain(|key, _| keep.contains(&key.as_str()));
+    if kept.is_empty() {
+        return delete_all_secrets(app);
+    }
+
+    save_secrets(app, &kept)?;
+    {
+        let mut cache = SECRETS_CACHE.write().unwrap();
+        *cache = Some(kept);
+    }
+    log::info!("Deleted all secrets except {}", keep.join(", "));
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    // Tests would require mocking the keyring, skipped for now
+    use super::*;
+
+    // Keychain access itself would require mocking the keyring; only the
+    // error mapping is covered here
+
+    #[test]
+    fn test_keyring_error_names_backend() {
+        let err = keyring_error(keyring::Error::NoEntry, |backend, message| {
+            SecretsError::LoadFailed { backend, message }
+        });
+        assert!(matches!(err, SecretsError::LoadFailed { .. }));
+        assert!(err.to_string().contains(KEYRING_BACKEND));
+    }
+
+    #[test]
+    fn test_delete_from_stores_continues_past_failures() {
+        let deleted = std::cell::RefCell::new(Vec::new());
+        let delete = |name: &'static str, fail: bool| {
+            let deleted = &deleted;
+            move || {
+                deleted.borrow_mut().push(name);
+                if fail {
+                    Err("locked".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+        };
+        let (first, broken, last) = (delete("a", false), delete("b", true), delete("c", false));
+
+        let err = delete_from_stores(&[("a", &first), ("b", &broken), ("c", &last)]).unwrap_err();
+        assert_eq!(*deleted.borrow(), ["a", "b", "c"]);
+        assert!(matches!(&err, SecretsError::DeleteIncomplete(message) if message == "b: locked"));
+
+        assert!(delete_from_stores(&[("a", &first), ("c", &last)]).is_ok());
+    }
+
+    #[test]
+    fn test_pick_secrets_includes_missing_keys() {
+        let secrets = HashMap::from([("a".to_string(), "1".to_string())]);
+        let picked = pick_secrets(&secrets, &["a", "b"]);
+        assert_eq!(picked.len(), 2);
+        assert_eq!(picked["a"].as_deref(), Some("1"));
+        assert_eq!(picked["b"], None);
+    }
+
+    #[test]
+    fn test_keyring_error_platform_failure_is_unavailable() {
+        let err = keyring_error(
+            keyring::Error::PlatformFailure("no keyutils".into()),
+            |backend, message| SecretsError::LoadFailed { backend, message },
+        );
+        assert!(matches!(err, SecretsError::BackendUnavailable { .. }));
+    }
+
+    #[test]
+    fn test_keyring_error_reports_unavailable_backend() {
+        let err = keyring_error(
+            keyring::Error::NoStorageAccess("no secret service".into()),
+            |backend, message| SecretsError::SaveFailed { backend, message },
+        );
+        assert!(matches!(err, SecretsError::BackendUnavailable { .. }));
+        assert!(err.to_string().starts_with(KEYRING_BACKEND));
+    }
 }
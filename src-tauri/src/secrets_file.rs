@@ -0,0 +1,224 @@
+//! Encrypted-file secret store
+//!
+//! Fallback for `secrets` when the OS keychain is unavailable (e.g. headless
+//! Linux or CI). Secrets are stored as one JSON object, encrypted with
+//! AES-256-GCM. The key is derived with HKDF from a machine-specific value and
+//! a random per-file salt, so the file can't be read on another machine. This
+//! protects against casual disclosure (backups, synced folders), not against
+//! other processes running as the same user.
+
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce};
+use base64::{Engine as _, engine::general_purpose};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Current file format version
+const FORMAT_VERSION: u32 = 1;
+
+/// HKDF context string, bound to the format version
+const KEY_INFO: &[u8] = b"pedaru secrets file v1";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Files holding a stable per-installation machine ID (Linux)
+const MACHINE_ID_PATHS: &[&str] = &["/etc/machine-id", "/var/lib/dbus/machine-id"];
+
+/// On-disk representation (binary fields are base64-encoded; the ciphertext
+/// ends with the GCM tag)
+#[derive(Debug, Serialize, Deserialize)]
+struct SecretsFile {
+    version: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+fn derive_cipher(machine_secret: &[u8], salt: &[u8]) -> Aes256Gcm {
+    let hkdf = Hkdf::<Sha256>::new(Some(salt), machine_secret);
+    let mut key = [0u8; 32];
+    hkdf.expand(KEY_INFO, &mut key)
+        .expect("32 bytes is a valid HKDF-SHA256 output length");
+    Aes256Gcm::new(&key.into())
+}
+
+fn encrypt(machine_secret: &[u8], plaintext: &[u8]) -> SecretsFile {
+    let salt: [u8; SALT_LEN] = rand::random();
+    let nonce: [u8; NONCE_LEN] = rand::random();
+    let ciphertext = derive_cipher(machine_secret, &salt)
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: &FORMAT_VERSION.to_be_bytes(),
+            },
+        )
+        .expect("AES-GCM encryption of an in-memory buffer can't fail");
+
+    let b64 = |bytes: &[u8]| general_purpose::STANDARD.encode(bytes);
+    SecretsFile {
+        version: FORMAT_VERSION,
+        salt: b64(&salt),
+        nonce: b64(&nonce),
+        ciphertext: b64(&ciphertext),
+    }
+}
+
+fn decrypt(machine_secret: &[u8], file: &SecretsFile) -> Result<Vec<u8>, String> {
+    if file.version != FORMAT_VERSION {
+        return Err(format!("unsupported file version {}", file.version));
+    }
+
+    let b64 = |field: &str, value: &str| {
+        general_purpose::STANDARD
+            .decode(value)
+            .map_err(|e| format!("invalid {}: {}", field, e))
+    };
+    let salt = b64("salt", &file.salt)?;
+    let nonce = b64("nonce", &file.nonce)?;
+    if nonce.len() != NONCE_LEN {
+        return Err("invalid nonce length".to_string());
+    }
+    let ciphertext = b64("ciphertext", &file.ciphertext)?;
+
+    derive_cipher(machine_secret, &salt)
+        .decrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: &FORMAT_VERSION.to_be_bytes(),
+            },
+        )
+        .map_err(|_| {
+            "authentication failed (the file was modified or created on another machine)"
+                .to_string()
+        })
+}
+
+/// Machine-specific key material
+///
+/// Uses the machine ID where available, otherwise the host and user names.
+fn machine_secret() -> Vec<u8> {
+    for path in MACHINE_ID_PATHS {
+        if let Ok(id) = fs::read_to_string(path) {
+            let id = id.trim();
+            if !id.is_empty() {
+                return id.as_bytes().to_vec();
+            }
+        }
+    }
+
+    let host = fs::read_to_string("/etc/hostname")
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .unwrap_or_default();
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default();
+    format!("{}:{}", host.trim(), user).into_bytes()
+}
+
+/// Load all secrets from the encrypted file (empty if it doesn't exist)
+pub fn load(path: &Path) -> Result<HashMap<String, String>, String> {
+    let json = match fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(e.to_string()),
+    };
+    let file: SecretsFile = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+    let plaintext = decrypt(&machine_secret(), &file)?;
+    serde_json::from_slice(&plaintext).map_err(|e| e.to_string())
+}
+
+/// Encrypt and write all secrets, replacing the file atomically
+pub fn save(path: &Path, secrets: &HashMap<String, String>) -> Result<(), String> {
+    let plaintext = serde_json::to_vec(secrets).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string(&encrypt(&machine_secret(), &plaintext))
+        .map_err(|e| e.to_string())?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    // Created owner-only, so the secrets are never readable by others (a
+    // leftover temp file is removed first, since `mode` only applies to new
+    // files)
+    let _ = fs::remove_file(&tmp_path);
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(&tmp_path)
+        .and_then(|mut file| file.write_all(json.as_bytes()))
+        .map_err(|e| e.to_string())?;
+    fs::rename(&tmp_path, path).map_err(|e| e.to_string())
+}
+
+/// Delete the encrypted file (succeeds if it doesn't exist)
+pub fn delete(path: &Path) -> Result<(), String> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_round_trip() {
+        let plaintext = b"{\"gemini_api_key\":\"secret value longer than one block\"}";
+        let file = encrypt(b"machine", plaintext);
+        assert_ne!(file.ciphertext, general_purpose::STANDARD.encode(plaintext));
+        assert_eq!(decrypt(b"machine", &file).unwrap(), plaintext);
+    }
+
+    #[test]
+    fn test_decrypt_rejects_other_machine() {
+        let file = encrypt(b"machine", b"secret");
+        assert!(decrypt(b"other machine", &file).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_rejects_tampering() {
+        let mut file = encrypt(b"machine", b"secret");
+        let mut ciphertext = general_purpose::STANDARD.decode(&file.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        file.ciphertext = general_purpose::STANDARD.encode(ciphertext);
+        assert!(decrypt(b"machine", &file).is_err());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secrets.enc.json");
+        assert!(load(&path).unwrap().is_empty());
+
+        let secrets = HashMap::from([("key".to_string(), "value".to_string())]);
+        save(&path, &secrets).unwrap();
+        assert_eq!(load(&path).unwrap(), secrets);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        delete(&path).unwrap();
+        assert!(!path.exists());
+        delete(&path).unwrap();
+    }
+}
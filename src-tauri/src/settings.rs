@@ -7,44 +7,199 @@
 use serde::{Deserialize, Serialize};
 
 use crate::db::{now_timestamp, open_db};
-use crate::error::{DatabaseError, PedaruError};
+use crate::error::{DatabaseError, PedaruError, TranslationError};
 use crate::secrets;
+use crate::translation::{
+    GenerationParams, PromptSettings, PromptTemplates, ProviderConfig, ProviderKind,
+    TextKindThresholds,
+};
 
 // ============================================================================
 // Constants - Setting Keys (for SQLite)
 // ============================================================================
 
+/// Active translation provider ("gemini", "openai" or "anthropic")
+pub const KEY_TRANSLATION_PROVIDER: &str = "translation_provider";
+/// Language translations and explanations are written in
+pub const KEY_TARGET_LANGUAGE: &str = "target_language";
+/// Custom translation system instructions per selection size (unset = default)
+pub const KEY_TRANSLATION_PROMPT_WORD: &str = "translation_prompt_word";
+pub const KEY_TRANSLATION_PROMPT_SENTENCE: &str = "translation_prompt_sentence";
+pub const KEY_TRANSLATION_PROMPT_PARAGRAPH: &str = "translation_prompt_paragraph";
+/// Word-count thresholds for choosing between the prompt templates
+pub const KEY_PROMPT_WORD_MAX_WORDS: &str = "prompt_word_max_words";
+pub const KEY_PROMPT_PARAGRAPH_MIN_WORDS: &str = "prompt_paragraph_min_words";
 pub const KEY_GEMINI_MODEL: &str = "gemini_model";
 pub const KEY_GEMINI_EXPLANATION_MODEL: &str = "gemini_explanation_model";
+pub const KEY_OPENAI_MODEL: &str = "openai_model";
+pub const KEY_OPENAI_EXPLANATION_MODEL: &str = "openai_explanation_model";
+pub const KEY_ANTHROPIC_MODEL: &str = "anthropic_model";
+pub const KEY_ANTHROPIC_EXPLANATION_MODEL: &str = "anthropic_explanation_model";
+/// Per-provider generation parameters (unset = provider default)
+pub const KEY_GEMINI_TEMPERATURE: &str = "gemini_temperature";
+pub const KEY_GEMINI_MAX_OUTPUT_TOKENS: &str = "gemini_max_output_tokens";
+pub const KEY_OPENAI_TEMPERATURE: &str = "openai_temperature";
+pub const KEY_OPENAI_MAX_OUTPUT_TOKENS: &str = "openai_max_output_tokens";
+pub const KEY_ANTHROPIC_TEMPERATURE: &str = "anthropic_temperature";
+pub const KEY_ANTHROPIC_MAX_OUTPUT_TOKENS: &str = "anthropic_max_output_tokens";
+pub const KEY_GOOGLE_GRANTED_SCOPES: &str = "google_granted_scopes";
+/// Set while Google rejects an account's tokens ("true"; unset = tokens are usable)
+pub const KEY_GOOGLE_NEEDS_REAUTH: &str = "google_needs_reauth";
+pub const KEY_MAX_CONCURRENT_DOWNLOADS: &str = "max_concurrent_downloads";
+/// Global download speed limit in bytes per second (0 or unset = unlimited)
+pub const KEY_MAX_DOWNLOAD_BYTES_PER_SEC: &str = "max_download_bytes_per_sec";
+/// Max age of cached translation results in seconds (0 = caching disabled)
+pub const KEY_TRANSLATION_CACHE_MAX_AGE_SECS: &str = "translation_cache_max_age_secs";
+/// Max Gemini requests per minute across all translation calls (0 = unlimited)
+pub const KEY_GEMINI_REQUESTS_PER_MINUTE: &str = "gemini_requests_per_minute";
+/// Storage cap for downloaded PDFs in bytes (unset = unlimited)
+pub const KEY_STORAGE_CAP_BYTES: &str = "storage_cap_bytes";
+/// Download the next item in a folder ahead of time ("true"/"false"; unset = off)
+pub const KEY_PREFETCH_ENABLED: &str = "prefetch_enabled";
+/// Space-separated MIME types that sync adds to the bookshelf (unset = PDFs only)
+pub const KEY_SYNC_MIME_TYPES: &str = "sync_mime_types";
+/// Minutes between automatic folder syncs (unset = auto-sync off)
+pub const KEY_AUTO_SYNC_INTERVAL_MINUTES: &str = "auto_sync_interval_minutes";
+/// Custom HTML pages shown in the browser after signing in (unset = built-in)
+pub const KEY_OAUTH_SUCCESS_PAGE: &str = "oauth_success_page";
+pub const KEY_OAUTH_FAILURE_PAGE: &str = "oauth_failure_page";
+pub const KEY_OAUTH_STATE_MISMATCH_PAGE: &str = "oauth_state_mismatch_page";
+/// Seconds before expiry that OAuth tokens are refreshed (unset = default)
+pub const KEY_TOKEN_REFRESH_MARGIN_SECS: &str = "token_refresh_margin_secs";
+/// Template download file names are built from, e.g. "{pdf_title}"
+/// (unset = the Drive file name)
+pub const KEY_DOWNLOAD_NAME_TEMPLATE: &str = "download_name_template";
+/// Minimum milliseconds between download progress events
+pub const KEY_DOWNLOAD_PROGRESS_INTERVAL_MS: &str = "download_progress_interval_ms";
+/// Proxy for outbound requests, e.g. "socks5://host:1080" (unset = the
+/// HTTP_PROXY/HTTPS_PROXY/ALL_PROXY environment variables)
+pub const KEY_PROXY_URL: &str = "proxy_url";
+/// Seconds allowed to connect to a server (unset = default)
+pub const KEY_HTTP_CONNECT_TIMEOUT_SECS: &str = "http_connect_timeout_secs";
+/// Seconds allowed for a request, or between reads of a download (unset = default)
+pub const KEY_HTTP_REQUEST_TIMEOUT_SECS: &str = "http_request_timeout_secs";
+/// Log level ("off", "error", "warn", "info", "debug" or "trace"; unset = info)
+pub const KEY_LOG_LEVEL: &str = "log_level";
+/// Database file size in bytes right after the last compaction
+pub const KEY_DB_SIZE_AFTER_COMPACTION: &str = "db_size_after_compaction";
+
+/// Default target language for translations
+pub const DEFAULT_TARGET_LANGUAGE: &str = "Japanese";
 
 /// Default Gemini model for translation (fast)
 pub const DEFAULT_GEMINI_MODEL: &str = "gemini-2.0-flash";
 /// Default Gemini model for detailed explanation (can be more capable)
 pub const DEFAULT_GEMINI_EXPLANATION_MODEL: &str = "gemini-2.0-flash";
+/// Default OpenAI models for translation and explanation
+pub const DEFAULT_OPENAI_MODEL: &str = "gpt-4o-mini";
+pub const DEFAULT_OPENAI_EXPLANATION_MODEL: &str = "gpt-4o";
+/// Default Anthropic models for translation and explanation
+pub const DEFAULT_ANTHROPIC_MODEL: &str = "claude-3-5-haiku-latest";
+pub const DEFAULT_ANTHROPIC_EXPLANATION_MODEL: &str = "claude-sonnet-4-0";
+
+/// Default number of bookshelf downloads allowed to run at once
+pub const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 3;
+
+/// Default minimum time between download progress events in milliseconds
+pub const DEFAULT_DOWNLOAD_PROGRESS_INTERVAL_MS: u64 = 100;
 
 // ============================================================================
 // Types
 // ============================================================================
 
-/// Gemini translation settings
+/// API key and models for a single translation provider
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-pub struct GeminiSettings {
+pub struct ProviderSettings {
     pub api_key: String,
     pub model: String,
     pub explanation_model: String,
+    /// Sampling temperature (None = provider default)
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    /// Response length limit (None or 0 = provider default)
+    #[serde(default)]
+    pub max_output_tokens: Option<i64>,
 }
 
-impl Default for GeminiSettings {
-    fn default() -> Self {
-        Self {
-            api_key: String::new(),
-            model: DEFAULT_GEMINI_MODEL.to_string(),
-            explanation_model: DEFAULT_GEMINI_EXPLANATION_MODEL.to_string(),
+/// Gemini translation settings
+pub type GeminiSettings = ProviderSettings;
+
+/// Translation settings for every provider plus the active one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranslationSettings {
+    pub provider: String,
+    #[serde(flatten)]
+    pub prompts: PromptSettings,
+    pub gemini: ProviderSettings,
+    pub openai: ProviderSettings,
+    pub anthropic: ProviderSettings,
+}
+
+/// Settings of the active provider, ready to run a translation request
+pub struct ActiveTranslationConfig {
+    pub kind: ProviderKind,
+    pub provider: ProviderSettings,
+    pub params: GenerationParams,
+    pub prompts: PromptSettings,
+}
+
+impl ActiveTranslationConfig {
+    /// Request configuration using the given model
+    pub fn provider_config<'a>(&'a self, model: &'a str) -> ProviderConfig<'a> {
+        ProviderConfig {
+            kind: self.kind,
+            api_key: &self.provider.api_key,
+            model,
+            params: self.params,
         }
     }
 }
 
+/// Where a provider's API key and model names are stored
+struct ProviderKeys {
+    api_key_secret: &'static str,
+    model: &'static str,
+    explanation_model: &'static str,
+    temperature: &'static str,
+    max_output_tokens: &'static str,
+    default_model: &'static str,
+    default_explanation_model: &'static str,
+}
+
+fn provider_keys(kind: ProviderKind) -> ProviderKeys {
+    match kind {
+        ProviderKind::Gemini => ProviderKeys {
+            api_key_secret: secrets::keys::GEMINI_API_KEY,
+            model: KEY_GEMINI_MODEL,
+            explanation_model: KEY_GEMINI_EXPLANATION_MODEL,
+            temperature: KEY_GEMINI_TEMPERATURE,
+            max_output_tokens: KEY_GEMINI_MAX_OUTPUT_TOKENS,
+            default_model: DEFAULT_GEMINI_MODEL,
+            default_explanation_model: DEFAULT_GEMINI_EXPLANATION_MODEL,
+        },
+        ProviderKind::OpenAi => ProviderKeys {
+            api_key_secret: secrets::keys::OPENAI_API_KEY,
+            model: KEY_OPENAI_MODEL,
+            explanation_model: KEY_OPENAI_EXPLANATION_MODEL,
+            temperature: KEY_OPENAI_TEMPERATURE,
+            max_output_tokens: KEY_OPENAI_MAX_OUTPUT_TOKENS,
+            default_model: DEFAULT_OPENAI_MODEL,
+            default_explanation_model: DEFAULT_OPENAI_EXPLANATION_MODEL,
+        },
+        ProviderKind::Anthropic => ProviderKeys {
+            api_key_secret: secrets::keys::ANTHROPIC_API_KEY,
+            model: KEY_ANTHROPIC_MODEL,
+            explanation_model: KEY_ANTHROPIC_EXPLANATION_MODEL,
+            temperature: KEY_ANTHROPIC_TEMPERATURE,
+            max_output_tokens: KEY_ANTHROPIC_MAX_OUTPUT_TOKENS,
+            default_model: DEFAULT_ANTHROPIC_MODEL,
+            default_explanation_model: DEFAULT_ANTHROPIC_EXPLANATION_MODEL,
+        },
+    }
+}
+
 // ============================================================================
 // Database Operations (for non-sensitive settings)
 // ============================================================================
@@ -82,44 +237,283 @@ pub fn set_setting(app: &tauri::AppHandle, key: &str, value: &str) -> Result<(),
     Ok(())
 }
 
-/// Get all Gemini settings
-/// API key is stored in Stronghold (encrypted), model names in SQLite
-pub fn get_gemini_settings(app: &tauri::AppHandle) -> Result<GeminiSettings, PedaruError> {
-    // Get API key from Stronghold (encrypted)
-    let api_key = secrets::get_secret(app, secrets::keys::GEMINI_API_KEY)?.unwrap_or_default();
+/// Delete a setting from SQLite (no-op if it does not exist)
+pub fn delete_setting(app: &tauri::AppHandle, key: &str) -> Result<(), PedaruError> {
+    let conn = open_db(app)?;
+
+    conn.execute("DELETE FROM settings WHERE key = ?1", [key])
+        .map_err(|source| PedaruError::Database(DatabaseError::OpenFailed { source }))?;
+
+    Ok(())
+}
+
+/// Get the settings for one translation provider
+/// API key is stored in the OS keychain, model names in SQLite
+pub fn get_provider_settings(
+    app: &tauri::AppHandle,
+    kind: ProviderKind,
+) -> Result<ProviderSettings, PedaruError> {
+    // Get API key from the keychain (encrypted)
+    let api_key = secrets::get_secret(app, provider_keys(kind).api_key_secret)?.unwrap_or_default();
+    provider_settings_with_key(app, kind, api_key)
+}
+
+/// Load the non-secret provider settings from SQLite around an API key
+/// already read from the keychain
+fn provider_settings_with_key(
+    app: &tauri::AppHandle,
+    kind: ProviderKind,
+    api_key: String,
+) -> Result<ProviderSettings, PedaruError> {
+    let keys = provider_keys(kind);
 
     // Get model names from SQLite (non-sensitive)
-    let model =
-        get_setting(app, KEY_GEMINI_MODEL)?.unwrap_or_else(|| DEFAULT_GEMINI_MODEL.to_string());
-    let explanation_model = get_setting(app, KEY_GEMINI_EXPLANATION_MODEL)?
-        .unwrap_or_else(|| DEFAULT_GEMINI_EXPLANATION_MODEL.to_string());
+    let model = get_setting(app, keys.model)?.unwrap_or_else(|| keys.default_model.to_string());
+    let explanation_model = get_setting(app, keys.explanation_model)?
+        .unwrap_or_else(|| keys.default_explanation_model.to_string());
+    let temperature = get_setting(app, keys.temperature)?.and_then(|v| v.parse().ok());
+    let max_output_tokens = get_setting(app, keys.max_output_tokens)?.and_then(|v| v.parse().ok());
 
-    Ok(GeminiSettings {
+    Ok(ProviderSettings {
         api_key,
         model,
         explanation_model,
+        temperature,
+        max_output_tokens,
     })
 }
 
-/// Save Gemini settings
-/// API key is stored in Stronghold (encrypted), model names in SQLite
-pub fn save_gemini_settings(
+/// Save the settings for one translation provider
+/// API key is stored in the OS keychain, model names in SQLite
+pub fn save_provider_settings(
     app: &tauri::AppHandle,
-    settings: &GeminiSettings,
+    kind: ProviderKind,
+    settings: &ProviderSettings,
 ) -> Result<(), PedaruError> {
-    // Store API key in Stronghold (encrypted)
+    let keys = provider_keys(kind);
+    let params = kind.generation_params(settings.temperature, settings.max_output_tokens)?;
+
+    // Store API key in the keychain (encrypted)
     if settings.api_key.is_empty() {
-        secrets::delete_secret(app, secrets::keys::GEMINI_API_KEY)?;
+        secrets::delete_secret(app, keys.api_key_secret)?;
     } else {
-        secrets::store_secret(app, secrets::keys::GEMINI_API_KEY, &settings.api_key)?;
+        secrets::store_secret(app, keys.api_key_secret, &settings.api_key)?;
     }
 
     // Store model names in SQLite (non-sensitive)
-    set_setting(app, KEY_GEMINI_MODEL, &settings.model)?;
+    set_setting(app, keys.model, &settings.model)?;
+    set_setting(app, keys.explanation_model, &settings.explanation_model)?;
+
+    // Store validated generation parameters (unset = provider default)
+    match params.temperature {
+        Some(temperature) => set_setting(app, keys.temperature, &temperature.to_string())?,
+        None => delete_setting(app, keys.temperature)?,
+    }
+    match params.max_output_tokens {
+        Some(tokens) => set_setting(app, keys.max_output_tokens, &tokens.to_string())?,
+        None => delete_setting(app, keys.max_output_tokens)?,
+    }
+    Ok(())
+}
+
+/// Get the active translation provider (defaults to Gemini)
+pub fn get_translation_provider(app: &tauri::AppHandle) -> Result<ProviderKind, PedaruError> {
+    match get_setting(app, KEY_TRANSLATION_PROVIDER)? {
+        Some(value) => ProviderKind::parse(&value),
+        None => Ok(ProviderKind::Gemini),
+    }
+}
+
+/// Get the language translations are written in
+pub fn get_target_language(app: &tauri::AppHandle) -> Result<String, PedaruError> {
+    Ok(get_setting(app, KEY_TARGET_LANGUAGE)?
+        .filter(|language| !language.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_TARGET_LANGUAGE.to_string()))
+}
+
+/// Get a custom prompt template, falling back to the built-in default
+fn get_prompt_template(
+    app: &tauri::AppHandle,
+    key: &str,
+    default: String,
+) -> Result<String, PedaruError> {
+    Ok(get_setting(app, key)?
+        .filter(|template| !template.trim().is_empty())
+        .unwrap_or(default))
+}
+
+/// Save a prompt template; blank or default templates are stored as unset
+/// so future changes to the built-in defaults still apply
+fn save_prompt_template(
+    app: &tauri::AppHandle,
+    key: &str,
+    template: &str,
+    default: &str,
+) -> Result<(), PedaruError> {
+    if template.trim().is_empty() || template == default {
+        delete_setting(app, key)
+    } else {
+        set_setting(app, key, template)
+    }
+}
+
+/// Get a word-count threshold, falling back to the default when unset or invalid
+fn get_threshold(app: &tauri::AppHandle, key: &str, default: usize) -> Result<usize, PedaruError> {
+    Ok(get_setting(app, key)?
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default))
+}
+
+/// Get target language, prompt templates and template thresholds
+pub fn get_prompt_settings(app: &tauri::AppHandle) -> Result<PromptSettings, PedaruError> {
+    let defaults = PromptTemplates::default();
+    let default_thresholds = TextKindThresholds::default();
+
+    Ok(PromptSettings {
+        target_language: get_target_language(app)?,
+        templates: PromptTemplates {
+            word: get_prompt_template(app, KEY_TRANSLATION_PROMPT_WORD, defaults.word)?,
+            sentence: get_prompt_template(app, KEY_TRANSLATION_PROMPT_SENTENCE, defaults.sentence)?,
+            paragraph: get_prompt_template(
+                app,
+                KEY_TRANSLATION_PROMPT_PARAGRAPH,
+                defaults.paragraph,
+            )?,
+        },
+        thresholds: TextKindThresholds {
+            word_max_words: get_threshold(
+                app,
+                KEY_PROMPT_WORD_MAX_WORDS,
+                default_thresholds.word_max_words,
+            )?,
+            paragraph_min_words: get_threshold(
+                app,
+                KEY_PROMPT_PARAGRAPH_MIN_WORDS,
+                default_thresholds.paragraph_min_words,
+            )?,
+        },
+    })
+}
+
+/// Save target language, prompt templates and template thresholds
+pub fn save_prompt_settings(
+    app: &tauri::AppHandle,
+    prompts: &PromptSettings,
+) -> Result<(), PedaruError> {
+    let thresholds = &prompts.thresholds;
+    if thresholds.paragraph_min_words <= thresholds.word_max_words {
+        return Err(PedaruError::Translation(TranslationError::InvalidSettings(
+            format!(
+                "paragraph threshold ({}) must be greater than word threshold ({})",
+                thresholds.paragraph_min_words, thresholds.word_max_words
+            ),
+        )));
+    }
+
+    let target_language = prompts.target_language.trim();
+    if target_language.is_empty() {
+        delete_setting(app, KEY_TARGET_LANGUAGE)?;
+    } else {
+        set_setting(app, KEY_TARGET_LANGUAGE, target_language)?;
+    }
+
+    let defaults = PromptTemplates::default();
+    save_prompt_template(
+        app,
+        KEY_TRANSLATION_PROMPT_WORD,
+        &prompts.templates.word,
+        &defaults.word,
+    )?;
+    save_prompt_template(
+        app,
+        KEY_TRANSLATION_PROMPT_SENTENCE,
+        &prompts.templates.sentence,
+        &defaults.sentence,
+    )?;
+    save_prompt_template(
+        app,
+        KEY_TRANSLATION_PROMPT_PARAGRAPH,
+        &prompts.templates.paragraph,
+        &defaults.paragraph,
+    )?;
+
     set_setting(
         app,
-        KEY_GEMINI_EXPLANATION_MODEL,
-        &settings.explanation_model,
+        KEY_PROMPT_WORD_MAX_WORDS,
+        &thresholds.word_max_words.to_string(),
     )?;
-    Ok(())
+    set_setting(
+        app,
+        KEY_PROMPT_PARAGRAPH_MIN_WORDS,
+        &thresholds.paragraph_min_words.to_string(),
+    )
+}
+
+/// Load the active provider's settings and the prompt settings
+pub fn get_active_translation_config(
+    app: &tauri::AppHandle,
+) -> Result<ActiveTranslationConfig, PedaruError> {
+    let kind = get_translation_provider(app)?;
+    let provider = get_provider_settings(app, kind)?;
+    let params = kind.generation_params(provider.temperature, provider.max_output_tokens)?;
+
+    Ok(ActiveTranslationConfig {
+        kind,
+        provider,
+        params,
+        prompts: get_prompt_settings(app)?,
+    })
+}
+
+/// Get settings for all translation providers
+pub fn get_translation_settings(
+    app: &tauri::AppHandle,
+) -> Result<TranslationSettings, PedaruError> {
+    // Read every provider's API key in one keychain pass
+    let secret_keys = ProviderKind::ALL.map(|kind| provider_keys(kind).api_key_secret);
+    let mut api_keys = secrets::get_secrets(app, &secret_keys)?;
+    let mut provider_settings = |kind: ProviderKind| {
+        let api_key = api_keys
+            .remove(provider_keys(kind).api_key_secret)
+            .flatten()
+            .unwrap_or_default();
+        provider_settings_with_key(app, kind, api_key)
+    };
+
+    Ok(TranslationSettings {
+        provider: get_translation_provider(app)?.as_str().to_string(),
+        prompts: get_prompt_settings(app)?,
+        gemini: provider_settings(ProviderKind::Gemini)?,
+        openai: provider_settings(ProviderKind::OpenAi)?,
+        anthropic: provider_settings(ProviderKind::Anthropic)?,
+    })
+}
+
+/// Save settings for all translation providers
+///
+/// The provider is validated before anything is written.
+pub fn save_translation_settings(
+    app: &tauri::AppHandle,
+    settings: &TranslationSettings,
+) -> Result<(), PedaruError> {
+    let provider = ProviderKind::parse(&settings.provider)?;
+
+    save_provider_settings(app, ProviderKind::Gemini, &settings.gemini)?;
+    save_provider_settings(app, ProviderKind::OpenAi, &settings.openai)?;
+    save_provider_settings(app, ProviderKind::Anthropic, &settings.anthropic)?;
+    save_prompt_settings(app, &settings.prompts)?;
+    set_setting(app, KEY_TRANSLATION_PROVIDER, provider.as_str())
+}
+
+/// Get all Gemini settings
+pub fn get_gemini_settings(app: &tauri::AppHandle) -> Result<GeminiSettings, PedaruError> {
+    get_provider_settings(app, ProviderKind::Gemini)
+}
+
+/// Save Gemini settings
+pub fn save_gemini_settings(
+    app: &tauri::AppHandle,
+    settings: &GeminiSettings,
+) -> Result<(), PedaruError> {
+    save_provider_settings(app, ProviderKind::Gemini, settings)
 }
@@ -0,0 +1,796 @@
+//! Google Drive folder sync
+//!
+//! `sync_folders` is the pipeline behind both the sync command and the
+//! auto-sync scheduler. Syncs are serialized, so a scheduled sync never races
+//! a manual one over the same rows and page tokens.
+//!
+//! The scheduler syncs at most one folder per tick. Due folders are queued
+//! `FOLDER_STAGGER_SECS` apart, so folders synced together (e.g. by a manual
+//! sync) drift apart instead of all syncing at once every interval.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::bookshelf::{self, StoredFolder, SyncResult};
+use crate::db::now_timestamp;
+use crate::error::{ConfigError, GoogleDriveError, NetworkError, PedaruError};
+use crate::google_drive;
+use crate::network;
+use crate::oauth;
+use crate::search;
+use crate::settings;
+use crate::thumbnail;
+
+/// Event emitted with the `SyncResult` of each automatic sync
+pub const AUTO_SYNC_EVENT: &str = "bookshelf-auto-synced";
+
+/// Event emitted when folders added without a name got their Drive name
+pub const FOLDER_NAMES_RESOLVED_EVENT: &str = "drive-folder-names-resolved";
+
+/// How often the scheduler looks for a due folder
+const SCHEDULER_TICK: Duration = Duration::from_secs(60);
+
+/// Minimum spacing between two folders' automatic syncs
+const FOLDER_STAGGER_SECS: i64 = 60;
+
+/// File types synced when the setting isn't set
+const DEFAULT_SYNC_MIME_TYPES: &[&str] = &[google_drive::PDF_MIME_TYPE];
+
+/// Serializes syncs (manual and automatic)
+static SYNC_LOCK: OnceLock<tokio::sync::Mutex<()>> = OnceLock::new();
+
+fn get_sync_lock() -> &'static tokio::sync::Mutex<()> {
+    SYNC_LOCK.get_or_init(|| tokio::sync::Mutex::new(()))
+}
+
+/// When each folder's last automatic sync failed (cleared on success)
+static FAILED_SYNCS: Mutex<Option<HashMap<String, i64>>> = Mutex::new(None);
+
+/// When a folder's next automatic sync is due
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledSync {
+    pub folder_id: String,
+    /// Unix timestamp (None when the folder isn't synced automatically)
+    pub next_sync: Option<i64>,
+}
+
+// ============================================================================
+// Sync Pipeline
+// ============================================================================
+
+/// Removals found while syncing a folder (applied once every folder is upserted)
+enum FolderRemovals {
+    /// Complete listing: items missing from it were removed from Drive
+    Listing(Vec<String>),
+    /// Incremental sync: files that may have left the folder
+    Changed(Vec<String>),
+}
+
+/// What changed in a synced folder since its last sync
+struct FolderSync {
+    files: Vec<google_drive::NestedDriveFile>,
+    removals: FolderRemovals,
+    /// Files that left the folder but are still on Drive
+    moved_out: Vec<google_drive::ChangedFile>,
+    /// Drive changes page token for the next sync
+    page_token: String,
+}
+
+/// Fetch a folder's changes from Drive's change log, or list it in full
+///
+/// The full listing is used on the first sync, when Drive has expired the
+/// saved page token, and when a recursive folder's subfolders changed. It
+/// also looks up which drive the folder is on, since a shared drive's
+/// changes are only in that drive's change log.
+async fn fetch_folder_changes(
+    app: &AppHandle,
+    folder: &bookshelf::StoredFolder,
+) -> Result<FolderSync, PedaruError> {
+    if let Some(token) = bookshelf::get_folder_page_token(app, &folder.folder_id)?
+        && let Some(changes) =
+            google_drive::list_changes(app, folder.account_id, &token, folder.drive_id.as_deref())
+                .await?
+    {
+        let tree = if folder.recursive {
+            bookshelf::get_folder_tree(app, &folder.folder_id)?
+        } else {
+            HashMap::from([(folder.folder_id.clone(), String::new())])
+        };
+        if let Some(matched) = google_drive::match_changes(
+            &changes.changes,
+            &folder.folder_id,
+            &tree,
+            folder.recursive,
+        ) {
+            return Ok(FolderSync {
+                files: matched.upserts,
+                removals: FolderRemovals::Changed(matched.removed),
+                moved_out: matched.moved_out,
+                page_token: changes.new_start_page_token,
+            });
+        }
+        log::info!(
+            "Subfolders of {} changed, listing it in full",
+            folder.folder_name
+        );
+    }
+
+    let drive_id = match google_drive::get_folder(app, folder.account_id, &folder.folder_id).await {
+        Ok(drive_folder) => {
+            if drive_folder.drive_id != folder.drive_id {
+                bookshelf::set_folder_drive_id(
+                    app,
+                    &folder.folder_id,
+                    drive_folder.drive_id.as_deref(),
+                )?;
+            }
+            drive_folder.drive_id
+        }
+        Err(e @ PedaruError::Network(_)) => return Err(e),
+        Err(e) => {
+            log::warn!(
+                "Couldn't look up the drive of {}: {}",
+                folder.folder_name,
+                e
+            );
+            folder.drive_id.clone()
+        }
+    };
+    let drive_id = drive_id.as_deref();
+
+    // Take the token first so changes made during the listing show up next time
+    let page_token = google_drive::get_start_page_token(app, folder.account_id, drive_id).await?;
+    let files = if folder.recursive {
+        let listing =
+            google_drive::list_files_recursive(app, folder.account_id, &folder.folder_id, drive_id)
+                .await?;
+        bookshelf::set_folder_tree(app, &folder.folder_id, &listing.folders)?;
+        listing.files
+    } else {
+        google_drive::list_files(app, folder.account_id, &folder.folder_id, drive_id)
+            .await?
+            .into_iter()
+            .map(|file| google_drive::NestedDriveFile {
+                file,
+                parent_id: folder.folder_id.clone(),
+                folder_path: String::new(),
+            })
+            .collect()
+    };
+    let file_ids = files.iter().map(|nested| nested.file.id.clone()).collect();
+
+    Ok(FolderSync {
+        files,
+        removals: FolderRemovals::Listing(file_ids),
+        moved_out: Vec::new(),
+        page_token,
+    })
+}
+
+/// Drop files whose type isn't synced, returning how many were dropped
+///
+/// Dropped files also leave the bookshelf: they're missing from a full
+/// listing and added to an incremental sync's removals, so an item synced
+/// under an older allowlist (or whose type changed) goes away.
+fn skip_unsynced_types(sync: &mut FolderSync, mime_types: &[String]) -> usize {
+    let (files, skipped): (Vec<_>, Vec<_>) = std::mem::take(&mut sync.files)
+        .into_iter()
+        .partition(|nested| is_synced_type(&nested.file.mime_type, mime_types));
+    sync.files = files;
+
+    for nested in &skipped {
+        if google_drive::is_native_file(&nested.file.mime_type) {
+            log::debug!(
+                "Skipping {}: only Google Docs and Slides files can be downloaded",
+                nested.file.name
+            );
+        }
+    }
+    let skipped_ids: HashSet<&str> = skipped.iter().map(|n| n.file.id.as_str()).collect();
+    match &mut sync.removals {
+        FolderRemovals::Listing(file_ids) => {
+            file_ids.retain(|id| !skipped_ids.contains(id.as_str()))
+        }
+        FolderRemovals::Changed(file_ids) => {
+            file_ids.extend(skipped.iter().map(|n| n.file.id.clone()))
+        }
+    }
+    skipped.len()
+}
+
+/// Look up the items missing from a full listing that are still on Drive
+///
+/// A missing item may have moved to another watched folder that isn't part
+/// of this sync (auto-sync syncs one folder at a time). Those still on Drive
+/// go to `moved_out`, so `plan_moves` moves them instead of them being
+/// removed. An item that can't be looked up is kept for now.
+async fn find_moved_out(
+    app: &AppHandle,
+    folder: &StoredFolder,
+    sync: &mut FolderSync,
+) -> Result<(), PedaruError> {
+    let FolderRemovals::Listing(file_ids) = &mut sync.removals else {
+        return Ok(());
+    };
+    for file_id in bookshelf::get_missing_folder_items(app, &folder.folder_id, file_ids)? {
+        match google_drive::get_file(app, folder.account_id, &file_id).await {
+            Ok(Some(changed)) if !changed.trashed => sync.moved_out.push(changed),
+            Ok(_) => {}
+            Err(e @ PedaruError::Network(_)) => return Err(e),
+            Err(e) => {
+                log::warn!("Couldn't look up {}, keeping it for now: {}", file_id, e);
+                file_ids.push(file_id);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Split files that left a folder into moves and removals
+///
+/// A file whose new parent is watched (directly or as a known subfolder) is
+/// moved there; anything else left the synced folders and is removed.
+fn plan_moves<'a>(
+    moved_out: &'a [google_drive::ChangedFile],
+    watched: &HashSet<String>,
+) -> (Vec<(&'a google_drive::ChangedFile, &'a str)>, Vec<String>) {
+    let mut moves = Vec::new();
+    let mut removals = Vec::new();
+    for changed in moved_out {
+        match changed.parents.iter().find(|p| watched.contains(*p)) {
+            Some(parent_id) => moves.push((changed, parent_id.as_str())),
+            None => removals.push(changed.file.id.clone()),
+        }
+    }
+    (moves, removals)
+}
+
+/// Sync `folders` with Google Drive, waiting for any sync in progress
+pub async fn sync_folders(
+    app: &AppHandle,
+    folders: Vec<StoredFolder>,
+) -> Result<SyncResult, PedaruError> {
+    let _guard = get_sync_lock().lock().await;
+
+    if let Err(e) = resolve_folder_names(app).await {
+        log::warn!("Failed to resolve folder names: {}", e);
+    }
+
+    let mime_types = get_sync_mime_types(app)?;
+    let mut added = Vec::new();
+    let mut updated = Vec::new();
+    let mut linked = Vec::new();
+    let mut skipped = 0;
+    let mut incremental = !folders.is_empty();
+    let mut synced_folders = Vec::with_capacity(folders.len());
+
+    for folder in folders {
+        let mut sync = fetch_folder_changes(app, &folder).await?;
+        // Before skipping unsynced types, which must still be removed
+        find_moved_out(app, &folder, &mut sync).await?;
+        incremental &= matches!(sync.removals, FolderRemovals::Changed(_));
+        skipped += skip_unsynced_types(&mut sync, &mime_types);
+
+        let items: Vec<bookshelf::DriveFileInput> = sync
+            .files
+            .iter()
+            .map(|nested| bookshelf::DriveFileInput {
+                file: &nested.file,
+                folder_id: &nested.parent_id,
+                folder_path: Some(nested.folder_path.as_str()).filter(|p| !p.is_empty()),
+            })
+            .collect();
+        let outcomes =
+            bookshelf::upsert_cloud_items(app, folder.account_id, &folder.folder_id, &items)?;
+
+        for (nested, outcome) in sync.files.iter().zip(outcomes) {
+            let file = &nested.file;
+            let synced = || bookshelf::SyncedFile {
+                drive_file_id: file.id.clone(),
+                file_name: file.name.clone(),
+            };
+            match outcome {
+                bookshelf::UpsertOutcome::Added => added.push(synced()),
+                bookshelf::UpsertOutcome::Updated => updated.push(synced()),
+                bookshelf::UpsertOutcome::Unchanged => {}
+            }
+
+            // Share an existing download of the same PDF from another folder
+            if let Some(md5_checksum) = &file.md5_checksum
+                && bookshelf::link_duplicate_download(app, &file.id, md5_checksum)?.is_some()
+            {
+                linked.push(file.id.clone());
+            }
+        }
+
+        bookshelf::update_folder_sync_time(app, &folder.folder_id)?;
+
+        synced_folders.push((
+            folder.folder_id,
+            sync.removals,
+            sync.moved_out,
+            sync.page_token,
+        ));
+    }
+
+    // Move files that went to another watched folder in place, keeping their
+    // download, tags and reading progress. The destination may not be part
+    // of this sync (auto-sync syncs one folder at a time).
+    let watched = bookshelf::get_watched_folder_ids(app)?;
+    for (_, removals, moved_out, _) in &mut synced_folders {
+        let (moves, gone) = plan_moves(moved_out, &watched);
+        for (changed, parent_id) in moves {
+            if bookshelf::move_item(app, &changed.file.id, parent_id)? {
+                updated.push(bookshelf::SyncedFile {
+                    drive_file_id: changed.file.id.clone(),
+                    file_name: changed.file.name.clone(),
+                });
+            }
+        }
+        if let FolderRemovals::Changed(file_ids) = removals {
+            file_ids.extend(gone);
+        }
+    }
+
+    // Remove items deleted from Drive. This runs after every folder has been
+    // upserted so files moved between synced folders aren't removed.
+    let mut removed = Vec::new();
+    for (folder_id, removals, _, _) in &synced_folders {
+        removed.extend(match removals {
+            FolderRemovals::Listing(file_ids) => {
+                bookshelf::reconcile_folder(app, folder_id, file_ids)
+            }
+            FolderRemovals::Changed(file_ids) => {
+                bookshelf::remove_folder_items(app, folder_id, file_ids)
+            }
+        }?);
+    }
+
+    // Remove items from folders that are no longer synced (but keep downloaded files)
+    removed.extend(bookshelf::remove_items_from_inactive_folders(app)?);
+
+    // Render thumbnails for linked duplicates and add them to the search
+    // index in the background
+    if !linked.is_empty() {
+        let index_app = app.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            for drive_file_id in linked {
+                if let Err(e) = thumbnail::generate_cloud_thumbnail(&index_app, &drive_file_id) {
+                    log::warn!("Failed to generate thumbnail for {}: {}", drive_file_id, e);
+                }
+                if let Err(e) = search::index_document(&index_app, &drive_file_id) {
+                    log::warn!("Failed to index {} for search: {}", drive_file_id, e);
+                }
+            }
+        });
+    }
+
+    // Only save the page tokens once the changes they cover have been applied
+    for (folder_id, _, _, page_token) in &synced_folders {
+        bookshelf::set_folder_page_token(app, folder_id, page_token)?;
+    }
+
+    if skipped > 0 {
+        log::info!("Skipped {} files whose type isn't synced", skipped);
+    }
+    Ok(SyncResult::new(
+        added,
+        updated,
+        removed,
+        incremental,
+        skipped,
+    ))
+}
+
+/// Fetch the Drive names of folders added by id or URL
+///
+/// Folders of accounts that aren't signed in wait until they are. A folder
+/// Drive can't find keeps its id as its name.
+pub async fn resolve_folder_names(app: &AppHandle) -> Result<usize, PedaruError> {
+    let mut resolved = 0;
+    for (folder_id, account_id) in bookshelf::get_folders_pending_name(app)? {
+        if !oauth::is_authenticated(app, account_id)? {
+            continue;
+        }
+        match google_drive::get_folder(app, account_id, &folder_id).await {
+            Ok(folder) => {
+                bookshelf::set_folder_name(app, &folder_id, Some(&folder.name))?;
+                resolved += 1;
+            }
+            Err(PedaruError::Network(_)) => break,
+            Err(
+                e @ PedaruError::GoogleDrive(
+                    GoogleDriveError::FileNotFound(_) | GoogleDriveError::InvalidFolderId(_),
+                ),
+            ) => {
+                log::info!("Keeping id as the name of folder {}: {}", folder_id, e);
+                bookshelf::set_folder_name(app, &folder_id, None)?;
+            }
+            Err(e) => {
+                log::warn!("Failed to fetch the name of folder {}: {}", folder_id, e);
+            }
+        }
+    }
+
+    if resolved > 0 {
+        let _ = app.emit(FOLDER_NAMES_RESOLVED_EVENT, resolved);
+    }
+    Ok(resolved)
+}
+
+// ============================================================================
+// Synced File Types
+// ============================================================================
+
+/// Whether files of `mime_type` are synced
+///
+/// Google Docs and Slides files count as PDFs, since they're exported as
+/// PDF on download. Other Google-native files (Sheets, Forms, ...) are never
+/// synced: there's nothing to download for them.
+fn is_synced_type(mime_type: &str, mime_types: &[String]) -> bool {
+    let mime_type = if google_drive::is_exportable(mime_type) {
+        google_drive::PDF_MIME_TYPE
+    } else if google_drive::is_native_file(mime_type) {
+        return false;
+    } else {
+        mime_type
+    };
+    mime_types.iter().any(|t| t == mime_type)
+}
+
+/// Normalize and check MIME types, dropping duplicates
+fn parse_mime_types<'a>(
+    mime_types: impl IntoIterator<Item = &'a str>,
+) -> Result<Vec<String>, PedaruError> {
+    let mut parsed: Vec<String> = Vec::new();
+    for mime_type in mime_types {
+        let normalized = mime_type.trim().to_ascii_lowercase();
+        let well_formed = normalized.split_once('/').is_some_and(|(kind, subtype)| {
+            !kind.is_empty() && !subtype.is_empty() && !subtype.contains('/')
+        }) && normalized
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '.' | '+' | '-' | '_'));
+        if !well_formed || google_drive::is_native_file(&normalized) {
+            return Err(ConfigError::InvalidMimeType(mime_type.to_string()).into());
+        }
+        if !parsed.contains(&normalized) {
+            parsed.push(normalized);
+        }
+    }
+    Ok(parsed)
+}
+
+/// Get the MIME types sync adds to the bookshelf
+pub fn get_sync_mime_types(app: &AppHandle) -> Result<Vec<String>, PedaruError> {
+    let saved = settings::get_setting(app, settings::KEY_SYNC_MIME_TYPES)?
+        .and_then(|v| parse_mime_types(v.split_whitespace()).ok())
+        .filter(|mime_types| !mime_types.is_empty());
+    Ok(saved.unwrap_or_else(|| {
+        DEFAULT_SYNC_MIME_TYPES
+            .iter()
+            .map(|t| t.to_string())
+            .collect()
+    }))
+}
+
+/// Set the MIME types sync adds to the bookshelf (an empty list restores
+/// the default, PDFs only), returning the types now in effect
+///
+/// Items of types taken off the list are removed by the next full sync of
+/// their folder.
+pub fn set_sync_mime_types(
+    app: &AppHandle,
+    mime_types: &[String],
+) -> Result<Vec<String>, PedaruError> {
+    let parsed = parse_mime_types(mime_types.iter().map(String::as_str))?;
+    if parsed.is_empty() {
+        settings::delete_setting(app, settings::KEY_SYNC_MIME_TYPES)?;
+    } else {
+        settings::set_setting(app, settings::KEY_SYNC_MIME_TYPES, &parsed.join(" "))?;
+    }
+    get_sync_mime_types(app)
+}
+
+// ============================================================================
+// Auto-Sync Scheduling
+// ============================================================================
+
+/// Get the auto-sync interval in minutes (None = auto-sync off)
+pub fn get_auto_sync_interval(app: &AppHandle) -> Result<Option<u64>, PedaruError> {
+    Ok(
+        settings::get_setting(app, settings::KEY_AUTO_SYNC_INTERVAL_MINUTES)?
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|&minutes| minutes > 0),
+    )
+}
+
+/// Set the auto-sync interval in minutes (None or 0 turns auto-sync off)
+pub fn set_auto_sync_interval(app: &AppHandle, minutes: Option<u64>) -> Result<(), PedaruError> {
+    match minutes.filter(|&minutes| minutes > 0) {
+        Some(minutes) => settings::set_setting(
+            app,
+            settings::KEY_AUTO_SYNC_INTERVAL_MINUTES,
+            &minutes.to_string(),
+        ),
+        None => settings::delete_setting(app, settings::KEY_AUTO_SYNC_INTERVAL_MINUTES),
+    }
+}
+
+/// Order folders by when they're due and space them `FOLDER_STAGGER_SECS` apart
+///
+/// Each candidate carries its last sync attempt (None = never synced, due
+/// now). Nothing is scheduled before `now`, so at most one folder is due.
+fn plan_syncs<T>(candidates: Vec<(T, Option<i64>)>, interval_secs: i64, now: i64) -> Vec<(T, i64)> {
+    let mut planned: Vec<(T, i64)> = candidates
+        .into_iter()
+        .map(|(folder, last_attempt)| (folder, last_attempt.map_or(now, |t| t + interval_secs)))
+        .collect();
+    planned.sort_by_key(|(_, due)| *due);
+
+    let mut earliest = now;
+    for (_, due) in &mut planned {
+        *due = (*due).max(earliest);
+        earliest = *due + FOLDER_STAGGER_SECS;
+    }
+    planned
+}
+
+/// Plan automatic syncs for the active folders (empty when auto-sync is off)
+///
+/// Folders that opted out or whose account is signed out aren't scheduled.
+/// A failed sync counts as an attempt, so it's retried an interval later.
+fn plan_folder_syncs(app: &AppHandle, now: i64) -> Result<Vec<(StoredFolder, i64)>, PedaruError> {
+    let Some(minutes) = get_auto_sync_interval(app)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut signed_in = HashSet::new();
+    for account in oauth::list_accounts(app)? {
+        if oauth::is_authenticated(app, account.id)? {
+            signed_in.insert(account.id);
+        }
+    }
+
+    let failed = FAILED_SYNCS.lock().unwrap().clone().unwrap_or_default();
+    let candidates = bookshelf::get_sync_folders(app)?
+        .into_iter()
+        .filter(|folder| folder.auto_sync && signed_in.contains(&folder.account_id))
+        .map(|folder| {
+            let last_attempt = folder
+                .last_synced
+                .max(failed.get(&folder.folder_id).copied());
+            (folder, last_attempt)
+        })
+        .collect();
+
+    let interval_secs = i64::try_from(minutes.saturating_mul(60)).unwrap_or(i64::MAX / 2);
+    Ok(plan_syncs(candidates, interval_secs, now))
+}
+
+/// Get the next automatic sync time of every active folder
+pub fn get_sync_schedule(app: &AppHandle) -> Result<Vec<ScheduledSync>, PedaruError> {
+    let planned: HashMap<String, i64> = plan_folder_syncs(app, now_timestamp())?
+        .into_iter()
+        .map(|(folder, due)| (folder.folder_id, due))
+        .collect();
+
+    Ok(bookshelf::get_sync_folders(app)?
+        .into_iter()
+        .map(|folder| ScheduledSync {
+            next_sync: planned.get(&folder.folder_id).copied(),
+            folder_id: folder.folder_id,
+        })
+        .collect())
+}
+
+/// Sync the folder that is due now, if any
+///
+/// Skipped while offline; a failure caused by going offline mid-sync isn't
+/// counted, so the folder is retried once the network is back.
+async fn sync_due_folder(app: &AppHandle) -> Result<(), PedaruError> {
+    let now = now_timestamp();
+    let Some((folder, _)) = plan_folder_syncs(app, now)?
+        .into_iter()
+        .find(|(_, due)| *due <= now)
+    else {
+        return Ok(());
+    };
+    if !network::check_connectivity().await {
+        return Ok(());
+    }
+
+    log::info!("Auto-syncing folder {}", folder.folder_name);
+    let folder_id = folder.folder_id.clone();
+    let result = sync_folders(app, vec![folder]).await;
+
+    let mut failed = FAILED_SYNCS.lock().unwrap();
+    let failed = failed.get_or_insert_with(HashMap::new);
+    match result {
+        Ok(result) => {
+            failed.remove(&folder_id);
+            let _ = app.emit(AUTO_SYNC_EVENT, result);
+            Ok(())
+        }
+        Err(PedaruError::Network(NetworkError::Offline { .. })) => Ok(()),
+        Err(e) => {
+            failed.insert(folder_id, now);
+            Err(e)
+        }
+    }
+}
+
+/// Sync due folders in the background, for the app's lifetime
+pub async fn run_auto_sync(app: AppHandle) {
+    loop {
+        tokio::time::sleep(SCHEDULER_TICK).await;
+        if let Err(e) = sync_due_folder(&app).await {
+            log::warn!("Auto-sync failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_syncs_staggers_due_folders() {
+        let now = 10_000;
+        let planned = plan_syncs(
+            vec![
+                ("later", Some(now - 100)),
+                ("synced_together_a", Some(now - 4000)),
+                ("synced_together_b", Some(now - 4000)),
+                ("never", None),
+            ],
+            3600,
+            now,
+        );
+        assert_eq!(
+            planned,
+            vec![
+                ("synced_together_a", now),
+                ("synced_together_b", now + FOLDER_STAGGER_SECS),
+                ("never", now + 2 * FOLDER_STAGGER_SECS),
+                ("later", now + 3500),
+            ]
+        );
+    }
+
+    fn moved_file(file_id: &str, parents: &[&str]) -> google_drive::ChangedFile {
+        google_drive::ChangedFile {
+            file: google_drive::DriveFile {
+                id: file_id.to_string(),
+                name: format!("{}.pdf", file_id),
+                size: None,
+                mime_type: "application/pdf".to_string(),
+                modified_time: None,
+                thumbnail_link: None,
+                md5_checksum: None,
+            },
+            parents: parents.iter().map(|p| p.to_string()).collect(),
+            trashed: false,
+        }
+    }
+
+    #[test]
+    fn test_plan_moves_prefers_move_to_watched_folder() {
+        let watched = HashSet::from(["other-root".to_string(), "other-sub".to_string()]);
+        let moved_out = [
+            moved_file("to-root", &["other-root"]),
+            moved_file("to-sub", &["unwatched", "other-sub"]),
+            moved_file("to-unwatched", &["unwatched"]),
+            moved_file("orphaned", &[]),
+        ];
+
+        let (moves, removals) = plan_moves(&moved_out, &watched);
+        let moves: Vec<_> = moves
+            .iter()
+            .map(|(changed, parent_id)| (changed.file.id.as_str(), *parent_id))
+            .collect();
+        assert_eq!(moves, [("to-root", "other-root"), ("to-sub", "other-sub")]);
+        assert_eq!(removals, ["to-unwatched", "orphaned"]);
+    }
+
+    fn nested_file(file_id: &str, mime_type: &str) -> google_drive::NestedDriveFile {
+        let mut changed = moved_file(file_id, &[]);
+        changed.file.mime_type = mime_type.to_string();
+        google_drive::NestedDriveFile {
+            file: changed.file,
+            parent_id: "root".to_string(),
+            folder_path: String::new(),
+        }
+    }
+
+    fn folder_sync(removals: FolderRemovals) -> FolderSync {
+        FolderSync {
+            files: vec![
+                nested_file("pdf", "application/pdf"),
+                nested_file("epub", "application/epub+zip"),
+                nested_file("sheet", "application/vnd.google-apps.spreadsheet"),
+            ],
+            removals,
+            moved_out: Vec::new(),
+            page_token: "token".to_string(),
+        }
+    }
+
+    fn file_ids(sync: &FolderSync) -> Vec<&str> {
+        sync.files.iter().map(|n| n.file.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_skip_unsynced_types_removes_skipped_files() {
+        let mime_types = vec!["application/pdf".to_string()];
+
+        let listed = ["pdf", "epub", "sheet"].map(String::from).to_vec();
+        let mut sync = folder_sync(FolderRemovals::Listing(listed));
+        assert_eq!(skip_unsynced_types(&mut sync, &mime_types), 2);
+        assert_eq!(file_ids(&sync), ["pdf"]);
+        assert!(matches!(&sync.removals, FolderRemovals::Listing(ids) if ids == &["pdf"]));
+
+        let mut sync = folder_sync(FolderRemovals::Changed(vec!["deleted".to_string()]));
+        assert_eq!(skip_unsynced_types(&mut sync, &mime_types), 2);
+        assert_eq!(file_ids(&sync), ["pdf"]);
+        assert!(matches!(
+            &sync.removals,
+            FolderRemovals::Changed(ids) if ids == &["deleted", "epub", "sheet"]
+        ));
+    }
+
+    #[test]
+    fn test_native_files_sync_only_when_exported_as_pdf() {
+        let pdf_only = vec!["application/pdf".to_string()];
+        assert!(is_synced_type("application/pdf", &pdf_only));
+        assert!(!is_synced_type("application/epub+zip", &pdf_only));
+        assert!(is_synced_type(
+            "application/vnd.google-apps.document",
+            &pdf_only
+        ));
+        assert!(is_synced_type(
+            "application/vnd.google-apps.presentation",
+            &pdf_only
+        ));
+        assert!(!is_synced_type(
+            "application/vnd.google-apps.spreadsheet",
+            &pdf_only
+        ));
+
+        let epub_only = vec!["application/epub+zip".to_string()];
+        assert!(!is_synced_type(
+            "application/vnd.google-apps.document",
+            &epub_only
+        ));
+    }
+
+    #[test]
+    fn test_parse_mime_types() {
+        assert_eq!(
+            parse_mime_types([
+                " Application/PDF ",
+                "application/epub+zip",
+                "application/pdf"
+            ])
+            .unwrap(),
+            ["application/pdf", "application/epub+zip"]
+        );
+        for invalid in [
+            "pdf",
+            "application/",
+            "/pdf",
+            "application/pdf/x",
+            "application/pdf; charset=x",
+            "application/vnd.google-apps.spreadsheet",
+        ] {
+            assert_eq!(
+                parse_mime_types([invalid]).unwrap_err().code(),
+                "config.invalid_mime_type",
+                "{invalid}"
+            );
+        }
+    }
+}
@@ -0,0 +1,281 @@
+//! Bookshelf thumbnail generation
+//!
+//! Renders the first page of a downloaded PDF to a small PNG with PDFium and
+//! stores it in the thumbnails directory (see `bookshelf::save_cloud_thumbnail`),
+//! so thumbnails work offline and for files Drive has no preview for.
+
+use base64::{Engine as _, engine::general_purpose};
+use pdfium_render::prelude::*;
+use rusqlite::OptionalExtension;
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Manager};
+
+use crate::bookshelf;
+use crate::db::{ToDbError, open_db};
+use crate::error::{GoogleDriveError, PdfError, PedaruError};
+
+/// Maximum thumbnail width in pixels
+const THUMBNAIL_MAX_WIDTH: i32 = 200;
+
+/// Maximum thumbnail height in pixels
+const THUMBNAIL_MAX_HEIGHT: i32 = 280;
+
+/// Longest edge of a stored thumbnail in pixels (larger images are downscaled)
+const STORED_THUMBNAIL_MAX_EDGE: u32 = 256;
+
+/// PDFium bindings, loaded once (from the app resources, then the system)
+static PDFIUM: OnceLock<Result<Mutex<Pdfium>, String>> = OnceLock::new();
+
+fn get_pdfium(app: &AppHandle) -> Result<&'static Mutex<Pdfium>, String> {
+    PDFIUM
+        .get_or_init(|| {
+            let bundled = app.path().resource_dir().ok().and_then(|dir| {
+                Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path(&dir)).ok()
+            });
+            let bindings = match bundled {
+                Some(bindings) => Ok(bindings),
+                None => Pdfium::bind_to_system_library(),
+            };
+            bindings
+                .map(|bindings| Mutex::new(Pdfium::new(bindings)))
+                .map_err(|e| format!("PDFium library not available: {:?}", e))
+        })
+        .as_ref()
+        .map_err(Clone::clone)
+}
+
+/// Render the first page of a PDF to a PNG
+pub fn render_first_page(app: &AppHandle, pdf_path: &str) -> Result<Vec<u8>, PedaruError> {
+    let render_err = |message: String| PdfError::RenderFailed {
+        path: pdf_path.to_string(),
+        message,
+    };
+
+    let pdfium = get_pdfium(app).map_err(render_err)?;
+    let pdfium = pdfium.lock().unwrap();
+
+    let document = pdfium
+        .load_pdf_from_file(pdf_path, None)
+        .map_err(|e| render_err(format!("{:?}", e)))?;
+    let page = document
+        .pages()
+        .first()
+        .map_err(|e| render_err(format!("{:?}", e)))?;
+
+    let config = PdfRenderConfig::new()
+        .set_target_width(THUMBNAIL_MAX_WIDTH)
+        .set_maximum_height(THUMBNAIL_MAX_HEIGHT);
+    let bitmap = page
+        .render_with_config(&config)
+        .map_err(|e| render_err(format!("{:?}", e)))?;
+
+    encode_png(
+        bitmap.width() as u32,
+        bitmap.height() as u32,
+        &bitmap.as_rgba_bytes(),
+    )
+    .map_err(|e| render_err(e.to_string()).into())
+}
+
+/// Encode RGBA pixels as a PNG image (best compression)
+fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Result<Vec<u8>, png::EncodingError> {
+    let mut buf = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut buf, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_compression(png::Compression::Best);
+        encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(rgba)?;
+    }
+    Ok(buf)
+}
+
+/// Decoded 8-bit RGBA image
+struct RgbaImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+/// Decode a PNG of any color type to 8-bit RGBA
+fn decode_png(data: &[u8]) -> Result<RgbaImage, String> {
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|e| e.to_string())?;
+    let bytes = &buf[..info.buffer_size()];
+
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => bytes.to_vec(),
+        png::ColorType::Rgb => bytes
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => bytes
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        png::ColorType::Grayscale => bytes.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        png::ColorType::Indexed => return Err("palette was not expanded".to_string()),
+    };
+    Ok(RgbaImage {
+        width: info.width,
+        height: info.height,
+        pixels,
+    })
+}
+
+/// Scale `width` x `height` down (keeping the aspect ratio) so neither edge
+/// exceeds `max_edge`
+fn fit_within(width: u32, height: u32, max_edge: u32) -> (u32, u32) {
+    let longest = width.max(height);
+    if longest <= max_edge {
+        return (width, height);
+    }
+    let scale = |edge: u32| {
+        let scaled =
+            (u64::from(edge) * u64::from(max_edge) + u64::from(longest) / 2) / u64::from(longest);
+        (scaled as u32).max(1)
+    };
+    (scale(width), scale(height))
+}
+
+/// Downscale by averaging the source pixels covered by each output pixel
+fn downscale(image: &RgbaImage, width: u32, height: u32) -> Vec<u8> {
+    let span = |i: u32, out: u32, src: u32| {
+        let start = u64::from(i) * u64::from(src) / u64::from(out);
+        let end = (u64::from(i + 1) * u64::from(src) / u64::from(out)).max(start + 1);
+        start as usize..end as usize
+    };
+
+    let src_width = image.width as usize;
+    let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height {
+        let rows = span(y, height, image.height);
+        for x in 0..width {
+            let cols = span(x, width, image.width);
+            let mut sum = [0u64; 4];
+            for row in rows.clone() {
+                for col in cols.clone() {
+                    let offset = (row * src_width + col) * 4;
+                    for (channel, total) in sum.iter_mut().enumerate() {
+                        *total += u64::from(image.pixels[offset + channel]);
+                    }
+                }
+            }
+            let count = (rows.len() * cols.len()) as u64;
+            pixels.extend(sum.map(|total| ((total + count / 2) / count) as u8));
+        }
+    }
+    pixels
+}
+
+/// Downscale a PNG thumbnail to `STORED_THUMBNAIL_MAX_EDGE` and recompress it
+///
+/// Small images keep their original encoding if recompressing doesn't
+/// make them smaller.
+pub fn compress_thumbnail(image: &[u8]) -> Result<Vec<u8>, PedaruError> {
+    let decoded = decode_png(image).map_err(PdfError::InvalidThumbnail)?;
+    let (width, height) = fit_within(decoded.width, decoded.height, STORED_THUMBNAIL_MAX_EDGE);
+    let resized = (width, height) != (decoded.width, decoded.height);
+    let pixels = if resized {
+        downscale(&decoded, width, height)
+    } else {
+        decoded.pixels
+    };
+
+    let mut png = encode_png(width, height, &pixels)
+        .map_err(|e| PdfError::InvalidThumbnail(e.to_string()))?;
+    if !resized && png.len() >= image.len() {
+        png = image.to_vec();
+    }
+
+    log::debug!(
+        "Thumbnail {}x{} ({} bytes) stored as {}x{} ({} bytes)",
+        decoded.width,
+        decoded.height,
+        image.len(),
+        width,
+        height,
+        png.len()
+    );
+    Ok(png)
+}
+
+/// Render and store the thumbnail for a downloaded cloud item
+///
+/// Returns the new base64-encoded thumbnail.
+pub fn generate_cloud_thumbnail(
+    app: &AppHandle,
+    drive_file_id: &str,
+) -> Result<String, PedaruError> {
+    let conn = open_db(app)?;
+    let local_path: Option<String> = conn
+        .query_row(
+            "SELECT local_path FROM bookshelf_cloud
+             WHERE drive_file_id = ?1 AND download_status = 'completed'",
+            [drive_file_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .db_err()?
+        .flatten();
+    drop(conn);
+
+    let local_path =
+        local_path.ok_or_else(|| GoogleDriveError::FileNotFound(drive_file_id.to_string()))?;
+
+    let png = render_first_page(app, &local_path)?;
+    let stored = bookshelf::save_cloud_thumbnail(app, drive_file_id, &png)?;
+    Ok(general_purpose::STANDARD.encode(stored))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_png_signature() {
+        let png = encode_png(2, 1, &[255, 0, 0, 255, 0, 0, 255, 255]).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    }
+
+    #[test]
+    fn test_compress_thumbnail_caps_longest_edge() {
+        let pixels: Vec<u8> = (0..600 * 300)
+            .flat_map(|i| [(i % 251) as u8, 0, 128, 255])
+            .collect();
+        let large = encode_png(600, 300, &pixels).unwrap();
+
+        let stored = decode_png(&compress_thumbnail(&large).unwrap()).unwrap();
+        assert_eq!((stored.width, stored.height), (256, 128));
+        assert_eq!(stored.pixels.len(), 256 * 128 * 4);
+
+        let small = encode_png(2, 1, &[255, 0, 0, 255, 0, 0, 255, 255]).unwrap();
+        let stored = decode_png(&compress_thumbnail(&small).unwrap()).unwrap();
+        assert_eq!((stored.width, stored.height), (2, 1));
+        assert_eq!(stored.pixels, [255, 0, 0, 255, 0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_compress_thumbnail_rejects_invalid_data() {
+        assert!(compress_thumbnail(b"not a png").is_err());
+    }
+
+    #[test]
+    fn test_downscale_averages_pixels() {
+        let image = RgbaImage {
+            width: 2,
+            height: 2,
+            pixels: vec![
+                0, 0, 0, 255, 255, 255, 255, 255, 0, 0, 0, 255, 255, 255, 255, 255,
+            ],
+        };
+        assert_eq!(downscale(&image, 1, 1), [128, 128, 128, 255]);
+        assert_eq!(fit_within(1000, 10, 256), (256, 3));
+        assert_eq!(fit_within(10, 1000, 256), (3, 256));
+    }
+}
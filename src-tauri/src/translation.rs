@@ -0,0 +1,1535 @@
+//! Provider-agnostic translation and explanation
+//!
+//! Prompts and response parsing are shared; each backend (Gemini, OpenAI,
+//! Anthropic) only implements `TranslationProvider` to send a prompt and
+//! return the raw model output.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use reqwest::{Client, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::AppHandle;
+
+use crate::anthropic::AnthropicProvider;
+use crate::error::{GeminiError, PedaruError, TranslationError};
+use crate::gemini::GeminiProvider;
+use crate::network;
+use crate::openai::OpenAiProvider;
+use crate::settings;
+use crate::translation_cache;
+use crate::translation_stream;
+
+// ============================================================================
+// Providers
+// ============================================================================
+
+/// A backend that can run a prompt against an LLM
+pub trait TranslationProvider {
+    /// Human-readable provider name used in error messages
+    fn name(&self) -> &'static str;
+
+    /// Send a prompt with a system instruction and return the raw response text
+    fn generate(
+        &self,
+        prompt: &str,
+        system_instruction: &str,
+    ) -> impl Future<Output = Result<String, PedaruError>> + Send;
+
+    /// Start a streaming request whose body is a server-sent event stream
+    fn start_stream(
+        &self,
+        prompt: &str,
+        system_instruction: &str,
+    ) -> impl Future<Output = Result<Response, PedaruError>> + Send;
+
+    /// Extract the text delta from one streamed JSON event
+    fn stream_delta(&self, event: &Value) -> Option<String>;
+}
+
+/// Supported translation providers, as stored in the `translation_provider` setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProviderKind {
+    Gemini,
+    OpenAi,
+    Anthropic,
+}
+
+impl ProviderKind {
+    pub const ALL: [ProviderKind; 3] = [
+        ProviderKind::Gemini,
+        ProviderKind::OpenAi,
+        ProviderKind::Anthropic,
+    ];
+
+    /// Identifier used in settings and by the frontend
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProviderKind::Gemini => "gemini",
+            ProviderKind::OpenAi => "openai",
+            ProviderKind::Anthropic => "anthropic",
+        }
+    }
+
+    /// Parse a provider identifier, rejecting unknown values
+    pub fn parse(value: &str) -> Result<Self, PedaruError> {
+        let normalized = value.trim().to_ascii_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == normalized)
+            .ok_or_else(|| TranslationError::UnsupportedProvider(value.to_string()).into())
+    }
+
+    /// Highest temperature the provider accepts (the lowest is always 0)
+    pub fn max_temperature(self) -> f64 {
+        match self {
+            ProviderKind::Gemini | ProviderKind::OpenAi => 2.0,
+            ProviderKind::Anthropic => 1.0,
+        }
+    }
+
+    /// Validate user-supplied generation parameters for this provider
+    ///
+    /// Temperature is clamped to the provider's range; a negative token limit
+    /// is rejected and zero means "provider default".
+    pub fn generation_params(
+        self,
+        temperature: Option<f64>,
+        max_output_tokens: Option<i64>,
+    ) -> Result<GenerationParams, PedaruError> {
+        if temperature.is_some_and(f64::is_nan) {
+            return Err(TranslationError::InvalidSettings(
+                "temperature must be a number".to_string(),
+            )
+            .into());
+        }
+        if let Some(tokens) = max_output_tokens
+            && tokens < 0
+        {
+            return Err(TranslationError::InvalidSettings(format!(
+                "max output tokens must not be negative (got {})",
+                tokens
+            ))
+            .into());
+        }
+
+        Ok(GenerationParams {
+            temperature: temperature.map(|t| t.clamp(0.0, self.max_temperature())),
+            max_output_tokens: max_output_tokens
+                .filter(|&tokens| tokens > 0)
+                .map(|tokens| u32::try_from(tokens).unwrap_or(u32::MAX)),
+        })
+    }
+}
+
+/// Sampling parameters sent with each request (None = provider default)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GenerationParams {
+    pub temperature: Option<f64>,
+    pub max_output_tokens: Option<u32>,
+}
+
+/// Run a prompt against the given provider
+pub(crate) async fn generate(
+    provider: &ProviderConfig<'_>,
+    prompt: &str,
+    system_instruction: &str,
+) -> Result<String, PedaruError> {
+    let ProviderConfig {
+        kind,
+        api_key,
+        model,
+        params,
+    } = *provider;
+    match kind {
+        ProviderKind::Gemini => {
+            GeminiProvider::new(api_key, model, params)
+                .generate(prompt, system_instruction)
+                .await
+        }
+        ProviderKind::OpenAi => {
+            OpenAiProvider::new(api_key, model, params)
+                .generate(prompt, system_instruction)
+                .await
+        }
+        ProviderKind::Anthropic => {
+            AnthropicProvider::new(api_key, model, params)
+                .generate(prompt, system_instruction)
+                .await
+        }
+    }
+}
+
+/// Build an HTTP client for LLM API requests
+///
+/// Streaming clients have no overall timeout, since long responses may take
+/// a while to finish; they only time out when the server goes quiet.
+pub(crate) fn http_client(provider: &'static str, streaming: bool) -> Result<Client, PedaruError> {
+    let builder = if streaming {
+        network::client_builder()
+    } else {
+        network::client_builder().timeout(network::http_timeouts().request())
+    };
+
+    builder.build().map_err(|e| {
+        TranslationError::ApiRequestFailed {
+            provider,
+            status: None,
+            message: format!("Failed to create HTTP client: {}", e),
+        }
+        .into()
+    })
+}
+
+/// Send an API request, turning network failures and non-success statuses
+/// into user-facing `TranslationError`s
+pub(crate) async fn send_request(
+    provider: &'static str,
+    request: RequestBuilder,
+) -> Result<Response, PedaruError> {
+    let request_failed =
+        |status: Option<u16>, message: String| TranslationError::ApiRequestFailed {
+            provider,
+            status,
+            message,
+        };
+
+    network::ensure_online(provider)?;
+    let response = request.send().await.map_err(|e| {
+        network::network_error(provider, &e).unwrap_or_else(|| {
+            request_failed(None, format!("Network error: {}", e.without_url())).into()
+        })
+    })?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response.text().await.unwrap_or_default();
+        let message = match status.as_u16() {
+            429 => "Rate limit exceeded. Please wait a moment and try again.".to_string(),
+            401 | 403 => format!(
+                "Invalid API key. Please check your {} API key in Settings.",
+                provider
+            ),
+            _ => format!("API error ({}): {}", status, error_text),
+        };
+        return Err(request_failed(Some(status.as_u16()), message).into());
+    }
+
+    Ok(response)
+}
+
+/// Send a JSON API request and return the response body
+pub(crate) async fn send_json_request(
+    provider: &'static str,
+    request: RequestBuilder,
+) -> Result<Value, PedaruError> {
+    send_request(provider, request)
+        .await?
+        .json()
+        .await
+        .map_err(|e| {
+            TranslationError::InvalidResponse {
+                provider,
+                message: e.to_string(),
+            }
+            .into()
+        })
+}
+
+// ============================================================================
+// Gemini Rate Limiting
+// ============================================================================
+
+/// Default Gemini requests per minute, matching the free-tier quota
+pub const DEFAULT_GEMINI_REQUESTS_PER_MINUTE: u32 = 15;
+
+/// Longest a request is queued for a free slot before it is rejected
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(30);
+
+/// Token bucket allowing a burst of `requests_per_minute` requests, then
+/// refilling one token every `60 / requests_per_minute` seconds
+///
+/// Queued requests take their token up front, so the balance can go negative.
+#[derive(Debug)]
+struct TokenBucket {
+    requests_per_minute: u32,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(requests_per_minute: u32, now: Instant) -> Self {
+        Self {
+            requests_per_minute,
+            tokens: requests_per_minute as f64,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        let capacity = self.requests_per_minute as f64;
+        self.tokens = (self.tokens + elapsed * capacity / 60.0).min(capacity);
+        self.last_refill = now;
+    }
+
+    /// Change the rate, keeping requests already queued in place
+    fn set_rate(&mut self, requests_per_minute: u32, now: Instant) {
+        self.refill(now);
+        self.requests_per_minute = requests_per_minute;
+        self.tokens = self.tokens.min(requests_per_minute as f64);
+    }
+
+    /// Take a token and return how long to wait before sending
+    ///
+    /// Returns `Err` with the wait instead, without taking a token, when it
+    /// would exceed `max_wait`.
+    fn reserve(&mut self, now: Instant, max_wait: Duration) -> Result<Duration, Duration> {
+        if self.requests_per_minute == 0 {
+            return Ok(Duration::ZERO);
+        }
+
+        self.refill(now);
+        let wait = if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) * 60.0 / self.requests_per_minute as f64)
+        };
+        if wait > max_wait {
+            return Err(wait);
+        }
+
+        self.tokens -= 1.0;
+        Ok(wait)
+    }
+}
+
+/// Gemini request budget shared by every translation call in the process
+static GEMINI_RATE_LIMITER: OnceLock<Mutex<TokenBucket>> = OnceLock::new();
+
+fn get_gemini_rate_limiter() -> &'static Mutex<TokenBucket> {
+    GEMINI_RATE_LIMITER.get_or_init(|| {
+        Mutex::new(TokenBucket::new(
+            DEFAULT_GEMINI_REQUESTS_PER_MINUTE,
+            Instant::now(),
+        ))
+    })
+}
+
+/// Load the Gemini rate limit from settings (called on app startup)
+pub fn init_gemini_rate_limit(app: &AppHandle) {
+    let limit = settings::get_setting(app, settings::KEY_GEMINI_REQUESTS_PER_MINUTE)
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_GEMINI_REQUESTS_PER_MINUTE);
+    get_gemini_rate_limiter()
+        .lock()
+        .expect("GEMINI_RATE_LIMITER mutex poisoned")
+        .set_rate(limit, Instant::now());
+}
+
+/// Get the Gemini rate limit in requests per minute (0 = unlimited)
+pub fn get_gemini_rate_limit() -> u32 {
+    get_gemini_rate_limiter()
+        .lock()
+        .expect("GEMINI_RATE_LIMITER mutex poisoned")
+        .requests_per_minute
+}
+
+/// Change the Gemini rate limit (0 = unlimited)
+pub fn set_gemini_rate_limit(app: &AppHandle, requests_per_minute: u32) -> Result<(), PedaruError> {
+    settings::set_setting(
+        app,
+        settings::KEY_GEMINI_REQUESTS_PER_MINUTE,
+        &requests_per_minute.to_string(),
+    )?;
+    get_gemini_rate_limiter()
+        .lock()
+        .expect("GEMINI_RATE_LIMITER mutex poisoned")
+        .set_rate(requests_per_minute, Instant::now());
+    Ok(())
+}
+
+/// Wait for a free Gemini request slot
+///
+/// Requests over the limit are queued; once the queue would take longer
+/// than `MAX_RATE_LIMIT_WAIT` they are rejected instead.
+pub(crate) async fn acquire_gemini_slot() -> Result<(), PedaruError> {
+    let (reserved, limit) = {
+        let mut bucket = get_gemini_rate_limiter()
+            .lock()
+            .expect("GEMINI_RATE_LIMITER mutex poisoned");
+        (
+            bucket.reserve(Instant::now(), MAX_RATE_LIMIT_WAIT),
+            bucket.requests_per_minute,
+        )
+    };
+
+    match reserved {
+        Ok(wait) => {
+            if !wait.is_zero() {
+                log::info!(
+                    "Gemini rate limit reached, waiting {:.1}s",
+                    wait.as_secs_f64()
+                );
+                tokio::time::sleep(wait).await;
+            }
+            Ok(())
+        }
+        Err(wait) => Err(GeminiError::TooManyRequests {
+            limit,
+            retry_after_secs: wait.as_secs_f64().ceil() as u64,
+        }
+        .into()),
+    }
+}
+
+// ============================================================================
+// Default Prompts (hardcoded in backend)
+// ============================================================================
+
+// Shared part of the translation system instructions (role and output format)
+macro_rules! translation_instruction_header {
+    () => {
+        r#"You are a professional translator into {target_language} and a language teacher.
+
+## Your Task
+Translate ONLY the "SELECTED TEXT" provided by the user. The context is for understanding only.
+
+## Output Format (STRICT - follow exactly):
+- Output MUST be valid JSON only. No markdown code blocks, no extra text.
+- The JSON structure MUST be:
+{
+  "translation": "Translation result in {target_language} (string)",
+  "points": ["Point 1 (string)", "Point 2 (string)", "Point 3 (string)"]
+}
+
+## Critical Rules:
+- The "points" field MUST be a flat array of strings. DO NOT use nested objects.
+- Each element in points must be a simple string, not an object.
+- All output text MUST be in {target_language}.
+- IMPORTANT: Translate ONLY the SELECTED TEXT, not the context.
+
+"#
+    };
+}
+
+// System instruction for single words, idioms and short phrases
+pub const DEFAULT_WORD_PROMPT: &str = concat!(
+    translation_instruction_header!(),
+    r#"## Translation Rules:
+- For single words, idioms, or short phrases (no spaces, or 2-3 words):
+  - translation: Only the meaning of the word/idiom. NOT a translation of the entire sentence.
+  - points: A flat array of strings containing (write the labels in {target_language}):
+    1. "Meaning: [explanation of the word in {target_language}]"
+    2. "Original: [Extract the COMPLETE original sentence containing the word from the context, with ***highlighted*** word]"
+    3. "Translation: [{target_language} translation of that complete sentence, with ***highlighted*** translation of the word]"
+    4. "Synonyms: [synonyms in the original language with {target_language} meanings]"
+  - Example output (when translating into Japanese):
+    {
+      "translation": "活用する、利用する",
+      "points": [
+        "単語の意味: 何かの力や資源を有効に使うこと",
+        "原文: The goal is to ***harness*** the power of AI.",
+        "訳: 目標はAIの力を***活用する***ことです。",
+        "類語・言い換え: utilize（活用する）, leverage（活かす）, exploit（利用する）"
+      ]
+    }
+  - CRITICAL: How to find the original sentence:
+    - The selected word appears at the EXACT BOUNDARY between "Context before" and "Context after".
+    - The original sentence containing the selected word is: (end of "Context before") + (selected word) + (beginning of "Context after")
+    - If the same word appears multiple times in the context, you MUST use ONLY the occurrence at the boundary position.
+    - DO NOT pick a sentence from earlier in Context before that happens to contain the same word."#
+);
+
+// System instruction for a single sentence
+pub const DEFAULT_SENTENCE_PROMPT: &str = concat!(
+    translation_instruction_header!(),
+    r#"## Translation Rules:
+- For sentences:
+  - translation: Full {target_language} translation of the text
+  - points: A flat array of strings with grammatical explanations:
+    1. Each point is a single string explaining one grammar structure
+    2. Focus on challenging structures: relative clauses, participle constructions, etc.
+    3. Include synonyms or alternative expressions where helpful"#
+);
+
+// System instruction for paragraphs and longer passages
+pub const DEFAULT_PARAGRAPH_PROMPT: &str = concat!(
+    translation_instruction_header!(),
+    r#"## Translation Rules:
+- For paragraphs and longer passages:
+  - translation: Full natural {target_language} translation of the text, keeping the paragraph structure
+  - points: A flat array of 3-5 strings:
+    1. A one-sentence summary of the passage's main idea
+    2. Explanations of the hardest sentence structures, quoting the original phrase
+    3. Key terms with their {target_language} translations"#
+);
+
+// User prompt for translation (actual content - data only)
+const TRANSLATION_PROMPT: &str = r#"SELECTED TEXT (translate this):
+{text}
+
+Context before:
+{context_before}
+
+Context after:
+{context_after}"#;
+
+// System instruction for explanation (behavioral guidelines)
+const EXPLANATION_SYSTEM_INSTRUCTION: &str = r#"You are an expert at explaining complex concepts in simple, easy-to-understand terms.
+
+## Output Format (STRICT - follow exactly):
+- Output MUST be valid JSON only. No markdown code blocks, no extra text.
+- The JSON structure MUST be:
+{
+  "summary": "One-sentence summary (string)",
+  "points": ["Point 1 (string)", "Point 2 (string)", "Point 3 (string)"]
+}
+
+## Critical Rules:
+- The "points" field MUST be a flat array of strings. DO NOT use nested objects.
+- All output text MUST be in {target_language}.
+
+## Explanation Guidelines:
+
+### Summary (summary field):
+- Summarize the essence in ONE sentence
+- Use phrases like "In short, ..." or "In other words, ..." (in {target_language})
+- Make it understandable even for someone unfamiliar with the topic
+
+### Explanation points (points field):
+- Rephrase technical terms in plain language: "X (that is, Y)"
+- Use familiar analogies or metaphors to explain abstract concepts
+- Add context about "why this matters" or "what benefit does this provide"
+- For technical content, explain practical use cases and benefits concretely
+- For academic content, explain the importance in the field and application examples
+- Each point should be independently understandable
+- Keep each point to 2-3 sentences"#;
+
+// User prompt for explanation (actual content)
+const EXPLANATION_PROMPT: &str = r#"Explain the following text.
+
+The user has selected text from a PDF document. The context shows the surrounding text:
+- "Context before" = text that appears BEFORE the selected text in the document
+- "Text to explain" = the actual text the user selected
+- "Context after" = text that appears AFTER the selected text in the document
+
+## Context before (for understanding only):
+{context_before}
+
+## Text to explain:
+{text}
+
+## Context after (for understanding only):
+{context_after}
+
+Use the context to understand the meaning, but explain only the selected text."#;
+
+// ============================================================================
+// Public Types
+// ============================================================================
+
+/// Structured translation result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranslationResponse {
+    pub translation: String,
+    pub points: Vec<String>,
+}
+
+/// Structured explanation result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExplanationResponse {
+    pub summary: String,
+    pub points: Vec<String>,
+}
+
+/// A translation target language offered in the UI
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SupportedLanguage {
+    /// English name, stored in settings and substituted into prompts
+    pub name: &'static str,
+    /// Name of the language in the language itself
+    pub native_name: &'static str,
+}
+
+const SUPPORTED_LANGUAGES: &[SupportedLanguage] = &[
+    SupportedLanguage {
+        name: "Japanese",
+        native_name: "日本語",
+    },
+    SupportedLanguage {
+        name: "English",
+        native_name: "English",
+    },
+    SupportedLanguage {
+        name: "Simplified Chinese",
+        native_name: "简体中文",
+    },
+    SupportedLanguage {
+        name: "Traditional Chinese",
+        native_name: "繁體中文",
+    },
+    SupportedLanguage {
+        name: "Korean",
+        native_name: "한국어",
+    },
+    SupportedLanguage {
+        name: "Spanish",
+        native_name: "Español",
+    },
+    SupportedLanguage {
+        name: "French",
+        native_name: "Français",
+    },
+    SupportedLanguage {
+        name: "German",
+        native_name: "Deutsch",
+    },
+    SupportedLanguage {
+        name: "Italian",
+        native_name: "Italiano",
+    },
+    SupportedLanguage {
+        name: "Portuguese",
+        native_name: "Português",
+    },
+    SupportedLanguage {
+        name: "Russian",
+        native_name: "Русский",
+    },
+    SupportedLanguage {
+        name: "Vietnamese",
+        native_name: "Tiếng Việt",
+    },
+    SupportedLanguage {
+        name: "Thai",
+        native_name: "ไทย",
+    },
+    SupportedLanguage {
+        name: "Indonesian",
+        native_name: "Bahasa Indonesia",
+    },
+];
+
+/// Target languages offered in the settings dropdown
+pub fn supported_languages() -> &'static [SupportedLanguage] {
+    SUPPORTED_LANGUAGES
+}
+
+/// How much text was selected, used to pick a prompt template
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextKind {
+    Word,
+    Sentence,
+    Paragraph,
+}
+
+/// Translation system instructions for each kind of selection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptTemplates {
+    pub word: String,
+    pub sentence: String,
+    pub paragraph: String,
+}
+
+impl Default for PromptTemplates {
+    fn default() -> Self {
+        Self {
+            word: DEFAULT_WORD_PROMPT.to_string(),
+            sentence: DEFAULT_SENTENCE_PROMPT.to_string(),
+            paragraph: DEFAULT_PARAGRAPH_PROMPT.to_string(),
+        }
+    }
+}
+
+impl PromptTemplates {
+    /// Template for the given kind of selection
+    pub fn select(&self, kind: TextKind) -> &str {
+        match kind {
+            TextKind::Word => &self.word,
+            TextKind::Sentence => &self.sentence,
+            TextKind::Paragraph => &self.paragraph,
+        }
+    }
+}
+
+/// Word-count limits used to classify a selection
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextKindThresholds {
+    /// Selections with at most this many words use the word template
+    pub word_max_words: usize,
+    /// Selections with at least this many words use the paragraph template
+    pub paragraph_min_words: usize,
+}
+
+impl Default for TextKindThresholds {
+    fn default() -> Self {
+        Self {
+            word_max_words: 3,
+            paragraph_min_words: 40,
+        }
+    }
+}
+
+/// Everything besides the provider that shapes the prompts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptSettings {
+    pub target_language: String,
+    pub templates: PromptTemplates,
+    pub thresholds: TextKindThresholds,
+}
+
+// ============================================================================
+// Prompt Building & Response Parsing
+// ============================================================================
+
+/// Approximate number of words in `text`
+///
+/// Whitespace-separated tokens count as one word each, except that tokens
+/// containing CJK characters (written without spaces) count as one word per
+/// two CJK characters.
+fn count_words(text: &str) -> usize {
+    text.split_whitespace()
+        .map(|token| {
+            let cjk_chars = token.chars().filter(|&c| is_cjk(c)).count();
+            cjk_chars.div_ceil(2).max(1)
+        })
+        .sum()
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'   // Hiragana, Katakana
+        | '\u{3400}'..='\u{4DBF}' // CJK Extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK Unified Ideographs
+        | '\u{AC00}'..='\u{D7AF}' // Hangul syllables
+    )
+}
+
+/// Classify a selection as a word, sentence or paragraph by its word count
+pub fn classify_text(text: &str, thresholds: &TextKindThresholds) -> TextKind {
+    let words = count_words(text);
+    if words <= thresholds.word_max_words {
+        TextKind::Word
+    } else if words >= thresholds.paragraph_min_words {
+        TextKind::Paragraph
+    } else {
+        TextKind::Sentence
+    }
+}
+
+/// Values substituted into prompt templates
+struct PromptValues<'a> {
+    text: &'a str,
+    context_before: &'a str,
+    context_after: &'a str,
+    target_language: &'a str,
+}
+
+/// Fill the `{target_language}`, `{text}`, `{context_before}` and
+/// `{context_after}` placeholders
+///
+/// The target language is substituted first so selected text containing a
+/// literal `{target_language}` is left untouched.
+fn build_prompt(template: &str, values: &PromptValues) -> String {
+    template
+        .replace("{target_language}", values.target_language)
+        .replace("{text}", values.text)
+        .replace("{context_before}", values.context_before)
+        .replace("{context_after}", values.context_after)
+}
+
+/// Parse JSON translation response, with fallback for markdown code blocks
+fn parse_translation_response(text: &str) -> Result<TranslationResponse, PedaruError> {
+    log::debug!("Raw API response: {}", text);
+
+    // Try to parse directly first
+    if let Ok(response) = serde_json::from_str::<TranslationResponse>(text) {
+        log::debug!("Parsed directly: {:?}", response);
+        return Ok(response);
+    }
+
+    // Try to extract JSON from markdown code block
+    let cleaned = text
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+
+    log::debug!("Cleaned text: {}", cleaned);
+
+    if let Ok(response) = serde_json::from_str::<TranslationResponse>(cleaned) {
+        log::debug!("Parsed from cleaned: {:?}", response);
+        return Ok(response);
+    }
+
+    // Try to parse as a more flexible JSON structure
+    if let Ok(value) = serde_json::from_str::<Value>(cleaned) {
+        log::debug!("Parsed as Value: {:?}", value);
+
+        // Handle both object and array responses
+        let obj = if value.is_array() {
+            // If it's an array, take the first element
+            value.as_array().and_then(|arr| arr.first()).cloned()
+        } else {
+            Some(value)
+        };
+
+        if let Some(obj) = obj {
+            let translation = obj
+                .get("translation")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string();
+
+            let points = obj
+                .get("points")
+                .and_then(|v| v.as_array())
+                .map(|arr| {
+                    arr.iter()
+                        .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            let response = TranslationResponse {
+                translation,
+                points,
+            };
+            log::debug!("Flexible parse result: {:?}", response);
+            return Ok(response);
+        }
+    }
+
+    log::warn!("All parsing failed, returning raw text");
+    // If all parsing fails, return the raw text as translation
+    Ok(TranslationResponse {
+        translation: text.to_string(),
+        points: vec![],
+    })
+}
+
+/// Parse JSON response for explanation, with fallback for markdown code blocks
+fn parse_explanation_response(text: &str) -> Result<ExplanationResponse, PedaruError> {
+    log::debug!("Raw API response (explanation): {}", text);
+
+    // Try to parse directly first
+    if let Ok(response) = serde_json::from_str::<ExplanationResponse>(text) {
+        log::debug!("Parsed directly: {:?}", response);
+        return Ok(response);
+    }
+
+    // Try to extract JSON from markdown code block
+    let cleaned = text
+        .trim()
+        .trim_start_matches("```json")
+        .trim_start_matches("```")
+        .trim_end_matches("```")
+        .trim();
+
+    log::debug!("Cleaned text: {}", cleaned);
+
+    if let Ok(response) = serde_json::from_str::<ExplanationResponse>(cleaned) {
+        log::debug!("Parsed from cleaned: {:?}", response);
+        return Ok(response);
+    }
+
+    // Try to parse as a more flexible JSON structure
+    if let Ok(value) = serde_json::from_str::<Value>(cleaned) {
+        log::debug!("Parsed as Value: {:?}", value);
+
+        // Handle both object and array responses
+        let obj = if value.is_array() {
+            value.as_array().and_then(|arr| arr.first()).cloned()
+        } else {
+            Some(value)
+        };
+
+        if let Some(obj) = obj {
+            let summary = obj
+                .get("summary")
+                .and_then(|v| v.as_str())
+                .unwrap_or("")
+                .to_string();
+
+            let points = obj
+                .get("points")
+                .and_then(|v| v.as_array())
+                .map(|arr| {
+                    arr.iter()
+                        .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            let response = ExplanationResponse { summary, points };
+            log::debug!("Flexible parse result: {:?}", response);
+            return Ok(response);
+        }
+    }
+
+    log::warn!("All parsing failed, returning raw text");
+    Ok(ExplanationResponse {
+        summary: text.to_string(),
+        points: vec![],
+    })
+}
+
+// ============================================================================
+// Active Requests
+// ============================================================================
+
+/// How often a non-streaming request checks whether it was cancelled
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// In-flight requests by id, and the latest request for each UI context
+#[derive(Default)]
+struct ActiveRequests {
+    cancel_flags: HashMap<String, Arc<AtomicBool>>,
+    latest_by_context: HashMap<String, String>,
+}
+
+impl ActiveRequests {
+    /// Add a request, cancelling the previous one for the same context
+    fn insert(&mut self, request_id: &str, context: Option<&str>) -> Arc<AtomicBool> {
+        let cancel_flag = Arc::new(AtomicBool::new(false));
+        self.cancel_flags
+            .insert(request_id.to_string(), cancel_flag.clone());
+        if let Some(context) = context
+            && let Some(previous) = self
+                .latest_by_context
+                .insert(context.to_string(), request_id.to_string())
+            && previous != request_id
+        {
+            self.cancel(&previous);
+        }
+        cancel_flag
+    }
+
+    fn remove(&mut self, request_id: &str, context: Option<&str>) {
+        self.cancel_flags.remove(request_id);
+        if let Some(context) = context
+            && self
+                .latest_by_context
+                .get(context)
+                .is_some_and(|latest| latest == request_id)
+        {
+            self.latest_by_context.remove(context);
+        }
+    }
+
+    fn cancel(&self, request_id: &str) -> bool {
+        match self.cancel_flags.get(request_id) {
+            Some(cancel_flag) => {
+                cancel_flag.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+static ACTIVE_REQUESTS: OnceLock<Mutex<ActiveRequests>> = OnceLock::new();
+
+/// Source of ids for requests the frontend doesn't name
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+fn get_active_requests() -> &'static Mutex<ActiveRequests> {
+    ACTIVE_REQUESTS.get_or_init(|| Mutex::new(ActiveRequests::default()))
+}
+
+/// An in-flight request, registered until dropped
+///
+/// Registering with a UI context (e.g. the translation popup) cancels the
+/// request still pending for that context, so a quick succession of
+/// selections doesn't pay for stale results or show them out of order.
+pub(crate) struct ActiveRequest {
+    request_id: String,
+    context: Option<String>,
+    cancel_flag: Arc<AtomicBool>,
+}
+
+impl ActiveRequest {
+    pub(crate) fn register(request_id: &str, context: Option<&str>) -> Self {
+        let cancel_flag = get_active_requests()
+            .lock()
+            .expect("ACTIVE_REQUESTS mutex poisoned")
+            .insert(request_id, context);
+        Self {
+            request_id: request_id.to_string(),
+            context: context.map(str::to_string),
+            cancel_flag,
+        }
+    }
+
+    /// Register a request under a generated id
+    fn register_unnamed(context: Option<&str>) -> Self {
+        let id = NEXT_REQUEST_ID.fetch_add(1, Ordering::SeqCst);
+        Self::register(&format!("pedaru-request-{}", id), context)
+    }
+
+    pub(crate) fn cancel_flag(&self) -> &AtomicBool {
+        &self.cancel_flag
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel_flag.load(Ordering::SeqCst)
+    }
+
+    /// Resolve once the request is cancelled
+    async fn cancelled(&self) {
+        wait_cancelled(&self.cancel_flag).await
+    }
+}
+
+/// Resolve once `cancel_flag` is set
+async fn wait_cancelled(cancel_flag: &AtomicBool) {
+    while !cancel_flag.load(Ordering::SeqCst) {
+        tokio::time::sleep(CANCEL_POLL_INTERVAL).await;
+    }
+}
+
+/// Run `future` unless `cancel_flag` is set first, returning `None` then
+///
+/// The future is dropped on cancellation, so a pending request or body read
+/// closes its connection instead of waiting for the server.
+pub(crate) async fn unless_cancelled<T>(
+    cancel_flag: &AtomicBool,
+    future: impl Future<Output = T>,
+) -> Option<T> {
+    tokio::select! {
+        biased;
+        () = wait_cancelled(cancel_flag) => None,
+        value = future => Some(value),
+    }
+}
+
+impl Drop for ActiveRequest {
+    fn drop(&mut self) {
+        get_active_requests()
+            .lock()
+            .expect("ACTIVE_REQUESTS mutex poisoned")
+            .remove(&self.request_id, self.context.as_deref());
+    }
+}
+
+/// Cancel an in-flight request; returns false if no such request is running
+pub fn cancel_request(request_id: &str) -> bool {
+    get_active_requests()
+        .lock()
+        .expect("ACTIVE_REQUESTS mutex poisoned")
+        .cancel(request_id)
+}
+
+// ============================================================================
+// Public API
+// ============================================================================
+
+/// Provider, API key, model and generation parameters for a request
+#[derive(Clone, Copy)]
+pub struct ProviderConfig<'a> {
+    pub kind: ProviderKind,
+    pub api_key: &'a str,
+    pub model: &'a str,
+    pub params: GenerationParams,
+}
+
+/// Cache key for a request
+///
+/// Covers the provider, model, generation parameters and both filled-in
+/// prompts, which already include the target language and the normalized
+/// selection.
+pub(crate) fn request_cache_key(
+    provider: &ProviderConfig<'_>,
+    prompt: &str,
+    system_instruction: &str,
+) -> String {
+    let temperature = format!("{:?}", provider.params.temperature);
+    let max_output_tokens = format!("{:?}", provider.params.max_output_tokens);
+    translation_cache::cache_key(&[
+        provider.kind.as_str(),
+        provider.model,
+        &temperature,
+        &max_output_tokens,
+        system_instruction,
+        prompt,
+    ])
+}
+
+/// Run a prompt, reusing a cached result for an identical earlier request
+///
+/// Cache failures are logged and never fail the request. Returns `None` if
+/// a newer request for the same `context` superseded this one.
+async fn generate_cached<T: Serialize + DeserializeOwned>(
+    app: &AppHandle,
+    provider: &ProviderConfig<'_>,
+    prompt: &str,
+    system_instruction: &str,
+    parse: fn(&str) -> Result<T, PedaruError>,
+    context: Option<&str>,
+) -> Result<Option<T>, PedaruError> {
+    let request = ActiveRequest::register_unnamed(context);
+    let key = request_cache_key(provider, prompt, system_instruction);
+
+    match translation_cache::lookup(app, &key) {
+        Ok(Some(cached)) => return Ok(Some(cached)),
+        Ok(None) => {}
+        Err(e) => log::warn!("Failed to read translation cache: {}", e),
+    }
+
+    // Dropping the request future closes the connection
+    let response_text = tokio::select! {
+        text = generate(provider, prompt, system_instruction) => text?,
+        () = request.cancelled() => return Ok(None),
+    };
+    let response = parse(&response_text)?;
+
+    if let Err(e) = translation_cache::store(app, &key, &response) {
+        log::warn!("Failed to write translation cache: {}", e);
+    }
+    if request.is_cancelled() {
+        return Ok(None);
+    }
+    Ok(Some(response))
+}
+
+/// Build the (user prompt, system instruction) pair for a translation
+///
+/// The system instruction is chosen from `prompts.templates` based on how much
+/// text was selected.
+fn translation_prompts(
+    prompts: &PromptSettings,
+    text: &str,
+    context_before: &str,
+    context_after: &str,
+) -> (String, String) {
+    let text = translation_cache::normalize_text(text);
+    let values = PromptValues {
+        text: &text,
+        context_before,
+        context_after,
+        target_language: &prompts.target_language,
+    };
+    let template = prompts
+        .templates
+        .select(classify_text(&text, &prompts.thresholds));
+    (
+        build_prompt(TRANSLATION_PROMPT, &values),
+        build_prompt(template, &values),
+    )
+}
+
+/// Build the (user prompt, system instruction) pair for an explanation
+fn explanation_prompts(
+    prompts: &PromptSettings,
+    text: &str,
+    context_before: &str,
+    context_after: &str,
+) -> (String, String) {
+    let text = translation_cache::normalize_text(text);
+    let values = PromptValues {
+        text: &text,
+        context_before,
+        context_after,
+        target_language: &prompts.target_language,
+    };
+    (
+        build_prompt(EXPLANATION_PROMPT, &values),
+        build_prompt(EXPLANATION_SYSTEM_INSTRUCTION, &values),
+    )
+}
+
+/// Translate text with the given provider
+///
+/// Returns a structured response with translation and explanation points,
+/// or `None` if a newer request for the same `context` superseded it.
+pub async fn translate_text(
+    app: &AppHandle,
+    provider: &ProviderConfig<'_>,
+    prompts: &PromptSettings,
+    text: &str,
+    context_before: &str,
+    context_after: &str,
+    context: Option<&str>,
+) -> Result<Option<TranslationResponse>, PedaruError> {
+    let (prompt, system_instruction) =
+        translation_prompts(prompts, text, context_before, context_after);
+    generate_cached(
+        app,
+        provider,
+        &prompt,
+        &system_instruction,
+        parse_translation_response,
+        context,
+    )
+    .await
+}
+
+/// Get explanation of text with the given provider
+///
+/// Returns a summary and explanation points, or `None` if a newer request
+/// for the same `context` superseded it. The context parameters help
+/// understand the text but are not included in output.
+pub async fn explain_text(
+    app: &AppHandle,
+    provider: &ProviderConfig<'_>,
+    prompts: &PromptSettings,
+    text: &str,
+    context_before: &str,
+    context_after: &str,
+    context: Option<&str>,
+) -> Result<Option<ExplanationResponse>, PedaruError> {
+    let (prompt, system_instruction) =
+        explanation_prompts(prompts, text, context_before, context_after);
+    generate_cached(
+        app,
+        provider,
+        &prompt,
+        &system_instruction,
+        parse_explanation_response,
+        context,
+    )
+    .await
+}
+
+// ============================================================================
+// Streaming API
+// ============================================================================
+
+/// Stream a prompt from the given provider, passing text deltas to `on_chunk`
+///
+/// Returns the full response text, or `None` if cancelled, which also stops
+/// a request still waiting for the server to respond.
+pub(crate) async fn generate_stream(
+    provider: &ProviderConfig<'_>,
+    prompt: &str,
+    system_instruction: &str,
+    cancel_flag: &AtomicBool,
+    on_chunk: &(dyn Fn(&str) + Sync),
+) -> Result<Option<String>, PedaruError> {
+    let ProviderConfig {
+        kind,
+        api_key,
+        model,
+        params,
+    } = *provider;
+    match kind {
+        ProviderKind::Gemini => {
+            let provider = GeminiProvider::new(api_key, model, params);
+            let Some(response) = unless_cancelled(
+                cancel_flag,
+                provider.start_stream(prompt, system_instruction),
+            )
+            .await
+            else {
+                return Ok(None);
+            };
+            translation_stream::read_event_stream(&provider, response?, cancel_flag, on_chunk).await
+        }
+        ProviderKind::OpenAi => {
+            let provider = OpenAiProvider::new(api_key, model, params);
+            let Some(response) = unless_cancelled(
+                cancel_flag,
+                provider.start_stream(prompt, system_instruction),
+            )
+            .await
+            else {
+                return Ok(None);
+            };
+            translation_stream::read_event_stream(&provider, response?, cancel_flag, on_chunk).await
+        }
+        ProviderKind::Anthropic => {
+            let provider = AnthropicProvider::new(api_key, model, params);
+            let Some(response) = unless_cancelled(
+                cancel_flag,
+                provider.start_stream(prompt, system_instruction),
+            )
+            .await
+            else {
+                return Ok(None);
+            };
+            translation_stream::read_event_stream(&provider, response?, cancel_flag, on_chunk).await
+        }
+    }
+}
+
+/// Translate text, streaming partial output as `translation-chunk` events
+///
+/// Emits a final `translation-done` event and returns the parsed response,
+/// or `None` if the stream was cancelled or superseded.
+pub async fn translate_text_stream(
+    app: &AppHandle,
+    provider: &ProviderConfig<'_>,
+    prompts: &PromptSettings,
+    text: &str,
+    context_before: &str,
+    context_after: &str,
+    request_id: &str,
+    context: Option<&str>,
+) -> Result<Option<TranslationResponse>, PedaruError> {
+    let (prompt, system_instruction) =
+        translation_prompts(prompts, text, context_before, context_after);
+    translation_stream::run_stream(
+        app,
+        provider,
+        &prompt,
+        &system_instruction,
+        parse_translation_response,
+        request_id,
+        context,
+    )
+    .await
+}
+
+/// Explain text, streaming partial output as `translation-chunk` events
+///
+/// Emits a final `translation-done` event and returns the parsed response,
+/// or `None` if the stream was cancelled or superseded.
+pub async fn explain_text_stream(
+    app: &AppHandle,
+    provider: &ProviderConfig<'_>,
+    prompts: &PromptSettings,
+    text: &str,
+    context_before: &str,
+    context_after: &str,
+    request_id: &str,
+    context: Option<&str>,
+) -> Result<Option<ExplanationResponse>, PedaruError> {
+    let (prompt, system_instruction) =
+        explanation_prompts(prompts, text, context_before, context_after);
+    translation_stream::run_stream(
+        app,
+        provider,
+        &prompt,
+        &system_instruction,
+        parse_explanation_response,
+        request_id,
+        context,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_newer_request_supersedes_same_context() {
+        let mut requests = ActiveRequests::default();
+        let first = requests.insert("a", Some("popup"));
+        let other = requests.insert("b", Some("window"));
+        let second = requests.insert("c", Some("popup"));
+        let unscoped = requests.insert("d", None);
+
+        assert!(first.load(Ordering::SeqCst));
+        assert!(!other.load(Ordering::SeqCst));
+        assert!(!second.load(Ordering::SeqCst));
+        assert!(!unscoped.load(Ordering::SeqCst));
+
+        // A finished superseded request doesn't forget the newer one
+        requests.remove("a", Some("popup"));
+        requests.insert("e", Some("popup"));
+        assert!(second.load(Ordering::SeqCst));
+
+        requests.remove("e", Some("popup"));
+        assert!(!requests.latest_by_context.contains_key("popup"));
+        assert!(!requests.cancel("e"));
+        assert!(requests.cancel("d"));
+    }
+
+    #[test]
+    fn test_provider_kind_round_trip() {
+        for kind in ProviderKind::ALL {
+            assert_eq!(ProviderKind::parse(kind.as_str()).unwrap(), kind);
+        }
+        assert_eq!(
+            ProviderKind::parse(" OpenAI ").unwrap(),
+            ProviderKind::OpenAi
+        );
+    }
+
+    #[test]
+    fn test_provider_kind_rejects_unknown() {
+        let err = ProviderKind::parse("mistral").unwrap_err();
+        assert!(err.to_string().contains("mistral"));
+    }
+
+    #[test]
+    fn test_generation_params_clamps_temperature() {
+        let params = ProviderKind::Anthropic
+            .generation_params(Some(1.7), Some(0))
+            .unwrap();
+        assert_eq!(params.temperature, Some(1.0));
+        assert_eq!(params.max_output_tokens, None);
+
+        let params = ProviderKind::Gemini
+            .generation_params(Some(-0.5), Some(512))
+            .unwrap();
+        assert_eq!(params.temperature, Some(0.0));
+        assert_eq!(params.max_output_tokens, Some(512));
+    }
+
+    #[test]
+    fn test_request_cache_key_covers_generation_params() {
+        let provider = |temperature, max_output_tokens| ProviderConfig {
+            kind: ProviderKind::Gemini,
+            api_key: "key",
+            model: "gemini-2.0-flash",
+            params: GenerationParams {
+                temperature,
+                max_output_tokens,
+            },
+        };
+        let key = |temperature, max_output_tokens| {
+            request_cache_key(
+                &provider(temperature, max_output_tokens),
+                "prompt",
+                "system",
+            )
+        };
+
+        assert_eq!(key(Some(0.2), Some(512)), key(Some(0.2), Some(512)));
+        assert_ne!(key(Some(0.2), Some(512)), key(Some(0.7), Some(512)));
+        assert_ne!(key(Some(0.2), Some(512)), key(None, Some(512)));
+        assert_ne!(key(Some(0.2), Some(512)), key(Some(0.2), Some(1024)));
+        assert_ne!(key(Some(0.2), Some(512)), key(Some(0.2), None));
+    }
+
+    #[test]
+    fn test_generation_params_rejects_negative_tokens() {
+        assert!(
+            ProviderKind::OpenAi
+                .generation_params(None, Some(-1))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_build_prompt_fills_placeholders() {
+        let values = PromptValues {
+            text: "harness",
+            context_before: "We aim to",
+            context_after: "the power",
+            target_language: "French",
+        };
+        let prompt = build_prompt(TRANSLATION_PROMPT, &values);
+        assert!(prompt.contains("harness"));
+        assert!(prompt.contains("We aim to"));
+        assert!(prompt.contains("the power"));
+        assert!(!prompt.contains('{'));
+
+        let system = build_prompt(DEFAULT_WORD_PROMPT, &values);
+        assert!(system.contains("MUST be in French"));
+        assert!(!system.contains("{target_language}"));
+    }
+
+    #[test]
+    fn test_build_prompt_keeps_placeholder_in_selected_text() {
+        let values = PromptValues {
+            text: "{target_language}",
+            context_before: "",
+            context_after: "",
+            target_language: "German",
+        };
+        assert!(build_prompt(TRANSLATION_PROMPT, &values).contains("{target_language}"));
+    }
+
+    #[test]
+    fn test_classify_text_by_word_count() {
+        let thresholds = TextKindThresholds::default();
+        assert_eq!(classify_text("harness", &thresholds), TextKind::Word);
+        assert_eq!(
+            classify_text("take into account", &thresholds),
+            TextKind::Word
+        );
+        assert_eq!(
+            classify_text("The goal is to harness the power of AI.", &thresholds),
+            TextKind::Sentence
+        );
+        assert_eq!(
+            classify_text(&"word ".repeat(40), &thresholds),
+            TextKind::Paragraph
+        );
+    }
+
+    #[test]
+    fn test_classify_text_counts_cjk_characters() {
+        let thresholds = TextKindThresholds::default();
+        assert_eq!(classify_text("活用", &thresholds), TextKind::Word);
+        assert_eq!(
+            classify_text("目標はAIの力を活用することです。", &thresholds),
+            TextKind::Sentence
+        );
+    }
+
+    #[test]
+    fn test_parse_translation_response_from_code_block() {
+        let text = "```json\n{\"translation\": \"活用する\", \"points\": [\"a\"]}\n```";
+        let response = parse_translation_response(text).unwrap();
+        assert_eq!(response.translation, "活用する");
+        assert_eq!(response.points, vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn test_token_bucket_paces_burst() {
+        let now = Instant::now();
+        let max_wait = Duration::from_secs(60);
+        let mut bucket = TokenBucket::new(6, now);
+
+        // The first six go straight through, then one every 10 seconds
+        let waits: Vec<Duration> = (0..9)
+            .map(|_| bucket.reserve(now, max_wait).unwrap())
+            .collect();
+        assert!(waits[..6].iter().all(|w| w.is_zero()));
+        assert_eq!(waits[6], Duration::from_secs(10));
+        assert_eq!(waits[7], Duration::from_secs(20));
+        assert_eq!(waits[8], Duration::from_secs(30));
+
+        // Later requests queue behind the ones already waiting
+        let later = now + Duration::from_secs(15);
+        assert_eq!(
+            bucket.reserve(later, max_wait).unwrap(),
+            Duration::from_secs(25)
+        );
+    }
+
+    #[test]
+    fn test_token_bucket_rejects_long_waits() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(2, now);
+        let max_wait = Duration::from_secs(30);
+
+        assert!(bucket.reserve(now, max_wait).is_ok());
+        assert!(bucket.reserve(now, max_wait).is_ok());
+        assert_eq!(bucket.reserve(now, max_wait), Ok(Duration::from_secs(30)));
+        // Rejected requests don't take a slot
+        assert_eq!(bucket.reserve(now, max_wait), Err(Duration::from_secs(60)));
+        assert_eq!(
+            bucket.reserve(now + Duration::from_secs(30), max_wait),
+            Ok(Duration::from_secs(30))
+        );
+    }
+
+    #[test]
+    fn test_token_bucket_unlimited() {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(0, now);
+        for _ in 0..100 {
+            assert_eq!(bucket.reserve(now, Duration::ZERO), Ok(Duration::ZERO));
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! Cache of translation and explanation results
+//!
+//! Results are stored as JSON keyed by a SHA-256 of everything that affects
+//! the response (provider, model, temperature, max output tokens, target
+//! language, selected text and the filled-in prompts; see
+//! `translation::request_cache_key`). Entries older than the configured max
+//! age are ignored and pruned on the next write.
+
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use tauri::AppHandle;
+
+use crate::db::{ToDbError, now_timestamp, open_db};
+use crate::error::{DatabaseError, PedaruError};
+use crate::settings;
+
+/// Default max age of cache entries (30 days)
+pub const DEFAULT_MAX_AGE_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// Trim and collapse internal whitespace so trivially different selections
+/// (e.g. across PDF line breaks) share a cache entry
+pub fn normalize_text(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Hash the request parts into a cache key
+pub fn cache_key(parts: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        // Length-prefix each part so ("ab", "c") and ("a", "bc") differ
+        hasher.update((part.len() as u64).to_le_bytes());
+        hasher.update(part.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Get the max age of cache entries in seconds (0 disables the cache)
+pub fn get_max_age(app: &AppHandle) -> Result<u64, PedaruError> {
+    Ok(
+        settings::get_setting(app, settings::KEY_TRANSLATION_CACHE_MAX_AGE_SECS)?
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(DEFAULT_MAX_AGE_SECS),
+    )
+}
+
+/// Set the max age of cache entries in seconds (0 disables the cache)
+pub fn set_max_age(app: &AppHandle, max_age_secs: u64) -> Result<(), PedaruError> {
+    settings::set_setting(
+        app,
+        settings::KEY_TRANSLATION_CACHE_MAX_AGE_SECS,
+        &max_age_secs.to_string(),
+    )
+}
+
+/// Look up a cached result that has not expired
+pub fn lookup<T: DeserializeOwned>(app: &AppHandle, key: &str) -> Result<Option<T>, PedaruError> {
+    let max_age = get_max_age(app)?;
+    if max_age == 0 {
+        return Ok(None);
+    }
+
+    let conn = open_db(app)?;
+    let response: Option<String> = conn
+        .query_row(
+            "SELECT response FROM translation_cache
+             WHERE cache_key = ?1 AND created_at >= ?2",
+            rusqlite::params![key, cutoff(max_age)],
+            |row| row.get(0),
+        )
+        .optional()
+        .db_err()?;
+
+    // An entry that no longer deserializes is treated as a miss
+    Ok(response.and_then(|json| serde_json::from_str(&json).ok()))
+}
+
+/// Store a result and prune expired entries
+pub fn store<T: Serialize>(app: &AppHandle, key: &str, value: &T) -> Result<(), PedaruError> {
+    let max_age = get_max_age(app)?;
+    if max_age == 0 {
+        return Ok(());
+    }
+
+    let json = serde_json::to_string(value).map_err(|e| {
+        PedaruError::Database(DatabaseError::QueryFailed(format!(
+            "Failed to serialize cache entry: {}",
+            e
+        )))
+    })?;
+
+    let conn = open_db(app)?;
+    conn.execute(
+        "INSERT INTO translation_cache (cache_key, response, created_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(cache_key) DO UPDATE SET response = ?2, created_at = ?3",
+        rusqlite::params![key, json, now_timestamp()],
+    )
+    .db_err()?;
+    conn.execute(
+        "DELETE FROM translation_cache WHERE created_at < ?1",
+        [cutoff(max_age)],
+    )
+    .db_err()?;
+
+    Ok(())
+}
+
+/// Delete every cached result, returning the number of entries removed
+pub fn clear_translation_cache(app: &AppHandle) -> Result<usize, PedaruError> {
+    let conn = open_db(app)?;
+    conn.execute("DELETE FROM translation_cache", []).db_err()
+}
+
+/// Oldest `created_at` still considered fresh
+fn cutoff(max_age_secs: u64) -> i64 {
+    now_timestamp().saturating_sub(i64::try_from(max_age_secs).unwrap_or(i64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_text_collapses_whitespace() {
+        assert_eq!(
+            normalize_text("  take\ninto \t account "),
+            "take into account"
+        );
+    }
+
+    #[test]
+    fn test_cache_key_separates_parts() {
+        assert_eq!(cache_key(&["a", "b"]), cache_key(&["a", "b"]));
+        assert_ne!(cache_key(&["ab", "c"]), cache_key(&["a", "bc"]));
+        assert_eq!(cache_key(&["x"]).len(), 64);
+    }
+}
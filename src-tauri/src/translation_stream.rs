@@ -0,0 +1,345 @@
+//! Streaming translation responses
+//!
+//! Streams a provider response as server-sent events and forwards each text
+//! delta to the frontend as a `translation-chunk` event, followed by a single
+//! `translation-done` event carrying the parsed result. Every stream is tagged
+//! with a caller-chosen request id so concurrent streams can be told apart and
+//! cancelled individually (see `translation::cancel_request`).
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use futures_util::StreamExt;
+use reqwest::Response;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tauri::{AppHandle, Emitter};
+
+use crate::error::{PedaruError, TranslationError};
+use crate::translation::{self, ActiveRequest, ProviderConfig, TranslationProvider};
+use crate::translation_cache;
+
+/// Event emitted for each streamed text delta
+pub const CHUNK_EVENT: &str = "translation-chunk";
+
+/// Event emitted once a stream finishes, fails or is cancelled
+pub const DONE_EVENT: &str = "translation-done";
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// Payload of the `translation-chunk` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranslationChunk {
+    pub request_id: String,
+    /// Raw text generated since the previous chunk
+    pub text: String,
+}
+
+/// Payload of the `translation-done` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranslationDone<T> {
+    pub request_id: String,
+    /// Parsed result (None when cancelled or failed)
+    pub response: Option<T>,
+    pub error: Option<String>,
+    pub cancelled: bool,
+}
+
+// ============================================================================
+// Streaming
+// ============================================================================
+
+/// Stream a prompt, emitting chunk events and a final done event
+///
+/// Cached results are returned immediately with only a done event. Returns
+/// `Ok(None)` when the stream was cancelled, either explicitly, by a newer
+/// request for the same `context`, or because the frontend stopped receiving
+/// events. A cancelled stream's done event never carries a result or error.
+pub(crate) async fn run_stream<T: Serialize + DeserializeOwned + Clone>(
+    app: &AppHandle,
+    provider: &ProviderConfig<'_>,
+    prompt: &str,
+    system_instruction: &str,
+    parse: fn(&str) -> Result<T, PedaruError>,
+    request_id: &str,
+    context: Option<&str>,
+) -> Result<Option<T>, PedaruError> {
+    let request = ActiveRequest::register(request_id, context);
+    let key = translation::request_cache_key(provider, prompt, system_instruction);
+    match translation_cache::lookup::<T>(app, &key) {
+        Ok(Some(cached)) => {
+            emit_done(app, request_id, Ok(Some(cached.clone())));
+            return Ok(Some(cached));
+        }
+        Ok(None) => {}
+        Err(e) => log::warn!("Failed to read translation cache: {}", e),
+    }
+
+    let cancel_flag = request.cancel_flag();
+    let on_chunk = |text: &str| {
+        let chunk = TranslationChunk {
+            request_id: request_id.to_string(),
+            text: text.to_string(),
+        };
+        // A failed emit means nobody is listening any more; stop streaming
+        if app.emit(CHUNK_EVENT, chunk).is_err() {
+            cancel_flag.store(true, Ordering::SeqCst);
+        }
+    };
+    let streamed =
+        translation::generate_stream(provider, prompt, system_instruction, cancel_flag, &on_chunk)
+            .await;
+
+    let mut result = streamed.and_then(|text| text.map(|text| parse(&text)).transpose());
+    if let Ok(Some(response)) = &result
+        && let Err(e) = translation_cache::store(app, &key, response)
+    {
+        log::warn!("Failed to write translation cache: {}", e);
+    }
+    // Superseded after the last chunk: the result is stale for the UI
+    if request.is_cancelled() {
+        result = Ok(None);
+    }
+
+    match result {
+        Ok(response) => {
+            emit_done(app, request_id, Ok(response.clone()));
+            Ok(response)
+        }
+        Err(e) => {
+            let message = e.to_string();
+            emit_done::<T>(app, request_id, Err(message));
+            Err(e)
+        }
+    }
+}
+
+fn emit_done<T: Serialize + Clone>(
+    app: &AppHandle,
+    request_id: &str,
+    result: Result<Option<T>, String>,
+) {
+    let done = match result {
+        Ok(response) => TranslationDone {
+            request_id: request_id.to_string(),
+            cancelled: response.is_none(),
+            response,
+            error: None,
+        },
+        Err(error) => TranslationDone {
+            request_id: request_id.to_string(),
+            response: None,
+            error: Some(error),
+            cancelled: false,
+        },
+    };
+    let _ = app.emit(DONE_EVENT, done);
+}
+
+/// Read a server-sent event stream, passing each text delta to `on_chunk`
+///
+/// Returns the full text, or `None` if `cancel_flag` was set. A cancel is
+/// noticed while waiting for the next chunk, and the response is dropped
+/// without reading the rest of the body, which closes the connection.
+pub(crate) async fn read_event_stream<P: TranslationProvider + Sync>(
+    provider: &P,
+    response: Response,
+    cancel_flag: &AtomicBool,
+    on_chunk: &(dyn Fn(&str) + Sync),
+) -> Result<Option<String>, PedaruError> {
+    let provider_name = provider.name();
+    let mut stream = response.bytes_stream();
+    let mut lines = LineBuffer::default();
+    let mut full_text = String::new();
+
+    loop {
+        // Waiting on a quiet server must not outlast a cancel
+        let Some(next) = translation::unless_cancelled(cancel_flag, stream.next()).await else {
+            return Ok(None);
+        };
+        let Some(bytes) = next else {
+            break;
+        };
+        let bytes = bytes.map_err(|e| TranslationError::ApiRequestFailed {
+            provider: provider_name,
+            status: None,
+            message: format!("Stream interrupted: {}", e.without_url()),
+        })?;
+
+        for line in lines.push(&bytes) {
+            let Some(event) = parse_sse_data(&line) else {
+                continue;
+            };
+            if let Some(message) = event_error(&event) {
+                // Reported inside a successful (200) streaming response
+                return Err(TranslationError::ApiRequestFailed {
+                    provider: provider_name,
+                    status: Some(200),
+                    message,
+                }
+                .into());
+            }
+            if let Some(delta) = provider.stream_delta(&event)
+                && !delta.is_empty()
+            {
+                on_chunk(&delta);
+                full_text.push_str(&delta);
+            }
+        }
+    }
+
+    if cancel_flag.load(Ordering::SeqCst) {
+        return Ok(None);
+    }
+    Ok(Some(full_text))
+}
+
+/// Splits a byte stream into complete lines
+///
+/// Bytes are buffered until a newline arrives so multi-byte UTF-8 characters
+/// split across network chunks are decoded intact.
+#[derive(Default)]
+struct LineBuffer {
+    pending: Vec<u8>,
+}
+
+impl LineBuffer {
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(bytes);
+        let mut lines = Vec::new();
+        while let Some(pos) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            lines.push(line.trim_end_matches(['\r', '\n']).to_string());
+        }
+        lines
+    }
+}
+
+/// Parse the JSON payload of an SSE `data:` line
+///
+/// Comments, other fields and OpenAI's `[DONE]` sentinel yield `None`.
+fn parse_sse_data(line: &str) -> Option<Value> {
+    let data = line.strip_prefix("data:")?.trim();
+    if data.is_empty() || data == "[DONE]" {
+        return None;
+    }
+    serde_json::from_str(data).ok()
+}
+
+/// Error message carried by a streamed event, if any
+fn event_error(event: &Value) -> Option<String> {
+    let error = event.get("error")?;
+    Some(
+        error
+            .get("message")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| error.to_string()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_buffer_handles_split_chunks() {
+        let mut buffer = LineBuffer::default();
+        let text = "data: {\"t\": \"活用\"}\r\n\ndata: [DONE]\n";
+        let bytes = text.as_bytes();
+        // Split inside the multi-byte character
+        let split = text.find('活').unwrap() + 1;
+
+        let mut lines = buffer.push(&bytes[..split]);
+        assert!(lines.is_empty());
+        lines.extend(buffer.push(&bytes[split..]));
+        assert_eq!(lines, vec!["data: {\"t\": \"活用\"}", "", "data: [DONE]"]);
+    }
+
+    #[test]
+    fn test_parse_sse_data() {
+        assert_eq!(
+            parse_sse_data("data: {\"a\": 1}"),
+            Some(serde_json::json!({"a": 1}))
+        );
+        assert_eq!(parse_sse_data("data: [DONE]"), None);
+        assert_eq!(parse_sse_data("event: ping"), None);
+        assert_eq!(parse_sse_data(": keep-alive"), None);
+    }
+
+    #[test]
+    fn test_event_error() {
+        let event = serde_json::json!({"type": "error", "error": {"message": "Overloaded"}});
+        assert_eq!(event_error(&event).as_deref(), Some("Overloaded"));
+        assert_eq!(event_error(&serde_json::json!({"type": "ping"})), None);
+    }
+
+    /// Provider whose events carry their delta as `{"t": "..."}`
+    struct TestProvider;
+
+    impl TranslationProvider for TestProvider {
+        fn name(&self) -> &'static str {
+            "Test"
+        }
+
+        async fn generate(&self, _: &str, _: &str) -> Result<String, PedaruError> {
+            unreachable!()
+        }
+
+        async fn start_stream(&self, _: &str, _: &str) -> Result<Response, PedaruError> {
+            unreachable!()
+        }
+
+        fn stream_delta(&self, event: &Value) -> Option<String> {
+            event["t"].as_str().map(str::to_string)
+        }
+    }
+
+    #[test]
+    fn test_cancel_stops_waiting_on_quiet_stream() {
+        use std::io::{Read, Write};
+        use std::time::{Duration, Instant};
+
+        // Sends one event, then keeps the connection open without a word
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let _ = socket.read(&mut [0; 1024]);
+            let _ = socket.write_all(
+                b"HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\r\ndata: {\"t\": \"Hi\"}\n\n",
+            );
+            std::thread::sleep(Duration::from_secs(30));
+        });
+
+        let cancel_flag = AtomicBool::new(false);
+        let chunks = std::sync::Mutex::new(Vec::new());
+        let on_chunk = |text: &str| chunks.lock().unwrap().push(text.to_string());
+        let started = Instant::now();
+        let result = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(async {
+                let response = reqwest::get(&url).await.unwrap();
+                let cancel = async {
+                    tokio::time::sleep(Duration::from_millis(300)).await;
+                    cancel_flag.store(true, Ordering::SeqCst);
+                };
+                let (result, ()) = tokio::join!(
+                    read_event_stream(&TestProvider, response, &cancel_flag, &on_chunk),
+                    cancel
+                );
+                result
+            });
+
+        assert_eq!(result.unwrap(), None);
+        assert_eq!(*chunks.lock().unwrap(), vec!["Hi"]);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
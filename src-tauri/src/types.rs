@@ -26,6 +26,17 @@ pub struct TocEntry {
     pub children: Vec<TocEntry>,
 }
 
+/// Bookshelf metadata read from a PDF's document info dictionary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentMetadata {
+    /// Title, if present and non-empty
+    pub title: Option<String>,
+    /// Author, if present and non-empty
+    pub author: Option<String>,
+    /// Number of pages in the document
+    pub page_count: u32,
+}
+
 /// Information extracted from a PDF document
 #[derive(Debug, Serialize, Clone)]
 pub struct PdfInfo {
@@ -28,6 +28,7 @@ import { useHeaderVisibility } from '@/hooks/useHeaderVisibility';
 import { useDocumentTitle } from '@/hooks/useDocumentTitle';
 import { useContextMenu } from '@/hooks/useContextMenu';
 import { useSessionPersistence } from '@/hooks/useSessionPersistence';
+import { useReadingSession } from '@/hooks/useReadingSession';
 import { useMenuHandlers } from '@/hooks/useMenuHandlers';
 import { useWindowSync } from '@/hooks/useWindowSync';
 import type { OpenWindow, Tab, HistoryEntry } from '@/hooks/types';
@@ -518,6 +519,9 @@ export default function Home() {
     isRestoringSessionRef
   );
 
+  // Reading statistics for downloaded cloud PDFs
+  useReadingSession(filePath, isStandaloneMode, currentPage);
+
   // Document title updates (extracted to hook)
   useDocumentTitle(fileName, pdfInfo, isStandaloneMode, currentPage, getChapterForPage);
 
@@ -8,6 +8,7 @@ import { Loader2, Languages, AlertCircle, ChevronDown, ChevronUp, Sparkles, Book
 import ReactMarkdown from 'react-markdown';
 import type { TranslationResponse, GeminiSettings } from '@/types';
 import { explainDirectly, getGeminiSettings, GEMINI_MODELS } from '@/lib/settings';
+import { getErrorMessage } from '@/lib/errorUtils';
 
 interface TranslationData {
   selectedText: string;
@@ -156,10 +157,12 @@ function TranslationContent() {
           data.contextAfter,
           geminiSettings.explanationModel
         );
-        setExplanationSummary(result.summary);
-        setExplanationPoints(result.points);
+        if (result) {
+          setExplanationSummary(result.summary);
+          setExplanationPoints(result.points);
+        }
       } catch (err) {
-        setError(err instanceof Error ? err.message : String(err));
+        setError(getErrorMessage(err));
       } finally {
         setIsExplaining(false);
       }
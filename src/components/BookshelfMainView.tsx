@@ -29,7 +29,7 @@ import {
 } from 'lucide-react';
 import { useGoogleAuth } from '@/hooks/useGoogleAuth';
 import { useBookshelf } from '@/hooks/useBookshelf';
-import { generateThumbnailsInBackground } from '@/lib/thumbnailGenerator';
+import { generateThumbnailsInBackground, thumbnailSrc } from '@/lib/thumbnailGenerator';
 import type { BookshelfItem as BookshelfItemType, DriveItem } from '@/types';
 import type { BookshelfMainViewProps } from '@/types/components';
 
@@ -421,10 +421,11 @@ export default function BookshelfMainView({ onOpenPdf, currentFilePath, onClose
     if (searchQuery.trim()) {
       const query = searchQuery.toLowerCase().trim();
       filtered = filtered.filter(item => {
+        const displayName = item.displayName.toLowerCase();
         const title = (item.pdfTitle || '').toLowerCase();
         const fileName = (item.fileName || '').toLowerCase();
         const author = (item.pdfAuthor || '').toLowerCase();
-        return title.includes(query) || fileName.includes(query) || author.includes(query);
+        return displayName.includes(query) || title.includes(query) || fileName.includes(query) || author.includes(query);
       });
     }
 
@@ -464,7 +465,7 @@ export default function BookshelfMainView({ onOpenPdf, currentFilePath, onClose
     try {
       for (let i = 0; i < downloadableItems.length; i++) {
         setCurrentDownloadIndex(i + 1);
-        await downloadItem(downloadableItems[i]);
+        await downloadItem(downloadableItems[i], false);
       }
     } finally {
       setIsDownloadingAll(false);
@@ -496,7 +497,7 @@ export default function BookshelfMainView({ onOpenPdf, currentFilePath, onClose
     const isDownloaded = item.downloadStatus === 'completed' && item.localPath;
     const isDownloading = item.downloadStatus === 'downloading';
     const hasError = item.downloadStatus === 'error';
-    const displayName = item.pdfTitle || item.fileName;
+    const displayName = item.displayName;
 
     if (viewMode === 'list') {
       // Table row for list view - rendered inside tbody
@@ -516,9 +517,9 @@ export default function BookshelfMainView({ onOpenPdf, currentFilePath, onClose
         onClick={() => isDownloaded && handleOpenPdf(item)}
       >
         <div className="aspect-[3/4] flex items-center justify-center bg-bg-primary/50">
-          {item.thumbnailData ? (
+          {item.thumbnailData || item.thumbnailPath ? (
             <img
-              src={`data:image/png;base64,${item.thumbnailData}`}
+              src={thumbnailSrc(item)}
               alt={displayName}
               className="w-full h-full object-cover"
             />
@@ -1339,7 +1340,7 @@ export default function BookshelfMainView({ onOpenPdf, currentFilePath, onClose
               <div className="flex items-center gap-3 text-sm text-text-primary">
                 <Loader2 className="w-4 h-4 text-accent animate-spin shrink-0" />
                 <span className="truncate flex-1">
-                  {downloadingItem ? (downloadingItem.pdfTitle || downloadingItem.fileName) : 'Starting...'}
+                  {downloadingItem ? downloadingItem.displayName : 'Starting...'}
                 </span>
               </div>
               {isDownloadingAll && totalDownloads > 0 && (
@@ -1405,7 +1406,7 @@ export default function BookshelfMainView({ onOpenPdf, currentFilePath, onClose
                     const isDownloaded = item.downloadStatus === 'completed' && item.localPath;
                     const isDownloading = item.downloadStatus === 'downloading';
                     const hasError = item.downloadStatus === 'error';
-                    const displayName = item.pdfTitle || item.fileName;
+                    const displayName = item.displayName;
 
                     return (
                       <tr
@@ -1416,9 +1417,9 @@ export default function BookshelfMainView({ onOpenPdf, currentFilePath, onClose
                         <td className="px-6 py-3">
                           <div className="flex items-center gap-3">
                             <div className="w-10 h-14 flex-shrink-0 flex items-center justify-center bg-bg-tertiary rounded overflow-hidden">
-                              {item.thumbnailData ? (
+                              {item.thumbnailData || item.thumbnailPath ? (
                                 <img
-                                  src={`data:image/png;base64,${item.thumbnailData}`}
+                                  src={thumbnailSrc(item)}
                                   alt={displayName}
                                   className="w-full h-full object-cover"
                                 />
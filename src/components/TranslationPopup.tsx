@@ -8,6 +8,11 @@ import ReactMarkdown from 'react-markdown';
 import type { TextSelection, GeminiSettings, TranslationResponse, ViewMode } from '@/types';
 import { translateWithGemini, explainDirectly, isGeminiConfigured, getGeminiSettings, GEMINI_MODELS } from '@/lib/settings';
 import type { TranslationPopupProps } from '@/types/components';
+import { getErrorMessage } from '@/lib/errorUtils';
+
+// Requests from the popup share a context, so a new selection cancels the
+// translation still pending for the previous one
+const POPUP_REQUEST_CONTEXT = 'translation-popup';
 
 // Custom components for ReactMarkdown to render ***text*** with yellow highlight
 const markdownComponents = {
@@ -233,10 +238,11 @@ export default function TranslationPopup({
             selection.selectedText,
             selection.contextBefore,
             selection.contextAfter,
-            settings.explanationModel
+            settings.explanationModel,
+            POPUP_REQUEST_CONTEXT
           );
 
-          if (!cancelled) {
+          if (!cancelled && result) {
             console.log('Explanation result:', JSON.stringify(result, null, 2));
             // Store summary and points from ExplanationResponse
             setExplanationSummary(result.summary);
@@ -250,17 +256,18 @@ export default function TranslationPopup({
             selection.selectedText,
             selection.contextBefore,
             selection.contextAfter,
-            settings.model
+            settings.model,
+            POPUP_REQUEST_CONTEXT
           );
 
-          if (!cancelled) {
+          if (!cancelled && result) {
             console.log('Translation result:', JSON.stringify(result, null, 2));
             setTranslationResponse(result);
           }
         }
       } catch (err) {
         if (!cancelled) {
-          setError(err instanceof Error ? err.message : String(err));
+          setError(getErrorMessage(err));
         }
       } finally {
         if (!cancelled) {
@@ -290,14 +297,17 @@ export default function TranslationPopup({
           selection.selectedText,
           selection.contextBefore,
           selection.contextAfter,
-          geminiSettings.explanationModel
+          geminiSettings.explanationModel,
+          POPUP_REQUEST_CONTEXT
         );
 
         // Update the explanation summary and points, keep the original translation
-        setExplanationSummary(result.summary);
-        setExplanationPoints(result.points);
+        if (result) {
+          setExplanationSummary(result.summary);
+          setExplanationPoints(result.points);
+        }
       } catch (err) {
-        setError(err instanceof Error ? err.message : String(err));
+        setError(getErrorMessage(err));
       } finally {
         setIsExplaining(false);
       }
@@ -4,6 +4,7 @@ import { useState, useCallback, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import type { BookshelfItem, SyncResult, DownloadProgress, ImportResult } from '@/types';
+import { getErrorCode, getErrorMessage } from '@/lib/errorUtils';
 
 /**
  * Hook for managing bookshelf items (PDFs from Google Drive)
@@ -28,7 +29,7 @@ export function useBookshelf() {
         setError(null);
       } catch (err) {
         console.error('Failed to load bookshelf items:', err);
-        setError(String(err));
+        setError(getErrorMessage(err));
         // Even on error, we should show empty state not loading
         setItems([]);
       } finally {
@@ -45,7 +46,7 @@ export function useBookshelf() {
 
     const setupListener = async () => {
       unlisten = await listen<DownloadProgress>('download-progress', (event) => {
-        const { driveFileId, progress } = event.payload;
+        const { driveFileId, progress, status } = event.payload;
 
         setItems((prevItems) =>
           prevItems.map((item) =>
@@ -53,7 +54,7 @@ export function useBookshelf() {
               ? {
                   ...item,
                   downloadProgress: progress,
-                  downloadStatus: progress >= 100 ? 'completed' : 'downloading',
+                  downloadStatus: status,
                 }
               : item
           )
@@ -81,7 +82,7 @@ export function useBookshelf() {
       setError(null);
     } catch (err) {
       console.error('Failed to load bookshelf items:', err);
-      setError(String(err));
+      setError(getErrorMessage(err));
       setItems([]);
     } finally {
       setIsLoading(false);
@@ -100,7 +101,7 @@ export function useBookshelf() {
       return result;
     } catch (err) {
       console.error('Failed to sync bookshelf:', err);
-      setError(String(err));
+      setError(getErrorMessage(err));
       return null;
     } finally {
       setIsSyncing(false);
@@ -109,8 +110,12 @@ export function useBookshelf() {
 
   /**
    * Download a bookshelf item
+   * (`userInitiated` downloads resume after a restart; bulk downloads pass false)
    */
-  const downloadItem = useCallback(async (item: BookshelfItem): Promise<string | null> => {
+  const downloadItem = useCallback(async (
+    item: BookshelfItem,
+    userInitiated = true
+  ): Promise<string | null> => {
     try {
       setError(null);
 
@@ -126,6 +131,7 @@ export function useBookshelf() {
       const localPath = await invoke<string>('download_bookshelf_item', {
         driveFileId: item.driveFileId,
         fileName: item.fileName,
+        userInitiated,
       });
 
       // Update local state with completed download
@@ -139,8 +145,13 @@ export function useBookshelf() {
 
       return localPath;
     } catch (err) {
+      // A cancelled download goes back to pending (see the 'download-progress'
+      // listener), so it isn't shown as an error
+      if (getErrorCode(err) === 'drive.download_cancelled') {
+        return null;
+      }
       console.error('Failed to download item:', err);
-      setError(String(err));
+      setError(getErrorMessage(err));
 
       // Update local state to show error
       setItems((prevItems) =>
@@ -155,6 +166,28 @@ export function useBookshelf() {
     }
   }, []);
 
+  /**
+   * Cancel every running and queued download
+   */
+  const cancelAllDownloads = useCallback(async (): Promise<number> => {
+    try {
+      const cancelled = await invoke<number>('cancel_all_bookshelf_downloads');
+
+      setItems((prevItems) =>
+        prevItems.map((item) =>
+          item.downloadStatus === 'downloading' || item.downloadStatus === 'queued'
+            ? { ...item, downloadStatus: 'pending' as const, downloadProgress: 0 }
+            : item
+        )
+      );
+
+      return cancelled;
+    } catch (err) {
+      console.error('Failed to cancel downloads:', err);
+      return 0;
+    }
+  }, []);
+
   /**
    * Cancel a downloading item
    */
@@ -197,7 +230,7 @@ export function useBookshelf() {
       setItems((prevItems) =>
         prevItems.map((item) =>
           item.driveFileId === driveFileId
-            ? { ...item, downloadStatus: 'pending' as const, downloadProgress: 0, localPath: undefined }
+            ? { ...item, downloadStatus: 'pending' as const, downloadProgress: 0, localPath: undefined, thumbnailData: undefined, thumbnailPath: undefined }
             : item
         )
       );
@@ -205,7 +238,7 @@ export function useBookshelf() {
       return true;
     } catch (err) {
       console.error('Failed to delete local copy:', err);
-      setError(String(err));
+      setError(getErrorMessage(err));
       return false;
     }
   }, []);
@@ -221,7 +254,7 @@ export function useBookshelf() {
       setItems((prevItems) =>
         prevItems.map((item) =>
           item.driveFileId === driveFileId
-            ? { ...item, downloadStatus: 'pending' as const, downloadProgress: 0, localPath: undefined, thumbnailData: undefined }
+            ? { ...item, downloadStatus: 'pending' as const, downloadProgress: 0, localPath: undefined, thumbnailData: undefined, thumbnailPath: undefined }
             : item
         )
       );
@@ -244,7 +277,7 @@ export function useBookshelf() {
       return result;
     } catch (err) {
       console.error('Failed to import local files:', err);
-      setError(String(err));
+      setError(getErrorMessage(err));
       return null;
     }
   }, [loadItems]);
@@ -260,7 +293,7 @@ export function useBookshelf() {
       return result;
     } catch (err) {
       console.error('Failed to import local directory:', err);
-      setError(String(err));
+      setError(getErrorMessage(err));
       return null;
     }
   }, [loadItems]);
@@ -278,7 +311,24 @@ export function useBookshelf() {
       return true;
     } catch (err) {
       console.error('Failed to delete item:', err);
-      setError(String(err));
+      setError(getErrorMessage(err));
+      return false;
+    }
+  }, []);
+
+  /**
+   * Set or clear (null) the custom display name of a cloud bookshelf item
+   */
+  const setCustomName = useCallback(async (driveFileId: string, customName: string | null): Promise<boolean> => {
+    try {
+      await invoke('set_bookshelf_custom_name', { driveFileId, customName });
+      // Reload to pick up the resolved display name
+      const bookshelfItems = await invoke<BookshelfItem[]>('get_bookshelf_items');
+      setItems(bookshelfItems);
+      return true;
+    } catch (err) {
+      console.error('Failed to set custom name:', err);
+      setError(getErrorMessage(err));
       return false;
     }
   }, []);
@@ -304,7 +354,7 @@ export function useBookshelf() {
       return newStatus;
     } catch (err) {
       console.error('Failed to toggle favorite:', err);
-      setError(String(err));
+      setError(getErrorMessage(err));
       return false;
     }
   }, []);
@@ -384,7 +434,7 @@ export function useBookshelf() {
    */
   const getItemsNeedingThumbnails = useCallback(() => {
     return items.filter(
-      (item) => item.downloadStatus === 'completed' && item.localPath && !item.thumbnailData
+      (item) => item.downloadStatus === 'completed' && item.localPath && !item.thumbnailData && !item.thumbnailPath
     );
   }, [items]);
 
@@ -414,6 +464,7 @@ export function useBookshelf() {
     sync,
     downloadItem,
     cancelDownload,
+    cancelAllDownloads,
     deleteLocalCopy,
     resetDownloadStatus,
     updateThumbnail,
@@ -423,6 +474,7 @@ export function useBookshelf() {
     importLocalDirectory,
     deleteItem,
     toggleFavorite,
+    setCustomName,
 
     // Getters
     getItemsNeedingThumbnails,
@@ -2,8 +2,10 @@
 
 import { useState, useCallback, useEffect, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import { open } from '@tauri-apps/plugin-shell';
-import type { AuthStatus, DriveFolder, DriveItem, StoredFolder } from '@/types';
+import type { AuthStatus, CommandError, DriveFolder, DriveItem, StoredFolder } from '@/types';
+import { getErrorMessage } from '@/lib/errorUtils';
 
 /**
  * Hook for managing Google OAuth authentication and Drive folder configuration
@@ -24,6 +26,18 @@ export function useGoogleAuth() {
   const [hasCheckedAuth, setHasCheckedAuth] = useState(false);
   // Track if folders have been loaded
   const foldersLoadedRef = useRef(false);
+  // Interval polling for the end of a running OAuth flow
+  const pollIntervalRef = useRef<ReturnType<typeof setInterval> | null>(null);
+
+  /**
+   * Stop polling for the end of the OAuth flow, if it is running
+   */
+  const stopPolling = useCallback(() => {
+    if (pollIntervalRef.current !== null) {
+      clearInterval(pollIntervalRef.current);
+      pollIntervalRef.current = null;
+    }
+  }, []);
 
   /**
    * Load synced folders from database (does NOT access Keychain)
@@ -44,6 +58,19 @@ export function useGoogleAuth() {
     loadSyncedFolders();
   }, [loadSyncedFolders]);
 
+  // Reload folders once those added by id or URL get their Drive names
+  useEffect(() => {
+    let unlisten: UnlistenFn | null = null;
+    listen('drive-folder-names-resolved', () => {
+      loadSyncedFolders();
+    }).then((fn) => {
+      unlisten = fn;
+    });
+    return () => {
+      unlisten?.();
+    };
+  }, [loadSyncedFolders]);
+
   /**
    * Check current authentication status
    * This will trigger Keychain access - only call when user wants to use Google Drive
@@ -59,7 +86,7 @@ export function useGoogleAuth() {
       return status;
     } catch (err) {
       console.error('Failed to check auth status:', err);
-      setError(String(err));
+      setError(getErrorMessage(err));
       setHasCheckedAuth(true);
       return null;
     } finally {
@@ -67,19 +94,55 @@ export function useGoogleAuth() {
     }
   }, []);
 
+  // Google rejected a refresh token: refresh the status so the UI can offer "Reconnect"
+  useEffect(() => {
+    let unlisten: UnlistenFn | null = null;
+    listen<number>('auth-reauth-required', () => {
+      checkAuthStatus();
+    }).then((fn) => {
+      unlisten = fn;
+    });
+    return () => {
+      unlisten?.();
+    };
+  }, [checkAuthStatus]);
+
+  // The OAuth flow failed in the background: stop waiting for it and show why
+  useEffect(() => {
+    let unlisten: UnlistenFn | null = null;
+    listen<CommandError>('auth-error', (event) => {
+      stopPolling();
+      setError(
+        event.payload.code === 'oauth.callback_timeout'
+          ? 'Authentication timed out. Please try again.'
+          : event.payload.message
+      );
+      setIsLoading(false);
+    }).then((fn) => {
+      unlisten = fn;
+    });
+    return () => {
+      unlisten?.();
+    };
+  }, [stopPolling]);
+
+  // Don't keep polling after unmount
+  useEffect(() => stopPolling, [stopPolling]);
+
   /**
    * Save OAuth credentials
+   * @param strict - Reject client IDs that don't end with .apps.googleusercontent.com
    */
-  const saveCredentials = useCallback(async (clientId: string, clientSecret: string) => {
+  const saveCredentials = useCallback(async (clientId: string, clientSecret: string, strict = true) => {
     try {
       setIsLoading(true);
-      await invoke('save_oauth_credentials', { clientId, clientSecret });
+      await invoke('save_oauth_credentials', { clientId, clientSecret, strict });
       await checkAuthStatus();
       setError(null);
       return true;
     } catch (err) {
       console.error('Failed to save credentials:', err);
-      setError(String(err));
+      setError(getErrorMessage(err));
       return false;
     } finally {
       setIsLoading(false);
@@ -87,15 +150,20 @@ export function useGoogleAuth() {
   }, [checkAuthStatus]);
 
   /**
-   * Start OAuth login flow
+   * Open the sign-in page from `getAuthUrl` and wait until `isConnected`
+   * (by default, until the default account is authenticated)
    */
-  const login = useCallback(async () => {
+  const runAuthFlow = useCallback(async (
+    getAuthUrl: () => Promise<string>,
+    isConnected: (status: AuthStatus) => boolean = (status) => status.authenticated,
+  ) => {
     try {
       setIsLoading(true);
       setError(null);
+      stopPolling();
 
       // Get OAuth URL from backend
-      const authUrl = await invoke<string>('start_google_auth');
+      const authUrl = await getAuthUrl();
 
       // Open in default browser
       await open(authUrl);
@@ -105,12 +173,12 @@ export function useGoogleAuth() {
       const maxAttempts = 150;
       let attempts = 0;
 
-      const pollInterval = setInterval(async () => {
+      pollIntervalRef.current = setInterval(async () => {
         attempts++;
         try {
           const status = await invoke<AuthStatus>('get_google_auth_status');
-          if (status.authenticated) {
-            clearInterval(pollInterval);
+          if (isConnected(status)) {
+            stopPolling();
             setAuthStatus(status);
             setHasCheckedAuth(true);
             setIsLoading(false);
@@ -120,7 +188,7 @@ export function useGoogleAuth() {
         }
 
         if (attempts >= maxAttempts) {
-          clearInterval(pollInterval);
+          stopPolling();
           setError('Authentication timed out. Please try again.');
           setIsLoading(false);
         }
@@ -128,10 +196,41 @@ export function useGoogleAuth() {
 
     } catch (err) {
       console.error('Failed to start login:', err);
-      setError(String(err));
+      setError(getErrorMessage(err));
       setIsLoading(false);
     }
-  }, []);
+  }, [stopPolling]);
+
+  /**
+   * Start OAuth login flow
+   */
+  const login = useCallback(
+    () => runAuthFlow(() => invoke<string>('start_google_auth')),
+    [runAuthFlow]
+  );
+
+  /**
+   * Sign in again after Google rejected the tokens (`connection` is
+   * 'needs_reauth'), or to grant a scope a Drive command reported missing.
+   * Synced folders, tags and downloads are kept.
+   * @param accountId - Account to reconnect (defaults to the default account)
+   * @param requiredScope - Extra scope to request (see `getRequiredScope`)
+   */
+  const reconnect = useCallback(
+    (accountId?: number, requiredScope?: string) =>
+      runAuthFlow(
+        () =>
+          invoke<string>('reauthenticate_google', {
+            accountId: accountId ?? null,
+            requiredScope: requiredScope ?? null,
+          }),
+        (status) =>
+          accountId === undefined
+            ? status.authenticated
+            : !!status.accounts?.some((a) => a.id === accountId && a.authenticated),
+      ),
+    [runAuthFlow]
+  );
 
   /**
    * Logout from Google
@@ -144,12 +243,30 @@ export function useGoogleAuth() {
       setError(null);
     } catch (err) {
       console.error('Failed to logout:', err);
-      setError(String(err));
+      setError(getErrorMessage(err));
     } finally {
       setIsLoading(false);
     }
   }, []);
 
+  /**
+   * Sign out of every account and delete stored secrets, optionally
+   * deleting downloaded PDFs too
+   */
+  const logoutEverywhere = useCallback(async (wipeDownloads = false) => {
+    try {
+      setIsLoading(true);
+      await invoke('logout', { wipeDownloads });
+      setError(null);
+    } catch (err) {
+      console.error('Failed to logout:', err);
+      setError(getErrorMessage(err));
+    } finally {
+      await checkAuthStatus();
+      setIsLoading(false);
+    }
+  }, [checkAuthStatus]);
+
   /**
    * List folders in Google Drive
    */
@@ -159,7 +276,7 @@ export function useGoogleAuth() {
       return folders;
     } catch (err) {
       console.error('Failed to list drive folders:', err);
-      setError(String(err));
+      setError(getErrorMessage(err));
       return [];
     }
   }, []);
@@ -173,7 +290,7 @@ export function useGoogleAuth() {
       return items;
     } catch (err) {
       console.error('Failed to list drive items:', err);
-      setError(String(err));
+      setError(getErrorMessage(err));
       return [];
     }
   }, []);
@@ -188,22 +305,24 @@ export function useGoogleAuth() {
       return count;
     } catch (err) {
       console.error('Failed to import drive files:', err);
-      setError(String(err));
+      setError(getErrorMessage(err));
       return 0;
     }
   }, []);
 
   /**
    * Add a folder to sync list
+   * @param folderId - Folder id or a pasted Drive folder URL
+   * @param folderName - Name to show (defaults to the folder's Drive name)
    */
-  const addSyncFolder = useCallback(async (folderId: string, folderName: string) => {
+  const addSyncFolder = useCallback(async (folderId: string, folderName?: string) => {
     try {
-      await invoke('add_drive_folder', { folderId, folderName });
+      await invoke('add_drive_folder', { folderId, folderName: folderName ?? null });
       await loadSyncedFolders();
       return true;
     } catch (err) {
       console.error('Failed to add sync folder:', err);
-      setError(String(err));
+      setError(getErrorMessage(err));
       return false;
     }
   }, [loadSyncedFolders]);
@@ -218,7 +337,7 @@ export function useGoogleAuth() {
       return true;
     } catch (err) {
       console.error('Failed to remove sync folder:', err);
-      setError(String(err));
+      setError(getErrorMessage(err));
       return false;
     }
   }, [loadSyncedFolders]);
@@ -235,7 +354,9 @@ export function useGoogleAuth() {
     checkAuthStatus,
     saveCredentials,
     login,
+    reconnect,
     logout,
+    logoutEverywhere,
 
     // Folder actions
     loadSyncedFolders,
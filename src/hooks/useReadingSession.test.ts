@@ -0,0 +1,69 @@
+import { describe, it, expect, vi, beforeEach } from 'vitest';
+import { renderHook, waitFor } from '@testing-library/react';
+import { useReadingSession } from './useReadingSession';
+import { startReadingSession, updateReadingSession } from '@/lib/settings';
+
+// Mock settings module
+vi.mock('@/lib/settings', () => ({
+  startReadingSession: vi.fn(),
+  updateReadingSession: vi.fn().mockResolvedValue(undefined),
+}));
+
+describe('useReadingSession', () => {
+  const mockStartReadingSession = startReadingSession as ReturnType<typeof vi.fn>;
+  const mockUpdateReadingSession = updateReadingSession as ReturnType<typeof vi.fn>;
+
+  beforeEach(() => {
+    mockStartReadingSession.mockReset().mockResolvedValue(7);
+    mockUpdateReadingSession.mockClear();
+  });
+
+  it('should not record without a file or in standalone mode', () => {
+    renderHook(() => useReadingSession(null, false, 1));
+    renderHook(() => useReadingSession('/path/to/file.pdf', true, 1));
+
+    expect(mockStartReadingSession).not.toHaveBeenCalled();
+  });
+
+  it('should start a session and extend it on page changes', async () => {
+    const { rerender } = renderHook(
+      ({ page }) => useReadingSession('/path/to/file.pdf', false, page),
+      { initialProps: { page: 3 } }
+    );
+
+    expect(mockStartReadingSession).toHaveBeenCalledWith('/path/to/file.pdf', 3);
+
+    rerender({ page: 4 });
+    await waitFor(() => expect(mockUpdateReadingSession).toHaveBeenCalledWith(7, 4));
+  });
+
+  it('should end the session when the document changes', async () => {
+    const { rerender } = renderHook(({ path, page }) => useReadingSession(path, false, page), {
+      initialProps: { path: '/path/to/a.pdf', page: 9 },
+    });
+    rerender({ path: '/path/to/a.pdf', page: 10 });
+    await waitFor(() => expect(mockUpdateReadingSession).toHaveBeenCalledWith(7, 10));
+    mockUpdateReadingSession.mockClear();
+
+    mockStartReadingSession.mockResolvedValue(8);
+    rerender({ path: '/path/to/b.pdf', page: 1 });
+
+    await waitFor(() => expect(mockUpdateReadingSession).toHaveBeenCalledWith(7, 10));
+    expect(mockUpdateReadingSession).not.toHaveBeenCalledWith(8, 1);
+    expect(mockStartReadingSession).toHaveBeenLastCalledWith('/path/to/b.pdf', 1);
+  });
+
+  it('should not update sessions for files not from Drive', async () => {
+    mockStartReadingSession.mockResolvedValue(null);
+    const { rerender, unmount } = renderHook(
+      ({ page }) => useReadingSession('/local.pdf', false, page),
+      { initialProps: { page: 1 } }
+    );
+
+    rerender({ page: 2 });
+    unmount();
+    await Promise.resolve();
+
+    expect(mockUpdateReadingSession).not.toHaveBeenCalled();
+  });
+});
@@ -0,0 +1,55 @@
+import { useEffect, useRef } from 'react';
+import { startReadingSession, updateReadingSession } from '@/lib/settings';
+
+/**
+ * Custom hook for recording reading sessions of downloaded cloud PDFs
+ *
+ * A session starts when a document is opened, is extended on every page
+ * change and ends when another document is opened or the viewer closes.
+ * Only the main window records, so standalone windows showing the same
+ * document don't count twice.
+ *
+ * @param filePath - Current PDF file path
+ * @param isStandaloneMode - Whether running in standalone window mode
+ * @param currentPage - Current page number
+ */
+export function useReadingSession(
+  filePath: string | null,
+  isStandaloneMode: boolean,
+  currentPage: number
+) {
+  // Session id of the open document (null for files not from Drive)
+  const sessionRef = useRef<Promise<number | null> | null>(null);
+  const pageRef = useRef(currentPage);
+
+  // Declared first: on a document change the old session's cleanup still
+  // sees the old page, and the new session starts at the new one
+  useEffect(() => {
+    pageRef.current = currentPage;
+    sessionRef.current
+      ?.then((id) => (id !== null ? updateReadingSession(id, currentPage) : undefined))
+      .catch((error) => {
+        console.error('Failed to update reading session:', error);
+      });
+  }, [currentPage]);
+
+  useEffect(() => {
+    if (!filePath || isStandaloneMode) return;
+
+    const session = startReadingSession(filePath, pageRef.current).catch((error) => {
+      console.error('Failed to start reading session:', error);
+      return null;
+    });
+    sessionRef.current = session;
+
+    return () => {
+      sessionRef.current = null;
+      const page = pageRef.current;
+      session
+        .then((id) => (id !== null ? updateReadingSession(id, page) : undefined))
+        .catch((error) => {
+          console.error('Failed to end reading session:', error);
+        });
+    };
+  }, [filePath, isStandaloneMode]);
+}
@@ -11,6 +11,7 @@ import type {
   WindowState,
 } from '@/types';
 import { loadSessionState, getLastOpenedPath } from '@/lib/database';
+import { getErrorMessage } from '@/lib/errorUtils';
 
 /**
  * URL parameters for startup
@@ -155,7 +156,7 @@ export function useStartup(config: UseStartupConfig): void {
         }
       } catch (err) {
         console.error('Error in standalone mode initialization:', err);
-        alert(`Failed to load PDF: ${err}`);
+        alert(`Failed to load PDF: ${getErrorMessage(err)}`);
       }
     }
 
@@ -179,7 +180,7 @@ export function useStartup(config: UseStartupConfig): void {
         }
       } catch (err) {
         console.error('Error loading PDF:', err);
-        alert(`Failed to load PDF: ${err}`);
+        alert(`Failed to load PDF: ${getErrorMessage(err)}`);
       }
     }
 
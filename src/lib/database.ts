@@ -6,6 +6,7 @@ import type {
   BookmarkState,
   HistoryEntry,
   PdfSessionState,
+  CompactResult,
 } from '@/types';
 
 // Re-export types for backward compatibility
@@ -53,6 +54,11 @@ export async function getRecentFiles(limit: number = 10): Promise<Array<{
   );
 }
 
+// Compact the database (VACUUM); fails with code 'database.busy' during downloads
+export async function compactDatabase(): Promise<CompactResult> {
+  return await invoke<CompactResult>('compact_database');
+}
+
 // Create default session state
 export function createDefaultState(): PdfSessionState {
   return {
@@ -0,0 +1,44 @@
+import { describe, it, expect } from 'vitest';
+import {
+  getErrorCode,
+  getErrorMessage,
+  getRequiredScope,
+  isCommandError,
+  isOfflineError,
+} from './errorUtils';
+
+describe('errorUtils', () => {
+  const commandError = { code: 'drive.not_authenticated', message: 'Not authenticated' };
+
+  it('recognizes command errors', () => {
+    expect(isCommandError(commandError)).toBe(true);
+    expect(isCommandError('plain string')).toBe(false);
+    expect(isCommandError(null)).toBe(false);
+    expect(isCommandError({ code: 1, message: 'x' })).toBe(false);
+  });
+
+  it('gets the message from any error shape', () => {
+    expect(getErrorMessage(commandError)).toBe('Not authenticated');
+    expect(getErrorMessage(new Error('boom'))).toBe('boom');
+    expect(getErrorMessage('failed')).toBe('failed');
+  });
+
+  it('gets the code only from command errors', () => {
+    expect(getErrorCode(commandError)).toBe('drive.not_authenticated');
+    expect(getErrorCode(new Error('boom'))).toBeNull();
+  });
+
+  it('gets the required scope of insufficient-scope errors', () => {
+    const scope = 'https://www.googleapis.com/auth/drive.readonly';
+    expect(
+      getRequiredScope({ code: 'drive.insufficient_scope', message: 'x', requiredScope: scope })
+    ).toBe(scope);
+    expect(getRequiredScope(commandError)).toBeNull();
+    expect(getRequiredScope(new Error('boom'))).toBeNull();
+  });
+
+  it('recognizes offline errors', () => {
+    expect(isOfflineError({ code: 'network.offline', message: 'Offline' })).toBe(true);
+    expect(isOfflineError(commandError)).toBe(false);
+  });
+});
@@ -0,0 +1,52 @@
+import type { CommandError } from '@/types';
+
+/**
+ * Check whether a value is a structured error returned by a Tauri command
+ */
+export function isCommandError(err: unknown): err is CommandError {
+  return (
+    typeof err === 'object' &&
+    err !== null &&
+    typeof (err as CommandError).code === 'string' &&
+    typeof (err as CommandError).message === 'string'
+  );
+}
+
+/**
+ * Get a human-readable message from a caught error
+ */
+export function getErrorMessage(err: unknown): string {
+  if (isCommandError(err) || err instanceof Error) {
+    return err.message;
+  }
+  return String(err);
+}
+
+/**
+ * Get the machine-readable code of a command error, or null for other errors
+ *
+ * @example
+ * if (getErrorCode(err) === 'drive.not_authenticated') showReconnect();
+ */
+export function getErrorCode(err: unknown): string | null {
+  return isCommandError(err) ? err.code : null;
+}
+
+/**
+ * Get the OAuth scope a `drive.insufficient_scope` error asks for, or null
+ *
+ * @example
+ * const scope = getRequiredScope(err);
+ * if (scope) reconnect(accountId, scope);
+ */
+export function getRequiredScope(err: unknown): string | null {
+  return isCommandError(err) ? (err.requiredScope ?? null) : null;
+}
+
+/**
+ * Check whether a command failed because the network is unreachable
+ * (local features keep working; retry once `checkConnectivity` succeeds)
+ */
+export function isOfflineError(err: unknown): boolean {
+  return getErrorCode(err) === 'network.offline';
+}
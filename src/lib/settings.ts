@@ -6,7 +6,26 @@
  */
 
 import { invoke } from '@tauri-apps/api/core';
-import type { GeminiSettings, GeminiModelOption, TranslationResponse, ExplanationResponse } from '@/types';
+import type {
+  GeminiSettings,
+  GeminiModelOption,
+  TranslationResponse,
+  ExplanationResponse,
+  TranslationSettings,
+  SupportedLanguage,
+  GeminiModelInfo,
+  KeyRotation,
+  HttpTimeouts,
+  ImportMode,
+  ImportSummary,
+  ScheduledSync,
+  CallbackPages,
+  DiagnosticsReport,
+  ReadingStats,
+  Annotation,
+  AnnotationRect,
+  NewAnnotation,
+} from '@/types';
 
 // ============================================
 // Default Values
@@ -88,43 +107,198 @@ export async function saveGeminiSettings(settings: GeminiSettings): Promise<void
 }
 
 /**
- * Translate text using Gemini API
- * Returns a structured response with translation and points
+ * Check that a Gemini API key and model work
+ * Uses the saved key/model for any argument that is omitted
+ */
+export async function testGeminiKey(apiKey?: string, model?: string): Promise<void> {
+  await invoke('test_gemini_key', { apiKey: apiKey ?? null, model: model ?? null });
+}
+
+/**
+ * Replace the Gemini API key; the saved key is kept if the new one doesn't work
+ */
+export async function rotateGeminiKey(newKey: string): Promise<KeyRotation> {
+  return invoke<KeyRotation>('rotate_gemini_key', { newKey });
+}
+
+/**
+ * List Gemini models that support translation, using the saved API key
+ * Falls back to a built-in list when the key is missing or the request fails
+ */
+export async function listGeminiModels(): Promise<GeminiModelInfo[]> {
+  return invoke<GeminiModelInfo[]>('list_gemini_models');
+}
+
+/**
+ * Get settings for all translation providers
+ */
+export async function getTranslationSettings(): Promise<TranslationSettings> {
+  return invoke<TranslationSettings>('get_translation_settings');
+}
+
+/**
+ * Save settings for all translation providers
+ */
+export async function saveTranslationSettings(settings: TranslationSettings): Promise<void> {
+  await invoke('save_translation_settings', { settingsData: settings });
+}
+
+/**
+ * Get the target languages available for translation
+ */
+export async function getSupportedLanguages(): Promise<SupportedLanguage[]> {
+  return invoke<SupportedLanguage[]>('get_supported_languages');
+}
+
+/**
+ * Delete all cached translation results
+ * Returns the number of entries removed
+ */
+export async function clearTranslationCache(): Promise<number> {
+  return invoke<number>('clear_translation_cache');
+}
+
+/**
+ * Get the max age of cached translations in seconds (0 = caching disabled)
+ */
+export async function getTranslationCacheMaxAge(): Promise<number> {
+  return invoke<number>('get_translation_cache_max_age');
+}
+
+/**
+ * Set the max age of cached translations in seconds (0 disables caching)
+ */
+export async function setTranslationCacheMaxAge(maxAgeSecs: number): Promise<void> {
+  await invoke('set_translation_cache_max_age', { maxAgeSecs });
+}
+
+/**
+ * Get the Gemini rate limit in requests per minute (0 = unlimited)
+ */
+export async function getGeminiRateLimit(): Promise<number> {
+  return invoke<number>('get_gemini_rate_limit');
+}
+
+/**
+ * Set the Gemini rate limit in requests per minute (0 = unlimited)
+ */
+export async function setGeminiRateLimit(requestsPerMinute: number): Promise<void> {
+  await invoke('set_gemini_rate_limit', { requestsPerMinute });
+}
+
+/**
+ * Export folders, non-secret settings, tags and favorites as a JSON bundle
+ * (API keys and tokens are never included)
+ */
+export async function exportConfig(): Promise<string> {
+  return invoke<string>('export_config');
+}
+
+/**
+ * Import a bundle from exportConfig; existing entries are kept unless
+ * `mode` is 'overwrite'
+ */
+export async function importConfig(json: string, mode: ImportMode = 'skip'): Promise<ImportSummary> {
+  return invoke<ImportSummary>('import_config', { json, mode });
+}
+
+/**
+ * Translate text using the active translation provider
+ * Returns a structured response with translation and points, or null if a
+ * newer request with the same `context` (e.g. 'popup') superseded it
  */
 export async function translateWithGemini(
   text: string,
   contextBefore: string,
   contextAfter: string,
-  modelOverride?: string
-): Promise<TranslationResponse> {
-  const result = await invoke<TranslationResponse>('translate_with_gemini', {
+  modelOverride?: string,
+  context?: string
+): Promise<TranslationResponse | null> {
+  const result = await invoke<TranslationResponse | null>('translate_text', {
     text,
     contextBefore,
     contextAfter,
     modelOverride: modelOverride ?? null,
+    context: context ?? null,
   });
   return result;
 }
 
 /**
  * Get explanation of text
- * Returns summary + explanation points
+ * Returns summary + explanation points, or null if a newer request with the
+ * same `context` superseded it
  */
 export async function explainDirectly(
   text: string,
   contextBefore: string,
   contextAfter: string,
-  modelOverride?: string
-): Promise<ExplanationResponse> {
-  const result = await invoke<ExplanationResponse>('explain_directly', {
+  modelOverride?: string,
+  context?: string
+): Promise<ExplanationResponse | null> {
+  const result = await invoke<ExplanationResponse | null>('explain_directly', {
     text,
     contextBefore,
     contextAfter,
     modelOverride: modelOverride ?? null,
+    context: context ?? null,
   });
   return result;
 }
 
+/**
+ * Translate text, streaming partial output as `translation-chunk` events
+ * Resolves to null if the stream was cancelled or superseded by a newer
+ * request with the same `context`
+ */
+export async function translateTextStream(
+  requestId: string,
+  text: string,
+  contextBefore: string,
+  contextAfter: string,
+  modelOverride?: string,
+  context?: string
+): Promise<TranslationResponse | null> {
+  return invoke<TranslationResponse | null>('translate_text_stream', {
+    requestId,
+    text,
+    contextBefore,
+    contextAfter,
+    modelOverride: modelOverride ?? null,
+    context: context ?? null,
+  });
+}
+
+/**
+ * Explain text, streaming partial output as `translation-chunk` events
+ * Resolves to null if the stream was cancelled or superseded by a newer
+ * request with the same `context`
+ */
+export async function explainTextStream(
+  requestId: string,
+  text: string,
+  contextBefore: string,
+  contextAfter: string,
+  modelOverride?: string,
+  context?: string
+): Promise<ExplanationResponse | null> {
+  return invoke<ExplanationResponse | null>('explain_text_stream', {
+    requestId,
+    text,
+    contextBefore,
+    contextAfter,
+    modelOverride: modelOverride ?? null,
+    context: context ?? null,
+  });
+}
+
+/**
+ * Cancel a streaming translation or explanation
+ */
+export async function cancelTranslationStream(requestId: string): Promise<boolean> {
+  return invoke<boolean>('cancel_translation_stream', { requestId });
+}
+
 /**
  * Check if Gemini API key is configured
  */
@@ -132,3 +306,236 @@ export async function isGeminiConfigured(): Promise<boolean> {
   const settings = await getGeminiSettings();
   return settings.apiKey.trim().length > 0;
 }
+
+/**
+ * Check whether Google's servers are reachable (leaves offline mode when they are)
+ */
+export async function checkConnectivity(): Promise<boolean> {
+  return invoke<boolean>('check_connectivity');
+}
+
+/**
+ * Check each subsystem for support requests (`live` also tests the Gemini key)
+ */
+export async function runDiagnostics(live = false): Promise<DiagnosticsReport> {
+  return invoke<DiagnosticsReport>('run_diagnostics', { live });
+}
+
+/**
+ * Get how many seconds before expiry Google tokens are refreshed
+ */
+export async function getTokenRefreshMargin(): Promise<number> {
+  return invoke<number>('get_token_refresh_margin');
+}
+
+/**
+ * Set how many seconds before expiry Google tokens are refreshed
+ * (0 to 1800, null restores the default of 300)
+ */
+export async function setTokenRefreshMargin(secs: number | null): Promise<void> {
+  await invoke('set_token_refresh_margin', { secs });
+}
+
+/**
+ * Get the sign-in callback pages (built-in pages for any not customized)
+ */
+export async function getOAuthCallbackPages(): Promise<CallbackPages> {
+  return invoke<CallbackPages>('get_oauth_callback_pages');
+}
+
+/**
+ * Save the sign-in callback pages (blank pages restore the built-in ones)
+ */
+export async function saveOAuthCallbackPages(pages: CallbackPages): Promise<void> {
+  await invoke('save_oauth_callback_pages', { pages });
+}
+
+/**
+ * Get the proxy used for Google and translation requests
+ * (null = the HTTP_PROXY/HTTPS_PROXY/ALL_PROXY environment variables)
+ */
+export async function getProxy(): Promise<string | null> {
+  return invoke<string | null>('get_proxy');
+}
+
+/**
+ * Set the proxy for outbound requests, e.g. "http://proxy:8080" or
+ * "socks5://proxy:1080" (null or empty clears it)
+ */
+export async function setProxy(url: string | null): Promise<void> {
+  await invoke('set_proxy', { url });
+}
+
+/**
+ * Get the connect and request timeouts for outbound requests
+ */
+export async function getHttpTimeouts(): Promise<HttpTimeouts> {
+  return invoke<HttpTimeouts>('get_http_timeouts');
+}
+
+/**
+ * Set the timeouts for outbound requests (connect 1 to 120 seconds, request
+ * 5 to 600; out-of-range values are clamped). Returns the timeouts applied.
+ */
+export async function setHttpTimeouts(timeouts: HttpTimeouts): Promise<HttpTimeouts> {
+  return invoke<HttpTimeouts>('set_http_timeouts', { timeouts });
+}
+
+/**
+ * Whether the next PDF in a folder is downloaded ahead of time
+ */
+export async function getPrefetchEnabled(): Promise<boolean> {
+  return invoke<boolean>('get_prefetch_enabled');
+}
+
+/**
+ * Turn prefetching on or off (turn it off on metered connections).
+ * Returns how many prefetches were cancelled.
+ */
+export async function setPrefetchEnabled(enabled: boolean): Promise<number> {
+  return invoke<number>('set_prefetch_enabled', { enabled });
+}
+
+/**
+ * Get the template download file names are built from
+ */
+export async function getDownloadNameTemplate(): Promise<string> {
+  return invoke<string>('get_download_name_template');
+}
+
+/**
+ * Set the template download file names are built from. Supports {pdf_title},
+ * {file_name} and {drive_file_id}; the extension is always kept. A blank
+ * template restores the default (the Drive name). Returns the template now in effect.
+ */
+export async function setDownloadNameTemplate(template: string): Promise<string> {
+  return invoke<string>('set_download_name_template', { template });
+}
+
+export type LogLevel = 'off' | 'error' | 'warn' | 'info' | 'debug' | 'trace';
+
+/**
+ * Get the level of messages written to the log
+ */
+export async function getLogLevel(): Promise<LogLevel> {
+  return invoke<LogLevel>('get_log_level');
+}
+
+/**
+ * Set the level of messages written to the log (null restores the default of info)
+ */
+export async function setLogLevel(level: LogLevel | null): Promise<void> {
+  await invoke('set_log_level', { level });
+}
+
+/**
+ * Get the auto-sync interval in minutes (null = auto-sync off)
+ */
+export async function getAutoSyncInterval(): Promise<number | null> {
+  return invoke<number | null>('get_auto_sync_interval');
+}
+
+/**
+ * Set the auto-sync interval in minutes (null or 0 turns auto-sync off)
+ */
+export async function setAutoSyncInterval(minutes: number | null): Promise<void> {
+  await invoke('set_auto_sync_interval', { minutes });
+}
+
+/**
+ * Get the MIME types sync adds to the bookshelf
+ */
+export async function getSyncMimeTypes(): Promise<string[]> {
+  return invoke<string[]>('get_sync_mime_types');
+}
+
+/**
+ * Set the MIME types sync adds to the bookshelf (an empty list restores PDFs only).
+ * Returns the types now in effect.
+ */
+export async function setSyncMimeTypes(mimeTypes: string[]): Promise<string[]> {
+  return invoke<string[]>('set_sync_mime_types', { mimeTypes });
+}
+
+/**
+ * Include or exclude a Drive folder from automatic syncs
+ */
+export async function setDriveFolderAutoSync(folderId: string, enabled: boolean): Promise<void> {
+  await invoke('set_drive_folder_auto_sync', { folderId, enabled });
+}
+
+/**
+ * Get when each synced folder is next synced automatically
+ */
+export async function getSyncSchedule(): Promise<ScheduledSync[]> {
+  return invoke<ScheduledSync[]>('get_sync_schedule');
+}
+
+/**
+ * Start recording a reading session for the PDF open at `localPath`,
+ * returning its id (null when the file isn't a downloaded cloud item)
+ */
+export async function startReadingSession(localPath: string, page: number): Promise<number | null> {
+  return invoke<number | null>('start_reading_session', { localPath, page });
+}
+
+/**
+ * Extend a reading session to now (call on page changes and when closing)
+ */
+export async function updateReadingSession(sessionId: number, page: number): Promise<void> {
+  await invoke('update_reading_session', { sessionId, page });
+}
+
+/**
+ * Get reading statistics across the bookshelf
+ */
+export async function getReadingStats(): Promise<ReadingStats> {
+  return invoke<ReadingStats>('get_reading_stats');
+}
+
+/**
+ * Add a highlight or note to a cloud document
+ */
+export async function addAnnotation(driveFileId: string, annotation: NewAnnotation): Promise<Annotation> {
+  return invoke<Annotation>('add_annotation', { driveFileId, annotation });
+}
+
+/**
+ * Get all annotations on a cloud document, ordered by page
+ */
+export async function getAnnotations(driveFileId: string): Promise<Annotation[]> {
+  return invoke<Annotation[]>('get_annotations', { driveFileId });
+}
+
+/**
+ * Change an annotation's area, color and note
+ */
+export async function updateAnnotation(
+  id: number,
+  rects: AnnotationRect[],
+  color: string,
+  note?: string
+): Promise<Annotation> {
+  return invoke<Annotation>('update_annotation', { id, rects, color, note: note ?? null });
+}
+
+/**
+ * Delete an annotation (returns false if it didn't exist)
+ */
+export async function deleteAnnotation(id: number): Promise<boolean> {
+  return invoke<boolean>('delete_annotation', { id });
+}
+
+/**
+ * Export a cloud document's annotations as JSON
+ */
+export async function exportAnnotations(driveFileId: string): Promise<string> {
+  return invoke<string>('export_annotations', { driveFileId });
+}
+
+/**
+ * Bake a cloud document's annotations into a copy of its PDF, returning its path
+ */
+export async function exportAnnotatedPdf(driveFileId: string): Promise<string> {
+  return invoke<string>('export_annotated_pdf', { driveFileId });
+}
@@ -2,6 +2,8 @@
  * Thumbnail generator for PDF files using PDF.js
  */
 
+import { convertFileSrc } from '@tauri-apps/api/core';
+
 // Thumbnail dimensions
 const THUMBNAIL_WIDTH = 200;
 const THUMBNAIL_HEIGHT = 280; // Approximately 4:3 aspect ratio for PDF pages
@@ -125,3 +127,14 @@ export async function generateThumbnailsInBackground(
 
   await processNext(0);
 }
+
+/**
+ * Image source for an item's thumbnail: inline data, or the thumbnail file
+ * served through the asset protocol
+ */
+export function thumbnailSrc(item: { thumbnailData?: string; thumbnailPath?: string }): string | undefined {
+  if (item.thumbnailData) {
+    return `data:image/png;base64,${item.thumbnailData}`;
+  }
+  return item.thumbnailPath ? convertFileSrc(item.thumbnailPath) : undefined;
+}
@@ -131,12 +131,49 @@ export interface PdfSessionState {
 // Google Drive / OAuth Types
 // ============================================
 
+/**
+ * Whether an account can talk to Google. `needs_reauth` means Google rejected
+ * its tokens: offer "Reconnect", which keeps its folders and downloads.
+ */
+export type AccountConnection = 'signed_out' | 'needs_reauth' | 'connected';
+
+/**
+ * Authentication status of a single Google account
+ */
+export interface AccountStatus {
+  id: number;
+  label: string;
+  /** Whether `connection` is 'connected' */
+  authenticated: boolean;
+  connection: AccountConnection;
+  granted_scopes: string[];
+}
+
 /**
  * OAuth authentication status
+ *
+ * `authenticated` and `granted_scopes` describe the default account.
  */
 export interface AuthStatus {
   authenticated: boolean;
+  /** OAuth client credentials are set (tokens may still be missing or rejected) */
   configured: boolean;
+  connection?: AccountConnection;
+  /** Scopes Google actually granted (may be narrower than requested) */
+  granted_scopes?: string[];
+  /** Every known account, connected or not */
+  accounts?: AccountStatus[];
+}
+
+/**
+ * HTML pages shown in the browser when Google redirects back after signing in
+ */
+export interface CallbackPages {
+  success: string;
+  /** `{message}` is replaced with why signing in failed */
+  failure: string;
+  /** Shown for a redirect that doesn't belong to the current sign-in */
+  stateMismatch: string;
 }
 
 /**
@@ -146,6 +183,8 @@ export interface DriveFolder {
   id: string;
   name: string;
   modifiedTime?: string;
+  /** Shared drive the folder is on (absent for My Drive) */
+  driveId?: string;
 }
 
 /**
@@ -166,18 +205,50 @@ export interface DriveItem {
  */
 export interface StoredFolder {
   folderId: string;
+  /** Google account the folder belongs to */
+  accountId: number;
   folderName: string;
   isActive: boolean;
   lastSynced?: number;
+  /** Whether subfolders are synced too */
+  recursive: boolean;
+  /** Whether the background scheduler syncs this folder */
+  autoSync: boolean;
+  /** Shared drive the folder is on (absent for My Drive or not yet known) */
+  driveId?: string;
+}
+
+/**
+ * When a folder is next synced automatically
+ */
+export interface ScheduledSync {
+  folderId: string;
+  /** Unix timestamp (absent when the folder isn't synced automatically) */
+  nextSync?: number;
+}
+
+/**
+ * A file added, updated or removed by a sync
+ */
+export interface SyncedFile {
+  driveFileId: string;
+  fileName: string;
 }
 
 /**
- * Result of a sync operation
+ * Result of a sync operation (the counts are the lengths of the lists)
  */
 export interface SyncResult {
   newFiles: number;
   updatedFiles: number;
   removedFiles: number;
+  added: SyncedFile[];
+  updated: SyncedFile[];
+  removed: SyncedFile[];
+  /** Whether every folder was synced from Drive's change log instead of listed in full */
+  incremental: boolean;
+  /** Files left out because their type isn't synced */
+  skippedFiles: number;
 }
 
 // ============================================
@@ -187,7 +258,18 @@ export interface SyncResult {
 /**
  * Download status of a bookshelf item
  */
-export type DownloadStatus = 'pending' | 'downloading' | 'completed' | 'error';
+export type DownloadStatus = 'pending' | 'queued' | 'downloading' | 'completed' | 'error';
+
+/**
+ * Why the last download of a bookshelf item stopped
+ */
+export type DownloadFailure =
+  | 'network'
+  | 'disk_full'
+  | 'cancelled'
+  | 'integrity'
+  | 'not_found'
+  | 'other';
 
 /**
  * Source type for bookshelf items
@@ -201,17 +283,42 @@ export type SourceType = 'google_drive' | 'local';
 export interface CloudItem {
   id: number;
   driveFileId: string;
+  /** Google account the item was synced or imported with */
+  accountId: number;
   driveFolderId: string;
+  rootFolderId?: string;
+  folderPath?: string;
   fileName: string;
   fileSize?: number;
+  driveModifiedTime?: string;
+  /** Base64 PNG, only for thumbnails not yet moved out of the database */
   thumbnailData?: string;
+  /** Absolute path of the thumbnail PNG (see thumbnailSrc) */
+  thumbnailPath?: string;
   localPath?: string;
   downloadStatus: DownloadStatus;
   downloadProgress: number;
+  /** Why the last download failed, when downloadStatus is 'error' */
+  downloadError?: string;
+  /** Kind of the last download failure (also set after a cancel) */
+  downloadFailure?: DownloadFailure;
+  /** Automatic retries used by the current or last download */
+  retryCount?: number;
   pdfTitle?: string;
   pdfAuthor?: string;
+  pageCount?: number;
+  /** Name set by the user, overriding pdfTitle and fileName */
+  customName?: string;
+  /** Name to show: customName, then pdfTitle, then fileName */
+  displayName: string;
   isFavorite: boolean;
   lastOpened?: number;
+  lastReadPage?: number;
+  tags?: string[];
+  /** When the item was added to the bookshelf (Unix seconds) */
+  createdAt: number;
+  /** When the item was last changed (Unix seconds) */
+  updatedAt: number;
 }
 
 /**
@@ -228,6 +335,10 @@ export interface LocalItem {
   pdfAuthor?: string;
   isFavorite: boolean;
   lastOpened?: number;
+  /** When the file was imported (Unix seconds) */
+  createdAt: number;
+  /** When the item was last changed (Unix seconds) */
+  updatedAt: number;
 }
 
 /**
@@ -237,20 +348,180 @@ export interface LocalItem {
 export interface BookshelfItem {
   id: number;
   driveFileId?: string;
+  accountId?: number;
   driveFolderId?: string;
+  rootFolderId?: string;
+  folderPath?: string;
   fileName: string;
   fileSize?: number;
+  driveModifiedTime?: string;
+  /** Base64 PNG, only for thumbnails not yet moved out of the database */
   thumbnailData?: string;
+  /** Absolute path of the thumbnail PNG (see thumbnailSrc) */
+  thumbnailPath?: string;
   localPath?: string;
   downloadStatus: DownloadStatus;
   downloadProgress: number;
+  /** Why the last download failed, when downloadStatus is 'error' */
+  downloadError?: string;
+  /** Kind of the last download failure (also set after a cancel) */
+  downloadFailure?: DownloadFailure;
+  /** Automatic retries used by the current or last download */
+  retryCount?: number;
   pdfTitle?: string;
   pdfAuthor?: string;
+  pageCount?: number;
+  customName?: string;
+  displayName: string;
   sourceType: SourceType;
   originalPath?: string;
+  /** When the item was added to the bookshelf (Unix seconds) */
   createdAt: number;
+  /** When the item was last changed (Unix seconds) */
+  updatedAt: number;
   isFavorite: boolean;
   lastOpened?: number;
+  lastReadPage?: number;
+  tags?: string[];
+}
+
+/**
+ * A page matching a full-text search
+ */
+export interface SearchHit {
+  driveFileId: string;
+  page: number;
+  /** HTML-escaped excerpt with matches wrapped in <mark> */
+  snippet: string;
+}
+
+/**
+ * Summary of a search indexing run
+ */
+export interface IndexSummary {
+  indexed: number;
+  upToDate: number;
+  skipped: number;
+  failed: number;
+}
+
+/**
+ * Saved reading position for a cloud document
+ */
+export interface ReadingPosition {
+  driveFileId: string;
+  page: number;
+  scrollOffset: number;
+  updatedAt: number;
+}
+
+/**
+ * Reading statistics for one cloud document
+ */
+export interface DocumentReadingStats {
+  driveFileId: string;
+  displayName: string;
+  /** Furthest page reached */
+  pagesRead: number;
+  pageCount?: number;
+  completed: boolean;
+  timeSpentSecs: number;
+  sessionCount: number;
+  lastReadAt?: number;
+}
+
+/**
+ * Reading statistics across the bookshelf
+ */
+export interface ReadingStats {
+  totalPagesRead: number;
+  documentsCompleted: number;
+  totalTimeSecs: number;
+  /** Most recently read first */
+  documents: DocumentReadingStats[];
+}
+
+export type AnnotationKind = 'highlight' | 'underline' | 'strikeout' | 'note';
+
+/**
+ * Area covered by an annotation, as fractions of the page size
+ * (origin at the top-left corner)
+ */
+export interface AnnotationRect {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+/**
+ * A highlight or note on a cloud document
+ */
+export interface Annotation {
+  id: number;
+  driveFileId: string;
+  /** 1-based page number */
+  page: number;
+  kind: AnnotationKind;
+  /** One rect per line for text spanning several lines */
+  rects: AnnotationRect[];
+  /** #RRGGBB or #RRGGBBAA */
+  color: string;
+  note?: string;
+  createdAt: number;
+  updatedAt: number;
+}
+
+export interface NewAnnotation {
+  page: number;
+  kind: AnnotationKind;
+  rects: AnnotationRect[];
+  color: string;
+  note?: string;
+}
+
+/**
+ * How favorites are treated when listing bookshelf items
+ */
+export type FavoritesMode = 'mixed' | 'first' | 'only';
+
+/**
+ * Options for listing bookshelf items
+ */
+export interface ItemQuery {
+  favorites?: FavoritesMode;
+  /** Only items carrying at least one of these tags */
+  tags?: string[];
+  sortBy?: SortColumn;
+  /** Defaults to 'asc' for name/status and 'desc' otherwise */
+  sortDirection?: SortDirection;
+  limit?: number;
+  offset?: number;
+}
+
+/**
+ * Column to sort bookshelf items by
+ */
+export type SortColumn =
+  | 'name'
+  | 'size'
+  | 'modifiedTime'
+  | 'downloadStatus'
+  | 'lastRead'
+  | 'createdAt'
+  | 'updatedAt';
+
+/**
+ * Sort direction for bookshelf items
+ */
+export type SortDirection = 'asc' | 'desc';
+
+/**
+ * One page of bookshelf items
+ */
+export interface ItemPage {
+  items: BookshelfItem[];
+  totalCount: number;
 }
 
 /**
@@ -262,6 +533,47 @@ export interface ImportResult {
   errorCount: number;
 }
 
+/**
+ * Outcome of deleting one item's local copy in a batch
+ */
+export interface DeleteResult {
+  driveFileId: string;
+  success: boolean;
+  error?: string;
+}
+
+/**
+ * Result of freeing space in the downloads directory
+ */
+export interface CleanupResult {
+  filesRemoved: number;
+  bytesFreed: number;
+}
+
+/**
+ * Disk space used by the downloads of one watched folder
+ */
+export interface FolderStorageUsage {
+  folderId: string;
+  folderName: string | null;
+  totalBytes: number;
+  itemCount: number;
+}
+
+/**
+ * Disk space used by downloaded cloud items
+ */
+export interface StorageUsage {
+  totalBytes: number;
+  /** Completed downloads, including ones sharing a file */
+  itemCount: number;
+  /** Downloads whose file is gone */
+  missingCount: number;
+  capBytes: number | null;
+  /** Largest folders first */
+  folders: FolderStorageUsage[];
+}
+
 /**
  * Download progress event
  */
@@ -270,6 +582,12 @@ export interface DownloadProgress {
   progress: number;
   downloadedBytes: number;
   totalBytes: number;
+  /** Download speed averaged over the last few seconds */
+  bytesPerSecond: number;
+  /** Estimated seconds remaining (absent when size or speed is unknown) */
+  etaSeconds?: number;
+  /** 'completed' or 'pending' (cancelled) on the last event of a download */
+  status: DownloadStatus;
 }
 
 // ============================================
@@ -297,6 +615,31 @@ export interface GeminiModelOption {
   description: string;
 }
 
+/**
+ * Gemini model reported by the models.list API (or the built-in fallback list)
+ */
+export interface GeminiModelInfo {
+  id: string;
+  displayName: string;
+}
+
+/**
+ * Outcome of replacing the Gemini API key
+ */
+export interface KeyRotation {
+  /** Whether the replaced key still worked (null if there was none or it couldn't be checked) */
+  previousKeyValid: boolean | null;
+}
+
+/**
+ * Connect and request timeouts for Google and translation requests, in seconds
+ */
+export interface HttpTimeouts {
+  connectSecs: number;
+  /** For downloads and streamed responses, how long the server may go quiet */
+  requestSecs: number;
+}
+
 /**
  * Gemini translation settings
  */
@@ -304,10 +647,55 @@ export interface GeminiSettings {
   apiKey: string;
   model: string;
   explanationModel: string;
+  /** Sampling temperature; null uses the provider default */
+  temperature?: number | null;
+  /** Response length limit; null or 0 uses the provider default */
+  maxOutputTokens?: number | null;
 }
 
 /**
- * Structured translation response from Gemini
+ * Supported translation providers
+ */
+export type TranslationProvider = 'gemini' | 'openai' | 'anthropic';
+
+/**
+ * API key and models for a single translation provider
+ */
+export type ProviderSettings = GeminiSettings;
+
+/**
+ * Translation settings for all providers
+ */
+export interface TranslationSettings {
+  provider: TranslationProvider;
+  /** English name of the output language, e.g. "Japanese" */
+  targetLanguage: string;
+  /** Translation system instructions by selection size */
+  templates: {
+    word: string;
+    sentence: string;
+    paragraph: string;
+  };
+  /** Word counts that decide which template is used */
+  thresholds: {
+    wordMaxWords: number;
+    paragraphMinWords: number;
+  };
+  gemini: ProviderSettings;
+  openai: ProviderSettings;
+  anthropic: ProviderSettings;
+}
+
+/**
+ * Translation target language offered in the UI
+ */
+export interface SupportedLanguage {
+  name: string;
+  nativeName: string;
+}
+
+/**
+ * Structured translation response
  */
 export interface TranslationResponse {
   translation: string;
@@ -315,13 +703,31 @@ export interface TranslationResponse {
 }
 
 /**
- * Structured explanation response from Gemini
+ * Structured explanation response
  */
 export interface ExplanationResponse {
   summary: string;
   points: string[];
 }
 
+/**
+ * Payload of the `translation-chunk` event (raw text generated so far since the last chunk)
+ */
+export interface TranslationChunk {
+  requestId: string;
+  text: string;
+}
+
+/**
+ * Payload of the `translation-done` event
+ */
+export interface TranslationDone<T> {
+  requestId: string;
+  response: T | null;
+  error: string | null;
+  cancelled: boolean;
+}
+
 /**
  * Text selection data for translation
  */
@@ -334,3 +740,85 @@ export interface TextSelection {
   contextLoading?: boolean;
   pageNumber?: number; // Page number where the selection was made
 }
+
+// ============================================
+// Command Error Types
+// ============================================
+
+/**
+ * Error returned by Tauri commands
+ * `code` is a stable identifier such as `drive.not_authenticated`
+ */
+export interface CommandError {
+  code: string;
+  message: string;
+  /** OAuth scope to pass to `reconnect` (only on `drive.insufficient_scope`) */
+  requiredScope?: string;
+}
+
+/**
+ * Result of compacting the database
+ */
+export interface CompactResult {
+  sizeBefore: number;
+  sizeAfter: number;
+  bytesReclaimed: number;
+}
+
+// ============================================
+// Configuration Bundle Types
+// ============================================
+
+/**
+ * How an import treats entries that already exist
+ */
+export type ImportMode = 'skip' | 'overwrite';
+
+/**
+ * Number of entries applied by a configuration import
+ */
+export interface ImportSummary {
+  folders: number;
+  settings: number;
+  /** Tag assignments added (tags are always merged) */
+  tags: number;
+  favorites: number;
+  /** Entries left alone because they already existed */
+  skipped: number;
+}
+
+// ============================================
+// Diagnostics Types
+// ============================================
+
+/**
+ * Outcome of one diagnostics check
+ */
+export type DiagnosticCheck<T> =
+  | { status: 'ok'; value: T }
+  | { status: 'err'; code: string; message: string };
+
+/**
+ * Health report returned by `run_diagnostics`
+ */
+export interface DiagnosticsReport {
+  generatedAt: number;
+  database: DiagnosticCheck<{
+    path: string;
+    schemaVersion: number;
+    /** Newest migration this build knows about */
+    latestVersion: number;
+  }>;
+  /** Name of the secrets backend in use */
+  secretsBackend: DiagnosticCheck<string>;
+  googleAuth: DiagnosticCheck<AuthStatus>;
+  gemini: DiagnosticCheck<{
+    keyPresent: boolean;
+    model: string;
+    /** Result of a live request (absent when not requested or no key is set) */
+    liveCheck?: DiagnosticCheck<null>;
+  }>;
+  downloads: DiagnosticCheck<{ path: string; freeSpaceBytes: number }>;
+  /** Cloud items per download status */
+  downloadStatusCounts: DiagnosticCheck<Record<string, number>>;
+}